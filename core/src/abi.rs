@@ -0,0 +1,551 @@
+//! ABI decoding helpers for Shadow-EVM
+//!
+//! Minimal Solidity ABI support for inspecting revert data.
+//! Covers elementary types, `bytes`, `string` and dynamic arrays (`T[]`),
+//! which is what custom errors use in practice.
+
+use crate::hashing::keccak256;
+use crate::types::{Address, U256};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use alloy_primitives::I256;
+use core::fmt;
+
+extern crate alloc;
+
+/// Size of an ABI word in bytes
+const WORD: usize = 32;
+
+/// Solidity ABI parameter type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiType {
+    /// `uint<N>` (bit width)
+    Uint(usize),
+    /// `int<N>` (bit width)
+    Int(usize),
+    /// `address`
+    Address,
+    /// `bool`
+    Bool,
+    /// `bytes<N>` (byte width)
+    FixedBytes(usize),
+    /// `bytes`
+    Bytes,
+    /// `string`
+    String,
+    /// `T[]`
+    Array(Box<AbiType>),
+}
+
+impl AbiType {
+    /// Parse a canonical Solidity type name (e.g. `uint256`, `address[]`)
+    pub fn parse(name: &str) -> Option<Self> {
+        if let Some(inner) = name.strip_suffix("[]") {
+            return Some(AbiType::Array(Box::new(Self::parse(inner)?)));
+        }
+
+        match name {
+            "address" => return Some(AbiType::Address),
+            "bool" => return Some(AbiType::Bool),
+            "bytes" => return Some(AbiType::Bytes),
+            "string" => return Some(AbiType::String),
+            "uint" => return Some(AbiType::Uint(256)),
+            "int" => return Some(AbiType::Int(256)),
+            _ => {}
+        }
+
+        if let Some(bits) = name.strip_prefix("uint") {
+            let bits: usize = bits.parse().ok()?;
+            return is_valid_int_width(bits).then_some(AbiType::Uint(bits));
+        }
+        if let Some(bits) = name.strip_prefix("int") {
+            let bits: usize = bits.parse().ok()?;
+            return is_valid_int_width(bits).then_some(AbiType::Int(bits));
+        }
+        if let Some(size) = name.strip_prefix("bytes") {
+            let size: usize = size.parse().ok()?;
+            return (1..=32)
+                .contains(&size)
+                .then_some(AbiType::FixedBytes(size));
+        }
+
+        None
+    }
+
+    /// Check if the type is dynamically sized (encoded via an offset)
+    pub fn is_dynamic(&self) -> bool {
+        matches!(self, AbiType::Bytes | AbiType::String | AbiType::Array(_))
+    }
+}
+
+/// Integer widths must be a multiple of 8 between 8 and 256
+fn is_valid_int_width(bits: usize) -> bool {
+    bits.is_multiple_of(8) && (8..=256).contains(&bits)
+}
+
+/// Decoded ABI value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiValue {
+    /// Unsigned integer
+    Uint(U256),
+    /// Signed integer
+    Int(I256),
+    /// Address
+    Address(Address),
+    /// Boolean
+    Bool(bool),
+    /// Fixed-size byte array
+    FixedBytes(Vec<u8>),
+    /// Dynamic byte array
+    Bytes(Vec<u8>),
+    /// UTF-8 string
+    String(String),
+    /// Dynamic array
+    Array(Vec<AbiValue>),
+}
+
+/// A decoded Solidity custom error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomError {
+    /// Signature that matched (e.g. `InsufficientBalance(uint256,uint256)`)
+    pub signature: &'static str,
+    /// 4-byte selector
+    pub selector: [u8; 4],
+    /// Decoded arguments
+    pub args: Vec<AbiValue>,
+}
+
+/// Signature of the standard revert reason raised by `require` and
+/// `revert("...")`
+pub const ERROR_STRING_SIGNATURE: &str = "Error(string)";
+
+/// Signature of the revert raised by failed `assert`s, overflows and
+/// other compiler-inserted checks
+pub const PANIC_SIGNATURE: &str = "Panic(uint256)";
+
+/// Revert data decoded by [`decode_revert`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodedError {
+    /// Standard `Error(string)` revert reason
+    Message(String),
+    /// Standard `Panic(uint256)` with its code; see [`panic_description`]
+    Panic(U256),
+    /// One of the supplied custom errors
+    Custom(CustomError),
+}
+
+/// Why an execution reverted, decoded by [`decode_revert_reason`]
+///
+/// Displays as the message for `StandardError`, as e.g. `panic 0x11:
+/// arithmetic overflow or underflow` for `PanicCode` and as the selector
+/// and data length for `CustomError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// `Error(string)` message, from `require` and `revert("...")`
+    StandardError(String),
+    /// `Panic(uint256)` code; see [`panic_description`]
+    PanicCode(U256),
+    /// Any other payload, left undecoded
+    ///
+    /// Also used for an `Error(string)` or `Panic(uint256)` selector whose
+    /// arguments do not decode.
+    CustomError {
+        /// First 4 bytes of the revert data
+        selector: [u8; 4],
+        /// Revert data after the selector
+        data: Vec<u8>,
+    },
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::StandardError(message) => f.write_str(message),
+            RevertReason::PanicCode(code) => {
+                write!(f, "panic {:#04x}: {}", code, panic_description(*code))
+            }
+            RevertReason::CustomError { selector, data } => {
+                f.write_str("custom error 0x")?;
+                for byte in selector {
+                    write!(f, "{byte:02x}")?;
+                }
+                write!(f, " with {} bytes of data", data.len())
+            }
+        }
+    }
+}
+
+/// Compute the 4-byte selector of a function or error signature
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    let mut selector = [0u8; 4];
+    selector.copy_from_slice(&hash[..4]);
+    selector
+}
+
+/// Parse the parameter types out of a signature like `Name(uint256,address)`
+pub fn parse_signature_params(signature: &str) -> Option<Vec<AbiType>> {
+    let open = signature.find('(')?;
+    let params = signature.get(open + 1..)?.strip_suffix(')')?;
+    if params.is_empty() {
+        return Some(Vec::new());
+    }
+    params.split(',').map(AbiType::parse).collect()
+}
+
+/// ABI-decode a parameter list (without selector)
+///
+/// Returns `None` if the data is truncated or a value is out of range for its type.
+pub fn decode_params(types: &[AbiType], data: &[u8]) -> Option<Vec<AbiValue>> {
+    types
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| decode_value(ty, data, i * WORD))
+        .collect()
+}
+
+/// Try to decode revert data as one of the given custom errors
+///
+/// Each signature's selector is compared against the first 4 bytes of
+/// `revert_data`; the first match is decoded using its parameter types.
+pub fn decode_custom_error(revert_data: &[u8], signatures: &[&'static str]) -> Option<CustomError> {
+    let (head, body) = split_selector(revert_data)?;
+
+    signatures.iter().find_map(|signature| {
+        let selector = selector(signature);
+        if selector != head {
+            return None;
+        }
+        let types = parse_signature_params(signature)?;
+        let args = decode_params(&types, body)?;
+        Some(CustomError {
+            signature,
+            selector,
+            args,
+        })
+    })
+}
+
+/// Decode revert data as `Error(string)`, `Panic(uint256)` or one of the
+/// given custom errors
+///
+/// The two standard errors are always recognized; custom errors are
+/// matched like in [`decode_custom_error`]. Returns `None` for empty revert
+/// data, an unknown selector or a payload that does not decode.
+pub fn decode_revert(revert_data: &[u8], custom_errors: &[&'static str]) -> Option<DecodedError> {
+    if let Some(error) =
+        decode_custom_error(revert_data, &[ERROR_STRING_SIGNATURE, PANIC_SIGNATURE])
+    {
+        return match error.args.as_slice() {
+            [AbiValue::String(message)] => Some(DecodedError::Message(message.clone())),
+            [AbiValue::Uint(code)] => Some(DecodedError::Panic(*code)),
+            _ => None,
+        };
+    }
+    decode_custom_error(revert_data, custom_errors).map(DecodedError::Custom)
+}
+
+/// Decode revert data as a [`RevertReason`]
+///
+/// Returns `None` if the data is shorter than a selector, e.g. for a bare
+/// `revert()`.
+pub fn decode_revert_reason(revert_data: &[u8]) -> Option<RevertReason> {
+    let (selector, data) = split_selector(revert_data)?;
+    Some(match decode_revert(revert_data, &[]) {
+        Some(DecodedError::Message(message)) => RevertReason::StandardError(message),
+        Some(DecodedError::Panic(code)) => RevertReason::PanicCode(code),
+        _ => RevertReason::CustomError {
+            selector,
+            data: data.to_vec(),
+        },
+    })
+}
+
+/// What a Solidity `Panic(uint256)` code means
+pub fn panic_description(code: U256) -> &'static str {
+    match u64::try_from(code) {
+        Ok(0x00) => "generic compiler panic",
+        Ok(0x01) => "assertion failed",
+        Ok(0x11) => "arithmetic overflow or underflow",
+        Ok(0x12) => "division or modulo by zero",
+        Ok(0x21) => "invalid enum value",
+        Ok(0x22) => "invalid storage byte array encoding",
+        Ok(0x31) => "pop on an empty array",
+        Ok(0x32) => "array index out of bounds",
+        Ok(0x41) => "out of memory",
+        Ok(0x51) => "call to an uninitialized function pointer",
+        _ => "unknown panic code",
+    }
+}
+
+/// Bit width of a field packed into a storage slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldWidth(pub usize);
+
+impl FieldWidth {
+    /// `address`
+    pub const ADDRESS: FieldWidth = FieldWidth(160);
+    /// `bool`
+    pub const BOOL: FieldWidth = FieldWidth(8);
+
+    /// `uint<bits>` or `int<bits>`
+    pub const fn bits(bits: usize) -> Self {
+        FieldWidth(bits)
+    }
+}
+
+/// Split a packed storage slot into its fields
+///
+/// Solidity packs consecutive fields into a slot starting from the
+/// low-order bits, so `layout` lists the fields in declaration order. Each
+/// field is returned as its raw unsigned bits; fields reaching past bit
+/// 256 are truncated.
+pub fn unpack_slot(value: U256, layout: &[FieldWidth]) -> Vec<U256> {
+    let mut shift = 0;
+    layout
+        .iter()
+        .map(|FieldWidth(bits)| {
+            let field = if shift >= 256 {
+                U256::ZERO
+            } else {
+                value >> shift
+            };
+            shift += bits;
+            if *bits >= 256 {
+                field
+            } else {
+                field & ((U256::from(1u64) << *bits) - U256::from(1u64))
+            }
+        })
+        .collect()
+}
+
+/// Split revert data into selector and payload
+fn split_selector(data: &[u8]) -> Option<([u8; 4], &[u8])> {
+    if data.len() < 4 {
+        return None;
+    }
+    let mut selector = [0u8; 4];
+    selector.copy_from_slice(&data[..4]);
+    Some((selector, &data[4..]))
+}
+
+/// Read the 32-byte word at `offset`
+fn read_word(data: &[u8], offset: usize) -> Option<&[u8]> {
+    data.get(offset..offset.checked_add(WORD)?)
+}
+
+/// Read a word as an in-bounds length or offset
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let value = U256::from_be_slice(read_word(data, offset)?);
+    if value > U256::from(data.len()) {
+        return None;
+    }
+    Some(value.to::<usize>())
+}
+
+/// Decode a single value whose head word is at `head` within `data`
+fn decode_value(ty: &AbiType, data: &[u8], head: usize) -> Option<AbiValue> {
+    if ty.is_dynamic() {
+        let offset = read_usize(data, head)?;
+        let len = read_usize(data, offset)?;
+        let start = offset + WORD;
+
+        return match ty {
+            AbiType::Bytes => Some(AbiValue::Bytes(data.get(start..start + len)?.to_vec())),
+            AbiType::String => {
+                let bytes = data.get(start..start + len)?;
+                Some(AbiValue::String(String::from_utf8(bytes.to_vec()).ok()?))
+            }
+            AbiType::Array(inner) => {
+                let types = vec![(**inner).clone(); len];
+                Some(AbiValue::Array(decode_params(&types, data.get(start..)?)?))
+            }
+            _ => None,
+        };
+    }
+
+    let word = read_word(data, head)?;
+    let value = U256::from_be_slice(word);
+
+    match ty {
+        AbiType::Uint(bits) => {
+            (*bits == 256 || value >> *bits == U256::ZERO).then_some(AbiValue::Uint(value))
+        }
+        AbiType::Int(bits) => {
+            let value = I256::from_raw(value);
+            // Sign-extended values must round-trip through the narrower width
+            let fits = *bits == 256 || {
+                let shift = 256 - *bits;
+                (value << shift).asr(shift) == value
+            };
+            fits.then_some(AbiValue::Int(value))
+        }
+        AbiType::Address => word[..12]
+            .iter()
+            .all(|b| *b == 0)
+            .then(|| AbiValue::Address(Address::from_slice(&word[12..]))),
+        AbiType::Bool => (value <= U256::from(1u64)).then(|| AbiValue::Bool(!value.is_zero())),
+        AbiType::FixedBytes(size) => word[*size..]
+            .iter()
+            .all(|b| *b == 0)
+            .then(|| AbiValue::FixedBytes(word[..*size].to_vec())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: u64) -> [u8; 32] {
+        U256::from(value).to_be_bytes()
+    }
+
+    #[test]
+    fn test_selector() {
+        // keccak256("transfer(address,uint256)")[..4]
+        assert_eq!(
+            selector("transfer(address,uint256)"),
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+    }
+
+    #[test]
+    fn test_unpack_slot() {
+        let owner = Address::repeat_byte(0xab);
+        let amount = U256::from(123_456_789u64);
+        let slot = (amount << 160) | U256::from_be_slice(owner.as_slice());
+
+        let fields = unpack_slot(slot, &[FieldWidth::ADDRESS, FieldWidth::bits(96)]);
+        assert_eq!(fields, vec![U256::from_be_slice(owner.as_slice()), amount]);
+
+        // Two uint128 halves
+        let fields = unpack_slot(U256::MAX, &[FieldWidth::bits(128), FieldWidth::bits(128)]);
+        assert_eq!(fields, vec![U256::MAX >> 128, U256::MAX >> 128]);
+    }
+
+    #[test]
+    fn test_parse_types() {
+        assert_eq!(AbiType::parse("uint"), Some(AbiType::Uint(256)));
+        assert_eq!(AbiType::parse("int8"), Some(AbiType::Int(8)));
+        assert_eq!(AbiType::parse("bytes32"), Some(AbiType::FixedBytes(32)));
+        assert_eq!(
+            AbiType::parse("address[]"),
+            Some(AbiType::Array(Box::new(AbiType::Address)))
+        );
+        assert_eq!(AbiType::parse("uint7"), None);
+        assert_eq!(AbiType::parse("bytes33"), None);
+    }
+
+    #[test]
+    fn test_decode_custom_error_static() {
+        let signature = "InsufficientBalance(uint256,uint256)";
+        let mut data = selector(signature).to_vec();
+        data.extend_from_slice(&word(100));
+        data.extend_from_slice(&word(250));
+
+        let err = decode_custom_error(&data, &["Unauthorized()", signature]).unwrap();
+
+        assert_eq!(err.signature, signature);
+        assert_eq!(err.selector, selector(signature));
+        assert_eq!(
+            err.args,
+            vec![
+                AbiValue::Uint(U256::from(100u64)),
+                AbiValue::Uint(U256::from(250u64))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_custom_error_dynamic() {
+        let signature = "Rejected(address,string)";
+        let who = Address::repeat_byte(0xAA);
+
+        let mut data = selector(signature).to_vec();
+        let mut addr_word = [0u8; 32];
+        addr_word[12..].copy_from_slice(who.as_slice());
+        data.extend_from_slice(&addr_word);
+        data.extend_from_slice(&word(64)); // offset of the string
+        data.extend_from_slice(&word(2)); // length
+        let mut text = [0u8; 32];
+        text[..2].copy_from_slice(b"no");
+        data.extend_from_slice(&text);
+
+        let err = decode_custom_error(&data, &[signature]).unwrap();
+        assert_eq!(
+            err.args,
+            vec![AbiValue::Address(who), AbiValue::String("no".into())]
+        );
+    }
+
+    #[test]
+    fn test_decode_custom_error_no_match() {
+        let mut data = selector("Unauthorized()").to_vec();
+        data.extend_from_slice(&word(1));
+
+        assert!(decode_custom_error(&data, &["Other(uint256)"]).is_none());
+        assert!(decode_custom_error(&[0x01, 0x02], &["Unauthorized()"]).is_none());
+    }
+
+    #[test]
+    fn test_decode_revert() {
+        let signature = "InsufficientBalance(uint256,uint256)";
+        let mut data = selector(signature).to_vec();
+        data.extend_from_slice(&word(100));
+        data.extend_from_slice(&word(250));
+
+        let Some(DecodedError::Custom(err)) = decode_revert(&data, &[signature]) else {
+            panic!("expected a custom error");
+        };
+        let [AbiValue::Uint(available), AbiValue::Uint(required)] = err.args.as_slice() else {
+            panic!("expected two uint256 fields");
+        };
+        assert_eq!(*available, U256::from(100u64));
+        assert_eq!(*required, U256::from(250u64));
+
+        // Error("low") without any custom errors supplied
+        let mut data = selector(ERROR_STRING_SIGNATURE).to_vec();
+        data.extend_from_slice(&word(32));
+        data.extend_from_slice(&word(3));
+        let mut text = [0u8; 32];
+        text[..3].copy_from_slice(b"low");
+        data.extend_from_slice(&text);
+        assert_eq!(
+            decode_revert(&data, &[]),
+            Some(DecodedError::Message("low".into()))
+        );
+
+        // Panic(0x11), as raised by checked arithmetic
+        let mut data = selector(PANIC_SIGNATURE).to_vec();
+        data.extend_from_slice(&word(0x11));
+        assert_eq!(
+            decode_revert(&data, &[]),
+            Some(DecodedError::Panic(U256::from(0x11u64)))
+        );
+        assert_eq!(
+            panic_description(U256::from(0x11u64)),
+            "arithmetic overflow or underflow"
+        );
+        assert_eq!(panic_description(U256::MAX), "unknown panic code");
+
+        assert_eq!(decode_revert(&[], &[signature]), None);
+    }
+
+    #[test]
+    fn test_decode_rejects_out_of_range() {
+        // 256 does not fit into uint8
+        assert!(decode_params(&[AbiType::Uint(8)], &word(256)).is_none());
+        // Truncated data
+        assert!(decode_params(&[AbiType::Uint(256)], &[0u8; 16]).is_none());
+        // -1 is a valid int8, +128 is not
+        assert_eq!(
+            decode_params(&[AbiType::Int(8)], &[0xff; 32]),
+            Some(vec![AbiValue::Int(I256::MINUS_ONE)])
+        );
+        assert!(decode_params(&[AbiType::Int(8)], &word(128)).is_none());
+    }
+}
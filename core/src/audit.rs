@@ -0,0 +1,55 @@
+//! Preimage audit trail for commitments
+//!
+//! An [`AuditTrail`] holds the exact bytes hashed into an
+//! [`ExecutionCommitment`]'s input hash, output hash and commitment, so an
+//! auditor can recompute each of them with any implementation of the hash
+//! function. It is recorded natively by `ShadowExecutor::execute_audited`
+//! and is never part of a proof.
+
+extern crate alloc;
+
+use crate::errors::Result;
+use crate::hashing::{Hasher, StateHashAlgo};
+use crate::input::ExecutionInput;
+use crate::output::{ExecutionCommitment, ExecutionOutput};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+/// Bytes hashed into each hash of an `ExecutionCommitment`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditTrail {
+    /// Hash function all preimages are hashed with
+    pub hash_algo: StateHashAlgo,
+    /// Bincode serialization of the `ExecutionInput`, hashed into `input_hash`
+    pub input_preimage: Vec<u8>,
+    /// Bincode serialization of the `ExecutionOutput`, hashed into `output_hash`
+    pub output_preimage: Vec<u8>,
+    /// Concatenation hashed into `commitment`; see `ExecutionCommitment`
+    pub commitment_preimage: Vec<u8>,
+}
+
+impl AuditTrail {
+    /// Record the preimages of `commitment`
+    ///
+    /// `input` must still hold its pre-state, as it did when hashed.
+    pub fn record(
+        input: &ExecutionInput,
+        output: &ExecutionOutput,
+        commitment: &ExecutionCommitment,
+    ) -> Result<Self> {
+        Ok(Self {
+            hash_algo: commitment.hash_algo,
+            input_preimage: bincode::serialize(input)?,
+            output_preimage: bincode::serialize(output)?,
+            commitment_preimage: commitment.preimage(),
+        })
+    }
+
+    /// Check that re-hashing every preimage reproduces `commitment`'s hashes
+    pub fn verify(&self, commitment: &ExecutionCommitment) -> bool {
+        self.hash_algo == commitment.hash_algo
+            && self.hash_algo.hash(&self.input_preimage) == commitment.input_hash
+            && self.hash_algo.hash(&self.output_preimage) == commitment.output_hash
+            && self.hash_algo.hash(&self.commitment_preimage) == commitment.commitment
+    }
+}
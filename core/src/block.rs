@@ -0,0 +1,475 @@
+//! Block-level execution for Shadow-EVM
+//!
+//! Executes several transactions on one running state and commits to the
+//! block as a whole. The block commitment is defined as:
+//!
+//! ```text
+//! input_hash      = hash_struct((block, txs, pre_state))
+//! output_hash     = keccak256(output_1.hash() || ... || output_n.hash())
+//! pre_state_root  = pre_state.compute_state_root()
+//! post_state_root = root of the last output's post-state (pre-state root if empty)
+//! ```
+//!
+//! The block number and timestamp are committed as in a single execution.
+//!
+//! `txs` are hashed as supplied; nonces assigned during execution are not
+//! part of the input hash.
+//!
+//! A [`BlockExecutor`] can advance time between transactions, recorded in
+//! `BatchExecutionInput::tx_contexts`. If it did, the `(number, timestamp)`
+//! each transaction ran at is appended to the hashed tuple:
+//! `hash_struct((block, txs, pre_state, tx_contexts))`. The committed block
+//! context is then the range executed: it opens at the starting `block` and
+//! closes at `ExecutionCommitment::last_block_context`.
+
+use crate::errors::{Result, ShadowEvmError};
+use crate::evm::ShadowExecutor;
+use crate::hashing::{hash_concat, hash_struct};
+use crate::input::{BlockEnv, TxInput};
+use crate::output::{ExecutionCommitment, ExecutionOutput};
+use crate::state::InMemoryDB;
+use crate::types::{BlockNumber, Hash, Timestamp};
+use alloc::format;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+extern crate alloc;
+
+/// Transactions to run in order on one shared pre-state
+///
+/// The input of `ShadowExecutor::execute_batch` and of the batch-execution
+/// guest. Each transaction sees the post-state of the one before it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchExecutionInput {
+    /// Block environment shared by all transactions
+    pub block: BlockEnv,
+    /// Transactions, in execution order
+    pub txs: Vec<TxInput>,
+    /// State before the first transaction
+    pub pre_state: InMemoryDB,
+    /// `(number, timestamp)` each transaction runs at, in order
+    ///
+    /// Empty to run every transaction in `block`'s context. Otherwise one
+    /// per transaction, never moving backwards and starting no earlier
+    /// than `block`; see `BlockExecutor::batch_input`.
+    #[serde(default)]
+    pub tx_contexts: Vec<(BlockNumber, Timestamp)>,
+}
+
+impl BatchExecutionInput {
+    /// Batch `txs` on top of `pre_state`
+    pub fn new(block: BlockEnv, txs: Vec<TxInput>, pre_state: InMemoryDB) -> Self {
+        Self {
+            block,
+            txs,
+            pre_state,
+            tx_contexts: Vec::new(),
+        }
+    }
+
+    /// Run each transaction at its own `(number, timestamp)`
+    pub fn with_tx_contexts(mut self, tx_contexts: Vec<(BlockNumber, Timestamp)>) -> Self {
+        self.tx_contexts = tx_contexts;
+        self
+    }
+
+    /// Hash of the batch, as committed in `input_hash`
+    ///
+    /// The contexts are only hashed if one differs from `block`'s, so a
+    /// batch that never advances hashes like `ShadowExecutor::execute_block`.
+    pub fn hash(&self) -> Hash {
+        if self.last_context().is_none() {
+            block_input_hash(&self.block, &self.txs, &self.pre_state)
+        } else {
+            hash_struct(&(&self.block, &self.txs, &self.pre_state, &self.tx_contexts))
+        }
+    }
+
+    /// Check that `tx_contexts` is empty or gives one valid context per
+    /// transaction
+    pub fn validate_contexts(&self) -> Result<()> {
+        if self.tx_contexts.is_empty() {
+            return Ok(());
+        }
+        if self.tx_contexts.len() != self.txs.len() {
+            return Err(ShadowEvmError::InvalidTransaction(format!(
+                "{} block contexts for {} transactions",
+                self.tx_contexts.len(),
+                self.txs.len()
+            )));
+        }
+        let mut previous = (self.block.number, self.block.timestamp);
+        for &(number, timestamp) in &self.tx_contexts {
+            if number < previous.0 || timestamp < previous.1 {
+                return Err(ShadowEvmError::InvalidTransaction(format!(
+                    "block context ({number}, {timestamp}) is before ({}, {})",
+                    previous.0, previous.1
+                )));
+            }
+            previous = (number, timestamp);
+        }
+        Ok(())
+    }
+
+    /// Block environment transaction `index` runs in
+    pub(crate) fn tx_block(&self, index: usize) -> BlockEnv {
+        let mut block = self.block.clone();
+        if let Some(&(number, timestamp)) = self.tx_contexts.get(index) {
+            block.number = number;
+            block.timestamp = timestamp;
+        }
+        block
+    }
+
+    /// Context of the last transaction, if it differs from `block`'s
+    pub(crate) fn last_context(&self) -> Option<(BlockNumber, Timestamp)> {
+        let start = (self.block.number, self.block.timestamp);
+        let last = *self.tx_contexts.last()?;
+        self.tx_contexts
+            .iter()
+            .any(|context| *context != start)
+            .then_some(last)
+    }
+
+    /// Execute the batch; see `ShadowExecutor::execute_batch`
+    pub fn execute(self) -> Result<(Vec<ExecutionOutput>, ExecutionCommitment)> {
+        ShadowExecutor::execute_batch(self)
+    }
+}
+
+/// Incrementally executes a block, one transaction at a time
+///
+/// Useful for sequencers that build a block transaction by transaction:
+/// each `push_tx` only executes the new transaction on the running state.
+/// `finalize` yields the same commitment as `ShadowExecutor::execute_block`
+/// over the same transactions.
+///
+/// # Example
+/// ```rust,ignore
+/// let mut executor = BlockExecutor::new(block, pre_state);
+/// executor.push_tx(tx1)?;
+/// executor.push_tx(tx2)?;
+/// let (outputs, commitment) = executor.finalize();
+/// ```
+///
+/// For time-dependent contracts, `advance_time` and `advance_block` move
+/// the block context of the transactions pushed after them.
+#[derive(Debug, Clone)]
+pub struct BlockExecutor {
+    block: BlockEnv,
+    /// Block context of the next transaction
+    current: BlockEnv,
+    pre_state: InMemoryDB,
+    state: InMemoryDB,
+    txs: Vec<TxInput>,
+    /// `(number, timestamp)` each pushed transaction ran at
+    tx_contexts: Vec<(BlockNumber, Timestamp)>,
+    outputs: Vec<ExecutionOutput>,
+}
+
+impl BlockExecutor {
+    /// Start a block on top of `pre_state`
+    pub fn new(block: BlockEnv, pre_state: InMemoryDB) -> Self {
+        Self {
+            current: block.clone(),
+            block,
+            state: pre_state.clone(),
+            pre_state,
+            txs: Vec::new(),
+            tx_contexts: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Execute one transaction on the running state
+    ///
+    /// On error the transaction is not added to the block and the running
+    /// state is left unchanged.
+    pub fn push_tx(&mut self, tx: TxInput) -> Result<ExecutionOutput> {
+        if self.txs.is_empty() {
+            self.pre_state.validate_code_hashes()?;
+        }
+
+        let output =
+            ShadowExecutor::execute_sequenced(&self.current, tx.clone(), self.state.clone())?;
+
+        self.state = output.post_state.clone();
+        self.txs.push(tx);
+        self.tx_contexts
+            .push((self.current.number, self.current.timestamp));
+        self.outputs.push(output.clone());
+
+        Ok(output)
+    }
+
+    /// Move the timestamp of subsequent transactions `seconds` ahead
+    pub fn advance_time(&mut self, seconds: u64) {
+        self.current.timestamp = self.current.timestamp.saturating_add(seconds);
+    }
+
+    /// Move the block number of subsequent transactions one ahead
+    ///
+    /// The timestamp is unchanged; call `advance_time` as well to model a
+    /// realistic block interval.
+    pub fn advance_block(&mut self) {
+        self.current.number = self.current.number.saturating_add(1);
+    }
+
+    /// Block context the next transaction executes in
+    pub fn current_block(&self) -> &BlockEnv {
+        &self.current
+    }
+
+    /// Current state after all pushed transactions
+    pub fn state(&self) -> &InMemoryDB {
+        &self.state
+    }
+
+    /// Number of transactions pushed so far
+    pub fn tx_count(&self) -> usize {
+        self.txs.len()
+    }
+
+    /// Input that re-executes the block pushed so far
+    ///
+    /// Carries the context every transaction ran at, so
+    /// `ShadowExecutor::execute_batch`, and the batch-execution guest,
+    /// reproduce the commitment of `finalize`.
+    pub fn batch_input(&self) -> BatchExecutionInput {
+        BatchExecutionInput::new(self.block.clone(), self.txs.clone(), self.pre_state.clone())
+            .with_tx_contexts(self.tx_contexts.clone())
+    }
+
+    /// Close the block and commit to it
+    ///
+    /// The commitment is that of `batch_input`.
+    pub fn finalize(self) -> (Vec<ExecutionOutput>, ExecutionCommitment) {
+        let input = BatchExecutionInput::new(self.block, self.txs, self.pre_state)
+            .with_tx_contexts(self.tx_contexts);
+        let commitment = block_commitment(
+            &input.block,
+            input.hash(),
+            input.pre_state.compute_state_root(),
+            &self.outputs,
+            input.last_context(),
+        );
+        (self.outputs, commitment)
+    }
+}
+
+/// Hash of a block execution's input
+fn block_input_hash(block: &BlockEnv, txs: &[TxInput], pre_state: &InMemoryDB) -> Hash {
+    hash_struct(&(block, txs, pre_state))
+}
+
+/// Commitment over a block's outputs
+///
+/// `last_context` closes the range of block contexts executed, if the
+/// block advanced past `block`.
+pub(crate) fn block_commitment(
+    block: &BlockEnv,
+    input_hash: Hash,
+    pre_state_root: Hash,
+    outputs: &[ExecutionOutput],
+    last_context: Option<(BlockNumber, Timestamp)>,
+) -> ExecutionCommitment {
+    let output_hashes: Vec<Hash> = outputs.iter().map(ExecutionOutput::hash).collect();
+    let parts: Vec<&[u8]> = output_hashes.iter().map(|hash| hash.as_slice()).collect();
+    let output_hash = hash_concat(&parts);
+
+    let post_state_root = outputs
+        .last()
+        .map_or(pre_state_root, ExecutionOutput::post_state_root);
+
+    let commitment =
+        ExecutionCommitment::new(input_hash, output_hash, pre_state_root, post_state_root)
+            .with_block_context(block.number, block.timestamp)
+            .with_chain_id(block.chain_id);
+    match last_context {
+        Some((number, timestamp)) => commitment.with_last_block_context(number, timestamp),
+        None => commitment,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AccountState;
+    use crate::types::{Address, U256};
+
+    fn setup() -> (Address, InMemoryDB) {
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        (sender, state)
+    }
+
+    #[test]
+    fn test_incremental_matches_one_shot() {
+        let (sender, pre_state) = setup();
+        let txs: Vec<TxInput> = (0..3u8)
+            .map(|i| TxInput::transfer(sender, Address::repeat_byte(0x10 + i), U256::from(1000u64)))
+            .collect();
+
+        let mut executor = BlockExecutor::new(BlockEnv::default(), pre_state.clone());
+        for tx in &txs {
+            let output = executor.push_tx(tx.clone()).unwrap();
+            assert!(output.is_success());
+        }
+        assert_eq!(executor.tx_count(), 3);
+        assert_eq!(executor.state().get_account(&sender).unwrap().nonce, 3);
+
+        let (outputs, commitment) = executor.finalize();
+        let (expected_outputs, expected) =
+            ShadowExecutor::execute_block(BlockEnv::default(), txs, pre_state).unwrap();
+
+        assert_eq!(commitment, expected);
+        assert_eq!(outputs.len(), expected_outputs.len());
+    }
+
+    #[test]
+    fn test_failed_push_leaves_state_unchanged() {
+        let (sender, pre_state) = setup();
+        let mut executor = BlockExecutor::new(BlockEnv::default(), pre_state);
+
+        executor
+            .push_tx(TxInput::transfer(
+                sender,
+                Address::repeat_byte(0x02),
+                U256::from(1u64),
+            ))
+            .unwrap();
+        let state_before = executor.state().clone();
+
+        // More value than the sender owns fails validation
+        let too_much = TxInput::transfer(sender, Address::repeat_byte(0x02), U256::MAX);
+        assert!(executor.push_tx(too_much).is_err());
+
+        assert_eq!(executor.tx_count(), 1);
+        assert_eq!(
+            executor.state().changed_accounts(&state_before),
+            Vec::<Address>::new()
+        );
+    }
+
+    #[test]
+    fn test_advance_time() {
+        let (sender, mut pre_state) = setup();
+        let clock = Address::repeat_byte(0xcc);
+        // Returns block.timestamp and block.number as two words:
+        // TIMESTAMP, PUSH1 0, MSTORE, NUMBER, PUSH1 32, MSTORE,
+        // PUSH1 64, PUSH1 0, RETURN
+        pre_state.insert_account(
+            clock,
+            AccountState::new_contract(
+                vec![
+                    0x42, 0x60, 0x00, 0x52, 0x43, 0x60, 0x20, 0x52, 0x60, 0x40, 0x60, 0x00, 0xf3,
+                ],
+                U256::ZERO,
+            ),
+        );
+        let read = |output: &ExecutionOutput, word: usize| {
+            U256::from_be_slice(&output.return_data[word * 32..(word + 1) * 32])
+        };
+        let call = TxInput::call(sender, clock, vec![]);
+
+        let mut executor = BlockExecutor::new(BlockEnv::default(), pre_state.clone());
+        let before = executor.push_tx(call.clone()).unwrap();
+        executor.advance_time(100);
+        let later = executor.push_tx(call.clone()).unwrap();
+        executor.advance_block();
+        let next_block = executor.push_tx(call.clone()).unwrap();
+
+        assert_eq!(read(&later, 0) - read(&before, 0), U256::from(100u64));
+        assert_eq!(read(&later, 1), read(&before, 1));
+        assert_eq!(read(&next_block, 0), read(&later, 0));
+        assert_eq!(read(&next_block, 1) - read(&later, 1), U256::from(1u64));
+
+        // The advances are bound by the commitment, which covers the range
+        // of contexts executed
+        let batch = executor.batch_input();
+        let (_, commitment) = executor.finalize();
+        let (_, unadvanced) =
+            ShadowExecutor::execute_block(BlockEnv::default(), vec![call; 3], pre_state).unwrap();
+        assert_ne!(commitment.input_hash, unadvanced.input_hash);
+        assert_eq!(commitment.timestamp, unadvanced.timestamp);
+        assert_eq!(unadvanced.last_block_context, None);
+        let start = BlockEnv::default();
+        assert_eq!(
+            commitment.last_block_context,
+            Some((start.number + 1, start.timestamp + 100))
+        );
+
+        // A guest re-executing the recorded input reproduces the commitment
+        let json = serde_json::to_string(&batch).unwrap();
+        let batch: BatchExecutionInput = serde_json::from_str(&json).unwrap();
+        let (outputs, replayed) = batch.execute().unwrap();
+        assert_eq!(replayed, commitment);
+        assert_eq!(read(&outputs[2], 1), read(&next_block, 1));
+    }
+
+    #[test]
+    fn test_invalid_tx_contexts() {
+        let (sender, pre_state) = setup();
+        let block = BlockEnv::default();
+        let txs = vec![
+            TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1u64)),
+            TxInput::transfer(sender, Address::repeat_byte(0x03), U256::from(1u64)),
+        ];
+        let batch = BatchExecutionInput::new(block.clone(), txs, pre_state);
+
+        let missing = batch
+            .clone()
+            .with_tx_contexts(vec![(block.number, block.timestamp)]);
+        let backwards = batch.with_tx_contexts(vec![
+            (block.number, block.timestamp + 10),
+            (block.number, block.timestamp + 5),
+        ]);
+        for batch in [missing, backwards] {
+            assert!(matches!(
+                batch.execute(),
+                Err(ShadowEvmError::InvalidTransaction(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_batch_matches_block() {
+        let (sender, pre_state) = setup();
+        let txs: Vec<TxInput> = (0..3u8)
+            .map(|i| TxInput::transfer(sender, Address::repeat_byte(0x10 + i), U256::from(1000u64)))
+            .collect();
+        let batch = BatchExecutionInput::new(BlockEnv::default(), txs.clone(), pre_state.clone());
+        let input_hash = batch.hash();
+
+        let json = serde_json::to_string(&batch).unwrap();
+        let batch: BatchExecutionInput = serde_json::from_str(&json).unwrap();
+        let (outputs, commitment) = batch.execute().unwrap();
+
+        // Nonces are assigned from the running state
+        assert!(outputs.iter().all(ExecutionOutput::is_success));
+        assert_eq!(outputs[2].post_state.get_account(&sender).unwrap().nonce, 3);
+        assert_eq!(commitment.input_hash, input_hash);
+        assert_eq!(commitment.post_state_root, outputs[2].post_state_root());
+
+        let (_, expected) =
+            ShadowExecutor::execute_block(BlockEnv::default(), txs, pre_state).unwrap();
+        assert_eq!(commitment, expected);
+    }
+
+    #[test]
+    fn test_empty_block() {
+        let (_, pre_state) = setup();
+        let root = pre_state.compute_state_root();
+
+        let (outputs, commitment) = BlockExecutor::new(BlockEnv::default(), pre_state).finalize();
+
+        assert!(outputs.is_empty());
+        assert_eq!(commitment.pre_state_root, root);
+        assert_eq!(commitment.post_state_root, root);
+    }
+}
@@ -0,0 +1,130 @@
+//! Commitment chains for sequential replay
+//!
+//! Links the commitments of consecutive executions into a single hash, so a
+//! verifier holding one chain root can check an entire transaction history:
+//!
+//! ```text
+//! root_0 = 0x00..00
+//! root_n = keccak256(root_{n-1} || commitment_n)
+//! ```
+//!
+//! The same fold is cheap to recompute on-chain over a `bytes32[]`.
+
+use crate::hashing::compute_commitment;
+use crate::output::ExecutionCommitment;
+use crate::types::Hash;
+use serde::{Deserialize, Serialize};
+
+/// Running hash over a sequence of execution commitments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentChain {
+    /// Current chain root
+    pub root: Hash,
+    /// Number of commitments folded into the root
+    pub length: u64,
+}
+
+impl CommitmentChain {
+    /// Create an empty chain (root is the zero hash)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append one commitment and return the new root
+    pub fn extend(&mut self, commitment: &ExecutionCommitment) -> Hash {
+        self.root = compute_commitment(&self.root, &commitment.commitment);
+        self.length += 1;
+        self.root
+    }
+
+    /// Append several commitments in order and return the final root
+    pub fn extend_batch(&mut self, commitments: &[ExecutionCommitment]) -> Hash {
+        for commitment in commitments {
+            self.extend(commitment);
+        }
+        self.root
+    }
+
+    /// Build a chain from genesis over `commitments`
+    pub fn from_commitments(commitments: &[ExecutionCommitment]) -> Self {
+        let mut chain = Self::new();
+        chain.extend_batch(commitments);
+        chain
+    }
+}
+
+/// Check that `chain` is exactly the chain of `commitments` from genesis
+///
+/// Recomputes the root from scratch; both root and length must match.
+pub fn verify_commitment_chain(
+    chain: &CommitmentChain,
+    commitments: &[ExecutionCommitment],
+) -> bool {
+    CommitmentChain::from_commitments(commitments) == *chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    extern crate alloc;
+
+    fn commitments(n: u8) -> Vec<ExecutionCommitment> {
+        (0..n)
+            .map(|i| {
+                ExecutionCommitment::new(
+                    Hash::repeat_byte(i),
+                    Hash::repeat_byte(i.wrapping_add(100)),
+                    Hash::ZERO,
+                    Hash::ZERO,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_extend_batch_matches_extend() {
+        let commitments = commitments(4);
+
+        let mut one_by_one = CommitmentChain::new();
+        for commitment in &commitments {
+            one_by_one.extend(commitment);
+        }
+
+        let mut batched = CommitmentChain::new();
+        let root = batched.extend_batch(&commitments);
+
+        assert_eq!(root, one_by_one.root);
+        assert_eq!(batched, one_by_one);
+        assert_eq!(batched.length, 4);
+    }
+
+    #[test]
+    fn test_root_definition() {
+        let commitments = commitments(2);
+        let chain = CommitmentChain::from_commitments(&commitments);
+
+        let root_1 = compute_commitment(&Hash::ZERO, &commitments[0].commitment);
+        let root_2 = compute_commitment(&root_1, &commitments[1].commitment);
+        assert_eq!(chain.root, root_2);
+    }
+
+    #[test]
+    fn test_verify_commitment_chain() {
+        let commitments = commitments(3);
+        let chain = CommitmentChain::from_commitments(&commitments);
+
+        assert!(verify_commitment_chain(&chain, &commitments));
+
+        // Reordered history
+        let mut swapped = commitments.clone();
+        swapped.swap(0, 1);
+        assert!(!verify_commitment_chain(&chain, &swapped));
+
+        // Truncated history
+        assert!(!verify_commitment_chain(&chain, &commitments[..2]));
+
+        assert!(verify_commitment_chain(&CommitmentChain::new(), &[]));
+    }
+}
@@ -0,0 +1,111 @@
+//! Size-aware binary encoding for Shadow-EVM
+//!
+//! Small payloads are encoded with bincode, large ones (big pre-states)
+//! with CBOR. Every encoded payload starts with a 4-byte magic prefix
+//! naming its format, so decoding never has to guess.
+
+use crate::errors::{Result, ShadowEvmError};
+use alloc::format;
+use alloc::vec::Vec;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+extern crate alloc;
+
+/// Magic prefix of bincode-encoded payloads
+pub const BINCODE_MAGIC: [u8; 4] = *b"SEB1";
+
+/// Magic prefix of CBOR-encoded payloads
+pub const CBOR_MAGIC: [u8; 4] = *b"SEC1";
+
+/// Estimated size above which `serialize_auto` switches to CBOR
+pub const CBOR_THRESHOLD_BYTES: usize = 1_000_000;
+
+/// Encode a value as CBOR (without magic prefix)
+pub fn to_cbor<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes)
+        .map_err(|e| ShadowEvmError::SerializationError(format!("{:?}", e)))?;
+    Ok(bytes)
+}
+
+/// Decode a value from CBOR (without magic prefix)
+pub fn from_cbor<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+    ciborium::from_reader(data).map_err(|e| ShadowEvmError::SerializationError(format!("{:?}", e)))
+}
+
+/// Estimate the encoded size of a value
+///
+/// Uses the exact bincode size, which is cheap to compute without
+/// allocating the encoding itself.
+pub fn estimated_serialized_bytes<T: Serialize>(value: &T) -> usize {
+    bincode::serialized_size(value).map_or(usize::MAX, |size| size as usize)
+}
+
+/// Encode a value, choosing the format by its estimated size
+///
+/// Values estimated above `CBOR_THRESHOLD_BYTES` are encoded as CBOR,
+/// everything else as bincode. The result carries a magic prefix.
+pub fn serialize_auto<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let (magic, body) = if estimated_serialized_bytes(value) > CBOR_THRESHOLD_BYTES {
+        (CBOR_MAGIC, to_cbor(value)?)
+    } else {
+        (BINCODE_MAGIC, bincode::serialize(value)?)
+    };
+
+    let mut bytes = Vec::with_capacity(magic.len() + body.len());
+    bytes.extend_from_slice(&magic);
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+/// Decode a value produced by `serialize_auto`
+pub fn deserialize_auto<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+    if data.len() < 4 {
+        return Err(ShadowEvmError::SerializationError(
+            "payload too short for format prefix".into(),
+        ));
+    }
+
+    let (magic, body) = data.split_at(4);
+    if magic == BINCODE_MAGIC {
+        Ok(bincode::deserialize(body)?)
+    } else if magic == CBOR_MAGIC {
+        from_cbor(body)
+    } else {
+        Err(ShadowEvmError::SerializationError(format!(
+            "unknown format prefix {:02x?}",
+            magic
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_small_values_use_bincode() {
+        let value = vec![1u64, 2, 3];
+        let bytes = serialize_auto(&value).unwrap();
+
+        assert_eq!(&bytes[..4], &BINCODE_MAGIC);
+        assert_eq!(deserialize_auto::<Vec<u64>>(&bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn test_large_values_use_cbor() {
+        let value = vec![0xabu8; CBOR_THRESHOLD_BYTES + 1];
+        let bytes = serialize_auto(&value).unwrap();
+
+        assert_eq!(&bytes[..4], &CBOR_MAGIC);
+        assert_eq!(deserialize_auto::<Vec<u8>>(&bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn test_unknown_prefix_rejected() {
+        assert!(deserialize_auto::<u64>(b"XXXX\x01").is_err());
+        assert!(deserialize_auto::<u64>(b"SE").is_err());
+    }
+}
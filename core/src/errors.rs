@@ -5,7 +5,7 @@ Provides comprehensive error handling for all execution scenarios.
 
 extern crate alloc;
 
-use crate::types::{Address, Hash};
+use crate::types::{Address, Hash, Opcode, U256};
 use alloc::string::{String, ToString};
 use thiserror::Error;
 
@@ -47,6 +47,90 @@ pub enum ShadowEvmError {
     /// Database error
     #[error("database error: {0}")]
     DatabaseError(String),
+
+    /// Account references code by hash that is not present in the state
+    #[error("missing code for hash {code_hash}")]
+    MissingCode { code_hash: Hash },
+
+    /// Execution emitted more logs or log data than allowed
+    #[error(
+        "log budget exceeded: {logs} logs / {data_bytes} bytes (limit {limit_logs} logs / {limit_bytes} bytes)"
+    )]
+    LogBudgetExceeded {
+        logs: usize,
+        data_bytes: usize,
+        limit_logs: usize,
+        limit_bytes: usize,
+    },
+
+    /// An account outside the declared access set was modified
+    #[error("account {address} changed outside the access set")]
+    ChangeOutsideAccessSet { address: Address },
+
+    /// Execution would use more gas than the caller's budget allows
+    #[error("gas budget exceeded: execution needs more than {max_gas} gas")]
+    GasBudgetExceeded { max_gas: u64 },
+
+    /// Total ether changed by more than the burned base fee
+    #[error("ether not conserved: pre total {pre_total}, post total {post_total}, burned fee {burned_fee}")]
+    EthConservationViolation {
+        pre_total: U256,
+        post_total: U256,
+        burned_fee: U256,
+    },
+
+    /// Execution created more accounts or storage slots than allowed
+    #[error("state budget exceeded: {new_accounts} new accounts, {new_slots} new storage slots")]
+    StateBudgetExceeded {
+        new_accounts: usize,
+        new_slots: usize,
+    },
+
+    /// A state proof does not match the root it is applied to
+    #[error("invalid state proof for account {address}")]
+    InvalidStateProof { address: Address },
+
+    /// Executing the same input gave different results under two root modes
+    #[error("execution result differs under state root mode {mode}")]
+    RootModeDivergence { mode: &'static str },
+
+    /// Two state root modes gave the same root for one state
+    #[error("state root modes {mode} and {other} give the same root")]
+    RootModeCollision {
+        mode: &'static str,
+        other: &'static str,
+    },
+
+    /// A required field is missing from externally supplied data
+    #[error("missing required field: {0}")]
+    MissingField(&'static str),
+
+    /// The sender has code, which EIP-3607 forbids
+    #[error("sender {address} has code (EIP-3607)")]
+    SenderHasCode { address: Address },
+
+    /// revm reported an account state that no execution can produce
+    #[error("invalid state update for account {address}: {reason}")]
+    InvalidStateUpdate {
+        address: Address,
+        reason: &'static str,
+    },
+
+    /// A frame's memory grew past the caller's cap
+    #[error("memory limit exceeded: {peak_words} words (limit {max_words})")]
+    MemoryLimitExceeded { peak_words: usize, max_words: usize },
+
+    /// Execution reached an opcode the caller forbade
+    #[error("forbidden opcode {0}")]
+    ForbiddenOpcode(Opcode),
+
+    /// A Merkle Patricia Trie proof is malformed or does not match its root
+    #[error("invalid trie proof: {0}")]
+    InvalidTrieProof(&'static str),
+
+    /// A state snapshot does not exist (any more)
+    #[error("unknown snapshot {0}")]
+    UnknownSnapshot(u64),
 }
 
 /// Result type for Shadow-EVM operations
@@ -5,15 +5,44 @@
 
 extern crate alloc;
 
+use crate::audit::AuditTrail;
+use crate::block::{block_commitment, BatchExecutionInput};
 use crate::errors::{Result, ShadowEvmError};
-use crate::input::{BlockEnv, ExecutionInput, TxInput};
-use crate::output::{ExecutionCommitment, ExecutionOutput, Log};
+use crate::hashing::keccak256;
+use crate::input::{BlockEnv, CallOptions, ExecutionInput, TxInput};
+use crate::invariants::check_eth_conservation;
+use crate::limits::{self, ExecutionLimits, MemoryMeter};
+use crate::output::{ExecutionCommitment, ExecutionOutput, ExecutionWarning, HaltReason, Log};
+use crate::precompile::{self, CustomPrecompile};
+use crate::preflight::{AccessSet, PreflightDB};
+use crate::sandbox::OpcodeFilter;
+use crate::spec::Hardfork;
 use crate::state::{AccountState, InMemoryDB};
-use crate::types::U256;
+use crate::trace::{DebugTrace, GasProfiler, Tracer, TracerInspector};
+use crate::types::{Address, Gas, Opcode, U256};
+use alloc::boxed::Box;
 use alloc::format;
 use alloc::vec::Vec;
-use revm::primitives::{BlockEnv as RevmBlockEnv, CfgEnv, SpecId, TxEnv, TxKind};
-use revm::Evm;
+use revm::inspectors::NoOpInspector;
+use revm::interpreter::{
+    CallInputs, CallOutcome, CreateInputs, CreateOutcome, EOFCreateInputs, Interpreter,
+};
+use revm::primitives::{
+    AccessListItem, BlockEnv as RevmBlockEnv, CfgEnv, EVMError, InvalidTransaction, Log as RevmLog,
+    ResultAndState, SpecId, TxEnv, TxKind, KECCAK_EMPTY,
+};
+use revm::{inspector_handle_register, Database, Evm, EvmContext, Inspector};
+
+/// Most simulations `ShadowExecutor::estimate_gas` runs while searching
+///
+/// Enough to find the exact limit under a block gas limit of up to 2^31.
+pub const MAX_ESTIMATE_ITERATIONS: usize = 32;
+
+/// Margin `ShadowExecutor::estimate_gas` adds to the lowest passing limit
+///
+/// Covers small state changes between estimating and executing, such as a
+/// storage slot another transaction sets first.
+pub const ESTIMATE_GAS_BUFFER_PERCENT: u64 = 5;
 
 /// Shadow-EVM Executor
 ///
@@ -35,8 +64,11 @@ impl ShadowExecutor {
     /// * `input` - Complete execution input including state, block, and transaction
     ///
     /// # Returns
-    /// * `Ok((output, commitment))` - Execution output and ZK commitment
-    /// * `Err(e)` - Execution error
+    /// * `Ok((output, commitment))` - Execution output and ZK commitment.
+    ///   Reverts and halts are results too: their status is part of the
+    ///   committed output, so "this call reverts" can be proven.
+    /// * `Err(e)` - The transaction could not be executed at all (invalid
+    ///   input, nonce or balance)
     ///
     /// # Example
     /// ```rust,ignore
@@ -44,29 +76,202 @@ impl ShadowExecutor {
     /// let (output, commitment) = ShadowExecutor::execute(input)?;
     /// ```
     pub fn execute(input: ExecutionInput) -> Result<(ExecutionOutput, ExecutionCommitment)> {
+        let (output, commitment, _) =
+            Self::execute_inspected(input, NoOpInspector, false, &[], |_| Ok(()))?;
+        Ok((output, commitment))
+    }
+
+    /// Execute with custom precompiles registered
+    ///
+    /// With no precompiles this is `execute`. The precompiles' native code
+    /// is not part of the input, so no commitment is returned; see
+    /// [`crate::precompile`].
+    pub fn execute_with_precompiles(
+        input: ExecutionInput,
+        precompiles: &[CustomPrecompile],
+    ) -> Result<ExecutionOutput> {
+        let (output, _, _) =
+            Self::execute_inspected(input, NoOpInspector, false, precompiles, |_| Ok(()))?;
+        Ok(output)
+    }
+
+    /// Execute with `inspector` attached and commit to the result
+    ///
+    /// The pipeline behind `execute` and each of its variants: validate,
+    /// hash, run, apply the result and commit. `check` sees the inspector
+    /// after the run and can reject the execution before it is applied.
+    /// Without `inspect` the inspector is not attached; see
+    /// [`Self::run_evm`].
+    fn execute_inspected<I: Inspector<InMemoryDB>>(
+        mut input: ExecutionInput,
+        inspector: I,
+        inspect: bool,
+        precompiles: &[CustomPrecompile],
+        check: impl FnOnce(&I) -> Result<()>,
+    ) -> Result<(ExecutionOutput, ExecutionCommitment, I)> {
+        input.validate_semantics()?;
+
         let input_hash = input.hash();
         let pre_state_root = input.pre_state_root();
+        let caller = input.tx.caller;
+        let caller_nonce = input.pre_state.get_account(&caller).map(|acc| acc.nonce);
+
+        let run = Self::run_evm(
+            input.block.clone(),
+            &input.tx,
+            &input.pre_state,
+            inspector,
+            inspect,
+            &input.options,
+            precompiles,
+        )?;
+        check(&run.inspector)?;
+
+        let pre_state = core::mem::take(&mut input.pre_state);
+        let mut output = Self::finish_tx(
+            &input.block,
+            &input.tx,
+            pre_state,
+            run.result,
+            run.halt_reason,
+        )?;
+        if !input.options.increment_nonce {
+            Self::restore_nonce(&mut output.post_state, caller, caller_nonce);
+        }
+
+        // Create cryptographic commitment
+        let commitment =
+            ExecutionCommitment::from_hashed_execution(&input, input_hash, pre_state_root, &output);
 
-        // Build and configure revm
-        let mut evm = Self::build_evm(input.block, input.tx.clone(), input.pre_state.clone())?;
+        Ok((output, commitment, run.inspector))
+    }
 
-        // Execute transaction using transact()
-        let result = evm
-            .transact()
-            .map_err(|e| ShadowEvmError::ExecutionHalted(format!("{:?}", e)))?;
+    /// Execute unrelated inputs in parallel
+    ///
+    /// Each input runs on its own pre-state exactly as with `execute`, so
+    /// one failing does not affect the others. Results are in input order.
+    #[cfg(feature = "std")]
+    pub fn execute_many(
+        inputs: Vec<ExecutionInput>,
+    ) -> Vec<Result<(ExecutionOutput, ExecutionCommitment)>> {
+        use rayon::prelude::*;
+
+        inputs.into_par_iter().map(Self::execute).collect()
+    }
 
-        // Apply state changes to our database
-        let mut post_state = input.pre_state;
-        Self::apply_state_changes(&mut post_state, &result)?;
+    /// Execute transactions one after another on a shared state
+    ///
+    /// Each transaction sees the post-state of the previous one. Nonces are
+    /// assigned from the running state, so the `nonce` field of each `TxInput`
+    /// is ignored. Returns one output per transaction; the post-state of the
+    /// last output is the final state of the sequence.
+    pub fn execute_sequence(
+        block: BlockEnv,
+        txs: Vec<TxInput>,
+        pre_state: InMemoryDB,
+    ) -> Result<Vec<ExecutionOutput>> {
+        pre_state.validate_code_hashes()?;
+
+        let mut state = pre_state;
+        let mut outputs = Vec::with_capacity(txs.len());
+
+        for tx in txs {
+            let output = Self::execute_sequenced(&block, tx, state)?;
+            state = output.post_state.clone();
+            outputs.push(output);
+        }
 
-        // Build output from result
-        let output = Self::build_output(result.result, post_state)?;
+        Ok(outputs)
+    }
 
-        // Create cryptographic commitment
-        let output_hash = output.hash();
-        let post_state_root = output.post_state_root();
-        let commitment =
-            ExecutionCommitment::new(input_hash, output_hash, pre_state_root, post_state_root);
+    /// Execute a block of transactions and commit to the whole block
+    ///
+    /// Runs the transactions like `execute_sequence`. The commitment binds
+    /// the block, all transactions and the pre-state to every output; see
+    /// [`crate::block`] for its exact definition.
+    pub fn execute_block(
+        block: BlockEnv,
+        txs: Vec<TxInput>,
+        pre_state: InMemoryDB,
+    ) -> Result<(Vec<ExecutionOutput>, ExecutionCommitment)> {
+        Self::execute_batch(BatchExecutionInput::new(block, txs, pre_state))
+    }
+
+    /// Execute a batch of transactions and commit to the whole batch
+    ///
+    /// Runs the transactions like `execute_sequence`, each in its context
+    /// from `tx_contexts` if there are any. `input_hash` is
+    /// `BatchExecutionInput::hash` and `post_state_root` is the root after
+    /// the last transaction.
+    pub fn execute_batch(
+        input: BatchExecutionInput,
+    ) -> Result<(Vec<ExecutionOutput>, ExecutionCommitment)> {
+        input.validate_contexts()?;
+        input.pre_state.validate_code_hashes()?;
+
+        let input_hash = input.hash();
+        let pre_state_root = input.pre_state.compute_state_root();
+        let last_context = input.last_context();
+
+        let blocks: Vec<BlockEnv> = (0..input.txs.len()).map(|i| input.tx_block(i)).collect();
+        let mut state = input.pre_state;
+        let mut outputs = Vec::with_capacity(input.txs.len());
+        for (block, tx) in blocks.iter().zip(input.txs) {
+            let output = Self::execute_sequenced(block, tx, state)?;
+            state = output.post_state.clone();
+            outputs.push(output);
+        }
+
+        let commitment = block_commitment(
+            &input.block,
+            input_hash,
+            pre_state_root,
+            &outputs,
+            last_context,
+        );
+        Ok((outputs, commitment))
+    }
+
+    /// Execute the next transaction of a sequence on the running `state`
+    ///
+    /// The nonce is taken from the running state rather than from `tx`.
+    pub(crate) fn execute_sequenced(
+        block: &BlockEnv,
+        mut tx: TxInput,
+        state: InMemoryDB,
+    ) -> Result<ExecutionOutput> {
+        tx.validate_intent()?;
+        tx.nonce = state
+            .get_account(&tx.caller)
+            .map_or(0, |account| account.nonce);
+
+        let mut output = Self::execute_tx(block.clone(), &tx, state)?;
+        Self::ensure_nonce_incremented(&mut output.post_state, tx.caller, tx.nonce);
+
+        Ok(output)
+    }
+
+    /// Execute with a gas budget independent of the transaction's gas limit
+    ///
+    /// Bounds the work spent on untrusted inputs. The transaction runs once,
+    /// with its gas limit lowered to `max_gas` if it is higher; the output
+    /// and commitment are those of that capped input, as it is the one
+    /// executed.
+    ///
+    /// # Errors
+    /// Returns `ShadowEvmError::GasBudgetExceeded` if the execution runs out
+    /// of gas under a gas limit lowered to `max_gas`.
+    pub fn execute_bounded(
+        mut input: ExecutionInput,
+        max_gas: Gas,
+    ) -> Result<(ExecutionOutput, ExecutionCommitment)> {
+        let capped = input.tx.gas_limit > max_gas;
+        input.tx.gas_limit = input.tx.gas_limit.min(max_gas);
+
+        let (output, commitment) = Self::execute(input)?;
+        if capped && output.ran_out_of_gas() {
+            return Err(ShadowEvmError::GasBudgetExceeded { max_gas });
+        }
 
         Ok((output, commitment))
     }
@@ -75,25 +280,476 @@ impl ShadowExecutor {
     ///
     /// Useful for dry-runs and gas estimation.
     pub fn simulate(input: ExecutionInput) -> Result<ExecutionOutput> {
-        let mut evm = Self::build_evm(input.block, input.tx, input.pre_state.clone())?;
+        input.validate_semantics()?;
+
+        let run = Self::transact(
+            input.block.clone(),
+            &input.tx,
+            &input.pre_state,
+            &input.options,
+        )?;
+
+        // Use the original pre_state for simulation output
+        let output = Self::build_output(run.result.result, input.pre_state, run.halt_reason)?;
+        Ok(output.with_gas_limit(input.tx.gas_limit))
+    }
+
+    /// Estimate a gas limit for the transaction, like `eth_estimateGas`
+    ///
+    /// The lowest passing limit from [`Self::estimate_min_gas`] plus
+    /// [`ESTIMATE_GAS_BUFFER_PERCENT`], without exceeding the block gas
+    /// limit or what the caller can pay for.
+    ///
+    /// # Errors
+    /// As for `estimate_min_gas`.
+    pub fn estimate_gas(input: ExecutionInput) -> Result<Gas> {
+        let cap = Self::max_gas_limit(&input);
+        let estimate = Self::estimate_min_gas(input)?;
+        let buffer = estimate.saturating_mul(ESTIMATE_GAS_BUFFER_PERCENT) / 100;
+        Ok(estimate.saturating_add(buffer).min(cap))
+    }
+
+    /// Estimate the lowest gas limit at which the transaction succeeds
+    ///
+    /// Simulates the transaction with the highest limit it could run with,
+    /// the block gas limit or what the caller's balance pays for at the
+    /// transaction's gas price if lower, then binary-searches down from
+    /// there. The transaction's own gas limit is ignored. A limit fails
+    /// whether it runs out of gas or reverts, as an inner call starved by
+    /// the 63/64 rule may make its caller revert.
+    ///
+    /// The search stops after [`MAX_ESTIMATE_ITERATIONS`] simulations,
+    /// returning the lowest limit found to pass by then. No buffer is added;
+    /// see [`Self::estimate_gas`] and `ExecutionBuilder::with_auto_gas_buffer`.
+    ///
+    /// # Errors
+    /// Returns `ShadowEvmError::ExecutionReverted` if the transaction does not
+    /// succeed even with the highest limit.
+    pub fn estimate_min_gas(input: ExecutionInput) -> Result<Gas> {
+        let mut probe = input;
+        probe.tx.gas_limit = Self::max_gas_limit(&probe);
+        let output = Self::simulate(probe.clone())?;
+        if !output.is_success() {
+            let reason = output
+                .revert_reason()
+                .map(|reason| format!(" ({reason})"))
+                .unwrap_or_default();
+            return Err(ShadowEvmError::ExecutionReverted(format!(
+                "{:?}{} with a gas limit of {}",
+                output.status, reason, probe.tx.gas_limit
+            )));
+        }
+
+        // Refunds only lower gas_used, so any limit below it fails
+        let mut failing = output.gas_used.saturating_sub(1);
+        let mut passing = probe.tx.gas_limit;
+        for _ in 1..MAX_ESTIMATE_ITERATIONS {
+            if passing - failing <= 1 {
+                break;
+            }
+            let mid = failing + (passing - failing) / 2;
+            probe.tx.gas_limit = mid;
+            match Self::simulate(probe.clone()) {
+                Ok(output) if output.is_success() => passing = mid,
+                _ => failing = mid,
+            }
+        }
+
+        Ok(passing)
+    }
+
+    /// Highest gas limit the transaction could run with
+    ///
+    /// The block gas limit, or the gas the caller's balance pays for at the
+    /// transaction's gas price after the value sent, whichever is lower.
+    /// Any higher limit fails up front for lack of funds.
+    fn max_gas_limit(input: &ExecutionInput) -> Gas {
+        if input.tx.gas_price.is_zero() {
+            return input.block.gas_limit;
+        }
+        let balance = input
+            .pre_state
+            .get_account(&input.tx.caller)
+            .map(|acc| acc.balance)
+            .unwrap_or_default();
+        let affordable = balance.saturating_sub(input.tx.value) / input.tx.gas_price;
+        input.block.gas_limit.min(affordable.saturating_to())
+    }
+
+    /// Execute with an opcode-level tracer attached
+    ///
+    /// Produces the same output and commitment as `execute`; the tracer is
+    /// returned with everything it observed.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let (output, commitment, trace) = ShadowExecutor::execute_traced(input, ExecutionTrace::new())?;
+    /// println!("{} opcodes executed", trace.len());
+    /// ```
+    pub fn execute_traced<T: Tracer>(
+        input: ExecutionInput,
+        tracer: T,
+    ) -> Result<(ExecutionOutput, ExecutionCommitment, T)> {
+        let (output, commitment, inspector) =
+            Self::execute_inspected(input, TracerInspector::new(tracer), true, &[], |_| Ok(()))?;
+        Ok((output, commitment, inspector.into_tracer()))
+    }
+
+    /// Execute with `forbidden` opcodes disallowed
+    ///
+    /// Produces the same output and commitment as `execute` if no forbidden
+    /// opcode runs, in any frame.
+    ///
+    /// # Errors
+    /// Returns `ShadowEvmError::ForbiddenOpcode` with the first forbidden
+    /// opcode reached, even if a caller frame would have recovered from it.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let forbidden = [Opcode::SELFDESTRUCT, Opcode::CREATE, Opcode::CREATE2];
+    /// let (output, commitment) = ShadowExecutor::execute_restricted(input, &forbidden)?;
+    /// ```
+    pub fn execute_restricted(
+        input: ExecutionInput,
+        forbidden: &[Opcode],
+    ) -> Result<(ExecutionOutput, ExecutionCommitment)> {
+        let (output, commitment, _) =
+            Self::execute_inspected(input, OpcodeFilter::new(forbidden), true, &[], |filter| {
+                match filter.hit() {
+                    Some(opcode) => Err(ShadowEvmError::ForbiddenOpcode(opcode)),
+                    None => Ok(()),
+                }
+            })?;
+        Ok((output, commitment))
+    }
+
+    /// Execute while measuring the peak memory of any frame
+    ///
+    /// Returns the output and commitment `execute` would produce, with the
+    /// largest memory size any frame reached in 32-byte words. Protects a
+    /// proving service from inputs that grief it with memory expansion.
+    ///
+    /// # Errors
+    /// Returns `ShadowEvmError::MemoryLimitExceeded` if a frame's memory grows
+    /// past `max_memory_words`.
+    pub fn execute_memory_bounded(
+        input: ExecutionInput,
+        max_memory_words: Option<usize>,
+    ) -> Result<(ExecutionOutput, ExecutionCommitment, usize)> {
+        let (output, commitment, meter) = Self::execute_inspected(
+            input,
+            MemoryMeter::new(max_memory_words),
+            true,
+            &[],
+            |meter| match max_memory_words.filter(|_| meter.exceeded()) {
+                Some(max_words) => Err(ShadowEvmError::MemoryLimitExceeded {
+                    peak_words: meter.peak_words(),
+                    max_words,
+                }),
+                None => Ok(()),
+            },
+        )?;
+        Ok((output, commitment, meter.peak_words()))
+    }
+
+    /// Execute and record the preimage of every committed hash
+    ///
+    /// Same output and commitment as `execute`, plus an [`AuditTrail`] from
+    /// which an auditor can recompute the input hash, output hash and
+    /// commitment independently. For debugging only: the trail is not
+    /// part of any proof.
+    pub fn execute_audited(
+        input: ExecutionInput,
+    ) -> Result<(ExecutionOutput, ExecutionCommitment, AuditTrail)> {
+        let (output, commitment) = Self::execute(input.clone())?;
+        let trail = AuditTrail::record(&input, &output, &commitment)?;
+        Ok((output, commitment, trail))
+    }
+
+    /// Execute for debugging, keeping the storage writes a revert unwound
+    ///
+    /// Returns the output with the opcode trace and every `SSTORE` made,
+    /// including those of frames that later reverted; see
+    /// [`DebugTrace::would_be_storage`]. Purely diagnostic: the commitment
+    /// is not returned.
+    pub fn execute_debug(input: ExecutionInput) -> Result<(ExecutionOutput, DebugTrace)> {
+        let (output, _, debug) = Self::execute_traced(input, DebugTrace::new())?;
+        Ok((output, debug))
+    }
+
+    /// Execute with per-opcode gas profiling
+    ///
+    /// Same result and commitment as `execute`, with
+    /// `ExecutionOutput::gas_profile` filled in, including its breakdown
+    /// by category.
+    pub fn execute_profiled(
+        input: ExecutionInput,
+    ) -> Result<(ExecutionOutput, ExecutionCommitment)> {
+        let gas_limit = input.tx.gas_limit;
+        let is_create = input.tx.to.is_none();
+
+        let (mut output, commitment, profiler) = Self::execute_traced(input, GasProfiler::new())?;
+        let breakdown = profiler.breakdown(gas_limit, is_create, &output);
+        let mut profile = profiler.into_profile();
+        profile.breakdown = breakdown;
+        output.gas_profile = Some(profile);
+        Ok((output, commitment))
+    }
+
+    /// Execute and return revm's unconverted result
+    ///
+    /// For callers that need fields `ExecutionOutput` does not model, such as
+    /// per-account status flags. Returns the raw result together with the
+    /// post-state `execute` would produce. No commitment is computed.
+    pub fn execute_raw(input: ExecutionInput) -> Result<(ResultAndState, InMemoryDB)> {
+        input.validate_semantics()?;
+
+        let caller_nonce = input
+            .pre_state
+            .get_account(&input.tx.caller)
+            .map(|acc| acc.nonce);
+        let run = Self::transact(input.block, &input.tx, &input.pre_state, &input.options)?;
+        let mut post_state = Self::apply_result(&input.tx, input.pre_state, &run.result)?;
+        if !input.options.increment_nonce {
+            Self::restore_nonce(&mut post_state, input.tx.caller, caller_nonce);
+        }
+
+        Ok((run.result, post_state))
+    }
+
+    /// Dry-run `input` to find the state its pre-state is missing
+    ///
+    /// Missing accounts and slots read as empty, so the run may not follow
+    /// the path of the real execution; see [`crate::preflight`]. Repeat
+    /// with the fetched state added until the returned set is empty.
+    pub fn preflight(input: ExecutionInput) -> Result<AccessSet> {
+        let (spec_id, cfg, block_env, tx_env) =
+            Self::configure(input.block, input.tx.clone(), &input.options)?;
+        let mut db = PreflightDB::new(Self::prepare_db(&input.tx, &input.pre_state));
+
+        let mut evm = Evm::builder()
+            .with_db(&mut db)
+            .with_spec_id(spec_id)
+            .modify_cfg_env(|c| *c = cfg)
+            .modify_block_env(|b| *b = block_env)
+            .modify_tx_env(|t| *t = tx_env)
+            .build();
+
+        // A failed validation (e.g. an unknown caller that cannot pay for
+        // gas) still reports what was requested up to that point
+        let _ = evm.transact();
+        drop(evm);
+
+        Ok(db.into_missing())
+    }
+
+    /// Run a single transaction against `pre_state` and build its output
+    fn execute_tx(
+        block: BlockEnv,
+        tx: &TxInput,
+        pre_state: InMemoryDB,
+    ) -> Result<ExecutionOutput> {
+        let run = Self::transact(block.clone(), tx, &pre_state, &CallOptions::default())?;
+        Self::finish_tx(&block, tx, pre_state, run.result, run.halt_reason)
+    }
+
+    /// Run a single transaction in revm without touching `pre_state`
+    ///
+    /// No inspector is attached; see [`Self::run_evm`].
+    fn transact(
+        block: BlockEnv,
+        tx: &TxInput,
+        pre_state: &InMemoryDB,
+        options: &CallOptions,
+    ) -> Result<Transacted<NoOpInspector>> {
+        Self::run_evm(block, tx, pre_state, NoOpInspector, false, options, &[])
+    }
+
+    /// Run a single transaction in revm, with `inspector` attached if `inspect`
+    ///
+    /// An attached inspector is wrapped in a [`HaltProbe`], so the reason of
+    /// a halt is known from the same run. Without `inspect` no hook runs per
+    /// step, which keeps plain executions cheap to prove; only a run halting
+    /// on an invalid opcode is then repeated with the probe to name it.
+    fn run_evm<I: Inspector<InMemoryDB>>(
+        block: BlockEnv,
+        tx: &TxInput,
+        pre_state: &InMemoryDB,
+        inspector: I,
+        inspect: bool,
+        options: &CallOptions,
+        precompiles: &[CustomPrecompile],
+    ) -> Result<Transacted<I>> {
+        debug_assert!(
+            tx.validate_intent().is_ok(),
+            "ambiguous create/call transaction"
+        );
+        let db = Self::prepare_db(tx, pre_state);
+        let (spec_id, cfg, block_env, tx_env) =
+            Self::configure(block.clone(), tx.clone(), options)?;
+        let custom_precompiles = precompiles.to_vec();
+        let max_call_depth = options.max_call_depth;
+        let builder = Evm::builder()
+            .with_db(db)
+            .with_external_context(HaltProbe::new(inspector))
+            .with_spec_id(spec_id)
+            .append_handler_register_box(Box::new(move |handler| {
+                precompile::register(handler, &custom_precompiles)
+            }))
+            .append_handler_register_box(Box::new(move |handler| {
+                limits::register_call_depth_limit(handler, max_call_depth)
+            }));
+        // Last, so the inspector sees every frame the limit cuts short
+        let builder = if inspect {
+            builder.append_handler_register(inspector_handle_register)
+        } else {
+            builder
+        };
+        let mut evm = builder
+            .modify_cfg_env(|c| *c = cfg)
+            .modify_block_env(|b| *b = block_env)
+            .modify_tx_env(|t| *t = tx_env)
+            .build();
 
         let result = evm
             .transact()
-            .map_err(|e| ShadowEvmError::ExecutionHalted(format!("{:?}", e)))?;
+            .map_err(|e| Self::transact_error(tx.caller, e))?;
+        let probe = evm.into_context().external;
+        if !inspect && Self::halted_on_invalid_opcode(&result.result) {
+            // Never attached, so the inspector is as it was passed in
+            return Self::run_evm(
+                block,
+                tx,
+                pre_state,
+                probe.inner,
+                true,
+                options,
+                precompiles,
+            );
+        }
+        let halt_reason = Self::halt_reason(&result.result, probe.last)?;
+        Ok(Transacted {
+            result,
+            halt_reason,
+            inspector: probe.inner,
+        })
+    }
 
-        // Use the original pre_state for simulation output
-        Self::build_output(result.result, input.pre_state)
+    /// Translate a revm error that prevented `caller`'s transaction
+    fn transact_error<E: core::fmt::Debug>(caller: Address, error: EVMError<E>) -> ShadowEvmError {
+        match error {
+            EVMError::Transaction(InvalidTransaction::RejectCallerWithCode) => {
+                ShadowEvmError::SenderHasCode { address: caller }
+            }
+            error => ShadowEvmError::ExecutionHalted(format!("{:?}", error)),
+        }
+    }
+
+    /// Apply a transaction's result to `pre_state` and build its output
+    fn finish_tx(
+        block: &BlockEnv,
+        tx: &TxInput,
+        pre_state: InMemoryDB,
+        result: ResultAndState,
+        halt_reason: Option<HaltReason>,
+    ) -> Result<ExecutionOutput> {
+        let post_state = Self::apply_result(tx, pre_state, &result)?;
+
+        // Build output from result
+        let output = Self::build_output(result.result, post_state, halt_reason)?;
+        Ok(output
+            .with_gas_limit(tx.gas_limit)
+            .with_effective_gas_price(tx.effective_gas_price(block.base_fee)))
+    }
+
+    /// Apply a transaction's state changes to `pre_state`
+    fn apply_result(
+        tx: &TxInput,
+        pre_state: InMemoryDB,
+        result: &ResultAndState,
+    ) -> Result<InMemoryDB> {
+        let mut post_state = pre_state;
+        Self::apply_state_changes(&mut post_state, result)?;
+        if tx.authorized_code.is_some() {
+            Self::clear_authorized_code(&mut post_state, tx.caller);
+        }
+        Ok(post_state)
+    }
+
+    /// Make sure the caller's nonce moved past the one the transaction used
+    ///
+    /// revm bumps the nonce itself; this guards the sequence against a state
+    /// application that missed it, which would make the next transaction fail.
+    fn ensure_nonce_incremented(db: &mut InMemoryDB, caller: Address, used_nonce: u64) {
+        if let Some(account) = db.get_account_mut(&caller) {
+            if account.nonce <= used_nonce {
+                account.nonce = used_nonce + 1;
+            }
+        }
+    }
+
+    /// Reset the caller's nonce to its pre-state value
+    ///
+    /// A caller that did not exist before and is left empty is removed
+    /// again, as `apply_state_changes` would have done.
+    fn restore_nonce(db: &mut InMemoryDB, caller: Address, pre_nonce: Option<u64>) {
+        if let Some(account) = db.get_account_mut(&caller) {
+            account.nonce = pre_nonce.unwrap_or(0);
+            if pre_nonce.is_none() && account.is_empty() && account.storage.is_empty() {
+                db.remove_account(&caller);
+            }
+        }
+    }
+
+    /// Prepare the database revm executes against
+    ///
+    /// If the transaction carries `authorized_code`, the code is attached to
+    /// the caller in this copy only, so the pre-state stays untouched.
+    fn prepare_db(tx: &TxInput, pre_state: &InMemoryDB) -> InMemoryDB {
+        let mut db = pre_state.clone();
+        if let Some(code) = &tx.authorized_code {
+            let mut caller = db
+                .get_account(&tx.caller)
+                .cloned()
+                .unwrap_or_else(|| AccountState::new_with_balance(U256::ZERO));
+            caller.code_hash = keccak256(code);
+            caller.code = code.clone();
+            db.insert_account(tx.caller, caller);
+        }
+        db
+    }
+
+    /// Drop delegated code from the caller after execution
+    ///
+    /// Mirrors an EIP-7702 delegation that only lives for one transaction:
+    /// storage and balance changes made by the code are kept, the code is not.
+    fn clear_authorized_code(db: &mut InMemoryDB, caller: Address) {
+        if let Some(account) = db.get_account_mut(&caller) {
+            account.code = Vec::new();
+            account.code_hash = KECCAK_EMPTY;
+        }
     }
 
-    /// Build revm EVM instance with Shadow-EVM configuration
-    fn build_evm(
+    /// Translate Shadow-EVM block and transaction into revm configuration
+    fn configure(
         block: BlockEnv,
         tx: TxInput,
-        db: InMemoryDB,
-    ) -> Result<Evm<'static, (), InMemoryDB>> {
-        // Configure chain - use Cancun (latest stable) spec
+        options: &CallOptions,
+    ) -> Result<(SpecId, CfgEnv, RevmBlockEnv, TxEnv)> {
+        // A fresh EVM per transaction picks up per-transaction overrides
+        let hardfork = tx.effective_hardfork(&block);
+        if tx.authorized_code.is_some() && hardfork < Hardfork::Prague {
+            return Err(ShadowEvmError::InvalidTransaction(format!(
+                "authorized code requires Prague or later, got {:?}",
+                hardfork
+            )));
+        }
+
+        // Configure chain
         let mut cfg = CfgEnv::default();
         cfg.chain_id = block.chain_id;
+        // A delegated caller has code, which EIP-3607 would otherwise reject
+        cfg.disable_eip3607 = !options.enforce_eip3607 || tx.authorized_code.is_some();
 
         // Configure block environment
         let block_env = RevmBlockEnv {
@@ -102,11 +758,13 @@ impl ShadowExecutor {
             gas_limit: U256::from(block.gas_limit),
             coinbase: block.coinbase,
             basefee: block.base_fee,
+            difficulty: block.difficulty,
             prevrandao: Some(block.prev_randao),
             ..Default::default()
         };
 
         // Configure transaction environment
+        let access_list = Self::access_list(&tx);
         let tx_env = TxEnv {
             caller: tx.caller,
             transact_to: match tx.to {
@@ -117,30 +775,61 @@ impl ShadowExecutor {
             data: tx.data.into(),
             gas_limit: tx.gas_limit,
             gas_price: tx.gas_price,
+            gas_priority_fee: tx.max_priority_fee,
             nonce: Some(tx.nonce),
+            access_list,
             ..Default::default()
         };
 
-        // Build EVM with Cancun spec (for latest features)
-        let evm = Evm::builder()
-            .with_db(db)
-            .with_spec_id(SpecId::CANCUN)
-            .modify_cfg_env(|c| *c = cfg)
-            .modify_block_env(|b| *b = block_env)
-            .modify_tx_env(|t| *t = tx_env)
-            .build();
+        Ok((hardfork.spec_id(), cfg, block_env, tx_env))
+    }
 
-        Ok(evm)
+    /// The transaction's access list in revm's form
+    fn access_list(tx: &TxInput) -> Vec<AccessListItem> {
+        tx.access_list
+            .iter()
+            .map(|(address, slots)| AccessListItem {
+                address: *address,
+                storage_keys: slots
+                    .iter()
+                    .map(|slot| slot.to_be_bytes::<32>().into())
+                    .collect(),
+            })
+            .collect()
     }
 
     /// Apply state changes from execution result to our database
+    ///
+    /// Follows EIP-161, so whether an account exists afterwards never
+    /// depends on how execution happened to load it:
+    /// - accounts that were only read are left as they were (absent ones
+    ///   are not created)
+    /// - touched accounts that end up empty are removed, including the
+    ///   coinbase when the transaction pays it no priority fee
+    ///
+    /// Accounts destroyed by SELFDESTRUCT are removed with their code and
+    /// storage. From Cancun on (EIP-6780) that only happens to contracts
+    /// created in the same transaction.
+    ///
+    /// Fails with `ShadowEvmError::InvalidStateUpdate`, leaving `db` partly
+    /// updated, if a touched account is in a state no execution can produce.
     fn apply_state_changes(
         db: &mut InMemoryDB,
         result: &revm::primitives::ResultAndState,
     ) -> Result<()> {
         // Apply balance changes and state changes
         for (addr, account) in &result.state {
+            if !account.is_touched() {
+                continue;
+            }
+            if account.is_selfdestructed() || account.is_empty() {
+                db.remove_account(addr);
+                continue;
+            }
+
             let info = &account.info;
+            let is_new = !db.account_exists(addr);
+            Self::validate_account_update(*addr, info, is_new)?;
 
             if let Some(existing) = db.get_account_mut(addr) {
                 // Update existing account
@@ -155,7 +844,8 @@ impl ShadowExecutor {
                 let mut new_acc = AccountState::new_with_balance(info.balance);
                 new_acc.nonce = info.nonce;
                 if let Some(code) = &info.code {
-                    new_acc.code = code.bytes().to_vec();
+                    // Unpadded: revm pads analyzed code, even empty code
+                    new_acc.code = code.original_bytes().to_vec();
                     new_acc.code_hash = info.code_hash;
                 }
                 // Add storage
@@ -169,10 +859,34 @@ impl ShadowExecutor {
         Ok(())
     }
 
+    /// Reject an account state revm cannot legitimately produce
+    ///
+    /// A guard against committing a corrupted result: the nonce can never
+    /// reach `u64::MAX` (EIP-2681), and the code of a new account, which is
+    /// copied into the post-state, must match its hash.
+    fn validate_account_update(
+        address: Address,
+        info: &revm::primitives::AccountInfo,
+        is_new: bool,
+    ) -> Result<()> {
+        let invalid = |reason| ShadowEvmError::InvalidStateUpdate { address, reason };
+
+        if info.nonce == u64::MAX {
+            return Err(invalid("nonce at u64::MAX"));
+        }
+        if let Some(code) = info.code.as_ref().filter(|_| is_new) {
+            if keccak256(&code.original_bytes()) != info.code_hash {
+                return Err(invalid("code does not match its hash"));
+            }
+        }
+        Ok(())
+    }
+
     /// Build execution output from revm result
     fn build_output(
         result: revm::primitives::ExecutionResult,
         db: InMemoryDB,
+        halt_reason: Option<HaltReason>,
     ) -> Result<ExecutionOutput> {
         match result {
             revm::primitives::ExecutionResult::Success {
@@ -182,9 +896,13 @@ impl ShadowExecutor {
                 logs,
                 ..
             } => {
-                let (return_data, created_address) = match output {
+                // A create returns the deployed runtime code, which is not
+                // return data: it is only reflected in the post-state
+                let (return_data, created) = match output {
                     revm::primitives::Output::Call(data) => (data.to_vec(), None),
-                    revm::primitives::Output::Create(data, addr) => (data.to_vec(), addr),
+                    revm::primitives::Output::Create(code, addr) => {
+                        (Vec::new(), addr.map(|addr| (addr, code.len())))
+                    }
                 };
 
                 let logs = Self::convert_logs(logs);
@@ -192,8 +910,15 @@ impl ShadowExecutor {
                 let mut output =
                     ExecutionOutput::success(return_data, gas_used, gas_refunded, logs, db);
 
-                if let Some(addr) = created_address {
-                    output = output.with_created_address(addr);
+                if let Some((addr, code_size)) = created {
+                    output = output
+                        .with_created_address(addr)
+                        .with_deployed_code_size(code_size);
+                    if code_size == 0 {
+                        output
+                            .warnings
+                            .push(ExecutionWarning::EmptyDeployedCode { address: addr });
+                    }
                 }
 
                 Ok(output)
@@ -202,11 +927,75 @@ impl ShadowExecutor {
                 Ok(ExecutionOutput::revert(output.to_vec(), gas_used, db))
             }
             revm::primitives::ExecutionResult::Halt { gas_used, .. } => {
-                Ok(ExecutionOutput::halt(gas_used, db))
+                let mut output = ExecutionOutput::halt(gas_used, db);
+                output.halt_reason = halt_reason;
+                Ok(output)
             }
         }
     }
 
+    /// Whether `result` halted on an invalid or undefined opcode
+    fn halted_on_invalid_opcode(result: &revm::primitives::ExecutionResult) -> bool {
+        use revm::primitives::HaltReason as Revm;
+
+        matches!(
+            result,
+            revm::primitives::ExecutionResult::Halt {
+                reason: Revm::OpcodeNotFound | Revm::InvalidFEOpcode,
+                ..
+            }
+        )
+    }
+
+    /// Convert the reason `result` halted (if it did) to a Shadow-EVM one
+    ///
+    /// revm does not report which opcode was invalid; `last_step` is the
+    /// opcode and program counter of the last step run, recorded by
+    /// [`HaltProbe`].
+    fn halt_reason(
+        result: &revm::primitives::ExecutionResult,
+        last_step: Option<(u8, usize)>,
+    ) -> Result<Option<HaltReason>> {
+        use revm::primitives::HaltReason as Revm;
+
+        let revm::primitives::ExecutionResult::Halt { reason, .. } = result else {
+            return Ok(None);
+        };
+
+        let reason = match reason {
+            Revm::OutOfGas(_) => HaltReason::OutOfGas,
+            Revm::OpcodeNotFound | Revm::InvalidFEOpcode => {
+                // The invalid opcode is the last one the halted frame ran
+                let (opcode, pc) = last_step.ok_or_else(|| {
+                    ShadowEvmError::ExecutionHalted(format!("{:?} without a step", reason))
+                })?;
+                HaltReason::InvalidOpcode { opcode, pc }
+            }
+            Revm::StackUnderflow => HaltReason::StackUnderflow,
+            Revm::StackOverflow => HaltReason::StackOverflow,
+            Revm::CallTooDeep => HaltReason::CallTooDeep,
+            Revm::NotActivated => HaltReason::NotActivated,
+            Revm::InvalidJump => HaltReason::InvalidJump,
+            Revm::OutOfOffset => HaltReason::ReturnDataOutOfBounds,
+            Revm::CreateCollision => HaltReason::CreateCollision,
+            Revm::PrecompileError => HaltReason::PrecompileFailed,
+            Revm::NonceOverflow => HaltReason::NonceOverflow,
+            Revm::CreateContractSizeLimit => HaltReason::CodeSizeLimit,
+            Revm::CreateContractStartingWithEF => HaltReason::CodeStartsWithEf,
+            Revm::CreateInitCodeSizeLimit => HaltReason::InitCodeSizeLimit,
+            Revm::StateChangeDuringStaticCall | Revm::CallNotAllowedInsideStatic => {
+                HaltReason::StaticStateChange
+            }
+            Revm::OutOfFunds | Revm::OverflowPayment => HaltReason::OutOfFunds,
+            Revm::EofAuxDataOverflow
+            | Revm::EofAuxDataTooSmall
+            | Revm::EOFFunctionStackOverflow
+            | Revm::InvalidEXTCALLTarget => HaltReason::Eof,
+        };
+
+        Ok(Some(reason))
+    }
+
     /// Convert revm logs to Shadow-EVM logs
     fn convert_logs(logs: Vec<revm::primitives::Log>) -> Vec<Log> {
         logs.into_iter()
@@ -215,11 +1004,114 @@ impl ShadowExecutor {
     }
 }
 
+/// Result of a revm run, with the inspector it ran under
+struct Transacted<I> {
+    result: ResultAndState,
+    /// Why the run halted, if it did
+    halt_reason: Option<HaltReason>,
+    inspector: I,
+}
+
+/// Inspector recording the last step run, around another inspector
+///
+/// A transaction halting on an invalid opcode halts in its top-level
+/// frame, whose last step is that opcode, so recording every step finds
+/// it. Every other hook is passed on to `inner`.
+struct HaltProbe<I> {
+    inner: I,
+    /// Opcode and program counter of the last step
+    last: Option<(u8, usize)>,
+}
+
+impl<I> HaltProbe<I> {
+    fn new(inner: I) -> Self {
+        Self { inner, last: None }
+    }
+}
+
+impl<DB: Database, I: Inspector<DB>> Inspector<DB> for HaltProbe<I> {
+    fn initialize_interp(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.inner.initialize_interp(interp, context);
+    }
+
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.last = Some((interp.current_opcode(), interp.program_counter()));
+        self.inner.step(interp, context);
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.inner.step_end(interp, context);
+    }
+
+    fn log(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>, log: &RevmLog) {
+        self.inner.log(interp, context, log);
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.inner.call(context, inputs)
+    }
+
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.inner.call_end(context, inputs, outcome)
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.inner.create(context, inputs)
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.inner.create_end(context, inputs, outcome)
+    }
+
+    fn eofcreate(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut EOFCreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.inner.eofcreate(context, inputs)
+    }
+
+    fn eofcreate_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &EOFCreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.inner.eofcreate_end(context, inputs, outcome)
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        self.inner.selfdestruct(contract, target, value);
+    }
+}
+
 /// Builder for creating execution inputs
 pub struct ExecutionBuilder {
     block: BlockEnv,
     tx: TxInput,
     state: InMemoryDB,
+    limits: ExecutionLimits,
+    check_conservation: bool,
+    auto_gas_buffer_percent: Option<u64>,
+    snapshot_state: bool,
 }
 
 impl ExecutionBuilder {
@@ -229,6 +1121,10 @@ impl ExecutionBuilder {
             block: BlockEnv::default(),
             tx: TxInput::default(),
             state: InMemoryDB::new(),
+            limits: ExecutionLimits::default(),
+            check_conservation: false,
+            auto_gas_buffer_percent: None,
+            snapshot_state: false,
         }
     }
 
@@ -250,28 +1146,149 @@ impl ExecutionBuilder {
         self
     }
 
-    /// Build the execution input
-    pub fn build(self) -> ExecutionInput {
-        ExecutionInput::new(self.block, self.tx, self.state)
+    /// Set the maximum number of logs (default: 1000)
+    pub fn with_max_logs(mut self, max_logs: usize) -> Self {
+        self.limits.max_logs = max_logs;
+        self
     }
 
-    /// Execute immediately
-    pub fn execute(self) -> Result<(ExecutionOutput, ExecutionCommitment)> {
-        ShadowExecutor::execute(self.build())
+    /// Set the maximum total log data in bytes (default: 256 KiB)
+    pub fn with_max_log_data_bytes(mut self, max_bytes: usize) -> Self {
+        self.limits.max_log_data_bytes = max_bytes;
+        self
     }
-}
 
-impl Default for ExecutionBuilder {
-    fn default() -> Self {
-        Self::new()
+    /// Set the maximum number of accounts the execution may create
+    pub fn with_max_new_accounts(mut self, max_accounts: usize) -> Self {
+        self.limits.max_new_accounts = Some(max_accounts);
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Set the maximum number of storage slots the execution may populate
+    pub fn with_max_storage_growth(mut self, total_new_slots: usize) -> Self {
+        self.limits.max_storage_growth = Some(total_new_slots);
+        self
+    }
+
+    /// Verify after execution that ether was conserved (default: false)
+    ///
+    /// See [`check_eth_conservation`].
+    pub fn with_conservation_check(mut self, enabled: bool) -> Self {
+        self.check_conservation = enabled;
+        self
+    }
+
+    /// Snapshot the pre-state before executing (default: false)
+    ///
+    /// The post-state then carries the snapshot, so the pre-state can be
+    /// restored from it with `revert_to_snapshot(id)` to try an alternative.
+    /// The id is the number of snapshots the pre-state already held, i.e.
+    /// 0 for a state without snapshots.
+    pub fn with_state_snapshot(mut self, enabled: bool) -> Self {
+        self.snapshot_state = enabled;
+        self
+    }
+
+    /// Replace the transaction's gas limit with an estimate plus 20%
+    ///
+    /// Applied by `try_build` and `execute`; see [`Self::with_auto_gas_buffer`].
+    pub fn with_auto_gas(self) -> Self {
+        self.with_auto_gas_buffer(20)
+    }
+
+    /// Replace the transaction's gas limit with an estimate plus `percent`
+    ///
+    /// The estimate comes from [`ShadowExecutor::estimate_min_gas`]; the
+    /// buffered limit is capped at the block gas limit and at what the
+    /// caller can pay for. Building fails if the
+    /// transaction does not succeed with any gas limit.
+    pub fn with_auto_gas_buffer(mut self, percent: u64) -> Self {
+        self.auto_gas_buffer_percent = Some(percent);
+        self
+    }
+
+    /// Estimate the gas the transaction needs
+    ///
+    /// See [`ShadowExecutor::estimate_gas`]; the configured limits and auto
+    /// gas play no part.
+    pub fn estimate_gas(self) -> Result<Gas> {
+        ShadowExecutor::estimate_gas(self.build())
+    }
+
+    /// Build the execution input
+    ///
+    /// Does not apply auto gas; use `try_build` for that.
+    pub fn build(self) -> ExecutionInput {
+        ExecutionInput::new(self.block, self.tx, self.state)
+    }
+
+    /// Build the execution input, filling in the gas limit if auto gas is on
+    pub fn try_build(self) -> Result<ExecutionInput> {
+        let buffer_percent = self.auto_gas_buffer_percent;
+        let mut input = self.build();
+
+        if let Some(percent) = buffer_percent {
+            let estimate = ShadowExecutor::estimate_min_gas(input.clone())?;
+            let buffered = estimate.saturating_mul(100 + percent) / 100;
+            input.tx.gas_limit = buffered.min(ShadowExecutor::max_gas_limit(&input));
+        }
+
+        Ok(input)
+    }
+
+    /// Execute immediately
+    ///
+    /// The output is checked against the configured limits.
+    pub fn execute(mut self) -> Result<(ExecutionOutput, ExecutionCommitment)> {
+        if self.snapshot_state {
+            self.state.snapshot();
+        }
+        let limits = self.limits;
+        let check_conservation = self.check_conservation;
+        // The base fee is only burned from London on
+        let burned_per_gas = if self.tx.effective_hardfork(&self.block) >= Hardfork::London {
+            self.block.base_fee
+        } else {
+            U256::ZERO
+        };
+        let pre_state =
+            (limits.limits_state_growth() || check_conservation).then(|| self.state.clone());
+
+        let (output, commitment) = ShadowExecutor::execute(self.try_build()?)?;
+
+        limits.check_logs(&output.logs)?;
+        if let Some(pre_state) = &pre_state {
+            if limits.limits_state_growth() {
+                limits.check_state_growth(pre_state, &output.post_state)?;
+            }
+            if check_conservation {
+                check_eth_conservation(
+                    pre_state,
+                    &output.post_state,
+                    output.gas_used,
+                    burned_per_gas,
+                )?;
+            }
+        }
+
+        Ok((output, commitment))
+    }
+}
+
+impl Default for ExecutionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing::StateHashAlgo;
     use crate::state::AccountState;
-    use crate::types::{Address, U256};
+    use crate::testing::contracts::{deploy_code, erc20_balance_slot, COUNTER_CODE};
+    use crate::testing::samples::SampleKind;
+    use crate::types::Hash;
 
     /// Test simple ETH transfer between accounts
     #[test]
@@ -299,13 +1316,7 @@ mod tests {
         assert_eq!(output.gas_used, 21000); // Standard transfer
 
         // Verify balances
-        let receiver_balance = output
-            .post_state
-            .accounts
-            .get(&receiver)
-            .map(|a| a.balance)
-            .unwrap_or(U256::ZERO);
-        assert_eq!(receiver_balance, transfer_amount);
+        assert_eq!(output.balance_of(receiver), transfer_amount);
 
         // Verify commitment is valid
         assert!(!commitment.commitment.is_zero());
@@ -351,6 +1362,91 @@ mod tests {
         // Created contract should exist in post_state
         let created = output.created_address.unwrap();
         assert!(output.post_state.accounts.contains_key(&created));
+
+        // The returned bytes are the code, not return data
+        assert!(output.return_data.is_empty());
+        assert_eq!(output.deployed_code_size, Some(1));
+        assert_eq!(output.created_code_hash(), Some(keccak256(&[0x42])));
+    }
+
+    #[test]
+    fn test_code_deposit_gas() {
+        let deployer = Address::repeat_byte(0x01);
+
+        // Deploys 0x600100 (PUSH1 1, STOP):
+        // PUSH3 0x600100, PUSH1 0, MSTORE, PUSH1 3, PUSH1 29, RETURN
+        let init_code = vec![
+            0x62, 0x60, 0x01, 0x00, 0x60, 0x00, 0x52, 0x60, 0x03, 0x60, 0x1d, 0xf3,
+        ];
+
+        let mut pre_state = InMemoryDB::new();
+        pre_state.insert_account(
+            deployer,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::create(deployer, init_code, U256::ZERO).with_gas_limit(100_000),
+            pre_state,
+        );
+
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+        assert!(output.is_success());
+        assert_eq!(output.deployed_code_size, Some(3));
+        assert_eq!(output.code_deposit_gas(), Some(600));
+    }
+
+    #[test]
+    fn test_empty_deployed_code_warning() {
+        let deployer = Address::repeat_byte(0x01);
+
+        let mut pre_state = InMemoryDB::new();
+        pre_state.insert_account(
+            deployer,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        // Init code that stops without returning any runtime code: STOP
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::create(deployer, vec![0x00], U256::ZERO).with_gas_limit(100_000),
+            pre_state.clone(),
+        );
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+
+        assert!(output.is_success());
+        assert_eq!(
+            output.warnings,
+            vec![ExecutionWarning::EmptyDeployedCode {
+                address: output.created_address.unwrap()
+            }]
+        );
+
+        // So does a create without init code, which still gets its value
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::create(deployer, Vec::new(), U256::from(1000u64)).with_gas_limit(100_000),
+            pre_state.clone(),
+        );
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+        let created = output.created_address.unwrap();
+        assert!(output.is_success());
+        assert_eq!(output.balance_of(created), U256::from(1000u64));
+        assert_eq!(
+            output.warnings,
+            vec![ExecutionWarning::EmptyDeployedCode { address: created }]
+        );
+
+        // Deploying code raises no warning
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::create(deployer, deploy_code(COUNTER_CODE), U256::ZERO)
+                .with_gas_limit(1_000_000),
+            pre_state,
+        );
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+        assert!(output.warnings.is_empty());
     }
 
     /// Test execution builder pattern
@@ -378,6 +1474,166 @@ mod tests {
         assert!(output.is_success());
     }
 
+    /// Test that auto gas replaces a guessed limit with a buffered estimate
+    #[test]
+    fn test_builder_auto_gas() {
+        let sender = Address::repeat_byte(0x01);
+        let reverter = Address::repeat_byte(0xaa);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // PUSH1 0, PUSH1 0, REVERT
+        state.insert_account(
+            reverter,
+            AccountState::new_contract(vec![0x60, 0x00, 0x60, 0x00, 0xfd], U256::ZERO),
+        );
+
+        let builder = ExecutionBuilder::new()
+            .with_tx(
+                TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64))
+                    .with_gas_limit(300_000),
+            )
+            .with_state(state.clone())
+            .with_auto_gas();
+        let input = builder.try_build().unwrap();
+        assert_eq!(input.tx.gas_limit, 25_200);
+
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+        assert!(output.is_success());
+
+        // A call that always reverts has no meaningful limit
+        let result = ExecutionBuilder::new()
+            .with_tx(TxInput::call(sender, reverter, vec![]))
+            .with_state(state)
+            .with_auto_gas()
+            .execute();
+        assert!(matches!(result, Err(ShadowEvmError::ExecutionReverted(_))));
+    }
+
+    /// Test gas estimation at its edges
+    #[test]
+    fn test_estimate_gas() {
+        let sender = SampleKind::sender();
+        let transfer = SampleKind::Transfer.input();
+        let buffered = 21_000 + 21_000 * ESTIMATE_GAS_BUFFER_PERCENT / 100;
+        assert_eq!(
+            ShadowExecutor::estimate_min_gas(transfer.clone()).unwrap(),
+            21_000
+        );
+        assert_eq!(
+            ShadowExecutor::estimate_gas(transfer.clone()).unwrap(),
+            buffered
+        );
+        let builder = ExecutionBuilder::new()
+            .with_tx(transfer.tx.clone())
+            .with_state(transfer.pre_state.clone());
+        assert_eq!(builder.estimate_gas().unwrap(), buffered);
+
+        // A sender who cannot pay for the block gas limit still gets an
+        // estimate, capped at what they can pay for
+        let mut poor = transfer.clone();
+        poor.tx.gas_price = U256::from(1_000_000_000u64);
+        let affordable = U256::from(22_000u64) * poor.tx.gas_price;
+        poor.pre_state.get_account_mut(&sender).unwrap().balance = poor.tx.value + affordable;
+        assert_eq!(
+            ShadowExecutor::estimate_min_gas(poor.clone()).unwrap(),
+            21_000
+        );
+        assert_eq!(ShadowExecutor::estimate_gas(poor).unwrap(), 22_000);
+
+        // A creation succeeds at the estimate, and not one below
+        let deploy = SampleKind::Deploy.input();
+        let estimate = ShadowExecutor::estimate_min_gas(deploy.clone()).unwrap();
+        let run = |gas_limit: Gas| {
+            let mut input = deploy.clone();
+            input.tx.gas_limit = gas_limit;
+            ShadowExecutor::simulate(input).unwrap()
+        };
+        assert!(run(estimate).is_success());
+        assert!(!run(estimate - 1).is_success());
+
+        // Always reverts
+        let result = ShadowExecutor::estimate_gas(SampleKind::Revert.input());
+        assert!(matches!(result, Err(ShadowEvmError::ExecutionReverted(_))));
+
+        // JUMPDEST, PUSH1 0, JUMP: runs out of gas at any limit
+        let looper = Address::repeat_byte(0xaa);
+        let mut input = SampleKind::Transfer.input();
+        input.pre_state.insert_account(
+            looper,
+            AccountState::new_contract(vec![0x5b, 0x60, 0x00, 0x56], U256::ZERO),
+        );
+        input.tx = TxInput::call(sender, looper, vec![]);
+        let result = ShadowExecutor::estimate_gas(input);
+        assert!(matches!(result, Err(ShadowEvmError::ExecutionReverted(_))));
+
+        // Calls a child storing to a fresh slot with all its gas, reverting
+        // if the call fails. The child only gets 63/64 of what is left, so
+        // the estimate exceeds the gas used with a generous limit.
+        let child = Address::repeat_byte(0xc1);
+        let mut parent = vec![
+            0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73,
+        ];
+        parent.extend_from_slice(child.as_slice());
+        parent.extend_from_slice(&[
+            0x5a, 0xf1, 0x15, 0x60, 0x26, 0x57, 0x00, 0x5b, 0x60, 0x00, 0x60, 0x00, 0xfd,
+        ]);
+        let mut nested = SampleKind::Transfer.input();
+        nested.pre_state.insert_account(
+            child,
+            AccountState::new_contract(vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00], U256::ZERO),
+        );
+        nested
+            .pre_state
+            .insert_account(looper, AccountState::new_contract(parent, U256::ZERO));
+        nested.tx = TxInput::call(sender, looper, vec![]).with_gas_limit(1_000_000);
+        let generous = ShadowExecutor::simulate(nested.clone()).unwrap();
+        assert!(generous.is_success());
+
+        let estimate = ShadowExecutor::estimate_min_gas(nested.clone()).unwrap();
+        assert!(estimate > generous.gas_used);
+        let run = |gas_limit: Gas| {
+            let mut input = nested.clone();
+            input.tx.gas_limit = gas_limit;
+            ShadowExecutor::simulate(input).unwrap()
+        };
+        assert!(run(estimate).is_success());
+        assert!(run(estimate - 1).is_revert());
+    }
+
+    /// Test restoring the pre-state from the post-state's snapshot
+    #[test]
+    fn test_builder_state_snapshot() {
+        let erc20 = SampleKind::Erc20.input();
+        let pre_root = erc20.pre_state.compute_state_root();
+
+        let (mut output, commitment) = ExecutionBuilder::new()
+            .with_tx(erc20.tx.clone())
+            .with_state(erc20.pre_state.clone())
+            .with_state_snapshot(true)
+            .execute()
+            .unwrap();
+        assert!(output.is_success());
+        assert_eq!(commitment.pre_state_root, pre_root);
+        // The snapshot is not committed to
+        let plain = ShadowExecutor::execute(erc20).unwrap().1;
+        assert_eq!(commitment.commitment, plain.commitment);
+
+        // Try a larger transfer from the same starting point
+        output.post_state.revert_to_snapshot(0).unwrap();
+        assert_eq!(output.post_state.compute_state_root(), pre_root);
+        let alternative = ExecutionBuilder::new()
+            .with_tx(SampleKind::Revert.input().tx)
+            .with_state(output.post_state)
+            .execute()
+            .unwrap()
+            .0;
+        assert!(alternative.is_revert());
+    }
+
     /// Test simulation (dry-run)
     #[test]
     fn test_simulate() {
@@ -431,4 +1687,1489 @@ mod tests {
         assert_eq!(output1.gas_used, output2.gas_used);
         assert_eq!(output1.post_state_root(), output2.post_state_root());
     }
+
+    /// Test that authorized code runs on a self-call and is cleared afterwards
+    #[test]
+    fn test_authorized_code_self_call() {
+        let caller = Address::repeat_byte(0x01);
+
+        // PUSH1 1, PUSH1 0, SSTORE, STOP
+        let code = vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00];
+
+        let mut pre_state = InMemoryDB::new();
+        pre_state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let block = BlockEnv::default().with_hardfork(Hardfork::Prague);
+        let tx = TxInput::call(caller, caller, vec![])
+            .with_gas_limit(100_000)
+            .with_authorized_code(code);
+
+        // Delegation is gated on Prague
+        let input = ExecutionInput::new(BlockEnv::default(), tx.clone(), pre_state.clone());
+        assert!(matches!(
+            ShadowExecutor::execute(input),
+            Err(ShadowEvmError::InvalidTransaction(_))
+        ));
+
+        let input = ExecutionInput::new(block, tx, pre_state.clone());
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+        assert!(output.is_success());
+
+        // The delegated code wrote to the caller's storage...
+        assert_eq!(output.storage_of(caller, U256::ZERO), U256::from(1u64));
+        assert_eq!(output.nonce_of(caller), 1);
+
+        // ...but the delegation does not outlive the transaction
+        let account = output.post_state.get_account(&caller).unwrap();
+        assert!(!account.is_contract());
+        assert_eq!(account.code_hash, KECCAK_EMPTY);
+
+        // Without delegation, a self-call is a plain no-op transfer
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::call(caller, caller, vec![]).with_gas_limit(100_000),
+            pre_state,
+        );
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+        let account = output.post_state.get_account(&caller).unwrap();
+        assert!(account.storage.is_empty());
+    }
+
+    /// Test that a dangling code hash fails validation instead of executing
+    #[test]
+    fn test_missing_code_rejected() {
+        let caller = Address::repeat_byte(0x01);
+        let target = Address::repeat_byte(0x02);
+
+        let mut broken = AccountState::new_with_balance(U256::ZERO);
+        broken.code_hash = crate::types::Hash::repeat_byte(0xab);
+
+        let mut pre_state = InMemoryDB::new();
+        pre_state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        pre_state.insert_account(target, broken);
+
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::call(caller, target, vec![]),
+            pre_state,
+        );
+
+        let err = ShadowExecutor::execute(input).unwrap_err();
+        assert!(matches!(err, ShadowEvmError::MissingCode { .. }));
+    }
+
+    /// Test that the builder enforces the log budget
+    #[test]
+    fn test_log_budget() {
+        let caller = Address::repeat_byte(0x01);
+        let emitter = Address::repeat_byte(0xee);
+
+        // PUSH1 32, PUSH1 0, LOG0, STOP (one log with 32 bytes of data)
+        let code = vec![0x60, 0x20, 0x60, 0x00, 0xa0, 0x00];
+
+        let builder = || {
+            let mut state = InMemoryDB::new();
+            state.insert_account(
+                caller,
+                AccountState::new_with_balance(U256::from(10u128.pow(18))),
+            );
+            state.insert_account(
+                emitter,
+                AccountState::new_contract(code.clone(), U256::ZERO),
+            );
+            ExecutionBuilder::new()
+                .with_state(state)
+                .with_tx(TxInput::call(caller, emitter, vec![]).with_gas_limit(100_000))
+        };
+
+        let (output, _) = builder().execute().unwrap();
+        assert_eq!(output.logs.len(), 1);
+
+        let err = builder().with_max_logs(0).execute().unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::LogBudgetExceeded {
+                logs: 1,
+                limit_logs: 0,
+                ..
+            }
+        ));
+
+        let err = builder().with_max_log_data_bytes(16).execute().unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::LogBudgetExceeded { data_bytes: 32, .. }
+        ));
+    }
+
+    /// Test that the builder enforces the state growth budget
+    #[test]
+    fn test_state_budget() {
+        let caller = Address::repeat_byte(0x01);
+        let counter = Address::repeat_byte(0xcc);
+
+        // PUSH1 1, PUSH1 0, SSTORE, STOP
+        let code = vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00];
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(counter, AccountState::new_contract(code, U256::ZERO));
+
+        let builder = || {
+            ExecutionBuilder::new()
+                .with_state(state.clone())
+                .with_tx(TxInput::call(caller, counter, vec![]).with_gas_limit(100_000))
+        };
+
+        assert!(builder().with_max_storage_growth(1).execute().is_ok());
+
+        let err = builder().with_max_storage_growth(0).execute().unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::StateBudgetExceeded { new_slots: 1, .. }
+        ));
+
+        // A transfer to a fresh address creates at least the receiver
+        let err = ExecutionBuilder::new()
+            .with_state(state.clone())
+            .with_tx(TxInput::transfer(
+                caller,
+                Address::repeat_byte(0x02),
+                U256::from(1u64),
+            ))
+            .with_max_new_accounts(0)
+            .execute()
+            .unwrap_err();
+        assert!(matches!(err, ShadowEvmError::StateBudgetExceeded { .. }));
+    }
+
+    /// Test that a plain transfer passes the conservation check
+    #[test]
+    fn test_conservation_check() {
+        let sender = Address::repeat_byte(0x01);
+        let receiver = Address::repeat_byte(0x02);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let block = BlockEnv {
+            coinbase: Address::repeat_byte(0xee),
+            ..Default::default()
+        };
+
+        let (output, _) = ExecutionBuilder::new()
+            .with_block(block.clone())
+            .with_state(state.clone())
+            .with_tx(TxInput::transfer(sender, receiver, U256::from(1000u64)))
+            .with_conservation_check(true)
+            .execute()
+            .unwrap();
+
+        assert!(output.is_success());
+
+        // Value sent to the miner changes its balance beyond the fee
+        let (output, _) = ExecutionBuilder::new()
+            .with_block(block.clone())
+            .with_state(state.clone())
+            .with_tx(TxInput::transfer(
+                sender,
+                block.coinbase,
+                U256::from(1000u64),
+            ))
+            .with_conservation_check(true)
+            .execute()
+            .unwrap();
+        assert!(output.is_success());
+
+        // So does a transaction the miner sends itself
+        state.insert_account(
+            block.coinbase,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let (output, _) = ExecutionBuilder::new()
+            .with_block(block.clone())
+            .with_state(state)
+            .with_tx(TxInput::transfer(
+                block.coinbase,
+                receiver,
+                U256::from(1000u64),
+            ))
+            .with_conservation_check(true)
+            .execute()
+            .unwrap();
+        assert!(output.is_success());
+    }
+
+    /// Test that a gas budget aborts expensive executions
+    #[test]
+    fn test_execute_bounded() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        // Three fresh SSTOREs: slot 0, 1, 2 = 1
+        let code = vec![
+            0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x01, 0x60, 0x01, 0x55, 0x60, 0x01, 0x60, 0x02,
+            0x55, 0x00,
+        ];
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(contract, AccountState::new_contract(code, U256::ZERO));
+
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::call(caller, contract, vec![]).with_gas_limit(1_000_000),
+            state,
+        );
+
+        let err = ShadowExecutor::execute_bounded(input.clone(), 50_000).unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::GasBudgetExceeded { max_gas: 50_000 }
+        ));
+
+        let (output, commitment) = ShadowExecutor::execute_bounded(input.clone(), 200_000).unwrap();
+        assert!(output.is_success());
+        assert!(output.gas_used > 50_000);
+        assert_eq!(output.gas_used + output.gas_left, 200_000);
+
+        // The commitment is to the capped input that was executed
+        let mut capped = input.clone();
+        capped.tx.gas_limit = 200_000;
+        let (_, expected) = ShadowExecutor::execute(capped).unwrap();
+        assert_eq!(commitment, expected);
+
+        // A budget above the gas limit changes nothing
+        let (_, bounded) = ShadowExecutor::execute_bounded(input.clone(), 2_000_000).unwrap();
+        let (_, unbounded) = ShadowExecutor::execute(input).unwrap();
+        assert_eq!(bounded, unbounded);
+    }
+
+    /// Test that nonces increment across a sequence from the same caller
+    #[test]
+    fn test_execute_sequence_nonces() {
+        let sender = Address::repeat_byte(0x01);
+        let receiver = Address::repeat_byte(0x02);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let txs = (0..5)
+            .map(|_| TxInput::transfer(sender, receiver, U256::from(1000u64)))
+            .collect();
+
+        let outputs = ShadowExecutor::execute_sequence(BlockEnv::default(), txs, state).unwrap();
+
+        assert_eq!(outputs.len(), 5);
+        for (i, output) in outputs.iter().enumerate() {
+            assert!(output.is_success());
+            assert_eq!(output.nonce_of(sender), i as u64 + 1);
+        }
+
+        assert_eq!(outputs[4].nonce_of(sender), 5);
+        assert_eq!(outputs[4].balance_of(receiver), U256::from(5000u64));
+    }
+
+    /// Test that transactions in one sequence can run under different hardforks
+    #[test]
+    fn test_sequence_per_tx_hardfork() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        // PUSH0, TLOAD, POP, PUSH1 1, PUSH0, SSTORE, STOP
+        // TLOAD only exists from Cancun on
+        let code = vec![0x5f, 0x5c, 0x50, 0x60, 0x01, 0x5f, 0x55, 0x00];
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(contract, AccountState::new_contract(code, U256::ZERO));
+
+        let block = BlockEnv::default().with_hardfork(Hardfork::Shanghai);
+        let tx = TxInput::call(caller, contract, vec![]).with_gas_limit(100_000);
+        let txs = vec![tx.clone(), tx.with_hardfork(Hardfork::Cancun)];
+
+        let outputs = ShadowExecutor::execute_sequence(block, txs, state).unwrap();
+
+        // Shanghai: TLOAD is undefined and halts
+        assert!(outputs[0].status.is_halt());
+        let account = outputs[0].post_state.get_account(&contract).unwrap();
+        assert!(account.storage.is_empty());
+
+        // Cancun: TLOAD succeeds and the store happens
+        assert!(outputs[1].is_success());
+        assert_eq!(
+            outputs[1].storage_of(contract, U256::ZERO),
+            U256::from(1u64)
+        );
+    }
+
+    /// Test that the same transaction is charged per the block's hardfork
+    #[test]
+    fn test_gas_differs_per_hardfork() {
+        let input = SampleKind::Deploy.input();
+        let words = input.tx.data.len().div_ceil(32) as u64;
+
+        let run = |hardfork: Hardfork| {
+            let mut input = input.clone();
+            input.block = input.block.with_hardfork(hardfork);
+            // The hardfork round-trips through the JSON input files
+            let json = serde_json::to_string(&input).unwrap();
+            let input: ExecutionInput = serde_json::from_str(&json).unwrap();
+            assert_eq!(input.block.hardfork, hardfork);
+            ShadowExecutor::execute(input).unwrap().0
+        };
+        let london = run(Hardfork::London);
+        let shanghai = run(Hardfork::Shanghai);
+
+        // Shanghai charges 2 gas per 32-byte word of init code (EIP-3860)
+        assert!(london.is_success() && shanghai.is_success());
+        assert_eq!(shanghai.gas_used, london.gas_used + 2 * words);
+    }
+
+    /// Test that an access list pre-warms the slots it lists
+    #[test]
+    fn test_access_list_gas() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        // PUSH1 0, SLOAD, STOP
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(
+            contract,
+            AccountState::new_contract(vec![0x60, 0x00, 0x54, 0x00], U256::ZERO),
+        );
+
+        let run = |tx: TxInput| {
+            let input = ExecutionInput::new(BlockEnv::default(), tx, state.clone());
+            let json = serde_json::to_string(&input).unwrap();
+            let input: ExecutionInput = serde_json::from_str(&json).unwrap();
+            ShadowExecutor::execute(input).unwrap().0.gas_used
+        };
+        let tx = TxInput::call(caller, contract, vec![]).with_gas_limit(100_000);
+        let cold = run(tx.clone());
+        let warm = run(tx.with_access_list(vec![(contract, vec![U256::ZERO])]));
+
+        // Listing costs 2400 for the address and 1900 for the slot; the
+        // SLOAD then skips the cold surcharge of 2000. The call target is
+        // warm either way.
+        assert_eq!(warm + 2000, cold + 2400 + 1900);
+    }
+
+    /// Test that tracing observes execution without changing its result
+    #[test]
+    fn test_execute_traced() {
+        use crate::trace::{diff_traces, ExecutionTrace};
+
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        // PUSH1 0, SLOAD, PUSH1 11, JUMPI, PUSH1 1, PUSH1 0, SSTORE, JUMPDEST, STOP
+        // Writes slot 0 only if it is still zero
+        let code = vec![
+            0x60, 0x00, 0x54, 0x60, 0x0b, 0x57, 0x60, 0x01, 0x60, 0x00, 0x55, 0x5b, 0x00,
+        ];
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(contract, AccountState::new_contract(code, U256::ZERO));
+
+        let tx = TxInput::call(caller, contract, vec![]).with_gas_limit(100_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx.clone(), state.clone());
+
+        let (output, commitment, trace) =
+            ShadowExecutor::execute_traced(input.clone(), ExecutionTrace::new()).unwrap();
+        let (_, plain) = ShadowExecutor::execute(input).unwrap();
+
+        assert!(output.is_success());
+        assert_eq!(commitment, plain);
+        assert_eq!(trace.len(), 9);
+        assert_eq!(trace.steps[0].opcode_name(), "PUSH1");
+        assert_eq!(trace.steps[8].opcode_name(), "STOP");
+        let sstore = &trace.steps[6];
+        assert_eq!(sstore.opcode_name(), "SSTORE");
+        assert!(sstore.gas_cost >= 20_000);
+
+        // With slot 0 already set, the JUMPI skips the store
+        state
+            .get_account_mut(&contract)
+            .unwrap()
+            .set_storage(U256::ZERO, U256::from(7u64));
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+        let (_, _, overridden) =
+            ShadowExecutor::execute_traced(input, ExecutionTrace::new()).unwrap();
+        assert_eq!(overridden.len(), 6);
+
+        // The loaded value is on the stack right after SLOAD
+        let diff = diff_traces(&trace, &overridden);
+        assert_eq!(diff.first_divergence, Some(2));
+        assert_eq!(diff.identical_prefix_len, 2);
+        assert_eq!(diff.steps_only_in_a, 7);
+        assert_eq!(diff.steps_only_in_b, 4);
+    }
+
+    /// Test that a reverted write shows up in the debug trace only
+    #[test]
+    fn test_execute_debug_keeps_reverted_writes() {
+        use crate::trace::StorageWrite;
+
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // PUSH1 0x2a, PUSH1 0x01, SSTORE, PUSH1 0, PUSH1 0, REVERT
+        state.insert_account(
+            contract,
+            AccountState::new_contract(
+                vec![0x60, 0x2a, 0x60, 0x01, 0x55, 0x60, 0x00, 0x60, 0x00, 0xfd],
+                U256::ZERO,
+            ),
+        );
+
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::call(caller, contract, vec![]).with_gas_limit(100_000),
+            state,
+        );
+        let (output, debug) = ShadowExecutor::execute_debug(input.clone()).unwrap();
+
+        assert!(output.is_revert());
+        assert_eq!(output.storage_of(contract, U256::from(1u64)), U256::ZERO);
+        assert_eq!(
+            debug.writes,
+            vec![StorageWrite {
+                address: contract,
+                slot: U256::from(1u64),
+                value: U256::from(0x2au64),
+                depth: 1,
+            }]
+        );
+        assert_eq!(
+            debug.would_be_storage()[&contract][&U256::from(1u64)],
+            U256::from(0x2au64)
+        );
+        assert_eq!(debug.trace.steps.last().unwrap().opcode_name(), "REVERT");
+
+        // Same output as a plain execution
+        let (plain, _) = ShadowExecutor::execute(input).unwrap();
+        assert_eq!(output.hash(), plain.hash());
+    }
+
+    /// Test that profiling charges a call only its own cost
+    #[test]
+    fn test_execute_profiled() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+        let callee = Address::repeat_byte(0xdd);
+
+        // PUSH1 0 (x5), PUSH20 callee, GAS, CALL, POP, STOP
+        let mut code = vec![
+            0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73,
+        ];
+        code.extend_from_slice(callee.as_slice());
+        code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x00]);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(contract, AccountState::new_contract(code, U256::ZERO));
+        // PUSH1 1, PUSH1 0, SSTORE, STOP
+        state.insert_account(
+            callee,
+            AccountState::new_contract(vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00], U256::ZERO),
+        );
+
+        let tx = TxInput::call(caller, contract, vec![]).with_gas_limit(200_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        let (output, commitment) = ShadowExecutor::execute_profiled(input.clone()).unwrap();
+        let (_, plain) = ShadowExecutor::execute(input).unwrap();
+        assert!(output.is_success());
+        assert_eq!(commitment, plain);
+
+        let profile = output.gas_profile.unwrap();
+        assert_eq!(profile.opcodes[0].name, "SSTORE");
+        assert_eq!(profile.opcodes[0].gas, 22_100);
+
+        // Cold account access only; the forwarded gas is charged to the callee
+        let call = profile.opcodes.iter().find(|op| op.name == "CALL").unwrap();
+        assert_eq!(call.gas, 2600);
+
+        assert_eq!(profile.total_gas(), output.gas_used - 21_000);
+    }
+
+    #[test]
+    fn test_gas_breakdown() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        // Writes 1 to slots 0..4, then returns one word of memory:
+        // (PUSH1 1, PUSH1 i, SSTORE) x5, PUSH1 32, PUSH1 0, RETURN
+        let mut code = Vec::new();
+        for slot in 0..5 {
+            code.extend_from_slice(&[0x60, 0x01, 0x60, slot, 0x55]);
+        }
+        code.extend_from_slice(&[0x60, 0x20, 0x60, 0x00, 0xf3]);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(contract, AccountState::new_contract(code, U256::ZERO));
+
+        // Four calldata bytes: two zero (4 gas each), two non-zero (16 each)
+        let tx =
+            TxInput::call(caller, contract, vec![0x00, 0x00, 0x01, 0x02]).with_gas_limit(500_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        let (output, _) = ShadowExecutor::execute_profiled(input).unwrap();
+        assert!(output.is_success());
+
+        let breakdown = output.gas_profile.unwrap().breakdown;
+        assert_eq!(breakdown.total(), output.gas_used);
+        assert_eq!(breakdown.base, 21_000);
+        assert_eq!(breakdown.calldata, 40);
+        assert_eq!(breakdown.storage, 5 * 22_100);
+        assert_eq!(breakdown.memory, 3);
+        assert_eq!(breakdown.call, 0);
+        assert!(
+            breakdown.storage
+                > breakdown.base + breakdown.calldata + breakdown.compute + breakdown.memory
+        );
+    }
+
+    /// Test that the raw result agrees with the converted output
+    #[test]
+    fn test_execute_raw() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        // PUSH1 1, PUSH1 0, SSTORE, STOP
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(
+            contract,
+            AccountState::new_contract(vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00], U256::ZERO),
+        );
+
+        let tx = TxInput::call(caller, contract, vec![]).with_gas_limit(100_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        let (raw, post_state) = ShadowExecutor::execute_raw(input.clone()).unwrap();
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+
+        assert_eq!(raw.result.gas_used(), output.gas_used);
+        assert!(raw.state[&contract].is_touched());
+        assert_eq!(
+            post_state.compute_state_root(),
+            output.post_state.compute_state_root()
+        );
+    }
+
+    /// Test that reverts and halts are committed like successes
+    #[test]
+    fn test_revert_and_halt_are_committed() {
+        let caller = Address::repeat_byte(0x01);
+        let reverter = Address::repeat_byte(0xaa);
+        let halter = Address::repeat_byte(0xbb);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // PUSH1 0, PUSH1 0, REVERT
+        state.insert_account(
+            reverter,
+            AccountState::new_contract(vec![0x60, 0x00, 0x60, 0x00, 0xfd], U256::ZERO),
+        );
+        // INVALID
+        state.insert_account(halter, AccountState::new_contract(vec![0xfe], U256::ZERO));
+
+        for (target, reverted) in [(reverter, true), (halter, false)] {
+            let tx = TxInput::call(caller, target, vec![]).with_gas_limit(100_000);
+            let input = ExecutionInput::new(BlockEnv::default(), tx, state.clone());
+            let input_hash = input.hash();
+
+            let (output, commitment) = ShadowExecutor::execute(input).unwrap();
+
+            assert_eq!(output.status.is_revert(), reverted);
+            assert_eq!(output.status.is_halt(), !reverted);
+            assert!(commitment.verify(&input_hash, &output.hash()));
+            assert_eq!(commitment.post_state_root, output.post_state_root());
+            // The caller still paid for gas and bumped its nonce
+            assert_eq!(output.nonce_of(caller), 1);
+        }
+    }
+
+    /// Test that running out of gas is told apart from reverts and other halts
+    #[test]
+    fn test_ran_out_of_gas() {
+        let caller = Address::repeat_byte(0x01);
+        let reverter = Address::repeat_byte(0xaa);
+        let halter = Address::repeat_byte(0xbb);
+        let writer = Address::repeat_byte(0xcc);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // PUSH1 0, PUSH1 0, REVERT
+        state.insert_account(
+            reverter,
+            AccountState::new_contract(vec![0x60, 0x00, 0x60, 0x00, 0xfd], U256::ZERO),
+        );
+        // INVALID
+        state.insert_account(halter, AccountState::new_contract(vec![0xfe], U256::ZERO));
+        // PUSH1 1, PUSH1 0, SSTORE, STOP
+        state.insert_account(
+            writer,
+            AccountState::new_contract(vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00], U256::ZERO),
+        );
+
+        let run = |target, gas_limit| {
+            let tx = TxInput::call(caller, target, vec![]).with_gas_limit(gas_limit);
+            let input = ExecutionInput::new(BlockEnv::default(), tx, state.clone());
+            ShadowExecutor::execute(input).unwrap().0
+        };
+
+        // The SSTORE does not fit in the limit
+        let out_of_gas = run(writer, 25_000);
+        assert!(out_of_gas.status.is_halt());
+        assert_eq!(out_of_gas.halt_reason, Some(HaltReason::OutOfGas));
+        assert!(out_of_gas.ran_out_of_gas());
+        assert_eq!(out_of_gas.gas_used, 25_000);
+
+        let reverted = run(reverter, 100_000);
+        assert!(reverted.is_revert());
+        assert!(!reverted.ran_out_of_gas());
+
+        let invalid = run(halter, 100_000);
+        assert_eq!(
+            invalid.halt_reason,
+            Some(HaltReason::InvalidOpcode {
+                opcode: 0xfe,
+                pc: 0
+            })
+        );
+        assert!(!invalid.ran_out_of_gas());
+
+        assert!(run(writer, 100_000).halt_reason.is_none());
+    }
+
+    /// Test that halts other than the common ones get their own reason
+    #[test]
+    fn test_halt_reason_variants() {
+        let caller = Address::repeat_byte(0x01);
+        let jumper = Address::repeat_byte(0xaa);
+        let copier = Address::repeat_byte(0xbb);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // PUSH1 3, JUMP, STOP: offset 3 is not a JUMPDEST
+        state.insert_account(
+            jumper,
+            AccountState::new_contract(vec![0x60, 0x03, 0x56, 0x00], U256::ZERO),
+        );
+        // PUSH1 1, PUSH1 0, PUSH1 0, RETURNDATACOPY: there is no return data
+        state.insert_account(
+            copier,
+            AccountState::new_contract(vec![0x60, 0x01, 0x60, 0x00, 0x60, 0x00, 0x3e], U256::ZERO),
+        );
+
+        let run = |target| {
+            let tx = TxInput::call(caller, target, vec![]).with_gas_limit(100_000);
+            let input = ExecutionInput::new(BlockEnv::default(), tx, state.clone());
+            ShadowExecutor::execute(input).unwrap().0.halt_reason
+        };
+
+        assert_eq!(run(jumper), Some(HaltReason::InvalidJump));
+        assert_eq!(run(copier), Some(HaltReason::ReturnDataOutOfBounds));
+    }
+
+    #[test]
+    fn test_invalid_opcode_location() {
+        use crate::trace::ExecutionTrace;
+
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // PUSH1 1, PUSH1 2, ADD, INVALID
+        state.insert_account(
+            contract,
+            AccountState::new_contract(vec![0x60, 0x01, 0x60, 0x02, 0x01, 0xfe], U256::ZERO),
+        );
+
+        let tx = TxInput::call(caller, contract, vec![]).with_gas_limit(100_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        let (output, _) = ShadowExecutor::execute(input.clone()).unwrap();
+        assert!(output.status.is_halt());
+        assert_eq!(
+            output.halt_reason,
+            Some(HaltReason::InvalidOpcode {
+                opcode: 0xfe,
+                pc: 5
+            })
+        );
+
+        // Simulation reports the same location, as does an inspected run
+        let simulated = ShadowExecutor::simulate(input.clone()).unwrap();
+        assert_eq!(simulated.halt_reason, output.halt_reason);
+        let (traced, _, trace) =
+            ShadowExecutor::execute_traced(input, ExecutionTrace::new()).unwrap();
+        assert_eq!(traced.halt_reason, output.halt_reason);
+        // Run once: three steps and the INVALID
+        assert_eq!(trace.len(), 4);
+    }
+
+    #[test]
+    fn test_max_call_depth() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // Counts its depth in slot 0, then calls itself with all gas:
+        // PUSH1 0, SLOAD, PUSH1 1, ADD, PUSH1 0, SSTORE,
+        // PUSH1 0, PUSH1 0, PUSH1 0, PUSH1 0, PUSH1 0, ADDRESS, GAS, CALL, POP, STOP
+        state.insert_account(
+            contract,
+            AccountState::new_contract(
+                vec![
+                    0x60, 0x00, 0x54, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00,
+                    0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x30, 0x5a, 0xf1, 0x50, 0x00,
+                ],
+                U256::ZERO,
+            ),
+        );
+
+        let tx = TxInput::call(caller, contract, vec![]).with_gas_limit(1_000_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        // Without a limit the recursion ends when gas runs out, and only the
+        // innermost call fails
+        let (unlimited, _) = ShadowExecutor::execute(input.clone()).unwrap();
+        assert!(unlimited.is_success());
+        assert!(unlimited.storage_of(contract, U256::ZERO) > U256::from(3u64));
+
+        let input = input.with_options(CallOptions::default().with_max_call_depth(3));
+        let (limited, _) = ShadowExecutor::execute(input).unwrap();
+        assert!(limited.status.is_halt());
+        assert_eq!(limited.halt_reason, Some(HaltReason::CallTooDeep));
+        assert_eq!(limited.storage_of(contract, U256::ZERO), U256::ZERO);
+    }
+
+    #[test]
+    fn test_transfer_to_contract() {
+        let sender = Address::repeat_byte(0x01);
+        let eoa = Address::repeat_byte(0x02);
+        let contract = Address::repeat_byte(0xcc);
+        let value = U256::from(1000u64);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // Fallback that always reverts: PUSH1 0, PUSH1 0, REVERT
+        state.insert_account(
+            contract,
+            AccountState::new_contract(vec![0x60, 0x00, 0x60, 0x00, 0xfd], U256::ZERO),
+        );
+
+        let run = |tx| {
+            let input = ExecutionInput::new(BlockEnv::default(), tx, state.clone());
+            ShadowExecutor::execute(input).unwrap().0
+        };
+
+        // A plain transfer cannot pay for the fallback
+        let transfer = run(TxInput::transfer(sender, contract, value));
+        assert_eq!(transfer.halt_reason, Some(HaltReason::OutOfGas));
+
+        // With gas for the fallback, the contract's revert is reported
+        let tx = TxInput::transfer_or_call(sender, contract, value, &state);
+        assert!(tx.gas_limit > 21_000);
+        let called = run(tx);
+        assert!(called.is_revert());
+        assert_eq!(called.balance_of(contract), U256::ZERO);
+
+        // To an EOA it is a plain transfer
+        let tx = TxInput::transfer_or_call(sender, eoa, value, &state);
+        assert_eq!(tx.gas_limit, 21_000);
+        assert_eq!(run(tx).balance_of(eoa), value);
+    }
+
+    /// Test that a burn credits the zero address like any other account
+    #[test]
+    fn test_burn_to_zero_address() {
+        let sender = Address::repeat_byte(0x01);
+        let value = U256::from(1000u64);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let burn = |state: InMemoryDB| {
+            let tx = TxInput::transfer(sender, Address::ZERO, value)
+                .with_nonce(state.get_account(&sender).unwrap().nonce);
+            ShadowExecutor::execute(ExecutionInput::new(BlockEnv::default(), tx, state)).unwrap()
+        };
+
+        // The first burn creates the zero account
+        let (first, commitment) = burn(state.clone());
+        assert!(first.is_success());
+        assert_eq!(first.balance_of(Address::ZERO), value);
+
+        let mut expected = state;
+        let sender_account = first.post_state.get_account(&sender).unwrap().clone();
+        expected.insert_account(sender, sender_account);
+        expected.insert_account(Address::ZERO, AccountState::new_with_balance(value));
+        assert_eq!(commitment.post_state_root, expected.compute_state_root());
+
+        // Later burns credit it
+        let (second, _) = burn(first.post_state);
+        assert_eq!(second.balance_of(Address::ZERO), value * U256::from(2u64));
+    }
+
+    /// Test that an impossible account state is not committed
+    #[test]
+    fn test_invalid_state_update() {
+        use revm::primitives::{Account, AccountInfo, ExecutionResult};
+
+        let address = Address::repeat_byte(0x01);
+        let result = |info: AccountInfo| {
+            let mut account = Account::from(info);
+            account.mark_touch();
+            ResultAndState {
+                result: ExecutionResult::Revert {
+                    gas_used: 0,
+                    output: Default::default(),
+                },
+                state: [(address, account)].into_iter().collect(),
+            }
+        };
+
+        let mut db = InMemoryDB::new();
+        let info = AccountInfo {
+            balance: U256::from(1u64),
+            nonce: u64::MAX - 1,
+            ..AccountInfo::default()
+        };
+        ShadowExecutor::apply_state_changes(&mut db, &result(info.clone())).unwrap();
+        assert_eq!(db.get_account(&address).unwrap().nonce, u64::MAX - 1);
+
+        let wrapped = AccountInfo {
+            nonce: u64::MAX,
+            ..info
+        };
+        let err = ShadowExecutor::apply_state_changes(&mut db, &result(wrapped)).unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::InvalidStateUpdate { address: a, .. } if a == address
+        ));
+        assert_eq!(db.get_account(&address).unwrap().nonce, u64::MAX - 1);
+    }
+
+    #[test]
+    fn test_gas_left() {
+        let sender = Address::repeat_byte(0x01);
+        let receiver = Address::repeat_byte(0x02);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let execute = |gas_limit| {
+            let tx =
+                TxInput::transfer(sender, receiver, U256::from(1000u64)).with_gas_limit(gas_limit);
+            let input = ExecutionInput::new(BlockEnv::default(), tx, state.clone());
+            ShadowExecutor::execute(input).unwrap()
+        };
+
+        let (output, commitment) = execute(100_000);
+        assert_eq!(output.gas_left, 100_000 - 21_000);
+        assert_eq!(output.gas_utilization_percent(), 21);
+
+        // Same execution, different limit: the commitment differs
+        let (exact, exact_commitment) = execute(21_000);
+        assert_eq!(exact.gas_left, 0);
+        assert_eq!(exact.gas_utilization_percent(), 100);
+        assert_ne!(exact_commitment.output_hash, commitment.output_hash);
+    }
+
+    #[test]
+    fn test_commitment_block_context() {
+        let sender = Address::repeat_byte(0x01);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let block = BlockEnv {
+            number: 19_000_000,
+            timestamp: 1_700_000_000,
+            ..BlockEnv::default()
+        };
+        let tx = TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64));
+        let input = ExecutionInput::new(block.clone(), tx, state);
+
+        let (output, commitment) = ShadowExecutor::execute(input.clone()).unwrap();
+        assert_eq!(commitment.block_number, block.number);
+        assert_eq!(commitment.timestamp, block.timestamp);
+        assert!(commitment.verify(&input.hash(), &output.hash()));
+        assert_eq!(
+            commitment,
+            ExecutionCommitment::from_execution(&input, &output)
+        );
+
+        // Claiming another block breaks the commitment
+        let mut stale = commitment.clone();
+        stale.block_number -= 1;
+        assert!(!stale.verify(&input.hash(), &output.hash()));
+    }
+
+    /// Test that parallel execution keeps the input order
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_execute_many() {
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let inputs: Vec<ExecutionInput> = (1..=100u64)
+            .map(|value| {
+                let tx = TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(value));
+                ExecutionInput::new(BlockEnv::default(), tx, state.clone())
+            })
+            .collect();
+
+        let results = ShadowExecutor::execute_many(inputs.clone());
+        assert_eq!(results.len(), 100);
+        for (input, result) in inputs.into_iter().zip(results) {
+            let (output, commitment) = result.unwrap();
+            assert!(output.is_success());
+            assert_eq!(
+                output.balance_of(Address::repeat_byte(0x02)),
+                input.tx.value
+            );
+            assert_eq!(commitment.input_hash, input.hash());
+        }
+    }
+
+    /// Test that the audit trail reproduces every committed hash
+    #[test]
+    fn test_execute_audited() {
+        use sha2::Digest;
+
+        let input = SampleKind::Erc20
+            .input()
+            .with_hash_algo(StateHashAlgo::Sha256)
+            .with_data_hashes()
+            .with_storage_claim(SampleKind::token(), U256::ZERO);
+
+        let (_, commitment, trail) = ShadowExecutor::execute_audited(input.clone()).unwrap();
+        assert_eq!(commitment, ShadowExecutor::execute(input).unwrap().1);
+
+        // Recompute with SHA-256 directly, not through the crate's hashing
+        let sha256 = |data: &[u8]| Hash::from_slice(&sha2::Sha256::digest(data));
+        assert_eq!(sha256(&trail.input_preimage), commitment.input_hash);
+        assert_eq!(sha256(&trail.output_preimage), commitment.output_hash);
+        assert_eq!(sha256(&trail.commitment_preimage), commitment.commitment);
+        assert!(trail.verify(&commitment));
+
+        let mut tampered = trail.clone();
+        tampered.commitment_preimage[0] ^= 1;
+        assert!(!tampered.verify(&commitment));
+    }
+
+    /// Test that the chain ID is committed and exposed
+    #[test]
+    fn test_commitment_chain_id() {
+        let sender = Address::repeat_byte(0x01);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let tx = TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64));
+        let run = |chain_id: u64| {
+            let block = BlockEnv {
+                chain_id,
+                ..BlockEnv::default()
+            };
+            ShadowExecutor::execute(ExecutionInput::new(block, tx.clone(), state.clone()))
+                .unwrap()
+                .1
+        };
+
+        let mainnet = run(1);
+        let optimism = run(10);
+        assert_eq!(mainnet.chain_id, 1);
+        assert_eq!(optimism.chain_id, 10);
+        assert_ne!(mainnet.commitment, optimism.commitment);
+
+        // The journal carries the chain ID, bound by the commitment
+        let journal = bincode::serialize(&optimism).unwrap();
+        let decoded: ExecutionCommitment = bincode::deserialize(&journal).unwrap();
+        assert_eq!(decoded.chain_id, 10);
+        let mut replayed = decoded.clone();
+        replayed.chain_id = 1;
+        assert!(!replayed.verify(&decoded.input_hash, &decoded.output_hash));
+    }
+
+    /// Test that the chosen hash function is used and recorded
+    #[test]
+    fn test_commitment_hash_algo() {
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let tx = TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1u64));
+        let keccak_input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        let (_, keccak) = ShadowExecutor::execute(keccak_input.clone()).unwrap();
+        assert_eq!(keccak.hash_algo, StateHashAlgo::Keccak);
+
+        let input = keccak_input.with_hash_algo(StateHashAlgo::Sha256);
+        let input_hash = input.hash();
+        let (output, sha256) = ShadowExecutor::execute(input.clone()).unwrap();
+
+        assert_eq!(sha256.hash_algo, StateHashAlgo::Sha256);
+        assert_eq!(sha256, ExecutionCommitment::from_execution(&input, &output));
+        assert!(sha256.verify(&input_hash, &output.hash_with_algo(StateHashAlgo::Sha256)));
+        assert_ne!(sha256.post_state_root, keccak.post_state_root);
+        assert_ne!(sha256.commitment, keccak.commitment);
+    }
+
+    /// Test that the coinbase only exists in the post-state if it was paid
+    #[test]
+    fn test_coinbase_in_post_state() {
+        let sender = Address::repeat_byte(0x01);
+        let coinbase = Address::repeat_byte(0xcb);
+        let block = BlockEnv {
+            coinbase,
+            ..BlockEnv::default()
+        };
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let transfer = TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1u64));
+        let run = |gas_price: U256| {
+            let mut tx = transfer.clone();
+            tx.gas_price = gas_price;
+            let input = ExecutionInput::new(block.clone(), tx, state.clone());
+            ShadowExecutor::execute(input).unwrap()
+        };
+
+        // Paying exactly the base fee leaves no priority fee for the coinbase
+        let (zero_fee, zero_fee_commitment) = run(block.base_fee);
+        assert!(zero_fee.post_state.get_account(&coinbase).is_none());
+
+        let (paid, paid_commitment) = run(block.base_fee * U256::from(2u64));
+        assert_eq!(
+            paid.balance_of(coinbase),
+            block.base_fee * U256::from(21_000u64)
+        );
+
+        assert_ne!(
+            zero_fee_commitment.post_state_root,
+            paid_commitment.post_state_root
+        );
+        // Besides the coinbase, only the sender (who paid the fee) differs
+        let mut without_coinbase = paid.post_state.clone();
+        without_coinbase.remove_account(&coinbase);
+        assert_eq!(
+            without_coinbase.changed_accounts(&zero_fee.post_state),
+            vec![sender]
+        );
+    }
+
+    /// Test that an EIP-1559 transaction pays and records the effective price
+    #[test]
+    fn test_effective_gas_price_recorded() {
+        let sender = Address::repeat_byte(0x01);
+        let coinbase = Address::repeat_byte(0xcb);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        // (base fee, effective price): the max fee of 100 caps the last one
+        for (base_fee, price) in [(0u64, 2u64), (50, 52), (99, 100)] {
+            let block = BlockEnv {
+                coinbase,
+                base_fee: U256::from(base_fee),
+                ..BlockEnv::default()
+            };
+            let tx = TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1u64))
+                .with_eip1559_fees(U256::from(100u64), U256::from(2u64));
+            let input = ExecutionInput::new(block, tx, state.clone());
+            let (output, _) = ShadowExecutor::execute(input).unwrap();
+
+            assert_eq!(output.effective_gas_price, U256::from(price));
+            // Only the part above the base fee reaches the coinbase
+            assert_eq!(
+                output.balance_of(coinbase),
+                U256::from((price - base_fee) * 21_000)
+            );
+            assert_eq!(
+                output.balance_of(sender),
+                U256::from(10u128.pow(18)) - U256::from(price * 21_000 + 1)
+            );
+        }
+    }
+
+    /// Test that self-destructed accounts leave the post-state
+    #[test]
+    fn test_selfdestruct_removes_account() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+        let heir = Address::with_last_byte(0x0e);
+
+        // PUSH1 0x0e, SELFDESTRUCT
+        let code = vec![0x60, 0x0e, 0xff];
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(
+            contract,
+            AccountState::new_contract(code.clone(), U256::from(5u64)),
+        );
+
+        // A contract destroying itself in its constructor never persists
+        let tx = TxInput::create(caller, code, U256::from(7u64)).with_gas_limit(100_000);
+        let (output, _) =
+            ShadowExecutor::execute(ExecutionInput::new(BlockEnv::default(), tx, state.clone()))
+                .unwrap();
+        let created = output.created_address.unwrap();
+        assert!(output.is_success());
+        assert!(!output.post_state.accounts.contains_key(&created));
+        assert_eq!(output.balance_of(heir), U256::from(7u64));
+
+        // An existing contract is only destroyed before Cancun (EIP-6780)
+        let call = TxInput::call(caller, contract, vec![]).with_gas_limit(100_000);
+        for (hardfork, destroyed) in [(Hardfork::Shanghai, true), (Hardfork::Cancun, false)] {
+            let block = BlockEnv::default().with_hardfork(hardfork);
+            let input = ExecutionInput::new(block, call.clone(), state.clone());
+            let (output, _) = ShadowExecutor::execute(input).unwrap();
+            assert!(output.is_success());
+            assert_eq!(
+                !output.post_state.accounts.contains_key(&contract),
+                destroyed
+            );
+            assert_eq!(output.balance_of(heir), U256::from(5u64));
+        }
+    }
+
+    /// Test that a forbidden opcode fails the execution
+    #[test]
+    fn test_execute_restricted() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // PUSH1 0, SELFDESTRUCT
+        state.insert_account(
+            contract,
+            AccountState::new_contract(vec![0x60, 0x00, 0xff], U256::ZERO),
+        );
+
+        let tx = TxInput::call(caller, contract, vec![]).with_gas_limit(100_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        let err =
+            ShadowExecutor::execute_restricted(input.clone(), &[Opcode::SELFDESTRUCT]).unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::ForbiddenOpcode(Opcode::SELFDESTRUCT)
+        ));
+
+        let (output, commitment) =
+            ShadowExecutor::execute_restricted(input.clone(), &[Opcode::CREATE]).unwrap();
+        assert!(output.is_success());
+        assert_eq!(commitment, ShadowExecutor::execute(input).unwrap().1);
+    }
+
+    /// Test that disabling the nonce increment keeps the caller's nonce
+    #[test]
+    fn test_call_options_skip_nonce_increment() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        let mut caller_account = AccountState::new_with_balance(U256::from(10u128.pow(18)));
+        caller_account.nonce = 5;
+        let mut state = InMemoryDB::new();
+        state.insert_account(caller, caller_account);
+        // PUSH1 1, PUSH1 0, SSTORE, STOP
+        state.insert_account(
+            contract,
+            AccountState::new_contract(vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00], U256::ZERO),
+        );
+
+        let tx = TxInput::call(caller, contract, vec![])
+            .with_gas_limit(100_000)
+            .with_nonce(5);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        let view = input
+            .clone()
+            .with_options(CallOptions::default().with_nonce_increment(false));
+        let (viewed, view_commitment) = ShadowExecutor::execute(view).unwrap();
+        let (executed, commitment) = ShadowExecutor::execute(input).unwrap();
+
+        assert_eq!(viewed.nonce_of(caller), 5);
+        assert_eq!(executed.nonce_of(caller), 6);
+
+        // Everything else matches the real execution
+        assert_eq!(viewed.storage_of(contract, U256::ZERO), U256::from(1u64));
+        assert_eq!(viewed.balance_of(caller), executed.balance_of(caller));
+        assert_ne!(view_commitment.post_state_root, commitment.post_state_root);
+        // The options are part of what the commitment binds
+        assert_ne!(view_commitment.input_hash, commitment.input_hash);
+    }
+
+    /// Test that a sender with code is rejected unless EIP-3607 is disabled
+    #[test]
+    fn test_eip3607_toggle() {
+        let sender = Address::repeat_byte(0xcc);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_contract(vec![0x00], U256::from(10u128.pow(18))),
+        );
+
+        // Contracts start at nonce 1
+        let tx = TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64))
+            .with_nonce(1);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        assert!(matches!(
+            ShadowExecutor::execute(input.clone()),
+            Err(ShadowEvmError::SenderHasCode { address }) if address == sender
+        ));
+
+        let input = input.with_options(CallOptions::default().with_eip3607(false));
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+        assert!(output.is_success());
+        assert_eq!(
+            output.balance_of(Address::repeat_byte(0x02)),
+            U256::from(1000u64)
+        );
+    }
+
+    /// Test that public inputs are bound into the commitment
+    #[test]
+    fn test_public_inputs_commitment() {
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let tx = TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1u64));
+        let plain = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        let request_1 = plain.clone().with_public_inputs(b"request-1".to_vec());
+        let request_2 = plain.clone().with_public_inputs(b"request-2".to_vec());
+
+        let (_, none) = ShadowExecutor::execute(plain).unwrap();
+        let (output_1, commitment_1) = ShadowExecutor::execute(request_1.clone()).unwrap();
+        let (output_2, commitment_2) = ShadowExecutor::execute(request_2).unwrap();
+
+        assert_eq!(none.public_inputs_hash, Hash::ZERO);
+        assert_eq!(commitment_1.public_inputs_hash, keccak256(b"request-1"));
+        assert_ne!(
+            commitment_1.public_inputs_hash,
+            commitment_2.public_inputs_hash
+        );
+        assert_ne!(commitment_1.input_hash, commitment_2.input_hash);
+        assert_ne!(commitment_1.commitment, commitment_2.commitment);
+
+        // Execution itself is unaffected
+        assert_eq!(output_1.hash(), output_2.hash());
+        assert_eq!(
+            commitment_1,
+            ExecutionCommitment::from_execution(&request_1, &output_1)
+        );
+    }
+
+    /// Test that calldata and return data hashes are committed on request
+    #[test]
+    fn test_data_hashes_commitment() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        // Returns the first calldata word:
+        // PUSH1 0, CALLDATALOAD, PUSH1 0, MSTORE, PUSH1 32, PUSH1 0, RETURN
+        let code = vec![
+            0x60, 0x00, 0x35, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+        ];
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(contract, AccountState::new_contract(code, U256::ZERO));
+
+        let tx = TxInput::call(caller, contract, U256::from(42u64).to_be_bytes_vec());
+        let plain = ExecutionInput::new(BlockEnv::default(), tx, state);
+        let hashed = plain.clone().with_data_hashes();
+
+        let (_, without) = ShadowExecutor::execute(plain).unwrap();
+        let (output, with) = ShadowExecutor::execute(hashed.clone()).unwrap();
+
+        assert_eq!(without.calldata_hash, None);
+        assert_eq!(without.return_data_hash, None);
+        assert_eq!(with.calldata_hash, Some(keccak256(&hashed.tx.data)));
+        assert_eq!(with.return_data_hash, Some(keccak256(&output.return_data)));
+        assert_eq!(output.return_data, U256::from(42u64).to_be_bytes_vec());
+
+        // Both hashes are bound by the commitment
+        assert!(with.verify(&with.input_hash, &with.output_hash));
+        let mut swapped = with.clone();
+        swapped.return_data_hash = Some(keccak256(b"other"));
+        assert!(!swapped.verify(&with.input_hash, &with.output_hash));
+        assert_eq!(with, ExecutionCommitment::from_execution(&hashed, &output));
+    }
+
+    /// Test that a memory cap stops a huge memory expansion
+    #[test]
+    fn test_execute_memory_bounded() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // PUSH3 0x100000, MLOAD, STOP: expands memory to 1 MiB + 32 bytes
+        state.insert_account(
+            contract,
+            AccountState::new_contract(vec![0x62, 0x10, 0x00, 0x00, 0x51, 0x00], U256::ZERO),
+        );
+
+        let tx = TxInput::call(caller, contract, vec![]).with_gas_limit(10_000_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        let (output, commitment, peak_words) =
+            ShadowExecutor::execute_memory_bounded(input.clone(), None).unwrap();
+        assert!(output.is_success());
+        assert_eq!(peak_words, 32_769);
+        assert_eq!(
+            commitment,
+            ShadowExecutor::execute(input.clone()).unwrap().1
+        );
+
+        let err = ShadowExecutor::execute_memory_bounded(input, Some(1024)).unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::MemoryLimitExceeded {
+                peak_words: 32_769,
+                max_words: 1024
+            }
+        ));
+    }
+
+    /// Test committing to a single post-execution storage value
+    #[test]
+    fn test_storage_claim() {
+        let input = SampleKind::Erc20.input();
+        let token = SampleKind::token();
+        let slot = erc20_balance_slot(SampleKind::receiver());
+
+        let (_, plain) = ShadowExecutor::execute(input.clone()).unwrap();
+        let claimed_input = input.with_storage_claim(token, slot);
+        let (output, claimed) = ShadowExecutor::execute(claimed_input.clone()).unwrap();
+
+        assert_eq!(plain.storage_claim, None);
+        assert_ne!(plain.commitment, claimed.commitment);
+        assert!(claimed.verify_storage_claim(token, slot, U256::from(30u64)));
+        assert!(!claimed.verify_storage_claim(token, slot, U256::from(31u64)));
+        assert!(claimed.verify_roots(&claimed_input, &output));
+
+        // The claimed value is bound by the commitment
+        let mut forged = claimed.clone();
+        forged.storage_claim.as_mut().unwrap().value = U256::from(31u64);
+        assert!(!forged.verify_storage_claim(token, slot, U256::from(31u64)));
+        assert!(!forged.verify_roots(&claimed_input, &output));
+
+        // ... and so is the post-state root it belongs to
+        let mut rerooted = claimed.clone();
+        rerooted.post_state_root = Hash::repeat_byte(0x01);
+        assert!(!rerooted.verify_storage_claim(token, slot, U256::from(30u64)));
+    }
 }
@@ -1,9 +1,13 @@
 //! Hashing utilities for Shadow-EVM
 //!
-//! Provides Keccak256 hashing for state commitments and input/output binding.
+//! Provides Keccak256 hashing for state commitments and input/output binding,
+//! with SHA-256 and Blake3 selectable through `StateHashAlgo`.
 //! All hashing functions are deterministic for ZK proof compatibility.
 
+use crate::input::TxInput;
 use crate::types::{Hash, B256};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use sha3::{Digest, Keccak256};
 
 /// Compute Keccak256 hash of arbitrary data
@@ -19,24 +23,40 @@ pub fn keccak256(data: &[u8]) -> Hash {
     B256::from_slice(&hasher.finalize())
 }
 
-/// Compute commitment hash for binding input to output
+/// Hash two hashes together: `keccak256(left || right)`
 ///
-/// This is the public input for ZK proof verification:
-/// `commitment = keccak256(input_hash || output_hash)`
+/// Links each commitment to its predecessor in a
+/// [`CommitmentChain`](crate::chain::CommitmentChain). Not the
+/// `ExecutionCommitment::commitment` of an execution, whose preimage also
+/// covers the block context, chain ID and optional claims; see
+/// `ExecutionCommitment::preimage`.
 ///
 /// # Arguments
-/// * `input_hash` - Hash of the ExecutionInput
-/// * `output_hash` - Hash of the ExecutionOutput
-///
-/// # Returns
-/// Combined commitment hash for on-chain verification
-pub fn compute_commitment(input_hash: &Hash, output_hash: &Hash) -> Hash {
+/// * `left` - First 32 bytes of the preimage
+/// * `right` - Last 32 bytes of the preimage
+pub fn compute_commitment(left: &Hash, right: &Hash) -> Hash {
     let mut data = [0u8; 64];
-    data[..32].copy_from_slice(input_hash.as_slice());
-    data[32..].copy_from_slice(output_hash.as_slice());
+    data[..32].copy_from_slice(left.as_slice());
+    data[32..].copy_from_slice(right.as_slice());
     keccak256(&data)
 }
 
+/// Domain tag for calldata commitments
+///
+/// Keeps calldata commitments from colliding with other keccak256 outputs
+/// (e.g. a plain hash of the same bytes, or an execution commitment).
+pub const CALLDATA_COMMITMENT_DOMAIN: &[u8] = b"shadow-evm/calldata/v1";
+
+/// Compute a commitment to a transaction's calldata without executing it
+///
+/// `commitment = keccak256(CALLDATA_COMMITMENT_DOMAIN || keccak256(selector || data))`,
+/// where `selector || data` is the full calldata in `tx.data`. Useful for
+/// data-availability style attestations that only bind the calldata.
+pub fn compute_calldata_commitment(tx: &TxInput) -> Hash {
+    let calldata_hash = keccak256(&tx.data);
+    hash_concat(&[CALLDATA_COMMITMENT_DOMAIN, calldata_hash.as_slice()])
+}
+
 /// Hash a serializable struct for commitment
 ///
 /// Uses bincode for deterministic serialization before hashing.
@@ -63,6 +83,49 @@ pub fn hash_concat(values: &[&[u8]]) -> Hash {
     B256::from_slice(&hasher.finalize())
 }
 
+/// A 32-byte hash function
+pub trait Hasher {
+    /// Hash arbitrary data
+    fn hash(&self, data: &[u8]) -> Hash;
+
+    /// Hash the concatenation of several byte slices
+    fn hash_concat(&self, values: &[&[u8]]) -> Hash {
+        self.hash(&values.concat())
+    }
+
+    /// Hash the bincode serialization of a value
+    fn hash_struct<T: Serialize>(&self, value: &T) -> Hash {
+        let bytes = bincode::serialize(value).expect("serialization should not fail");
+        self.hash(&bytes)
+    }
+}
+
+/// Hash function used for state roots and commitments
+///
+/// Keccak256 matches the EVM and is the default. SHA-256 suits verifiers
+/// with a cheap SHA-256 precompile; Blake3 is fast inside proof systems.
+/// The algorithm is recorded in every `ExecutionCommitment`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StateHashAlgo {
+    /// Keccak256
+    #[default]
+    Keccak,
+    /// SHA-256
+    Sha256,
+    /// Blake3 (32-byte output)
+    Blake3,
+}
+
+impl Hasher for StateHashAlgo {
+    fn hash(&self, data: &[u8]) -> Hash {
+        match self {
+            StateHashAlgo::Keccak => keccak256(data),
+            StateHashAlgo::Sha256 => B256::from_slice(&Sha256::digest(data)),
+            StateHashAlgo::Blake3 => B256::from(*blake3::hash(data).as_bytes()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,4 +195,44 @@ mod tests {
 
         assert_eq!(h1, h2);
     }
+
+    #[test]
+    fn test_calldata_commitment() {
+        let caller = crate::types::Address::repeat_byte(0x01);
+        let to = crate::types::Address::repeat_byte(0x02);
+
+        let transfer = TxInput::call(caller, to, vec![0xa9, 0x05, 0x9c, 0xbb, 0x01]);
+        let approve = TxInput::call(caller, to, vec![0x09, 0x5e, 0xa7, 0xb3, 0x01]);
+
+        let c1 = compute_calldata_commitment(&transfer);
+        assert_eq!(c1, compute_calldata_commitment(&transfer.clone()));
+        assert_ne!(c1, compute_calldata_commitment(&approve));
+
+        // Domain separated from a plain hash of the calldata
+        assert_ne!(c1, keccak256(&transfer.data));
+    }
+
+    #[test]
+    fn test_state_hash_algos() {
+        let algos = [
+            StateHashAlgo::Keccak,
+            StateHashAlgo::Sha256,
+            StateHashAlgo::Blake3,
+        ];
+
+        for algo in algos {
+            assert_eq!(algo.hash(b"hello"), algo.hash(b"hello"));
+            assert_eq!(algo.hash_concat(&[b"hel", b"lo"]), algo.hash(b"hello"));
+        }
+
+        assert_eq!(StateHashAlgo::Keccak.hash(b"hello"), keccak256(b"hello"));
+        assert_eq!(
+            hex::encode(StateHashAlgo::Sha256.hash(b"hello")),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(
+            hex::encode(StateHashAlgo::Blake3.hash(b"")),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+    }
 }
@@ -3,18 +3,37 @@
 //! Defines the public and private inputs for ZK proof generation.
 //! The hash of ExecutionInput becomes part of the ZK proof's public input.
 
-use crate::hashing::hash_struct;
-use crate::state::InMemoryDB;
+use crate::codec;
+use crate::errors::{Result, ShadowEvmError};
+use crate::hashing::{keccak256, Hasher, StateHashAlgo};
+use crate::spec::Hardfork;
+use crate::state::{InMemoryDB, StateRootMode};
+use crate::trie::rlp;
 use crate::types::{Address, BlockNumber, Gas, Hash, Timestamp, U256};
+use alloc::string::String;
 use alloc::vec::Vec;
+use alloy_primitives::B512;
+use revm::precompile::secp256k1::ecrecover;
 use serde::{Deserialize, Serialize};
 
 extern crate alloc;
 
+/// Bound on the base fee change between blocks (EIP-1559): at most 1/8
+pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+
+/// Half the secp256k1 curve order, the largest `s` EIP-2 allows
+const SECP256K1N_HALF: U256 = U256::from_limbs([
+    0xdfe9_2f46_681b_20a0,
+    0x5d57_6e73_57a4_501d,
+    0xffff_ffff_ffff_ffff,
+    0x7fff_ffff_ffff_ffff,
+]);
+
 /// Block environment for execution context
 ///
 /// Contains all block-level parameters needed for EVM execution.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct BlockEnv {
     /// Block number
     pub number: BlockNumber,
@@ -23,13 +42,26 @@ pub struct BlockEnv {
     /// Block gas limit
     pub gas_limit: Gas,
     /// Block coinbase (miner/validator address)
+    ///
+    /// Receives the priority fee. Like any account it only appears in the
+    /// post-state if it ends up non-empty, so a zero-fee transaction does
+    /// not create it.
     pub coinbase: Address,
     /// Block base fee (EIP-1559)
     pub base_fee: U256,
     /// Previous block's RANDAO value (post-merge)
     pub prev_randao: Hash,
+    /// Block difficulty (pre-merge)
+    ///
+    /// Committed alongside `prev_randao` whatever the hardfork, so blocks
+    /// differing only in the value their spec ignores still hash apart.
+    #[serde(default)]
+    pub difficulty: U256,
     /// Chain ID (1 for mainnet, etc.)
     pub chain_id: u64,
+    /// Hardfork rules the block executes under
+    #[serde(default)]
+    pub hardfork: Hardfork,
 }
 
 impl Default for BlockEnv {
@@ -41,7 +73,9 @@ impl Default for BlockEnv {
             coinbase: Address::ZERO,
             base_fee: U256::from(1_000_000_000u64), // 1 gwei
             prev_randao: Hash::ZERO,
+            difficulty: U256::ZERO,
             chain_id: 1, // Mainnet
+            hardfork: Hardfork::Cancun,
         }
     }
 }
@@ -70,12 +104,72 @@ impl BlockEnv {
             ..Default::default()
         }
     }
+
+    /// Set the hardfork
+    pub fn with_hardfork(mut self, hardfork: Hardfork) -> Self {
+        self.hardfork = hardfork;
+        self
+    }
+
+    /// Set the difficulty
+    pub fn with_difficulty(mut self, difficulty: U256) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+
+    /// Base fee of the next block under the EIP-1559 update rule
+    ///
+    /// Moves this block's base fee towards `gas_target` by at most 1/8: up
+    /// when `gas_used` is above the target (by at least 1 wei), down when
+    /// below, unchanged at the target. A zero target leaves it unchanged.
+    pub fn next_base_fee(&self, gas_used: Gas, gas_target: Gas) -> U256 {
+        if gas_used == gas_target || gas_target == 0 {
+            return self.base_fee;
+        }
+
+        let target = U256::from(gas_target);
+        let denominator = U256::from(BASE_FEE_MAX_CHANGE_DENOMINATOR);
+        if gas_used > gas_target {
+            let excess = U256::from(gas_used - gas_target);
+            let delta = (self.base_fee * excess / target / denominator).max(U256::from(1u64));
+            self.base_fee.saturating_add(delta)
+        } else {
+            let shortfall = U256::from(gas_target - gas_used);
+            self.base_fee - self.base_fee * shortfall / target / denominator
+        }
+    }
+}
+
+/// Envelope type of a transaction
+///
+/// Derived from the fee fields of a [`TxInput`]; see `TxInput::tx_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TxType {
+    /// Type 0: a single `gas_price`
+    Legacy,
+    /// Type 1: access list, legacy gas price (EIP-2930)
+    Eip2930,
+    /// Type 2: max fee and max priority fee per gas (EIP-1559)
+    Eip1559,
+}
+
+/// ECDSA signature of a legacy transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TxSignature {
+    /// Recovery id, including the chain id for EIP-155 transactions
+    pub v: u64,
+    /// Signature `r`
+    pub r: U256,
+    /// Signature `s`
+    pub s: U256,
 }
 
 /// Transaction input for EVM execution
 ///
 /// Contains all transaction parameters needed for a single EVM call.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct TxInput {
     /// Caller address (tx.origin and initial msg.sender)
     pub caller: Address,
@@ -88,9 +182,38 @@ pub struct TxInput {
     /// Gas limit for execution
     pub gas_limit: Gas,
     /// Gas price (for gas accounting)
+    ///
+    /// The max fee per gas if `max_priority_fee` is set.
     pub gas_price: U256,
     /// Transaction nonce
     pub nonce: u64,
+    /// Code temporarily attached to the caller (EIP-7702 style delegation)
+    ///
+    /// The code is only visible for the duration of this transaction and is
+    /// not persisted to the post-state.
+    #[serde(default)]
+    pub authorized_code: Option<Vec<u8>>,
+    /// Hardfork override for this transaction (defaults to the block's)
+    ///
+    /// Only needed when a sequence spans a fork boundary.
+    #[serde(default)]
+    pub hardfork: Option<Hardfork>,
+    /// Signature of the transaction, if it was signed
+    ///
+    /// Only used for `tx_hash`, which checks it against `caller`.
+    #[serde(default)]
+    pub signature: Option<TxSignature>,
+    /// Max priority fee per gas, making this an EIP-1559 transaction
+    ///
+    /// The sender then pays `effective_gas_price` rather than `gas_price`.
+    #[serde(default)]
+    pub max_priority_fee: Option<U256>,
+    /// Accounts and storage slots to pre-warm (EIP-2930)
+    ///
+    /// Listed entries pay the access list cost up front and are then warm
+    /// on first access, as on mainnet. Requires Berlin or later.
+    #[serde(default)]
+    pub access_list: Vec<(Address, Vec<U256>)>,
 }
 
 impl Default for TxInput {
@@ -103,6 +226,11 @@ impl Default for TxInput {
             gas_limit: 10_000_000,
             gas_price: U256::from(1_000_000_000u64), // 1 gwei
             nonce: 0,
+            authorized_code: None,
+            hardfork: None,
+            signature: None,
+            max_priority_fee: None,
+            access_list: Vec::new(),
         }
     }
 }
@@ -119,6 +247,17 @@ impl TxInput {
     }
 
     /// Create a value transfer transaction
+    ///
+    /// The gas limit covers a transfer to an account without code only. If
+    /// `to` is a contract, the transfer runs its `receive` or fallback
+    /// function, which runs out of gas at this limit; use
+    /// `transfer_or_call` when the target is not known to be an EOA.
+    ///
+    /// A transfer to `Address::ZERO` burns the value. It is not
+    /// special-cased: the zero address is credited like any other account,
+    /// created in the post-state if absent, and included in the state root.
+    /// The default `BlockEnv::coinbase` is also the zero address, so it
+    /// additionally receives any priority fee.
     pub fn transfer(caller: Address, to: Address, value: U256) -> Self {
         Self {
             caller,
@@ -129,6 +268,35 @@ impl TxInput {
         }
     }
 
+    /// Create a value transfer that also works if `to` holds code in `state`
+    ///
+    /// A plain `transfer` to an EOA; a `call` with empty calldata and the
+    /// default gas limit to a contract, whose code then decides whether to
+    /// accept the value (and may revert).
+    pub fn transfer_or_call(caller: Address, to: Address, value: U256, state: &InMemoryDB) -> Self {
+        match state.get_account(&to) {
+            Some(account) if account.is_contract() => Self {
+                value,
+                ..Self::call(caller, to, Vec::new())
+            },
+            _ => Self::transfer(caller, to, value),
+        }
+    }
+
+    /// Create an EIP-1559 call transaction
+    ///
+    /// `max_fee` is stored as `gas_price`; the sender pays
+    /// `effective_gas_price` of the block's base fee.
+    pub fn eip1559(
+        caller: Address,
+        to: Address,
+        data: Vec<u8>,
+        max_fee: U256,
+        max_priority_fee: U256,
+    ) -> Self {
+        Self::call(caller, to, data).with_eip1559_fees(max_fee, max_priority_fee)
+    }
+
     /// Create a contract creation transaction
     pub fn create(caller: Address, init_code: Vec<u8>, value: U256) -> Self {
         Self {
@@ -145,17 +313,230 @@ impl TxInput {
         self.to.is_none()
     }
 
+    /// Check that the transaction is unambiguously a create or a call
+    ///
+    /// A transaction without `to` is a create. Rejects the one combination
+    /// that cannot be meant as a create: an EIP-7702 `authorized_code`,
+    /// which only applies to calls. Empty init code is a valid create, of
+    /// a contract without code, as on Ethereum.
+    pub fn validate_intent(&self) -> Result<()> {
+        if self.is_create() && self.authorized_code.is_some() {
+            return Err(ShadowEvmError::InvalidTransaction(String::from(
+                "create transaction with authorized code; set `to` to make a call",
+            )));
+        }
+        Ok(())
+    }
+
     /// Set the gas limit
     pub fn with_gas_limit(mut self, gas_limit: Gas) -> Self {
         self.gas_limit = gas_limit;
         self
     }
 
+    /// Make this an EIP-1559 transaction with the given fee caps per gas
+    pub fn with_eip1559_fees(mut self, max_fee: U256, max_priority_fee: U256) -> Self {
+        self.gas_price = max_fee;
+        self.max_priority_fee = Some(max_priority_fee);
+        self
+    }
+
+    /// Price per gas paid in a block with `base_fee`
+    ///
+    /// `min(max_fee, base_fee + max_priority_fee)` for an EIP-1559
+    /// transaction, `gas_price` otherwise.
+    pub fn effective_gas_price(&self, base_fee: U256) -> U256 {
+        match self.max_priority_fee {
+            Some(priority_fee) => self.gas_price.min(base_fee.saturating_add(priority_fee)),
+            None => self.gas_price,
+        }
+    }
+
+    /// Pre-warm the given accounts and storage slots (EIP-2930)
+    pub fn with_access_list(mut self, access_list: Vec<(Address, Vec<U256>)>) -> Self {
+        self.access_list = access_list;
+        self
+    }
+
+    /// Envelope type, from the fee fields and access list that are set
+    pub fn tx_type(&self) -> TxType {
+        if self.max_priority_fee.is_some() {
+            TxType::Eip1559
+        } else if !self.access_list.is_empty() {
+            TxType::Eip2930
+        } else {
+            TxType::Legacy
+        }
+    }
+
     /// Set the nonce
     pub fn with_nonce(mut self, nonce: u64) -> Self {
         self.nonce = nonce;
         self
     }
+
+    /// Attach code to the caller for the duration of the transaction
+    pub fn with_authorized_code(mut self, code: Vec<u8>) -> Self {
+        self.authorized_code = Some(code);
+        self
+    }
+
+    /// Run this transaction under a different hardfork than its block
+    pub fn with_hardfork(mut self, hardfork: Hardfork) -> Self {
+        self.hardfork = Some(hardfork);
+        self
+    }
+
+    /// Attach the signature of the transaction
+    pub fn with_signature(mut self, signature: TxSignature) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+
+    /// Hardfork this transaction executes under within `block`
+    pub fn effective_hardfork(&self, block: &BlockEnv) -> Hardfork {
+        self.hardfork.unwrap_or(block.hardfork)
+    }
+
+    /// Ethereum hash of the signed legacy transaction on `chain_id`
+    ///
+    /// `keccak256(rlp([nonce, gas_price, gas_limit, to, value, data, v, r, s]))`,
+    /// matching the on-chain hash. Only returned for a transaction that
+    /// really is the one executed: the signature must recover to `caller`,
+    /// be replay-protected for `chain_id` if it uses EIP-155, and no
+    /// `authorized_code` may change what the caller runs. `None` otherwise,
+    /// for unsigned inputs and for typed transactions, whose hash covers
+    /// fields `TxInput` does not carry.
+    pub fn tx_hash(&self, chain_id: u64) -> Option<Hash> {
+        if self.authorized_code.is_some() || self.recover_signer(chain_id)? != self.caller {
+            return None;
+        }
+        let signature = self.signature?;
+        let mut fields = self.legacy_fields();
+        fields.extend([
+            rlp::encode_u64(signature.v),
+            rlp::encode_u256(signature.r),
+            rlp::encode_u256(signature.s),
+        ]);
+        Some(keccak256(&rlp::encode_list(&fields)))
+    }
+
+    /// Address whose key signed this legacy transaction for `chain_id`
+    ///
+    /// Accepts pre-EIP-155 (`v` of 27 or 28) and EIP-155 signatures. `None`
+    /// for unsigned and typed transactions, an EIP-155 `v` for another
+    /// chain, an `s` in the upper half of the curve order (EIP-2) or a
+    /// signature that does not recover.
+    pub fn recover_signer(&self, chain_id: u64) -> Option<Address> {
+        if self.tx_type() != TxType::Legacy {
+            return None;
+        }
+        let signature = self.signature?;
+        if signature.r.is_zero() || signature.s.is_zero() || signature.s > SECP256K1N_HALF {
+            return None;
+        }
+
+        let mut fields = self.legacy_fields();
+        let recovery_id = match signature.v {
+            27 | 28 => signature.v - 27,
+            v if v >= 35 && (v - 35) / 2 == chain_id => {
+                fields.extend([
+                    rlp::encode_u64(chain_id),
+                    rlp::encode_u64(0),
+                    rlp::encode_u64(0),
+                ]);
+                (v - 35) % 2
+            }
+            _ => return None,
+        };
+        let signing_hash = keccak256(&rlp::encode_list(&fields));
+
+        let mut compact = [0u8; 64];
+        compact[..32].copy_from_slice(&signature.r.to_be_bytes::<32>());
+        compact[32..].copy_from_slice(&signature.s.to_be_bytes::<32>());
+        let recovered = ecrecover(&B512::from(compact), recovery_id as u8, &signing_hash).ok()?;
+        Some(Address::from_slice(&recovered[12..]))
+    }
+
+    /// RLP items of the unsigned legacy transaction
+    fn legacy_fields(&self) -> Vec<Vec<u8>> {
+        let to = self.to.as_ref().map_or(&[][..], |to| to.as_slice());
+        alloc::vec![
+            rlp::encode_u64(self.nonce),
+            rlp::encode_u256(self.gas_price),
+            rlp::encode_u64(self.gas_limit),
+            rlp::encode_bytes(to),
+            rlp::encode_u256(self.value),
+            rlp::encode_bytes(&self.data),
+        ]
+    }
+}
+
+/// Per-call execution options
+///
+/// Part of [`ExecutionInput`], and so of its hash: each option changes the
+/// post-state or the output a proof commits to.
+///
+/// # Example
+/// ```rust,ignore
+/// // A pure view call: the post-state root only reflects what the call did
+/// let input = input.with_options(CallOptions::default().with_nonce_increment(false));
+/// let (output, commitment) = ShadowExecutor::execute(input)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CallOptions {
+    /// Bump the caller's nonce, as a real transaction does (default: true)
+    ///
+    /// When disabled, the caller's nonce in the post-state is left at its
+    /// pre-state value, so repeated simulations of calls from the same
+    /// caller produce comparable roots. Gas is still charged. A contract
+    /// created this way still gets the address derived from the caller's
+    /// current nonce.
+    pub increment_nonce: bool,
+    /// Reject transactions whose sender has code, per EIP-3607 (default: true)
+    ///
+    /// Disable for account-abstraction experiments that send from contract
+    /// accounts. A rejected transaction fails with
+    /// `ShadowEvmError::SenderHasCode`. Senders delegated through
+    /// `TxInput::authorized_code` are always accepted.
+    pub enforce_eip3607: bool,
+    /// Maximum call depth, the top-level frame being depth 1 (default: `None`)
+    ///
+    /// `None` keeps the EVM's limit of 1024, past which only the offending
+    /// call fails. A configured limit instead halts the whole transaction
+    /// with `HaltReason::CallTooDeep`, to catch runaway recursion in tests.
+    pub max_call_depth: Option<usize>,
+}
+
+impl Default for CallOptions {
+    fn default() -> Self {
+        Self {
+            increment_nonce: true,
+            enforce_eip3607: true,
+            max_call_depth: None,
+        }
+    }
+}
+
+impl CallOptions {
+    /// Set whether the caller's nonce is bumped
+    pub fn with_nonce_increment(mut self, enabled: bool) -> Self {
+        self.increment_nonce = enabled;
+        self
+    }
+
+    /// Set whether senders with code are rejected (EIP-3607)
+    pub fn with_eip3607(mut self, enabled: bool) -> Self {
+        self.enforce_eip3607 = enabled;
+        self
+    }
+
+    /// Halt the transaction once calls nest deeper than `max_depth`
+    pub fn with_max_call_depth(mut self, max_depth: usize) -> Self {
+        self.max_call_depth = Some(max_depth);
+        self
+    }
 }
 
 /// Complete execution input for Shadow-EVM
@@ -168,6 +549,7 @@ impl TxInput {
 /// - The hash binds the execution to specific inputs
 /// - Pre-state root allows verification of initial state
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ExecutionInput {
     /// Block environment
     pub block: BlockEnv,
@@ -175,6 +557,34 @@ pub struct ExecutionInput {
     pub tx: TxInput,
     /// Pre-execution state
     pub pre_state: InMemoryDB,
+    /// Hash function for the input/output hashes, state roots and commitment
+    #[serde(default)]
+    pub hash_algo: StateHashAlgo,
+    /// Application-level data bound into the proof (e.g. a request id)
+    ///
+    /// Opaque to execution. Part of the input hash; its own hash is exposed
+    /// as `ExecutionCommitment::public_inputs_hash`.
+    #[serde(default)]
+    pub public_inputs: Vec<u8>,
+    /// Also commit to the hashes of the calldata and the return data
+    ///
+    /// See `ExecutionCommitment::calldata_hash`.
+    #[serde(default)]
+    pub commit_data_hashes: bool,
+    /// Storage slot `(address, slot)` whose post-execution value to claim
+    ///
+    /// See `ExecutionCommitment::storage_claim`.
+    #[serde(default)]
+    pub claim_storage: Option<(Address, U256)>,
+    /// Per-call execution options; see [`CallOptions`]
+    #[serde(default)]
+    pub options: CallOptions,
+    /// How the committed pre- and post-state roots are computed
+    ///
+    /// `StateRootMode::Mpt` makes them comparable with the `stateRoot` of
+    /// block headers. Only `Nested` roots use `hash_algo`.
+    #[serde(default)]
+    pub state_root_mode: StateRootMode,
 }
 
 impl ExecutionInput {
@@ -184,6 +594,57 @@ impl ExecutionInput {
             block,
             tx,
             pre_state,
+            hash_algo: StateHashAlgo::default(),
+            public_inputs: Vec::new(),
+            commit_data_hashes: false,
+            claim_storage: None,
+            options: CallOptions::default(),
+            state_root_mode: StateRootMode::default(),
+        }
+    }
+
+    /// Commit with a different hash function
+    pub fn with_hash_algo(mut self, hash_algo: StateHashAlgo) -> Self {
+        self.hash_algo = hash_algo;
+        self
+    }
+
+    /// Attach application-level public inputs
+    pub fn with_public_inputs(mut self, public_inputs: Vec<u8>) -> Self {
+        self.public_inputs = public_inputs;
+        self
+    }
+
+    /// Commit to state roots computed in `mode`
+    pub fn with_state_root_mode(mut self, mode: StateRootMode) -> Self {
+        self.state_root_mode = mode;
+        self
+    }
+
+    /// Execute with non-default per-call options
+    pub fn with_options(mut self, options: CallOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Commit to the hashes of the calldata and the return data
+    pub fn with_data_hashes(mut self) -> Self {
+        self.commit_data_hashes = true;
+        self
+    }
+
+    /// Commit to the post-execution value of `slot` in `address`'s storage
+    pub fn with_storage_claim(mut self, address: Address, slot: U256) -> Self {
+        self.claim_storage = Some((address, slot));
+        self
+    }
+
+    /// Hash of the public inputs, ZERO if there are none
+    pub fn public_inputs_hash(&self) -> Hash {
+        if self.public_inputs.is_empty() {
+            Hash::ZERO
+        } else {
+            self.hash_algo.hash(&self.public_inputs)
         }
     }
 
@@ -191,12 +652,53 @@ impl ExecutionInput {
     ///
     /// This hash is used as part of the ZK proof's public input.
     pub fn hash(&self) -> Hash {
-        hash_struct(self)
+        self.hash_algo.hash_struct(self)
     }
 
     /// Get the pre-state root
     pub fn pre_state_root(&self) -> Hash {
-        self.pre_state.compute_state_root()
+        self.state_root(&self.pre_state)
+    }
+
+    /// Root of `state` as this input commits to it
+    ///
+    /// Computed in `state_root_mode`, with `hash_algo` for nested roots.
+    pub fn state_root(&self, state: &InMemoryDB) -> Hash {
+        match self.state_root_mode {
+            StateRootMode::Nested => state.compute_state_root_with_algo(self.hash_algo),
+            mode => state.compute_state_root_with(mode),
+        }
+    }
+
+    /// Check the input for inconsistencies that deserialization cannot catch
+    ///
+    /// Run before execution so malformed inputs fail loudly instead of
+    /// executing against a silently different state.
+    pub fn validate_semantics(&self) -> Result<()> {
+        self.tx.validate_intent()?;
+        self.pre_state.validate_code_hashes()
+    }
+
+    /// Estimated serialized size in bytes
+    pub fn estimated_serialized_bytes(&self) -> usize {
+        codec::estimated_serialized_bytes(self)
+    }
+
+    /// Encode as CBOR (without format prefix)
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        codec::to_cbor(self)
+    }
+
+    /// Encode with bincode, or CBOR if larger than `codec::CBOR_THRESHOLD_BYTES`
+    ///
+    /// The result starts with a magic prefix naming the format.
+    pub fn serialize_auto(&self) -> Result<Vec<u8>> {
+        codec::serialize_auto(self)
+    }
+
+    /// Decode an input produced by `serialize_auto`
+    pub fn deserialize_auto(data: &[u8]) -> Result<Self> {
+        codec::deserialize_auto(data)
     }
 
     /// Get the caller address
@@ -225,6 +727,153 @@ mod tests {
         let block = BlockEnv::default();
         assert_eq!(block.chain_id, 1);
         assert_eq!(block.gas_limit, 30_000_000);
+        assert_eq!(block.hardfork, Hardfork::Cancun);
+    }
+
+    #[test]
+    fn test_next_base_fee() {
+        let block = BlockEnv::default();
+        let target = block.gas_limit / 2;
+
+        // Full block: up by 1/8
+        assert_eq!(
+            block.next_base_fee(block.gas_limit, target),
+            U256::from(1_125_000_000u64)
+        );
+        // Empty block: down by 1/8
+        assert_eq!(block.next_base_fee(0, target), U256::from(875_000_000u64));
+        // At target: unchanged
+        assert_eq!(block.next_base_fee(target, target), block.base_fee);
+
+        // Any excess raises the fee by at least 1 wei
+        let cheap = BlockEnv {
+            base_fee: U256::from(7u64),
+            ..BlockEnv::default()
+        };
+        assert_eq!(cheap.next_base_fee(target + 1, target), U256::from(8u64));
+    }
+
+    #[test]
+    fn test_tx_hardfork_override() {
+        let block = BlockEnv::default().with_hardfork(Hardfork::Shanghai);
+        let tx = TxInput::default();
+
+        assert_eq!(tx.effective_hardfork(&block), Hardfork::Shanghai);
+
+        let tx = tx.with_hardfork(Hardfork::Cancun);
+        assert_eq!(tx.effective_hardfork(&block), Hardfork::Cancun);
+    }
+
+    #[test]
+    fn test_validate_intent() {
+        let caller = Address::repeat_byte(0x01);
+        let valid = [
+            TxInput::create(caller, vec![0x00], U256::ZERO),
+            TxInput::call(caller, Address::repeat_byte(0x02), Vec::new()),
+            // Deploys an account without code
+            TxInput::create(caller, Vec::new(), U256::from(1u64)),
+        ];
+        for tx in valid {
+            let input = ExecutionInput::new(BlockEnv::default(), tx, InMemoryDB::new());
+            assert!(input.validate_semantics().is_ok());
+        }
+
+        let delegated_create =
+            TxInput::create(caller, vec![0x00], U256::ZERO).with_authorized_code(vec![0x00]);
+        let input = ExecutionInput::new(BlockEnv::default(), delegated_create, InMemoryDB::new());
+        assert!(matches!(
+            input.validate_semantics(),
+            Err(ShadowEvmError::InvalidTransaction(_))
+        ));
+    }
+
+    #[test]
+    fn test_effective_gas_price() {
+        let base_fee = U256::from(50u64);
+        let tx = TxInput::default().with_eip1559_fees(U256::from(100u64), U256::from(2u64));
+        assert_eq!(tx.effective_gas_price(base_fee), U256::from(52u64));
+
+        let tx = tx.with_eip1559_fees(U256::from(51u64), U256::from(2u64));
+        assert_eq!(tx.effective_gas_price(base_fee), U256::from(51u64));
+
+        let legacy = TxInput {
+            gas_price: U256::from(7u64),
+            ..TxInput::default()
+        };
+        assert_eq!(legacy.effective_gas_price(base_fee), U256::from(7u64));
+        assert_eq!(legacy.tx_type(), TxType::Legacy);
+        let with_list = legacy.with_access_list(vec![(Address::ZERO, Vec::new())]);
+        assert_eq!(with_list.tx_type(), TxType::Eip2930);
+
+        let tx = TxInput::eip1559(
+            Address::repeat_byte(0x01),
+            Address::repeat_byte(0x02),
+            Vec::new(),
+            U256::from(100u64),
+            U256::from(2u64),
+        );
+        assert_eq!(tx.tx_type(), TxType::Eip1559);
+        for (base_fee, expected) in [(0u64, 2u64), (50, 52), (98, 100), (99, 100)] {
+            assert_eq!(
+                tx.effective_gas_price(U256::from(base_fee)),
+                U256::from(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn test_tx_hash() {
+        // Example transaction of EIP-155:
+        // 0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000
+        //   8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276
+        //   a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83
+        let sender: Address = "0x9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F"
+            .parse()
+            .unwrap();
+        let tx = TxInput {
+            caller: sender,
+            to: Some(Address::repeat_byte(0x35)),
+            value: U256::from(10u128.pow(18)),
+            gas_limit: 21_000,
+            gas_price: U256::from(20_000_000_000u64),
+            nonce: 9,
+            ..TxInput::default()
+        };
+        assert_eq!(tx.tx_hash(1), None);
+
+        let signature = TxSignature {
+            v: 37,
+            r: U256::from_str_radix(
+                "28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276",
+                16,
+            )
+            .unwrap(),
+            s: U256::from_str_radix(
+                "67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+                16,
+            )
+            .unwrap(),
+        };
+        let expected: Hash = "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788"
+            .parse()
+            .unwrap();
+        let signed = tx.with_signature(signature);
+        assert_eq!(signed.recover_signer(1), Some(sender));
+        assert_eq!(signed.tx_hash(1), Some(expected));
+
+        // Replay-protected for chain 1 only
+        assert_eq!(signed.recover_signer(5), None);
+        assert_eq!(signed.tx_hash(5), None);
+
+        // Not the transaction that is executed
+        let mut other_caller = signed.clone();
+        other_caller.caller = Address::repeat_byte(0x01);
+        assert_eq!(other_caller.tx_hash(1), None);
+        let mut tampered = signed.clone();
+        tampered.value += U256::from(1u64);
+        assert_eq!(tampered.tx_hash(1), None);
+        let delegated = signed.clone().with_authorized_code(vec![0x00]);
+        assert_eq!(delegated.tx_hash(1), None);
     }
 
     #[test]
@@ -302,4 +951,65 @@ mod tests {
 
         assert_ne!(input1.hash(), input2.hash());
     }
+
+    #[test]
+    fn test_difficulty_committed() {
+        // Pre-merge blocks with the same (zero) randao
+        let block = BlockEnv::default().with_hardfork(Hardfork::London);
+        let input1 = ExecutionInput::new(
+            block.clone().with_difficulty(U256::from(1u64)),
+            TxInput::default(),
+            InMemoryDB::new(),
+        );
+        let input2 = ExecutionInput::new(
+            block.with_difficulty(U256::from(2u64)),
+            TxInput::default(),
+            InMemoryDB::new(),
+        );
+
+        assert_ne!(input1.hash(), input2.hash());
+    }
+
+    #[test]
+    fn test_serialize_auto() {
+        let small = ExecutionInput::new(BlockEnv::default(), TxInput::default(), InMemoryDB::new());
+        let bytes = small.serialize_auto().unwrap();
+        assert_eq!(&bytes[..4], &codec::BINCODE_MAGIC);
+        assert_eq!(
+            ExecutionInput::deserialize_auto(&bytes).unwrap().hash(),
+            small.hash()
+        );
+
+        // A large contract pushes the input over the CBOR threshold
+        let mut pre_state = InMemoryDB::new();
+        pre_state.insert_account(
+            Address::repeat_byte(0xcc),
+            AccountState::new_contract(vec![0x5b; 1_100_000], U256::ZERO),
+        );
+        let large = ExecutionInput::new(BlockEnv::default(), TxInput::default(), pre_state);
+        assert!(large.estimated_serialized_bytes() > codec::CBOR_THRESHOLD_BYTES);
+
+        let bytes = large.serialize_auto().unwrap();
+        assert_eq!(&bytes[..4], &codec::CBOR_MAGIC);
+        assert_eq!(
+            ExecutionInput::deserialize_auto(&bytes).unwrap().hash(),
+            large.hash()
+        );
+    }
+
+    #[test]
+    fn test_json_rejects_unknown_fields() {
+        let input = ExecutionInput::new(BlockEnv::default(), TxInput::default(), InMemoryDB::new());
+        let json = serde_json::to_string(&input).unwrap();
+        assert!(serde_json::from_str::<ExecutionInput>(&json).is_ok());
+
+        // camelCase instead of snake_case
+        let misspelled = json.replace("\"gas_limit\"", "\"gasLimit\"");
+        let err = serde_json::from_str::<ExecutionInput>(&misspelled).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `gasLimit`"),
+            "{}",
+            err
+        );
+    }
 }
@@ -0,0 +1,172 @@
+//! Post-execution invariant checks for Shadow-EVM
+//!
+//! Checks that relate pre- and post-state and must hold for any correct
+//! execution, independent of what the transaction did. They are cheap
+//! compared to execution and catch bugs in state application early.
+
+use crate::errors::{Result, ShadowEvmError};
+use crate::evm::ShadowExecutor;
+use crate::input::ExecutionInput;
+use crate::state::{InMemoryDB, StateRootMode};
+use crate::types::{Gas, Hash, U256};
+use alloc::vec::Vec;
+
+extern crate alloc;
+
+/// Check that no ether was created or destroyed beyond the burned base fee
+///
+/// Verifies `sum(pre.balances) == sum(post.balances) + gas_used * base_fee`,
+/// summing over every account, the miner included. The priority fee only
+/// moves ether from the sender to the miner, so value sent to the miner or
+/// by the miner needs no special case. Pass a `base_fee` of zero before
+/// London, when nothing is burned.
+pub fn check_eth_conservation(
+    pre: &InMemoryDB,
+    post: &InMemoryDB,
+    gas_used: Gas,
+    base_fee: U256,
+) -> Result<()> {
+    let pre_total = total_balance(pre);
+    let post_total = total_balance(post);
+    let burned_fee = U256::from(gas_used).saturating_mul(base_fee);
+
+    if pre_total != post_total.saturating_add(burned_fee) {
+        return Err(ShadowEvmError::EthConservationViolation {
+            pre_total,
+            post_total,
+            burned_fee,
+        });
+    }
+
+    Ok(())
+}
+
+/// Check that the state root mode changes only the root, not the execution
+///
+/// Executes `input` once in each [`StateRootMode`] and checks that status,
+/// gas, logs, return data and post-state are identical across runs, that
+/// each run commits to the pre- and post-state roots of its own mode, and
+/// that no two modes give the same post-state root. Returns the committed
+/// post-state root in each mode, so a migration from one mode to another
+/// can compare roots knowing the execution underneath is the same.
+pub fn check_root_mode_independence(input: &ExecutionInput) -> Result<Vec<(StateRootMode, Hash)>> {
+    let mut reference = None;
+    let mut roots: Vec<(StateRootMode, Hash)> = Vec::with_capacity(StateRootMode::ALL.len());
+
+    for mode in StateRootMode::ALL {
+        let input = input.clone().with_state_root_mode(mode);
+        let (output, commitment) = ShadowExecutor::execute(input.clone())?;
+        let output_hash = output.hash();
+        if *reference.get_or_insert(output_hash) != output_hash {
+            return Err(ShadowEvmError::RootModeDivergence { mode: mode.name() });
+        }
+
+        let root = input.state_root(&output.post_state);
+        if !commitment.verify_roots(&input, &output) {
+            return Err(ShadowEvmError::CommitmentMismatch {
+                expected: root,
+                actual: commitment.post_state_root,
+            });
+        }
+        if let Some((other, _)) = roots.iter().find(|(_, other_root)| *other_root == root) {
+            return Err(ShadowEvmError::RootModeCollision {
+                mode: mode.name(),
+                other: other.name(),
+            });
+        }
+        roots.push((mode, root));
+    }
+
+    Ok(roots)
+}
+
+/// Sum of all balances
+fn total_balance(db: &InMemoryDB) -> U256 {
+    db.accounts.values().fold(U256::ZERO, |total, account| {
+        total.saturating_add(account.balance)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{BlockEnv, TxInput};
+    use crate::state::AccountState;
+    use crate::types::Address;
+
+    #[test]
+    fn test_eth_conservation() {
+        let miner = Address::repeat_byte(0xee);
+        let alice = Address::repeat_byte(0x01);
+        let bob = Address::repeat_byte(0x02);
+        let base_fee = U256::from(8u64);
+
+        let mut pre = InMemoryDB::new();
+        pre.insert_account(
+            alice,
+            AccountState::new_with_balance(U256::from(1_000_000u64)),
+        );
+        pre.insert_account(miner, AccountState::new_with_balance(U256::from(5u64)));
+
+        // Alice sends 100 wei to Bob and pays 21000 * 10 for gas, of which
+        // 21000 * 8 is burned and 21000 * 2 goes to the miner
+        let mut post = pre.clone();
+        post.get_account_mut(&alice).unwrap().balance = U256::from(1_000_000u64 - 100 - 210_000);
+        post.insert_account(bob, AccountState::new_with_balance(U256::from(100u64)));
+        post.get_account_mut(&miner).unwrap().balance = U256::from(5u64 + 42_000);
+
+        assert!(check_eth_conservation(&pre, &post, 21_000, base_fee).is_ok());
+
+        // Value sent to the miner is conserved like any other
+        let mut tipped = post.clone();
+        tipped.get_account_mut(&alice).unwrap().balance -= U256::from(50u64);
+        tipped.get_account_mut(&miner).unwrap().balance += U256::from(50u64);
+        assert!(check_eth_conservation(&pre, &tipped, 21_000, base_fee).is_ok());
+
+        // Ether minted out of thin air
+        post.get_account_mut(&bob).unwrap().balance = U256::from(101u64);
+        let err = check_eth_conservation(&pre, &post, 21_000, base_fee).unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::EthConservationViolation { burned_fee, .. }
+                if burned_fee == U256::from(168_000u64)
+        ));
+    }
+
+    #[test]
+    fn test_root_mode_independence() {
+        let sender = Address::repeat_byte(0x01);
+        let receiver = Address::repeat_byte(0x02);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::transfer(sender, receiver, U256::from(1000u64)),
+            state,
+        );
+
+        let roots = check_root_mode_independence(&input).unwrap();
+        assert_eq!(roots.len(), StateRootMode::ALL.len());
+
+        // Same execution, but every mode has its own root
+        let (output, _) = ShadowExecutor::execute(input.clone()).unwrap();
+        for (i, (mode, root)) in roots.iter().enumerate() {
+            assert_eq!(*root, output.post_state.compute_state_root_with(*mode));
+            assert!(roots[i + 1..].iter().all(|(_, other)| other != root));
+
+            // Each mode commits to its own roots
+            let (_, commitment) =
+                ShadowExecutor::execute(input.clone().with_state_root_mode(*mode)).unwrap();
+            assert_eq!(commitment.state_root_mode, *mode);
+            assert_eq!(commitment.post_state_root, *root);
+            assert_eq!(
+                commitment.pre_state_root,
+                input.pre_state.compute_state_root_with(*mode)
+            );
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Storage layout helpers for well-known contracts
+//!
+//! Computes raw storage slots and packed values so that protocol state can
+//! be written straight into an `InMemoryDB`, without replaying the
+//! deployment and setup transactions that would normally produce it.
+
+use crate::hashing::keccak256;
+use crate::types::{Address, Hash, U256};
+
+/// Storage layout of a Uniswap V3 pool (`UniswapV3Pool.sol`)
+///
+/// Only the code needs to be deployed; the pool's storage can then be
+/// populated directly:
+///
+/// ```rust,ignore
+/// let mut pool = AccountState::new_contract(pool_code, U256::ZERO);
+/// pool.set_storage(
+///     UniswapV3PoolState::SLOT0_SLOT,
+///     UniswapV3PoolState::slot0(sqrt_price_x96, tick, 0, 1, 0, true),
+/// );
+/// pool.set_storage(
+///     UniswapV3PoolState::liquidity_slot(owner, tick_lower, tick_upper),
+///     U256::from(liquidity),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct UniswapV3PoolState;
+
+impl UniswapV3PoolState {
+    /// Slot of the packed `slot0` struct
+    pub const SLOT0_SLOT: U256 = U256::from_limbs([0, 0, 0, 0]);
+    /// Slot of the in-range `liquidity`
+    pub const LIQUIDITY_SLOT: U256 = U256::from_limbs([4, 0, 0, 0]);
+    /// Slot of the `positions` mapping
+    pub const POSITIONS_SLOT: U256 = U256::from_limbs([7, 0, 0, 0]);
+
+    /// Packed `slot0` storage value
+    ///
+    /// Fields are packed from the low-order bits up, as the Solidity
+    /// compiler lays them out:
+    ///
+    /// ```text
+    /// bits   0..160  sqrtPriceX96
+    /// bits 160..184  tick (int24, two's complement)
+    /// bits 184..200  observationIndex
+    /// bits 200..216  observationCardinality
+    /// bits 216..232  observationCardinalityNext
+    /// bits 232..240  feeProtocol
+    /// bits 240..248  unlocked
+    /// ```
+    ///
+    /// `observationCardinalityNext` is set to `observation_cardinality`,
+    /// which is the pool's state unless the oracle buffer is being grown.
+    /// `sqrt_price_x96` is truncated to 160 bits.
+    pub fn slot0(
+        sqrt_price_x96: U256,
+        tick: i32,
+        observation_index: u16,
+        observation_cardinality: u16,
+        fee_protocol: u8,
+        unlocked: bool,
+    ) -> U256 {
+        let sqrt_price = sqrt_price_x96 & ((U256::from(1u64) << 160) - U256::from(1u64));
+        let tick = U256::from((tick as u32) & 0x00ff_ffff);
+
+        sqrt_price
+            | (tick << 160)
+            | (U256::from(observation_index) << 184)
+            | (U256::from(observation_cardinality) << 200)
+            | (U256::from(observation_cardinality) << 216)
+            | (U256::from(fee_protocol) << 232)
+            | (U256::from(unlocked as u8) << 240)
+    }
+
+    /// Key of a position in the `positions` mapping
+    ///
+    /// `keccak256(abi.encodePacked(owner, tickLower, tickUpper))`, as
+    /// computed by `Position.get`.
+    pub fn position_key(owner: Address, tick_lower: i32, tick_upper: i32) -> Hash {
+        let mut packed = [0u8; 26];
+        packed[..20].copy_from_slice(owner.as_slice());
+        packed[20..23].copy_from_slice(&tick_lower.to_be_bytes()[1..]);
+        packed[23..].copy_from_slice(&tick_upper.to_be_bytes()[1..]);
+        keccak256(&packed)
+    }
+
+    /// Slot holding the liquidity of a position
+    ///
+    /// `liquidity` is the first field of `Position.Info`, so it lives at
+    /// the mapping entry's base slot:
+    /// `keccak256(position_key || POSITIONS_SLOT)`. Positions are keyed by
+    /// their owner as well as their tick range; for positions minted
+    /// through the periphery the owner is the `NonfungiblePositionManager`.
+    pub fn liquidity_slot(owner: Address, tick_lower: i32, tick_upper: i32) -> U256 {
+        let mut preimage = [0u8; 64];
+        preimage[..32]
+            .copy_from_slice(Self::position_key(owner, tick_lower, tick_upper).as_slice());
+        preimage[32..].copy_from_slice(&Self::POSITIONS_SLOT.to_be_bytes::<32>());
+        U256::from_be_bytes(keccak256(&preimage).0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot0_packing() {
+        let sqrt_price = U256::from(1u64) << 96;
+        let packed = UniswapV3PoolState::slot0(sqrt_price, -1, 3, 10, 0x44, true);
+
+        let field = |shift: usize, bits: usize| {
+            (packed >> shift) & ((U256::from(1u64) << bits) - U256::from(1u64))
+        };
+
+        assert_eq!(field(0, 160), sqrt_price);
+        assert_eq!(field(160, 24), U256::from(0x00ff_ffffu64));
+        assert_eq!(field(184, 16), U256::from(3u64));
+        assert_eq!(field(200, 16), U256::from(10u64));
+        assert_eq!(field(216, 16), U256::from(10u64));
+        assert_eq!(field(232, 8), U256::from(0x44u64));
+        assert_eq!(field(240, 8), U256::from(1u64));
+        assert_eq!(packed >> 248, U256::ZERO);
+    }
+
+    #[test]
+    fn test_position_key_packing() {
+        let owner = Address::repeat_byte(0xaa);
+        let mut packed = [0xaau8; 26];
+        // int24 -60 and 60, big-endian two's complement
+        packed[20..23].copy_from_slice(&[0xff, 0xff, 0xc4]);
+        packed[23..].copy_from_slice(&[0x00, 0x00, 0x3c]);
+
+        assert_eq!(
+            UniswapV3PoolState::position_key(owner, -60, 60),
+            keccak256(&packed)
+        );
+    }
+
+    #[test]
+    fn test_liquidity_slot() {
+        let owner = Address::repeat_byte(0xaa);
+        let slot = UniswapV3PoolState::liquidity_slot(owner, -60, 60);
+
+        let mut preimage = [0u8; 64];
+        preimage[..32].copy_from_slice(UniswapV3PoolState::position_key(owner, -60, 60).as_slice());
+        preimage[63] = 7;
+        assert_eq!(slot, U256::from_be_bytes(keccak256(&preimage).0));
+
+        assert_ne!(slot, UniswapV3PoolState::liquidity_slot(owner, -120, 60));
+    }
+}
@@ -56,38 +56,111 @@
 //!
 //! ## Modules
 //!
+//! - [`abi`] - ABI decoding for revert data
+//! - [`audit`] - Preimage audit trail for commitments
+//! - [`block`] - Block-level and incremental execution
+//! - [`chain`] - Commitment chains for sequential replay
+//! - [`codec`] - Size-aware bincode/CBOR encoding
 //! - [`types`] - Core type definitions (Address, Hash, U256)
 //! - [`errors`] - Error types and Result alias
 //! - [`hashing`] - Keccak256 and commitment functions
 //! - [`state`] - In-memory state database
+//! - [`testing`] - Mock contracts for tests and examples
+//! - [`trace`] - Opcode-level execution tracing
+//! - [`trie`] - Merkle Patricia Trie, RLP encoding and storage proofs
 //! - [`input`] - Execution input types
+//! - [`invariants`] - Post-execution invariant checks
+//! - [`layouts`] - Storage layouts of well-known contracts
+//! - [`limits`] - Execution resource limits
+//! - [`multi`] - Independent executions under one Merkle root
 //! - [`output`] - Execution output and commitment types
+//! - [`precompile`] - Custom precompiles for coprocessor operations
+//! - [`preflight`] - Discovery of missing pre-state
+//! - `rpc` - Conversion from alloy RPC types (`alloy` feature)
+//! - [`sandbox`] - Opcode restrictions for sandboxed execution
+//! - [`smt`] - Sparse Merkle state root and incremental updates
+//! - [`spec`] - Hardfork selection
 //! - [`evm`] - Core EVM executor
+//! - [`witness`] - Execution witness size and pruning
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
 extern crate alloc;
 
+pub mod abi;
+pub mod audit;
+pub mod block;
+pub mod chain;
+pub mod codec;
 pub mod errors;
 pub mod evm;
 pub mod hashing;
 pub mod input;
+pub mod invariants;
+pub mod layouts;
+pub mod limits;
+pub mod multi;
 pub mod output;
+pub mod precompile;
+pub mod preflight;
+#[cfg(feature = "alloy")]
+pub mod rpc;
+pub mod sandbox;
+pub mod smt;
+pub mod spec;
 pub mod state;
+pub mod testing;
+pub mod trace;
+pub mod trie;
 pub mod types;
+pub mod witness;
 
 // Re-exports for convenience
+pub use abi::{AbiType, AbiValue, CustomError, DecodedError, FieldWidth, RevertReason};
+pub use audit::AuditTrail;
+pub use block::{BatchExecutionInput, BlockExecutor};
+pub use chain::{verify_commitment_chain, CommitmentChain};
 pub use errors::{Result, ShadowEvmError};
-pub use evm::{ExecutionBuilder, ShadowExecutor};
-pub use hashing::{compute_commitment, hash_struct, keccak256};
-pub use input::{BlockEnv, ExecutionInput, TxInput};
-pub use output::{ExecutionCommitment, ExecutionOutput, ExecutionStatus, Log};
-pub use state::{AccountState, InMemoryDB};
-pub use types::{Address, Bytes, Gas, Hash, U256};
+pub use evm::{
+    ExecutionBuilder, ShadowExecutor, ESTIMATE_GAS_BUFFER_PERCENT, MAX_ESTIMATE_ITERATIONS,
+};
+pub use hashing::{
+    compute_calldata_commitment, compute_commitment, hash_struct, keccak256, Hasher, StateHashAlgo,
+};
+pub use input::{BlockEnv, CallOptions, ExecutionInput, TxInput, TxSignature, TxType};
+pub use invariants::check_eth_conservation;
+pub use layouts::UniswapV3PoolState;
+pub use limits::{ExecutionLimits, MemoryMeter};
+pub use multi::{commitments_root, MultiExecutionInput};
+pub use output::{
+    ExecutionCommitment, ExecutionOutput, ExecutionStatus, ExecutionWarning, HaltReason, Log,
+    OutputDifference, StorageClaim,
+};
+pub use precompile::{CustomPrecompile, PrecompileGas};
+pub use preflight::{AccessSet, PreflightDB};
+pub use smt::{AccountProof, AccountUpdate, StateChunk, StateDelta};
+pub use spec::Hardfork;
+pub use state::{
+    AccountDiff, AccountState, InMemoryDB, SlotOrder, SnapshotId, StateDiff, StateRootMode,
+};
+pub use trace::{
+    diff_traces, CallExit, CallFrame, CallKind, CallTrace, DebugTrace, ExecutionTrace,
+    GasBreakdown, GasProfile, GasProfiler, OpcodeGas, StorageWrite, TraceDiff, TraceStep, Tracer,
+};
+pub use trie::{verify_storage_proof, SlotProof, StorageProof};
+pub use types::{Address, Bytes, Gas, Hash, Opcode, U256};
+pub use witness::{ExecutionWitness, WitnessStats};
 
 /// Library version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Version of the execution guest, committed in its journal
+///
+/// Bump whenever proofs from the guest stop being interchangeable with
+/// those of the previous version: a changed journal layout or execution
+/// rules. Rebuilds that change neither keep it, unlike the image ID.
+pub const GUEST_VERSION: u32 = 1;
+
 /// Prelude module for convenient imports
 pub mod prelude {
     pub use crate::{
@@ -0,0 +1,307 @@
+//! Execution resource limits for Shadow-EVM
+//!
+//! Bounds on what a single execution may produce. Everything that ends up in
+//! the output is hashed (and possibly committed) inside the ZK-VM, so these
+//! limits keep proof cost predictable.
+
+use crate::errors::{Result, ShadowEvmError};
+use crate::output::Log;
+use crate::state::InMemoryDB;
+use alloc::sync::Arc;
+use revm::handler::register::EvmHandler;
+use revm::interpreter::{Gas, InstructionResult, Interpreter, InterpreterResult};
+use revm::primitives::Bytes;
+use revm::{Database, EvmContext, FrameOrResult, Inspector};
+
+extern crate alloc;
+
+/// Default maximum number of logs per execution
+pub const DEFAULT_MAX_LOGS: usize = 1000;
+
+/// Default maximum total log data per execution (256 KiB)
+pub const DEFAULT_MAX_LOG_DATA_BYTES: usize = 256 * 1024;
+
+/// Limits checked against an execution's output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    /// Maximum number of logs emitted
+    pub max_logs: usize,
+    /// Maximum total size of log data in bytes
+    pub max_log_data_bytes: usize,
+    /// Maximum number of accounts created (unbounded if `None`)
+    pub max_new_accounts: Option<usize>,
+    /// Maximum number of storage slots going from zero to non-zero (unbounded if `None`)
+    pub max_storage_growth: Option<usize>,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self {
+            max_logs: DEFAULT_MAX_LOGS,
+            max_log_data_bytes: DEFAULT_MAX_LOG_DATA_BYTES,
+            max_new_accounts: None,
+            max_storage_growth: None,
+        }
+    }
+}
+
+impl ExecutionLimits {
+    /// Check emitted logs against the log budget
+    pub fn check_logs(&self, logs: &[Log]) -> Result<()> {
+        let data_bytes = logs.iter().map(|log| log.data.len()).sum();
+
+        if logs.len() > self.max_logs || data_bytes > self.max_log_data_bytes {
+            return Err(ShadowEvmError::LogBudgetExceeded {
+                logs: logs.len(),
+                data_bytes,
+                limit_logs: self.max_logs,
+                limit_bytes: self.max_log_data_bytes,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check whether any state growth limit is configured
+    pub fn limits_state_growth(&self) -> bool {
+        self.max_new_accounts.is_some() || self.max_storage_growth.is_some()
+    }
+
+    /// Check state growth between pre- and post-state against the budget
+    pub fn check_state_growth(&self, pre: &InMemoryDB, post: &InMemoryDB) -> Result<()> {
+        let (new_accounts, new_slots) = state_growth(pre, post);
+
+        let accounts_exceeded = self.max_new_accounts.is_some_and(|max| new_accounts > max);
+        let slots_exceeded = self.max_storage_growth.is_some_and(|max| new_slots > max);
+
+        if accounts_exceeded || slots_exceeded {
+            return Err(ShadowEvmError::StateBudgetExceeded {
+                new_accounts,
+                new_slots,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Limit call nesting to `max_depth` frames and halt the transaction past it
+///
+/// The top-level frame has depth 1. A call or create that would start a
+/// deeper frame fails with `CallTooDeep`, as at the EVM's own limit of 1024.
+/// Unlike there, its caller does not continue: every enclosing frame halts
+/// with the same reason, so the transaction ends in
+/// `HaltReason::CallTooDeep`. Does nothing if `max_depth` is `None`.
+pub(crate) fn register_call_depth_limit<EXT, DB: Database>(
+    handler: &mut EvmHandler<'_, EXT, DB>,
+    max_depth: Option<usize>,
+) {
+    let Some(max_depth) = max_depth else {
+        return;
+    };
+    let too_deep = |gas_limit| InterpreterResult {
+        result: InstructionResult::CallTooDeep,
+        output: Bytes::new(),
+        gas: Gas::new(gas_limit),
+    };
+
+    // The journal depth is the number of frames already open
+    let call = handler.execution.call.clone();
+    handler.execution.call = Arc::new(move |context, inputs| {
+        if context.evm.journaled_state.depth >= max_depth {
+            let offset = inputs.return_memory_offset.clone();
+            return Ok(FrameOrResult::new_call_result(
+                too_deep(inputs.gas_limit),
+                offset,
+            ));
+        }
+        call(context, inputs)
+    });
+
+    let create = handler.execution.create.clone();
+    handler.execution.create = Arc::new(move |context, inputs| {
+        if context.evm.journaled_state.depth >= max_depth {
+            return Ok(FrameOrResult::new_create_result(
+                too_deep(inputs.gas_limit),
+                None,
+            ));
+        }
+        create(context, inputs)
+    });
+
+    let insert_call = handler.execution.insert_call_outcome.clone();
+    handler.execution.insert_call_outcome =
+        Arc::new(move |context, frame, shared_memory, outcome| {
+            let halt = outcome.result.result == InstructionResult::CallTooDeep;
+            insert_call(context, frame, shared_memory, outcome)?;
+            if halt {
+                frame.interpreter_mut().instruction_result = InstructionResult::CallTooDeep;
+            }
+            Ok(())
+        });
+
+    let insert_create = handler.execution.insert_create_outcome.clone();
+    handler.execution.insert_create_outcome = Arc::new(move |context, frame, outcome| {
+        let halt = outcome.result.result == InstructionResult::CallTooDeep;
+        insert_create(context, frame, outcome)?;
+        if halt {
+            frame.interpreter_mut().instruction_result = InstructionResult::CallTooDeep;
+        }
+        Ok(())
+    });
+}
+
+/// Inspector tracking the peak memory size of any frame
+///
+/// Memory expansion costs gas quadratically in the size of a frame's memory,
+/// so a huge expansion can make an execution disproportionately expensive
+/// to prove. With `max_words` set, the frame whose memory grows past it
+/// halts right after the expanding instruction, as does every frame after
+/// that.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryMeter {
+    max_words: Option<usize>,
+    peak_words: usize,
+}
+
+impl MemoryMeter {
+    /// Meter halting execution past `max_words` 32-byte words, if set
+    pub fn new(max_words: Option<usize>) -> Self {
+        Self {
+            max_words,
+            peak_words: 0,
+        }
+    }
+
+    /// Largest memory size of any frame, in 32-byte words
+    pub fn peak_words(&self) -> usize {
+        self.peak_words
+    }
+
+    /// Check whether the peak exceeded the cap
+    pub fn exceeded(&self) -> bool {
+        self.max_words.is_some_and(|max| self.peak_words > max)
+    }
+}
+
+impl<DB: Database> Inspector<DB> for MemoryMeter {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if self.exceeded() {
+            interp.instruction_result = InstructionResult::MemoryLimitOOG;
+        }
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        // Memory always grows in whole words
+        self.peak_words = self.peak_words.max(interp.shared_memory.len() / 32);
+        if self.exceeded() {
+            interp.instruction_result = InstructionResult::MemoryLimitOOG;
+        }
+    }
+}
+
+/// Count accounts and non-zero storage slots present in `post` but not in `pre`
+fn state_growth(pre: &InMemoryDB, post: &InMemoryDB) -> (usize, usize) {
+    let mut new_accounts = 0;
+    let mut new_slots = 0;
+
+    for (address, account) in &post.accounts {
+        match pre.get_account(address) {
+            Some(before) => {
+                new_slots += account
+                    .storage
+                    .keys()
+                    .filter(|slot| !before.storage.contains_key(slot))
+                    .count();
+            }
+            None => {
+                new_accounts += 1;
+                new_slots += account.storage.len();
+            }
+        }
+    }
+
+    (new_accounts, new_slots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AccountState;
+    use crate::types::{Address, U256};
+
+    fn log_with_data(len: usize) -> Log {
+        Log::new(Address::ZERO, vec![], vec![0u8; len])
+    }
+
+    #[test]
+    fn test_default_limits() {
+        let limits = ExecutionLimits::default();
+        assert_eq!(limits.max_logs, 1000);
+        assert_eq!(limits.max_log_data_bytes, 256 * 1024);
+        assert!(limits.check_logs(&[log_with_data(32)]).is_ok());
+    }
+
+    #[test]
+    fn test_log_budget_exceeded() {
+        let limits = ExecutionLimits {
+            max_logs: 1,
+            max_log_data_bytes: 64,
+            ..Default::default()
+        };
+
+        assert!(limits.check_logs(&[log_with_data(64)]).is_ok());
+
+        let err = limits
+            .check_logs(&[log_with_data(1), log_with_data(1)])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::LogBudgetExceeded {
+                logs: 2,
+                data_bytes: 2,
+                ..
+            }
+        ));
+
+        assert!(limits.check_logs(&[log_with_data(65)]).is_err());
+    }
+
+    #[test]
+    fn test_state_growth() {
+        let existing = Address::repeat_byte(0x01);
+
+        let mut pre = InMemoryDB::new();
+        let mut account = AccountState::new_with_balance(U256::from(1u64));
+        account.set_storage(U256::from(1u64), U256::from(1u64));
+        pre.insert_account(existing, account);
+
+        let mut post = pre.clone();
+        let account = post.get_account_mut(&existing).unwrap();
+        account.set_storage(U256::from(1u64), U256::from(2u64)); // modified, not new
+        account.set_storage(U256::from(2u64), U256::from(1u64)); // new
+        let mut created = AccountState::new_with_balance(U256::ZERO);
+        created.set_storage(U256::from(7u64), U256::from(7u64)); // new
+        post.insert_account(Address::repeat_byte(0x02), created);
+
+        assert_eq!(state_growth(&pre, &post), (1, 2));
+
+        let unbounded = ExecutionLimits::default();
+        assert!(!unbounded.limits_state_growth());
+        assert!(unbounded.check_state_growth(&pre, &post).is_ok());
+
+        let limits = ExecutionLimits {
+            max_new_accounts: Some(1),
+            max_storage_growth: Some(1),
+            ..Default::default()
+        };
+        let err = limits.check_state_growth(&pre, &post).unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::StateBudgetExceeded {
+                new_accounts: 1,
+                new_slots: 2
+            }
+        ));
+    }
+}
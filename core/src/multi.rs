@@ -0,0 +1,183 @@
+//! Several independent executions under one commitment
+//!
+//! Proving has a fixed overhead per proof. When executions are small, it is
+//! cheaper to run many of them in one guest and commit a single Merkle root
+//! over their commitments:
+//!
+//! ```text
+//! leaf_i       = keccak256(0x00 || commitment_i.commitment)
+//! node(l, r)   = keccak256(0x01 || l || r)
+//! odd node out = carried up to the next level unchanged
+//! tree([])     = 0x00..00
+//! root         = keccak256(count || tree), count as 8-byte big-endian
+//! ```
+//!
+//! The prefixes keep a leaf from passing as a node and the count keeps
+//! batches of different sizes apart. Each execution runs against its own
+//! pre-state; nothing is shared between them. A verifier holding the root,
+//! the count and one execution's commitment checks its inclusion with the
+//! sibling hashes along its path.
+
+use crate::errors::Result;
+use crate::evm::ShadowExecutor;
+use crate::hashing::hash_concat;
+use crate::input::ExecutionInput;
+use crate::output::ExecutionCommitment;
+use crate::types::Hash;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+extern crate alloc;
+
+/// Independent executions to run and commit together
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MultiExecutionInput {
+    /// Executions, each with its own pre-state
+    pub inputs: Vec<ExecutionInput>,
+}
+
+impl MultiExecutionInput {
+    /// Batch the given executions
+    pub fn new(inputs: Vec<ExecutionInput>) -> Self {
+        Self { inputs }
+    }
+
+    /// Add an execution to the batch
+    pub fn push(&mut self, input: ExecutionInput) {
+        self.inputs.push(input);
+    }
+
+    /// Number of executions in the batch
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Whether the batch is empty
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// Execute every input in order
+    ///
+    /// Returns each execution's commitment and the Merkle root over them.
+    /// Fails on the first input that cannot be executed at all; reverts
+    /// and halts are committed like in `ShadowExecutor::execute`.
+    pub fn execute(self) -> Result<(Vec<ExecutionCommitment>, Hash)> {
+        let commitments = self
+            .inputs
+            .into_iter()
+            .map(|input| ShadowExecutor::execute(input).map(|(_, commitment)| commitment))
+            .collect::<Result<Vec<_>>>()?;
+        let root = commitments_root(&commitments);
+
+        Ok((commitments, root))
+    }
+}
+
+/// Prefix of a leaf hash
+const LEAF_PREFIX: u8 = 0x00;
+
+/// Prefix of an inner node hash
+const NODE_PREFIX: u8 = 0x01;
+
+/// Merkle root over the `commitment` field of each commitment
+///
+/// See the [module documentation](self) for the exact construction.
+pub fn commitments_root(commitments: &[ExecutionCommitment]) -> Hash {
+    let count = (commitments.len() as u64).to_be_bytes();
+    hash_concat(&[&count, tree_root(commitments).as_slice()])
+}
+
+/// Root of the prefixed tree over the commitments, before the count
+fn tree_root(commitments: &[ExecutionCommitment]) -> Hash {
+    let mut level: Vec<Hash> = commitments
+        .iter()
+        .map(|c| hash_concat(&[&[LEAF_PREFIX], c.commitment.as_slice()]))
+        .collect();
+    if level.is_empty() {
+        return Hash::ZERO;
+    }
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_concat(&[&[NODE_PREFIX], left.as_slice(), right.as_slice()]),
+                [single] => *single,
+                _ => unreachable!("chunks(2) yields one or two nodes"),
+            })
+            .collect();
+    }
+
+    level[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{BlockEnv, TxInput};
+    use crate::state::{AccountState, InMemoryDB};
+    use crate::types::{Address, U256};
+
+    fn leaf(commitment: &ExecutionCommitment) -> Hash {
+        hash_concat(&[&[0x00], commitment.commitment.as_slice()])
+    }
+
+    fn node(left: Hash, right: Hash) -> Hash {
+        hash_concat(&[&[0x01], left.as_slice(), right.as_slice()])
+    }
+
+    fn with_count(count: u64, tree: Hash) -> Hash {
+        hash_concat(&[&count.to_be_bytes(), tree.as_slice()])
+    }
+
+    fn transfer(from: u8, to: u8) -> ExecutionInput {
+        let sender = Address::repeat_byte(from);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::transfer(sender, Address::repeat_byte(to), U256::from(1000u64)),
+            state,
+        )
+    }
+
+    #[test]
+    fn test_multi_execution_root() {
+        let first = transfer(0x01, 0x02);
+        let second = transfer(0x03, 0x04);
+        let (_, c1) = ShadowExecutor::execute(first.clone()).unwrap();
+        let (_, c2) = ShadowExecutor::execute(second.clone()).unwrap();
+
+        let batch = MultiExecutionInput::new(vec![first, second]);
+        let (commitments, root) = batch.execute().unwrap();
+
+        assert_eq!(commitments, vec![c1.clone(), c2.clone()]);
+        assert_eq!(root, with_count(2, node(leaf(&c1), leaf(&c2))));
+    }
+
+    #[test]
+    fn test_commitments_root_odd_count() {
+        let commitments: Vec<_> = [(0x01, 0x02), (0x03, 0x04), (0x05, 0x06)]
+            .into_iter()
+            .map(|(from, to)| ShadowExecutor::execute(transfer(from, to)).unwrap().1)
+            .collect();
+
+        // The third leaf is carried up unchanged
+        let left = node(leaf(&commitments[0]), leaf(&commitments[1]));
+        assert_eq!(
+            commitments_root(&commitments),
+            with_count(3, node(left, leaf(&commitments[2])))
+        );
+        assert_eq!(commitments_root(&[]), with_count(0, Hash::ZERO));
+
+        // An inner node does not pass for a leaf
+        let mut forged = commitments[0].clone();
+        forged.commitment = node(leaf(&commitments[0]), leaf(&commitments[1]));
+        assert_ne!(tree_root(&[forged]), tree_root(&commitments[..2]));
+    }
+}
@@ -3,9 +3,13 @@
 //! Represents the result of EVM execution for ZK proof commitment.
 //! The hash of ExecutionOutput is bound to the input for verification.
 
-use crate::hashing::{compute_commitment, hash_struct};
-use crate::state::InMemoryDB;
-use crate::types::{Address, Gas, Hash};
+use crate::abi::{self, CustomError, RevertReason};
+use crate::codec;
+use crate::errors::{Result, ShadowEvmError};
+use crate::hashing::{hash_struct, keccak256, Hasher, StateHashAlgo};
+use crate::state::{InMemoryDB, StateDiff, StateRootMode};
+use crate::trace::GasProfile;
+use crate::types::{Address, BlockNumber, Gas, Hash, Timestamp, U256};
 use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
@@ -68,6 +72,145 @@ impl ExecutionStatus {
     }
 }
 
+/// Why an execution halted
+///
+/// Every halt consumes the whole gas limit, but only `OutOfGas` means the
+/// limit was too low; the others point at a bug in the called code. Part of
+/// the output hash, so each of revm's halts maps to a fixed variant here.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HaltReason {
+    /// Ran out of gas (including memory expansion and call stipends)
+    OutOfGas,
+    /// Executed an undefined opcode or `INVALID` (0xfe)
+    InvalidOpcode {
+        /// The offending opcode byte
+        opcode: u8,
+        /// Program counter of the opcode, within the frame that halted
+        pc: usize,
+    },
+    /// Popped from an empty stack
+    StackUnderflow,
+    /// Pushed onto a full stack
+    StackOverflow,
+    /// Nested calls exceeded the maximum call depth
+    ///
+    /// See `CallOptions::max_call_depth`.
+    CallTooDeep,
+    /// Ran an opcode the active hardfork does not have yet
+    NotActivated,
+    /// Jumped to a destination that is not a `JUMPDEST`
+    InvalidJump,
+    /// Read past the end of the return data (`RETURNDATACOPY`)
+    ReturnDataOutOfBounds,
+    /// Created a contract at an address that already has code or a nonce
+    CreateCollision,
+    /// A precompile rejected its input
+    PrecompileFailed,
+    /// The creating account's nonce is at its maximum
+    NonceOverflow,
+    /// Deployed code exceeds the EIP-170 size limit
+    CodeSizeLimit,
+    /// Deployed code starts with the `0xEF` byte (EIP-3541)
+    CodeStartsWithEf,
+    /// Init code exceeds the EIP-3860 size limit
+    InitCodeSizeLimit,
+    /// Changed state, or called with value, inside a static call
+    StaticStateChange,
+    /// Transferred more value than the sender holds
+    OutOfFunds,
+    /// An EOF-only check failed
+    Eof,
+}
+
+/// Something suspicious about an execution that did not make it fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionWarning {
+    /// A create succeeded but deployed no code
+    ///
+    /// Usually init code that never `RETURN`s the runtime code: the new
+    /// account exists and can hold value, but there is nothing to call.
+    EmptyDeployedCode {
+        /// Address of the code-less contract
+        address: Address,
+    },
+}
+
+/// A field in which two outputs differ, as reported by `ExecutionOutput::diff`
+///
+/// `left` is the value in the output `diff` was called on, `right` the one
+/// in the output it was compared with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputDifference {
+    /// Execution status
+    Status {
+        left: ExecutionStatus,
+        right: ExecutionStatus,
+    },
+    /// Halt reason
+    HaltReason {
+        left: Option<HaltReason>,
+        right: Option<HaltReason>,
+    },
+    /// Return data
+    ReturnData { left: Vec<u8>, right: Vec<u8> },
+    /// Gas used
+    GasUsed { left: Gas, right: Gas },
+    /// Gas refunded
+    GasRefunded { left: Gas, right: Gas },
+    /// Gas left
+    GasLeft { left: Gas, right: Gas },
+    /// Effective gas price
+    EffectiveGasPrice { left: U256, right: U256 },
+    /// Created contract address
+    CreatedAddress {
+        left: Option<Address>,
+        right: Option<Address>,
+    },
+    /// Deployed code size
+    DeployedCodeSize {
+        left: Option<usize>,
+        right: Option<usize>,
+    },
+    /// Number of logs (only logs at indices both have are compared)
+    LogCount { left: usize, right: usize },
+    /// Log at `index`
+    Log { index: usize, left: Log, right: Log },
+    /// Account exists in only one post-state
+    AccountExists {
+        address: Address,
+        left: bool,
+        right: bool,
+    },
+    /// Balance of an account in both post-states
+    Balance {
+        address: Address,
+        left: U256,
+        right: U256,
+    },
+    /// Nonce of an account in both post-states
+    Nonce {
+        address: Address,
+        left: u64,
+        right: u64,
+    },
+    /// Code of an account in both post-states (by code hash)
+    Code {
+        address: Address,
+        left: Hash,
+        right: Hash,
+    },
+    /// Storage slot of an account in both post-states
+    Storage {
+        address: Address,
+        slot: U256,
+        left: U256,
+        right: U256,
+    },
+}
+
+/// Gas charged per byte of deployed code (EIP-170 era code deposit)
+pub const CODE_DEPOSIT_GAS_PER_BYTE: Gas = 200;
+
 /// Complete execution output for Shadow-EVM
 ///
 /// Contains all results from EVM execution that need to be committed to the ZK proof.
@@ -76,17 +219,45 @@ pub struct ExecutionOutput {
     /// Execution status
     pub status: ExecutionStatus,
     /// Return data from execution
+    ///
+    /// Empty for a successful create: the bytes its init code returns
+    /// become the deployed code (see `created_code_hash`).
     pub return_data: Vec<u8>,
     /// Gas used during execution
     pub gas_used: Gas,
     /// Gas refunded (from storage clears, etc.)
     pub gas_refunded: Gas,
+    /// Gas limit left unused (`gas_limit - gas_used`)
+    ///
+    /// Committed with the rest of the output, so a proof also attests to
+    /// how much of the limit the execution needed.
+    #[serde(default)]
+    pub gas_left: Gas,
+    /// Price per gas the sender paid (see `TxInput::effective_gas_price`)
+    #[serde(default)]
+    pub effective_gas_price: U256,
     /// Logs emitted during execution
     pub logs: Vec<Log>,
     /// Post-execution state
     pub post_state: InMemoryDB,
     /// Created contract address (if contract creation)
     pub created_address: Option<Address>,
+    /// Why execution halted (only set when `status` is `Halt`)
+    #[serde(default)]
+    pub halt_reason: Option<HaltReason>,
+    /// Size of the deployed runtime code (only set for successful creates)
+    #[serde(default)]
+    pub deployed_code_size: Option<usize>,
+    /// Warnings raised by the execution
+    ///
+    /// Diagnostic only: not serialized and not part of the output hash.
+    #[serde(skip)]
+    pub warnings: Vec<ExecutionWarning>,
+    /// Per-opcode gas usage, set by `ShadowExecutor::execute_profiled`
+    ///
+    /// Diagnostic only: not serialized and not part of the output hash.
+    #[serde(skip)]
+    pub gas_profile: Option<GasProfile>,
 }
 
 impl ExecutionOutput {
@@ -103,9 +274,15 @@ impl ExecutionOutput {
             return_data,
             gas_used,
             gas_refunded,
+            gas_left: 0,
+            effective_gas_price: U256::ZERO,
             logs,
             post_state,
             created_address: None,
+            halt_reason: None,
+            deployed_code_size: None,
+            warnings: Vec::new(),
+            gas_profile: None,
         }
     }
 
@@ -116,9 +293,15 @@ impl ExecutionOutput {
             return_data,
             gas_used,
             gas_refunded: 0,
+            gas_left: 0,
+            effective_gas_price: U256::ZERO,
             logs: Vec::new(),
             post_state,
             created_address: None,
+            halt_reason: None,
+            deployed_code_size: None,
+            warnings: Vec::new(),
+            gas_profile: None,
         }
     }
 
@@ -129,9 +312,15 @@ impl ExecutionOutput {
             return_data: Vec::new(),
             gas_used,
             gas_refunded: 0,
+            gas_left: 0,
+            effective_gas_price: U256::ZERO,
             logs: Vec::new(),
             post_state,
             created_address: None,
+            halt_reason: None,
+            deployed_code_size: None,
+            warnings: Vec::new(),
+            gas_profile: None,
         }
     }
 
@@ -145,6 +334,59 @@ impl ExecutionOutput {
         self.post_state.compute_state_root()
     }
 
+    /// Hash of this output with the given hash function
+    pub fn hash_with_algo(&self, algo: StateHashAlgo) -> Hash {
+        algo.hash_struct(self)
+    }
+
+    /// Balance of `address` after execution (zero if it does not exist)
+    pub fn balance_of(&self, address: Address) -> U256 {
+        self.post_state
+            .get_account(&address)
+            .map_or(U256::ZERO, |account| account.balance)
+    }
+
+    /// Nonce of `address` after execution (zero if it does not exist)
+    pub fn nonce_of(&self, address: Address) -> u64 {
+        self.post_state
+            .get_account(&address)
+            .map_or(0, |account| account.nonce)
+    }
+
+    /// Storage value of `address` at `slot` after execution (zero if unset)
+    pub fn storage_of(&self, address: Address, slot: U256) -> U256 {
+        self.post_state
+            .get_account(&address)
+            .map_or(U256::ZERO, |account| account.get_storage(&slot))
+    }
+
+    /// Changes the execution made to `pre`, its pre-state
+    pub fn state_diff(&self, pre: &InMemoryDB) -> StateDiff {
+        pre.diff(&self.post_state)
+    }
+
+    /// Estimated serialized size in bytes
+    pub fn estimated_serialized_bytes(&self) -> usize {
+        codec::estimated_serialized_bytes(self)
+    }
+
+    /// Encode as CBOR (without format prefix)
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        codec::to_cbor(self)
+    }
+
+    /// Encode with bincode, or CBOR if larger than `codec::CBOR_THRESHOLD_BYTES`
+    ///
+    /// The result starts with a magic prefix naming the format.
+    pub fn serialize_auto(&self) -> Result<Vec<u8>> {
+        codec::serialize_auto(self)
+    }
+
+    /// Decode an output produced by `serialize_auto`
+    pub fn deserialize_auto(data: &[u8]) -> Result<Self> {
+        codec::deserialize_auto(data)
+    }
+
     /// Check if execution was successful
     pub fn is_success(&self) -> bool {
         self.status.is_success()
@@ -155,6 +397,23 @@ impl ExecutionOutput {
         self.status.is_revert()
     }
 
+    /// Whether execution halted because it ran out of gas
+    ///
+    /// Distinguishes "the gas limit was too low" from a revert, which
+    /// refunds unused gas, and from other halts, which are code bugs.
+    pub fn ran_out_of_gas(&self) -> bool {
+        self.halt_reason == Some(HaltReason::OutOfGas)
+    }
+
+    /// Share of the gas limit used, in percent (0 if the limit was zero)
+    pub fn gas_utilization_percent(&self) -> u64 {
+        let gas_limit = self.gas_used + self.gas_left;
+        if gas_limit == 0 {
+            return 0;
+        }
+        self.gas_used * 100 / gas_limit
+    }
+
     /// Get effective gas used (accounting for refunds)
     pub fn effective_gas_used(&self) -> Gas {
         // Refund is capped at half of gas used
@@ -163,13 +422,249 @@ impl ExecutionOutput {
         self.gas_used - actual_refund
     }
 
+    /// Gas charged for storing the deployed code (only set for successful creates)
+    ///
+    /// Included in `gas_used`; the rest went to running the constructor.
+    pub fn code_deposit_gas(&self) -> Option<Gas> {
+        self.deployed_code_size
+            .map(|size| size as Gas * CODE_DEPOSIT_GAS_PER_BYTE)
+    }
+
+    /// Code hash of the contract a successful create deployed
+    pub fn created_code_hash(&self) -> Option<Hash> {
+        let address = self.created_address?;
+        self.post_state
+            .get_account(&address)
+            .map(|account| account.code_hash)
+    }
+
+    /// Check that only accounts in `access_set` differ from `pre_state`
+    ///
+    /// A soundness audit: any account created, removed or modified in
+    /// `post_state` that is not listed is reported as
+    /// `ShadowEvmError::ChangeOutsideAccessSet`.
+    pub fn assert_changes_within(
+        &self,
+        pre_state: &InMemoryDB,
+        access_set: &[Address],
+    ) -> Result<()> {
+        match self
+            .post_state
+            .changed_accounts(pre_state)
+            .into_iter()
+            .find(|address| !access_set.contains(address))
+        {
+            Some(address) => Err(ShadowEvmError::ChangeOutsideAccessSet { address }),
+            None => Ok(()),
+        }
+    }
+
+    /// Decode the revert data as one of the given Solidity custom errors
+    ///
+    /// Returns `None` unless the execution reverted with data matching
+    /// one of `signatures` (e.g. `"InsufficientBalance(uint256,uint256)"`).
+    pub fn decode_custom_error(&self, signatures: &[&'static str]) -> Option<CustomError> {
+        if !self.is_revert() {
+            return None;
+        }
+        abi::decode_custom_error(&self.return_data, signatures)
+    }
+
+    /// Why the execution reverted, decoded from the return data
+    ///
+    /// Recognizes `Error(string)` and `Panic(uint256)`; any other payload
+    /// is returned as a `RevertReason::CustomError`. Returns `None` unless
+    /// the execution reverted with at least a 4-byte selector.
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        if !self.is_revert() {
+            return None;
+        }
+        abi::decode_revert_reason(&self.return_data)
+    }
+
+    /// Every field in which this output differs from `other`
+    ///
+    /// Differences are reported top-level fields first (status, return
+    /// data, gas, logs), then the post-state by address and slot, so the
+    /// first entry is the most fundamental divergence. Empty if the outputs
+    /// are equal; the diagnostic `warnings` and `gas_profile` are ignored.
+    pub fn diff(&self, other: &ExecutionOutput) -> Vec<OutputDifference> {
+        let mut diffs = Vec::new();
+
+        if self.status != other.status {
+            diffs.push(OutputDifference::Status {
+                left: self.status,
+                right: other.status,
+            });
+        }
+        if self.halt_reason != other.halt_reason {
+            diffs.push(OutputDifference::HaltReason {
+                left: self.halt_reason.clone(),
+                right: other.halt_reason.clone(),
+            });
+        }
+        if self.return_data != other.return_data {
+            diffs.push(OutputDifference::ReturnData {
+                left: self.return_data.clone(),
+                right: other.return_data.clone(),
+            });
+        }
+        if self.gas_used != other.gas_used {
+            diffs.push(OutputDifference::GasUsed {
+                left: self.gas_used,
+                right: other.gas_used,
+            });
+        }
+        if self.gas_refunded != other.gas_refunded {
+            diffs.push(OutputDifference::GasRefunded {
+                left: self.gas_refunded,
+                right: other.gas_refunded,
+            });
+        }
+        if self.gas_left != other.gas_left {
+            diffs.push(OutputDifference::GasLeft {
+                left: self.gas_left,
+                right: other.gas_left,
+            });
+        }
+        if self.effective_gas_price != other.effective_gas_price {
+            diffs.push(OutputDifference::EffectiveGasPrice {
+                left: self.effective_gas_price,
+                right: other.effective_gas_price,
+            });
+        }
+        if self.created_address != other.created_address {
+            diffs.push(OutputDifference::CreatedAddress {
+                left: self.created_address,
+                right: other.created_address,
+            });
+        }
+        if self.deployed_code_size != other.deployed_code_size {
+            diffs.push(OutputDifference::DeployedCodeSize {
+                left: self.deployed_code_size,
+                right: other.deployed_code_size,
+            });
+        }
+
+        if self.logs.len() != other.logs.len() {
+            diffs.push(OutputDifference::LogCount {
+                left: self.logs.len(),
+                right: other.logs.len(),
+            });
+        }
+        for (index, (left, right)) in self.logs.iter().zip(&other.logs).enumerate() {
+            if left != right {
+                diffs.push(OutputDifference::Log {
+                    index,
+                    left: left.clone(),
+                    right: right.clone(),
+                });
+            }
+        }
+
+        diff_states(&self.post_state, &other.post_state, &mut diffs);
+        diffs
+    }
+
     /// Set the created contract address
     pub fn with_created_address(mut self, address: Address) -> Self {
         self.created_address = Some(address);
         self
     }
+
+    /// Set the gas left unused, given the transaction's gas limit
+    pub fn with_gas_limit(mut self, gas_limit: Gas) -> Self {
+        self.gas_left = gas_limit.saturating_sub(self.gas_used);
+        self
+    }
+
+    /// Set the price per gas the sender paid
+    pub fn with_effective_gas_price(mut self, price: U256) -> Self {
+        self.effective_gas_price = price;
+        self
+    }
+
+    /// Set the deployed code size
+    pub fn with_deployed_code_size(mut self, size: usize) -> Self {
+        self.deployed_code_size = Some(size);
+        self
+    }
+
+    /// Set the halt reason
+    pub fn with_halt_reason(mut self, reason: HaltReason) -> Self {
+        self.halt_reason = Some(reason);
+        self
+    }
+}
+
+/// Append the account-level differences between two post-states
+fn diff_states(left: &InMemoryDB, right: &InMemoryDB, diffs: &mut Vec<OutputDifference>) {
+    let mut addresses: Vec<&Address> = left.accounts.keys().chain(right.accounts.keys()).collect();
+    addresses.sort();
+    addresses.dedup();
+
+    for address in addresses {
+        let address = *address;
+        let (l, r) = match (left.get_account(&address), right.get_account(&address)) {
+            (Some(l), Some(r)) => (l, r),
+            (l, r) => {
+                diffs.push(OutputDifference::AccountExists {
+                    address,
+                    left: l.is_some(),
+                    right: r.is_some(),
+                });
+                continue;
+            }
+        };
+
+        if l.balance != r.balance {
+            diffs.push(OutputDifference::Balance {
+                address,
+                left: l.balance,
+                right: r.balance,
+            });
+        }
+        if l.nonce != r.nonce {
+            diffs.push(OutputDifference::Nonce {
+                address,
+                left: l.nonce,
+                right: r.nonce,
+            });
+        }
+        if l.code != r.code {
+            diffs.push(OutputDifference::Code {
+                address,
+                left: keccak256(&l.code),
+                right: keccak256(&r.code),
+            });
+        }
+
+        let mut slots: Vec<&U256> = l.storage.keys().chain(r.storage.keys()).collect();
+        slots.sort();
+        slots.dedup();
+        for slot in slots {
+            let (lv, rv) = (l.get_storage(slot), r.get_storage(slot));
+            if lv != rv {
+                diffs.push(OutputDifference::Storage {
+                    address,
+                    slot: *slot,
+                    left: lv,
+                    right: rv,
+                });
+            }
+        }
+    }
 }
 
+/// Tag of the last block context in the commitment preimage
+const LAST_BLOCK_CONTEXT_TAG: u8 = 0x01;
+
+/// Tag of the storage claim in the commitment preimage
+///
+/// Without it, a preimage with data hashes but no claim could not be told
+/// apart from one with a claim whose fields happen to line up.
+const STORAGE_CLAIM_TAG: u8 = 0x02;
+
 /// ZK Proof public commitment
 ///
 /// This is what gets verified on-chain.
@@ -178,7 +673,12 @@ impl ExecutionOutput {
 /// # Structure
 /// - `input_hash`: Keccak256 of ExecutionInput
 /// - `output_hash`: Keccak256 of ExecutionOutput
-/// - `commitment`: Keccak256(input_hash || output_hash)
+/// - `commitment`: Keccak256(input_hash || output_hash || block_number || timestamp || chain_id),
+///   with the block context as 8-byte big-endian integers, followed by
+///   `calldata_hash || return_data_hash` if the data hashes are committed,
+///   then `0x02 || address || slot || value || post_state_root` if a
+///   storage value is claimed, then `0x01 || last_block_number || last_timestamp` if a
+///   block execution ran past its starting context
 ///
 /// The verifier only needs to check the commitment against the proof.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -193,6 +693,90 @@ pub struct ExecutionCommitment {
     pub post_state_root: Hash,
     /// Combined commitment (public input for ZK verifier)
     pub commitment: Hash,
+    /// Hash function all of the above were computed with
+    #[serde(default)]
+    pub hash_algo: StateHashAlgo,
+    /// How `pre_state_root` and `post_state_root` were computed
+    ///
+    /// Taken from the input, so already bound through `input_hash`. With
+    /// `StateRootMode::Mpt` both roots can be checked against the
+    /// `stateRoot` of block headers.
+    #[serde(default)]
+    pub state_root_mode: StateRootMode,
+    /// Hash of the input's application-level public inputs (ZERO if none)
+    ///
+    /// Already bound through `input_hash`; exposed so a verifier can match
+    /// the proof to a request without the full input.
+    #[serde(default)]
+    pub public_inputs_hash: Hash,
+    /// Number of the block the execution ran in
+    ///
+    /// Part of the commitment preimage, so an on-chain verifier can reject
+    /// proofs of stale executions by reading it from the journal.
+    #[serde(default)]
+    pub block_number: BlockNumber,
+    /// Timestamp of the block the execution ran in
+    ///
+    /// Part of the commitment preimage, like `block_number`.
+    #[serde(default)]
+    pub timestamp: Timestamp,
+    /// Chain ID the execution ran under
+    ///
+    /// Part of the commitment preimage, so an on-chain verifier can reject
+    /// proofs made for another chain by comparing it with `block.chainid`.
+    #[serde(default)]
+    pub chain_id: u64,
+    /// `GUEST_VERSION` of the guest that committed this (0 if computed natively)
+    ///
+    /// Not part of the commitment preimage: an execution has the same
+    /// commitment whichever guest proved it. Lets an on-chain verifier
+    /// reject proofs from incompatible guests, which the image ID alone
+    /// cannot express since it changes on every rebuild.
+    #[serde(default)]
+    pub guest_version: u32,
+    /// Ethereum hash of the executed transaction, if it was signed
+    ///
+    /// Already bound through `input_hash`; exposed so an on-chain verifier
+    /// can match the proof to a transaction. See `TxInput::tx_hash`.
+    #[serde(default)]
+    pub tx_hash: Option<Hash>,
+    /// Keccak256 of the transaction's calldata, if committed
+    ///
+    /// Set together with `return_data_hash` (see `with_data_hashes`); both
+    /// are then part of the commitment preimage, so a verifier can check
+    /// the calldata and return data against known hashes without either
+    /// being in the journal.
+    #[serde(default)]
+    pub calldata_hash: Option<Hash>,
+    /// Keccak256 of the execution's return data, if committed
+    #[serde(default)]
+    pub return_data_hash: Option<Hash>,
+    /// A single storage value claimed to hold after execution
+    ///
+    /// Part of the commitment preimage together with `post_state_root`,
+    /// tagged with `0x02`, so a contract can check one value from the journal alone instead of
+    /// the whole post-state. See `verify_storage_claim`.
+    #[serde(default)]
+    pub storage_claim: Option<StorageClaim>,
+    /// Block number and timestamp the last transaction of a block ran at
+    ///
+    /// Only set when a `BlockExecutor` advanced past the starting
+    /// `block_number` and `timestamp`, which then open the range of block
+    /// contexts executed and this closes it. Part of the commitment
+    /// preimage, tagged with `0x01`.
+    #[serde(default)]
+    pub last_block_context: Option<(BlockNumber, Timestamp)>,
+}
+
+/// Storage value claimed by an `ExecutionCommitment`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StorageClaim {
+    /// Account owning the slot
+    pub address: Address,
+    /// Storage slot
+    pub slot: U256,
+    /// Value of the slot in the post-state
+    pub value: U256,
 }
 
 impl ExecutionCommitment {
@@ -203,31 +787,292 @@ impl ExecutionCommitment {
         pre_state_root: Hash,
         post_state_root: Hash,
     ) -> Self {
-        let commitment = compute_commitment(&input_hash, &output_hash);
-        Self {
+        Self::new_with_algo(
+            StateHashAlgo::Keccak,
             input_hash,
             output_hash,
             pre_state_root,
             post_state_root,
-            commitment,
-        }
+        )
+    }
+
+    /// Create commitment from hashes computed with `hash_algo`
+    ///
+    /// `commitment = H(input_hash || output_hash || 0 || 0 || 0)` with
+    /// `H = hash_algo`; set the block context with `with_block_context`
+    /// and `with_chain_id`.
+    pub fn new_with_algo(
+        hash_algo: StateHashAlgo,
+        input_hash: Hash,
+        output_hash: Hash,
+        pre_state_root: Hash,
+        post_state_root: Hash,
+    ) -> Self {
+        let mut commitment = Self {
+            input_hash,
+            output_hash,
+            pre_state_root,
+            post_state_root,
+            commitment: Hash::ZERO,
+            hash_algo,
+            state_root_mode: StateRootMode::default(),
+            public_inputs_hash: Hash::ZERO,
+            block_number: 0,
+            timestamp: 0,
+            chain_id: 0,
+            guest_version: 0,
+            tx_hash: None,
+            calldata_hash: None,
+            return_data_hash: None,
+            storage_claim: None,
+            last_block_context: None,
+        };
+        commitment.commitment = commitment.combine(&input_hash, &output_hash);
+        commitment
+    }
+
+    /// Set the block context and recompute the commitment over it
+    pub fn with_block_context(mut self, block_number: BlockNumber, timestamp: Timestamp) -> Self {
+        self.block_number = block_number;
+        self.timestamp = timestamp;
+        self.commitment = self.combine(&self.input_hash, &self.output_hash);
+        self
+    }
+
+    /// Set the chain ID and recompute the commitment over it
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self.commitment = self.combine(&self.input_hash, &self.output_hash);
+        self
+    }
+
+    /// Commit to the calldata and return data hashes
+    ///
+    /// Appends them to the preimage and recomputes the commitment.
+    pub fn with_data_hashes(mut self, calldata_hash: Hash, return_data_hash: Hash) -> Self {
+        self.calldata_hash = Some(calldata_hash);
+        self.return_data_hash = Some(return_data_hash);
+        self.commitment = self.combine(&self.input_hash, &self.output_hash);
+        self
+    }
+
+    /// Calldata and return data hashes, if both are committed
+    fn data_hashes(&self) -> Option<(Hash, Hash)> {
+        self.calldata_hash.zip(self.return_data_hash)
+    }
+
+    /// Claim that `slot` of `address` holds `value` in the post-state
+    ///
+    /// Appends the claim and the post-state root to the preimage and
+    /// recomputes the commitment. The claim is taken on trust here; see
+    /// `verify_roots` to check it against a post-state.
+    pub fn with_storage_claim(mut self, address: Address, slot: U256, value: U256) -> Self {
+        self.storage_claim = Some(StorageClaim {
+            address,
+            slot,
+            value,
+        });
+        self.commitment = self.combine(&self.input_hash, &self.output_hash);
+        self
+    }
+
+    /// Commit to the block context the last transaction of a block ran at
+    ///
+    /// Appends it to the preimage and recomputes the commitment.
+    pub fn with_last_block_context(
+        mut self,
+        block_number: BlockNumber,
+        timestamp: Timestamp,
+    ) -> Self {
+        self.last_block_context = Some((block_number, timestamp));
+        self.commitment = self.combine(&self.input_hash, &self.output_hash);
+        self
+    }
+
+    /// Storage claim and the root it is a member of, if a value is claimed
+    fn claim_preimage(&self) -> Option<(StorageClaim, Hash)> {
+        self.storage_claim
+            .map(|claim| (claim, self.post_state_root))
+    }
+
+    /// Record how the state roots were computed
+    pub fn with_state_root_mode(mut self, state_root_mode: StateRootMode) -> Self {
+        self.state_root_mode = state_root_mode;
+        self
+    }
+
+    /// Set the version of the guest committing this
+    pub fn with_guest_version(mut self, guest_version: u32) -> Self {
+        self.guest_version = guest_version;
+        self
+    }
+
+    /// Set the hash of the input's public inputs
+    pub fn with_public_inputs_hash(mut self, public_inputs_hash: Hash) -> Self {
+        self.public_inputs_hash = public_inputs_hash;
+        self
+    }
+
+    /// Set the hash of the executed transaction
+    pub fn with_tx_hash(mut self, tx_hash: Option<Hash>) -> Self {
+        self.tx_hash = tx_hash;
+        self
     }
 
     /// Create commitment from execution input and output
+    ///
+    /// Uses the input's hash function throughout.
     pub fn from_execution(input: &crate::input::ExecutionInput, output: &ExecutionOutput) -> Self {
-        Self::new(
-            input.hash(),
-            output.hash(),
-            input.pre_state_root(),
-            output.post_state_root(),
+        Self::from_hashed_execution(input, input.hash(), input.pre_state_root(), output)
+    }
+
+    /// `from_execution` with the input hash and pre-state root already
+    /// computed, so `input.pre_state` may have been moved out since
+    pub(crate) fn from_hashed_execution(
+        input: &crate::input::ExecutionInput,
+        input_hash: Hash,
+        pre_state_root: Hash,
+        output: &ExecutionOutput,
+    ) -> Self {
+        let algo = input.hash_algo;
+        let commitment = Self::new_with_algo(
+            algo,
+            input_hash,
+            output.hash_with_algo(algo),
+            pre_state_root,
+            input.state_root(&output.post_state),
         )
+        .with_state_root_mode(input.state_root_mode)
+        .with_public_inputs_hash(input.public_inputs_hash())
+        .with_block_context(input.block.number, input.block.timestamp)
+        .with_chain_id(input.block.chain_id)
+        .with_tx_hash(input.tx.tx_hash(input.block.chain_id));
+
+        let commitment = if input.commit_data_hashes {
+            commitment.with_data_hashes(keccak256(&input.tx.data), keccak256(&output.return_data))
+        } else {
+            commitment
+        };
+
+        match input.claim_storage {
+            Some((address, slot)) => {
+                let value = output.storage_of(address, slot);
+                commitment.with_storage_claim(address, slot, value)
+            }
+            None => commitment,
+        }
+    }
+
+    /// Verify that the committed output is `expected_output_hash`
+    ///
+    /// For a verifier that knows what the answer should be but not the
+    /// exact input. Also checks that `commitment` binds the stated
+    /// `output_hash`, so the field cannot have been swapped on its own.
+    pub fn verify_output(&self, expected_output_hash: &Hash) -> bool {
+        self.verify(&self.input_hash, expected_output_hash)
+    }
+
+    /// Verify that the commitment claims `slot` of `address` holds `value`
+    ///
+    /// Lets a verifier that trusts the proof check one storage value
+    /// without the post-state. Also checks that `commitment` binds the
+    /// claim and the post-state root it belongs to.
+    pub fn verify_storage_claim(&self, address: Address, slot: U256, value: U256) -> bool {
+        let expected = StorageClaim {
+            address,
+            slot,
+            value,
+        };
+        self.storage_claim == Some(expected) && self.verify(&self.input_hash, &self.output_hash)
+    }
+
+    /// Verify that the stated state roots are those of `input` and `output`
+    ///
+    /// `new` takes the roots on trust; this recomputes both with the
+    /// input's hash function and root mode and also requires the
+    /// commitment to use them. A storage claim, if any, must hold in
+    /// `output`'s post-state.
+    // `map_or` rather than `Option::is_none_or`, which older toolchains such
+    // as the zkVM guest's lack
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn verify_roots(
+        &self,
+        input: &crate::input::ExecutionInput,
+        output: &ExecutionOutput,
+    ) -> bool {
+        self.hash_algo == input.hash_algo
+            && self.state_root_mode == input.state_root_mode
+            && self.pre_state_root == input.pre_state_root()
+            && self.post_state_root == input.state_root(&output.post_state)
+            && self.storage_claim.map_or(true, |claim| {
+                output.storage_of(claim.address, claim.slot) == claim.value
+            })
     }
 
     /// Verify that this commitment matches given input/output
     pub fn verify(&self, input_hash: &Hash, output_hash: &Hash) -> bool {
         self.input_hash == *input_hash
             && self.output_hash == *output_hash
-            && self.commitment == compute_commitment(input_hash, output_hash)
+            && self.commitment == self.combine(input_hash, output_hash)
+    }
+
+    /// Combine input and output hashes with this commitment's block
+    /// context, chain ID, and data hashes and storage claim, if any
+    fn combine(&self, input_hash: &Hash, output_hash: &Hash) -> Hash {
+        self.hash_algo
+            .hash(&self.preimage_with(input_hash, output_hash))
+    }
+
+    /// Exact bytes hashed into `commitment`
+    ///
+    /// Lets an auditor recompute the commitment independently; see
+    /// `crate::audit::AuditTrail`.
+    pub fn preimage(&self) -> Vec<u8> {
+        self.preimage_with(&self.input_hash, &self.output_hash)
+    }
+
+    /// Commitment preimage for the given input and output hashes
+    fn preimage_with(&self, input_hash: &Hash, output_hash: &Hash) -> Vec<u8> {
+        let block_number = self.block_number.to_be_bytes();
+        let timestamp = self.timestamp.to_be_bytes();
+        let chain_id = self.chain_id.to_be_bytes();
+        let data_hashes = self.data_hashes();
+        let claim = self.claim_preimage().map(|(claim, post_state_root)| {
+            (
+                claim.address,
+                claim.slot.to_be_bytes::<32>(),
+                claim.value.to_be_bytes::<32>(),
+                post_state_root,
+            )
+        });
+        let mut preimage: Vec<&[u8]> = [
+            input_hash.as_slice(),
+            output_hash.as_slice(),
+            &block_number,
+            &timestamp,
+            &chain_id,
+        ]
+        .to_vec();
+        if let Some((calldata_hash, return_data_hash)) = &data_hashes {
+            preimage.push(calldata_hash.as_slice());
+            preimage.push(return_data_hash.as_slice());
+        }
+        if let Some((address, slot, value, post_state_root)) = &claim {
+            preimage.extend([
+                &[STORAGE_CLAIM_TAG][..],
+                address.as_slice(),
+                slot,
+                value,
+                post_state_root.as_slice(),
+            ]);
+        }
+        let last_block_context = self
+            .last_block_context
+            .map(|(number, timestamp)| (number.to_be_bytes(), timestamp.to_be_bytes()));
+        if let Some((number, timestamp)) = &last_block_context {
+            preimage.extend([&[LAST_BLOCK_CONTEXT_TAG][..], number, timestamp]);
+        }
+        preimage.concat()
     }
 
     /// Get the bytes for on-chain verification
@@ -236,6 +1081,48 @@ impl ExecutionCommitment {
     pub fn to_bytes(&self) -> [u8; 32] {
         self.commitment.0
     }
+
+    /// Canonical JSON representation
+    ///
+    /// An object with the commitment, the state roots, the hash function
+    /// and every field of the preimage, so the commitment can be recomputed
+    /// from the JSON alone. Hashes and storage words are `0x`-prefixed,
+    /// lowercase hex strings and the block context is numbers. The storage
+    /// claim and last block context carry the `tag` byte that precedes them
+    /// in the preimage. Data hashes, a storage claim and a last block
+    /// context that are not committed are `null`.
+    pub fn to_json(&self) -> serde_json::Value {
+        let hex = |bytes: &[u8]| alloy_primitives::hex::encode_prefixed(bytes);
+        let storage_claim = self.storage_claim.map(|claim| {
+            serde_json::json!({
+                "tag": hex(&[STORAGE_CLAIM_TAG]),
+                "address": hex(claim.address.as_slice()),
+                "slot": hex(&claim.slot.to_be_bytes::<32>()),
+                "value": hex(&claim.value.to_be_bytes::<32>()),
+            })
+        });
+        serde_json::json!({
+            "input_hash": hex(self.input_hash.as_slice()),
+            "output_hash": hex(self.output_hash.as_slice()),
+            "pre_state_root": hex(self.pre_state_root.as_slice()),
+            "post_state_root": hex(self.post_state_root.as_slice()),
+            "commitment": hex(self.commitment.as_slice()),
+            "hash_algo": self.hash_algo,
+            "block_number": self.block_number,
+            "timestamp": self.timestamp,
+            "chain_id": self.chain_id,
+            "calldata_hash": self.calldata_hash.map(|hash| hex(hash.as_slice())),
+            "return_data_hash": self.return_data_hash.map(|hash| hex(hash.as_slice())),
+            "storage_claim": storage_claim,
+            "last_block_context": self.last_block_context.map(|(number, timestamp)| {
+                serde_json::json!({
+                    "tag": hex(&[LAST_BLOCK_CONTEXT_TAG]),
+                    "block_number": number,
+                    "timestamp": timestamp,
+                })
+            }),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -293,6 +1180,22 @@ mod tests {
         assert_eq!(output.effective_gas_used(), 80_000);
     }
 
+    #[test]
+    fn test_decode_custom_error() {
+        let signature = "InsufficientBalance(uint256,uint256)";
+        let mut data = abi::selector(signature).to_vec();
+        data.extend_from_slice(&crate::types::U256::from(5u64).to_be_bytes::<32>());
+        data.extend_from_slice(&crate::types::U256::from(9u64).to_be_bytes::<32>());
+
+        let output = ExecutionOutput::revert(data.clone(), 30_000, InMemoryDB::new());
+        let err = output.decode_custom_error(&[signature]).unwrap();
+        assert_eq!(err.args.len(), 2);
+
+        // Only reverts carry error data
+        let output = ExecutionOutput::success(data, 30_000, 0, vec![], InMemoryDB::new());
+        assert!(output.decode_custom_error(&[signature]).is_none());
+    }
+
     #[test]
     fn test_commitment_deterministic() {
         let input_hash = Hash::repeat_byte(0x01);
@@ -318,6 +1221,71 @@ mod tests {
         assert!(!commitment.verify(&output_hash, &input_hash)); // Wrong order
     }
 
+    #[test]
+    fn test_commitment_verify_output() {
+        let output_hash = Hash::repeat_byte(0x02);
+        let commitment =
+            ExecutionCommitment::new(Hash::repeat_byte(0x01), output_hash, Hash::ZERO, Hash::ZERO);
+
+        assert!(commitment.verify_output(&output_hash));
+        assert!(!commitment.verify_output(&Hash::repeat_byte(0x03)));
+
+        // A stated output hash the commitment does not bind is rejected
+        let mut swapped = commitment;
+        swapped.output_hash = Hash::repeat_byte(0x03);
+        assert!(!swapped.verify_output(&Hash::repeat_byte(0x03)));
+    }
+
+    #[test]
+    fn test_commitment_verify_roots() {
+        use crate::evm::ShadowExecutor;
+        use crate::input::{BlockEnv, ExecutionInput, TxInput};
+        use crate::state::AccountState;
+
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u64.pow(18))),
+        );
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64)),
+            state,
+        );
+        let (output, commitment) = ShadowExecutor::execute(input.clone()).unwrap();
+        assert!(commitment.verify_roots(&input, &output));
+
+        let wrong_pre = ExecutionCommitment::new(
+            commitment.input_hash,
+            commitment.output_hash,
+            Hash::repeat_byte(0xee),
+            commitment.post_state_root,
+        );
+        assert!(!wrong_pre.verify_roots(&input, &output));
+
+        let swapped = ExecutionCommitment::new(
+            commitment.input_hash,
+            commitment.output_hash,
+            commitment.post_state_root,
+            commitment.pre_state_root,
+        );
+        assert!(!swapped.verify_roots(&input, &output));
+
+        // Ethereum roots are selected through the input, and bound by its hash
+        let mpt_input = input.clone().with_state_root_mode(StateRootMode::Mpt);
+        let (mpt_output, mpt) = ShadowExecutor::execute(mpt_input.clone()).unwrap();
+        assert_eq!(mpt.state_root_mode, StateRootMode::Mpt);
+        assert_eq!(mpt.pre_state_root, input.pre_state.compute_state_root_mpt());
+        assert_eq!(
+            mpt.post_state_root,
+            mpt_output.post_state.compute_state_root_mpt()
+        );
+        assert_ne!(mpt.input_hash, commitment.input_hash);
+        assert!(mpt.verify_roots(&mpt_input, &mpt_output));
+        assert!(!mpt.verify_roots(&input, &output));
+    }
+
     #[test]
     fn test_commitment_different_inputs() {
         let input1 = Hash::repeat_byte(0x01);
@@ -329,4 +1297,300 @@ mod tests {
 
         assert_ne!(c1.commitment, c2.commitment);
     }
+
+    #[test]
+    fn test_commitment_to_json() {
+        let commitment = ExecutionCommitment::new(
+            Hash::repeat_byte(0x01),
+            Hash::repeat_byte(0xAB),
+            Hash::repeat_byte(0x03),
+            Hash::ZERO,
+        );
+
+        let json = commitment.to_json();
+        let object = json.as_object().unwrap();
+
+        for key in [
+            "input_hash",
+            "output_hash",
+            "pre_state_root",
+            "post_state_root",
+            "commitment",
+        ] {
+            let value = object[key].as_str().unwrap();
+            assert_eq!(value.len(), 66);
+            assert!(value.starts_with("0x"));
+            assert!(value[2..]
+                .chars()
+                .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        }
+        assert_eq!(object["output_hash"], format!("0x{}", "ab".repeat(32)));
+        assert!(object["calldata_hash"].is_null());
+        assert!(object["storage_claim"].is_null());
+    }
+
+    /// Test that the commitment can be recomputed from its JSON alone
+    #[test]
+    fn test_commitment_json_covers_preimage() {
+        let commitment = ExecutionCommitment::new(
+            Hash::repeat_byte(0x01),
+            Hash::repeat_byte(0x02),
+            Hash::repeat_byte(0x03),
+            Hash::repeat_byte(0x04),
+        )
+        .with_block_context(17, 1_700_000_000)
+        .with_chain_id(5)
+        .with_data_hashes(Hash::repeat_byte(0x05), Hash::repeat_byte(0x06))
+        .with_storage_claim(
+            Address::repeat_byte(0x07),
+            U256::from(8u64),
+            U256::from(9u64),
+        )
+        .with_last_block_context(18, 1_700_000_012);
+
+        let json = commitment.to_json();
+        let hash = |key: &str| json[key].as_str().unwrap().parse::<Hash>().unwrap();
+        let word = |key: &str| {
+            let value = json["storage_claim"][key].as_str().unwrap();
+            U256::from_str_radix(&value[2..], 16).unwrap()
+        };
+        let number = |key: &str| json[key].as_u64().unwrap();
+        assert_eq!(json["storage_claim"]["tag"], "0x02");
+        assert_eq!(json["last_block_context"]["tag"], "0x01");
+
+        let rebuilt = ExecutionCommitment::new_with_algo(
+            serde_json::from_value(json["hash_algo"].clone()).unwrap(),
+            hash("input_hash"),
+            hash("output_hash"),
+            hash("pre_state_root"),
+            hash("post_state_root"),
+        )
+        .with_block_context(number("block_number"), number("timestamp"))
+        .with_chain_id(number("chain_id"))
+        .with_data_hashes(hash("calldata_hash"), hash("return_data_hash"))
+        .with_storage_claim(
+            json["storage_claim"]["address"]
+                .as_str()
+                .unwrap()
+                .parse()
+                .unwrap(),
+            word("slot"),
+            word("value"),
+        )
+        .with_last_block_context(
+            json["last_block_context"]["block_number"].as_u64().unwrap(),
+            json["last_block_context"]["timestamp"].as_u64().unwrap(),
+        );
+        assert_eq!(rebuilt.commitment, hash("commitment"));
+    }
+
+    /// Test that the storage claim is tagged in the preimage
+    #[test]
+    fn test_storage_claim_tagged_in_preimage() {
+        let commitment = ExecutionCommitment::new(
+            Hash::repeat_byte(0x01),
+            Hash::repeat_byte(0x02),
+            Hash::repeat_byte(0x03),
+            Hash::repeat_byte(0x04),
+        );
+        let claimed = commitment.clone().with_storage_claim(
+            Address::repeat_byte(0x07),
+            U256::from(8u64),
+            U256::from(9u64),
+        );
+
+        // input_hash || output_hash || block_number || timestamp || chain_id
+        let fixed = 32 + 32 + 8 + 8 + 8;
+        let preimage = claimed.preimage();
+        assert_eq!(preimage[..fixed], commitment.preimage()[..]);
+        assert_eq!(preimage[fixed], STORAGE_CLAIM_TAG);
+        assert_eq!(preimage.len(), fixed + 1 + 20 + 32 + 32 + 32);
+    }
+
+    #[test]
+    fn test_output_serialize_auto() {
+        let output =
+            ExecutionOutput::revert(vec![0x08, 0xc3, 0x79, 0xa0], 21_000, InMemoryDB::new());
+
+        let bytes = output.serialize_auto().unwrap();
+        let decoded = ExecutionOutput::deserialize_auto(&bytes).unwrap();
+        assert_eq!(decoded.hash(), output.hash());
+
+        let cbor = output.to_cbor().unwrap();
+        assert_eq!(
+            codec::from_cbor::<ExecutionOutput>(&cbor).unwrap().hash(),
+            output.hash()
+        );
+    }
+
+    #[test]
+    fn test_diff_pinpoints_storage_slot() {
+        use crate::state::AccountState;
+
+        let contract = Address::repeat_byte(0xcc);
+        let mut account = AccountState::new_contract(vec![0x00], U256::ZERO);
+        account.set_storage(U256::from(1u64), U256::from(10u64));
+        account.set_storage(U256::from(2u64), U256::from(20u64));
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(contract, account);
+        let left = ExecutionOutput::success(vec![], 21000, 0, vec![], state.clone());
+
+        state
+            .get_account_mut(&contract)
+            .unwrap()
+            .set_storage(U256::from(2u64), U256::from(21u64));
+        let right = ExecutionOutput::success(vec![], 21000, 0, vec![], state);
+
+        assert_eq!(
+            left.diff(&right),
+            vec![OutputDifference::Storage {
+                address: contract,
+                slot: U256::from(2u64),
+                left: U256::from(20u64),
+                right: U256::from(21u64),
+            }]
+        );
+        assert!(left.diff(&left.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_assert_changes_within() {
+        use crate::evm::ExecutionBuilder;
+        use crate::input::{BlockEnv, TxInput};
+        use crate::state::AccountState;
+        use crate::types::U256;
+
+        let sender = Address::repeat_byte(0x01);
+        let receiver = Address::repeat_byte(0x02);
+        let block = BlockEnv::default();
+
+        let mut pre_state = InMemoryDB::new();
+        pre_state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let (mut output, _) = ExecutionBuilder::new()
+            .with_block(block.clone())
+            .with_state(pre_state.clone())
+            .with_tx(TxInput::transfer(sender, receiver, U256::from(1000u64)))
+            .execute()
+            .unwrap();
+
+        let access_set = [sender, receiver, block.coinbase];
+        let within = output.assert_changes_within(&pre_state, &access_set);
+        assert!(within.is_ok());
+        assert!(output.assert_changes_within(&pre_state, &[sender]).is_err());
+
+        // A change leaking to an unrelated account is caught
+        let stranger = Address::repeat_byte(0x66);
+        output
+            .post_state
+            .insert_account(stranger, AccountState::new_with_balance(U256::from(1u64)));
+        let err = output
+            .assert_changes_within(&pre_state, &access_set)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::ChangeOutsideAccessSet { address } if address == stranger
+        ));
+    }
+
+    #[test]
+    fn test_state_diff_replays_execution() {
+        use crate::evm::ShadowExecutor;
+        use crate::testing::samples::SampleKind;
+
+        let input = SampleKind::Erc20.input();
+        let (output, _) = ShadowExecutor::execute(input.clone()).unwrap();
+        let diff = output.state_diff(&input.pre_state);
+
+        // Sender pays gas, the token moves balances; nothing is created
+        assert!(diff.created.is_empty() && diff.deleted.is_empty());
+        assert_eq!(diff.modified[&SampleKind::token()].storage_changes.len(), 2);
+
+        let mut replayed = input.pre_state.clone();
+        diff.apply_to(&mut replayed);
+        assert_eq!(replayed.compute_state_root(), output.post_state_root());
+    }
+
+    #[test]
+    fn test_revert_reason() {
+        use crate::evm::ShadowExecutor;
+        use crate::input::TxInput;
+        use crate::state::AccountState;
+        use crate::testing::samples::SampleKind;
+
+        // Reverts with the data appended to its code
+        let reverting = |data: &[u8]| {
+            let len = u8::try_from(data.len()).unwrap();
+            let mut code = vec![
+                0x60, len, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, len, 0x60, 0x00, 0xfd,
+            ];
+            code.extend_from_slice(data);
+            let contract = Address::repeat_byte(0xcc);
+            let mut input = SampleKind::Transfer.input();
+            input
+                .pre_state
+                .insert_account(contract, AccountState::new_contract(code, U256::ZERO));
+            input.tx = TxInput::call(SampleKind::sender(), contract, Vec::new());
+            let (output, _) = ShadowExecutor::execute(input).unwrap();
+            assert!(output.is_revert());
+            output.revert_reason()
+        };
+        let word = |value: u64| U256::from(value).to_be_bytes::<32>();
+
+        // Error("low")
+        let mut data = abi::selector(abi::ERROR_STRING_SIGNATURE).to_vec();
+        data.extend_from_slice(&word(32));
+        data.extend_from_slice(&word(3));
+        let mut text = [0u8; 32];
+        text[..3].copy_from_slice(b"low");
+        data.extend_from_slice(&text);
+        let reason = reverting(&data).unwrap();
+        assert_eq!(reason, RevertReason::StandardError("low".into()));
+        assert_eq!(reason.to_string(), "low");
+
+        // Panic(0x12)
+        let mut panic = abi::selector(abi::PANIC_SIGNATURE).to_vec();
+        panic.extend_from_slice(&word(0x12));
+        let reason = reverting(&panic).unwrap();
+        assert_eq!(reason, RevertReason::PanicCode(U256::from(0x12u64)));
+        assert_eq!(reason.to_string(), "panic 0x12: division or modulo by zero");
+
+        // InsufficientBalance(uint256,uint256), not decoded
+        let mut custom = abi::selector("InsufficientBalance(uint256,uint256)").to_vec();
+        custom.extend_from_slice(&word(1));
+        custom.extend_from_slice(&word(2));
+        let reason = reverting(&custom).unwrap();
+        assert_eq!(
+            reason,
+            RevertReason::CustomError {
+                selector: abi::selector("InsufficientBalance(uint256,uint256)"),
+                data: custom[4..].to_vec(),
+            }
+        );
+        assert_eq!(
+            reason.to_string(),
+            format!(
+                "custom error 0x{} with 64 bytes of data",
+                alloy_primitives::hex::encode(&custom[..4])
+            )
+        );
+
+        // A truncated standard error falls back to a custom error
+        assert!(matches!(
+            reverting(&data[..40]),
+            Some(RevertReason::CustomError { data, .. }) if data.len() == 36
+        ));
+
+        // No selector, no reason
+        assert_eq!(reverting(&[]), None);
+        assert_eq!(reverting(&[0xde, 0xad]), None);
+
+        // Only reverts have a reason
+        let success = ExecutionOutput::success(data, 21_000, 0, Vec::new(), InMemoryDB::new());
+        assert_eq!(success.revert_reason(), None);
+    }
 }
@@ -0,0 +1,159 @@
+//! Custom precompiles for coprocessor operations
+//!
+//! A custom precompile runs native code at a fixed address, for operations
+//! that would be too expensive in EVM bytecode. Register them per call with
+//! `ShadowExecutor::execute_with_precompiles`. Each call is charged its
+//! [`PrecompileGas`] cost like a standard precompile: a call forwarding too
+//! little gas fails and consumes all of it.
+//!
+//! Custom precompiles are not part of `ExecutionInput`, so their executions
+//! come without a commitment: the input hash could not tell which native
+//! code produced the output.
+
+use crate::types::{Address, Gas};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use revm::handler::register::EvmHandler;
+use revm::primitives::{
+    Bytes, Env, Precompile, PrecompileError, PrecompileOutput, PrecompileResult, StatefulPrecompile,
+};
+use revm::{ContextPrecompile, Database};
+
+extern crate alloc;
+
+/// Native implementation of a custom precompile
+///
+/// Returns the output bytes, or an error message that fails the call.
+pub type PrecompileFn = fn(&[u8]) -> core::result::Result<Vec<u8>, String>;
+
+/// Gas schedule of a custom precompile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecompileGas {
+    /// Same cost for every call
+    Fixed(Gas),
+    /// `base + per_word * ceil(input_len / 32)`, like SHA256 and IDENTITY
+    PerWord {
+        /// Cost of a call with empty input
+        base: Gas,
+        /// Cost per 32-byte word of input
+        per_word: Gas,
+    },
+}
+
+impl PrecompileGas {
+    /// Gas charged for a call with `input_len` bytes of input
+    pub fn cost(&self, input_len: usize) -> Gas {
+        match *self {
+            PrecompileGas::Fixed(gas) => gas,
+            PrecompileGas::PerWord { base, per_word } => {
+                let words = (input_len as Gas).div_ceil(32);
+                base.saturating_add(per_word.saturating_mul(words))
+            }
+        }
+    }
+}
+
+/// A native function callable at `address`
+#[derive(Debug, Clone, Copy)]
+pub struct CustomPrecompile {
+    /// Address the precompile is called at
+    pub address: Address,
+    /// Gas charged per call
+    pub gas: PrecompileGas,
+    /// Implementation
+    pub run: PrecompileFn,
+}
+
+impl CustomPrecompile {
+    /// Create a custom precompile
+    pub fn new(address: Address, gas: PrecompileGas, run: PrecompileFn) -> Self {
+        Self { address, gas, run }
+    }
+}
+
+impl StatefulPrecompile for CustomPrecompile {
+    fn call(&self, input: &Bytes, gas_limit: u64, _env: &Env) -> PrecompileResult {
+        let cost = self.gas.cost(input.len());
+        if cost > gas_limit {
+            return Err(PrecompileError::OutOfGas.into());
+        }
+
+        let output = (self.run)(input).map_err(PrecompileError::Other)?;
+        Ok(PrecompileOutput::new(cost, output.into()))
+    }
+}
+
+/// Add `precompiles` to those of the active spec, replacing any at the
+/// same address
+pub(crate) fn register<EXT, DB: Database>(
+    handler: &mut EvmHandler<'_, EXT, DB>,
+    precompiles: &[CustomPrecompile],
+) {
+    if precompiles.is_empty() {
+        return;
+    }
+
+    let custom: Vec<_> = precompiles
+        .iter()
+        .map(|precompile| {
+            let stateful = Precompile::Stateful(Arc::new(*precompile));
+            (precompile.address, ContextPrecompile::Ordinary(stateful))
+        })
+        .collect();
+
+    let standard = handler.pre_execution.load_precompiles();
+    handler.pre_execution.load_precompiles = Arc::new(move || {
+        let mut loaded = standard.clone();
+        loaded.extend(custom.clone());
+        loaded
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::ShadowExecutor;
+    use crate::input::{BlockEnv, ExecutionInput, TxInput};
+    use crate::state::{AccountState, InMemoryDB};
+    use crate::types::U256;
+
+    fn echo(input: &[u8]) -> core::result::Result<Vec<u8>, String> {
+        Ok(input.to_vec())
+    }
+
+    #[test]
+    fn test_precompile_gas_charged() {
+        let caller = Address::repeat_byte(0x01);
+        let address = Address::with_last_byte(0x42);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let tx = TxInput::call(caller, address, vec![]).with_gas_limit(100_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+
+        let (plain, _) = ShadowExecutor::execute(input.clone()).unwrap();
+
+        let precompile = CustomPrecompile::new(address, PrecompileGas::Fixed(3000), echo);
+        let metered = ShadowExecutor::execute_with_precompiles(input, &[precompile]).unwrap();
+
+        assert!(metered.is_success());
+        assert_eq!(metered.gas_used, plain.gas_used + 3000);
+    }
+
+    #[test]
+    fn test_per_word_cost() {
+        let gas = PrecompileGas::PerWord {
+            base: 60,
+            per_word: 12,
+        };
+        assert_eq!(gas.cost(0), 60);
+        assert_eq!(gas.cost(1), 72);
+        assert_eq!(gas.cost(32), 72);
+        assert_eq!(gas.cost(33), 84);
+    }
+}
@@ -0,0 +1,157 @@
+//! Preflight execution against a partial pre-state
+//!
+//! Before an execution can be proven, the host has to fetch every account
+//! and storage slot it reads. [`PreflightDB`] wraps whatever state is
+//! already known and, instead of failing on a miss, records it and answers
+//! with an empty account or a zero value. `ShadowExecutor::preflight` runs a
+//! transaction on it and returns the recorded misses as an [`AccessSet`].
+//!
+//! Because misses read as zero, the preflight run may take a different path
+//! than the real execution (e.g. a balance check that reverts early).
+//! Fetch the reported state, add it to the pre-state and preflight again
+//! until the set comes back empty.
+
+use crate::errors::{Result, ShadowEvmError};
+use crate::state::InMemoryDB;
+use crate::types::{Address, Hash, U256};
+use alloc::collections::BTreeSet;
+use revm::primitives::{AccountInfo, Bytecode};
+use revm::Database;
+use serde::{Deserialize, Serialize};
+
+extern crate alloc;
+
+/// State an execution requested that the pre-state did not contain
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessSet {
+    /// Accounts missing from the pre-state
+    pub accounts: BTreeSet<Address>,
+    /// Storage slots missing from the pre-state
+    pub slots: BTreeSet<(Address, U256)>,
+    /// Block numbers whose hash was requested but not known
+    pub block_hashes: BTreeSet<u64>,
+}
+
+impl AccessSet {
+    /// Whether nothing was missing
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.slots.is_empty() && self.block_hashes.is_empty()
+    }
+}
+
+/// Database that records misses instead of failing on them
+///
+/// A slot absent from a known account is reported as missing too: a
+/// partial state cannot tell an unset slot from one that was not fetched.
+#[derive(Debug, Clone, Default)]
+pub struct PreflightDB {
+    known: InMemoryDB,
+    missing: AccessSet,
+}
+
+impl PreflightDB {
+    /// Wrap the state known so far
+    pub fn new(known: InMemoryDB) -> Self {
+        Self {
+            known,
+            missing: AccessSet::default(),
+        }
+    }
+
+    /// Misses recorded so far
+    pub fn missing(&self) -> &AccessSet {
+        &self.missing
+    }
+
+    /// Consume the database, returning the recorded misses
+    pub fn into_missing(self) -> AccessSet {
+        self.missing
+    }
+}
+
+impl Database for PreflightDB {
+    type Error = ShadowEvmError;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>> {
+        if self.known.get_account(&address).is_none() {
+            self.missing.accounts.insert(address);
+        }
+        self.known.basic(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: Hash) -> Result<Bytecode> {
+        self.known.code_by_hash(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, slot: U256) -> Result<U256> {
+        let known = self
+            .known
+            .get_account(&address)
+            .is_some_and(|account| account.storage.contains_key(&slot));
+        if !known {
+            self.missing.slots.insert((address, slot));
+        }
+        self.known.storage(address, slot)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<Hash> {
+        match self.known.block_hashes.get(&number) {
+            Some(hash) => Ok(*hash),
+            None => {
+                self.missing.block_hashes.insert(number);
+                Ok(Hash::ZERO)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::ShadowExecutor;
+    use crate::hashing::keccak256;
+    use crate::input::{BlockEnv, ExecutionInput, TxInput};
+    use crate::state::AccountState;
+
+    #[test]
+    fn test_preflight_erc20_balance_slot() {
+        let holder = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0xee);
+
+        // balances[msg.sender] with `balances` at slot 0:
+        // CALLER, PUSH1 0, MSTORE, PUSH1 0, PUSH1 32, MSTORE,
+        // PUSH1 64, PUSH1 0, SHA3, SLOAD, STOP
+        let code = vec![
+            0x33, 0x60, 0x00, 0x52, 0x60, 0x00, 0x60, 0x20, 0x52, 0x60, 0x40, 0x60, 0x00, 0x20,
+            0x54, 0x00,
+        ];
+        let mut preimage = [0u8; 64];
+        preimage[12..32].copy_from_slice(holder.as_slice());
+        let balance_slot = U256::from_be_bytes(keccak256(&preimage).0);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            holder,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(token, AccountState::new_contract(code, U256::ZERO));
+
+        let tx = TxInput::call(holder, token, vec![]).with_gas_limit(100_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx.clone(), state.clone());
+
+        let missing = ShadowExecutor::preflight(input).unwrap();
+        assert!(!missing.accounts.contains(&token));
+        assert_eq!(
+            missing.slots.iter().collect::<Vec<_>>(),
+            vec![&(token, balance_slot)]
+        );
+
+        // Once the slot is supplied, nothing is missing from the token
+        state
+            .get_account_mut(&token)
+            .unwrap()
+            .set_storage(balance_slot, U256::from(1000u64));
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+        assert!(ShadowExecutor::preflight(input).unwrap().slots.is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+//! Conversion from alloy RPC types
+//!
+//! Builds execution inputs from blocks and transactions as returned by an
+//! Ethereum JSON-RPC node (`eth_getBlockByNumber`,
+//! `eth_getTransactionByHash`). The pre-state still has to be supplied
+//! separately. Requires the `alloy` feature.
+
+use crate::errors::{Result, ShadowEvmError};
+use crate::input::{BlockEnv, ExecutionInput, TxInput, TxSignature};
+use crate::state::InMemoryDB;
+use crate::types::U256;
+use alloc::format;
+use alloc::vec::Vec;
+use alloy_consensus::{Transaction as _, TxEnvelope};
+use alloy_rpc_types_eth::{Block, Header, Transaction};
+
+extern crate alloc;
+
+impl BlockEnv {
+    /// Block environment of an RPC block header
+    ///
+    /// The chain ID is not part of a header and is left at the default;
+    /// `ExecutionInput::from_alloy` takes it from the transaction.
+    ///
+    /// # Errors
+    /// Returns `ShadowEvmError::MissingField` for blocks without a base fee
+    /// (before London).
+    pub fn from_alloy_header(header: &Header) -> Result<Self> {
+        let base_fee = header
+            .base_fee_per_gas
+            .ok_or(ShadowEvmError::MissingField("block.baseFeePerGas"))?;
+
+        Ok(Self {
+            number: header.number,
+            timestamp: header.timestamp,
+            gas_limit: header.gas_limit,
+            coinbase: header.beneficiary,
+            base_fee: U256::from(base_fee),
+            prev_randao: header.mix_hash,
+            difficulty: header.difficulty,
+            ..Self::default()
+        })
+    }
+}
+
+impl TxInput {
+    /// Transaction input of an RPC transaction
+    ///
+    /// The gas price is the effective one under `base_fee`, the base fee of
+    /// the including block. Access lists are carried over; blobs and
+    /// authorizations are not. Neither is the signature of a typed
+    /// transaction, only that of a legacy one, so that `tx_hash` reproduces
+    /// its on-chain hash.
+    pub fn from_alloy_tx(tx: &Transaction, base_fee: Option<u64>) -> Self {
+        let signature = match &tx.inner {
+            TxEnvelope::Legacy(signed) => {
+                let parity = signed.signature().v() as u64;
+                let v = match signed.tx().chain_id {
+                    Some(chain_id) => 35 + 2 * chain_id + parity, // EIP-155
+                    None => 27 + parity,
+                };
+                Some(TxSignature {
+                    v,
+                    r: signed.signature().r(),
+                    s: signed.signature().s(),
+                })
+            }
+            _ => None,
+        };
+
+        Self {
+            caller: tx.from,
+            to: tx.to(),
+            value: tx.value(),
+            data: tx.input().to_vec(),
+            gas_limit: tx.gas_limit(),
+            gas_price: U256::from(tx.effective_gas_price(base_fee)),
+            nonce: tx.nonce(),
+            signature,
+            access_list: tx.access_list().map_or_else(Vec::new, |list| {
+                list.iter()
+                    .map(|item| {
+                        let slots = item
+                            .storage_keys
+                            .iter()
+                            .map(|key| U256::from_be_bytes(key.0))
+                            .collect();
+                        (item.address, slots)
+                    })
+                    .collect()
+            }),
+            ..Self::default()
+        }
+    }
+}
+
+impl ExecutionInput {
+    /// Execution input for `tx` as included in `block`
+    ///
+    /// # Errors
+    /// Fails if the block has no base fee, or if the transaction reports
+    /// being included in a different block.
+    pub fn from_alloy(block: &Block, tx: &Transaction, pre_state: InMemoryDB) -> Result<Self> {
+        let header = &block.header;
+        if let Some(number) = tx.block_number {
+            if number != header.number {
+                return Err(ShadowEvmError::InvalidTransaction(format!(
+                    "transaction is included in block {}, not block {}",
+                    number, header.number
+                )));
+            }
+        }
+
+        let mut block_env = BlockEnv::from_alloy_header(header)?;
+        if let Some(chain_id) = tx.chain_id() {
+            block_env.chain_id = chain_id;
+        }
+        let tx_input = TxInput::from_alloy_tx(tx, header.base_fee_per_gas);
+
+        Ok(Self::new(block_env, tx_input, pre_state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Address, Hash};
+    use alloy_consensus::{Signed, TxEnvelope, TxLegacy};
+    use alloy_primitives::{Bytes, PrimitiveSignature, TxKind};
+    use alloy_rpc_types_eth::BlockTransactions;
+
+    const NUMBER: u64 = 21_000_000;
+
+    fn block() -> Block {
+        let inner = alloy_consensus::Header {
+            number: NUMBER,
+            timestamp: 1_730_000_000,
+            gas_limit: 30_000_000,
+            beneficiary: Address::repeat_byte(0xcb),
+            base_fee_per_gas: Some(7),
+            mix_hash: Hash::repeat_byte(0x42),
+            ..Default::default()
+        };
+
+        Block {
+            header: Header {
+                hash: Hash::ZERO,
+                inner,
+                total_difficulty: None,
+                size: None,
+            },
+            uncles: Vec::new(),
+            transactions: BlockTransactions::Hashes(Vec::new()),
+            withdrawals: None,
+        }
+    }
+
+    fn transaction(block_number: u64) -> Transaction {
+        let legacy = TxLegacy {
+            chain_id: Some(10),
+            nonce: 3,
+            gas_price: 20,
+            gas_limit: 50_000,
+            to: TxKind::Call(Address::repeat_byte(0x02)),
+            value: U256::from(1000u64),
+            input: Bytes::from(vec![0xab, 0xcd]),
+        };
+        let signature = PrimitiveSignature::new(U256::from(1u64), U256::from(1u64), false);
+
+        Transaction {
+            inner: TxEnvelope::Legacy(Signed::new_unchecked(legacy, signature, Hash::ZERO)),
+            block_hash: None,
+            block_number: Some(block_number),
+            transaction_index: None,
+            effective_gas_price: None,
+            from: Address::repeat_byte(0x01),
+        }
+    }
+
+    #[test]
+    fn test_from_alloy() {
+        let input =
+            ExecutionInput::from_alloy(&block(), &transaction(NUMBER), InMemoryDB::new()).unwrap();
+
+        assert_eq!(input.caller(), Address::repeat_byte(0x01));
+        assert_eq!(input.target(), Some(Address::repeat_byte(0x02)));
+        assert_eq!(input.block.number, NUMBER);
+        assert_eq!(input.block.coinbase, Address::repeat_byte(0xcb));
+        assert_eq!(input.block.base_fee, U256::from(7u64));
+        assert_eq!(input.block.chain_id, 10);
+        assert_eq!(input.tx.gas_price, U256::from(20u64));
+        assert_eq!(input.tx.nonce, 3);
+        assert_eq!(input.tx.data, vec![0xab, 0xcd]);
+        // EIP-155 recovery id for chain 10
+        assert_eq!(input.tx.signature.map(|signature| signature.v), Some(55));
+    }
+
+    #[test]
+    fn test_from_alloy_errors() {
+        let wrong_block =
+            ExecutionInput::from_alloy(&block(), &transaction(NUMBER + 1), InMemoryDB::new());
+        assert!(matches!(
+            wrong_block,
+            Err(ShadowEvmError::InvalidTransaction(_))
+        ));
+
+        let mut pre_london = block();
+        pre_london.header.inner.base_fee_per_gas = None;
+        let missing =
+            ExecutionInput::from_alloy(&pre_london, &transaction(NUMBER), InMemoryDB::new());
+        assert!(matches!(missing, Err(ShadowEvmError::MissingField(_))));
+    }
+}
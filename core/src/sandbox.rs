@@ -0,0 +1,50 @@
+//! Opcode restrictions for sandboxed execution
+//!
+//! A coprocessor deployment can forbid opcodes such as `SELFDESTRUCT`,
+//! `CREATE` or `CALL` to constrain what provable programs may do. See
+//! `ShadowExecutor::execute_restricted`.
+
+extern crate alloc;
+
+use crate::types::Opcode;
+use alloc::vec::Vec;
+use revm::interpreter::{InstructionResult, Interpreter};
+use revm::{Database, EvmContext, Inspector};
+
+/// Inspector halting execution at the first forbidden opcode
+///
+/// The frame that reaches a forbidden opcode halts before executing it, as
+/// does every frame after that. The opcode is kept for the caller to turn
+/// the whole execution into an error.
+#[derive(Debug, Clone, Default)]
+pub struct OpcodeFilter {
+    forbidden: Vec<Opcode>,
+    hit: Option<Opcode>,
+}
+
+impl OpcodeFilter {
+    /// Filter rejecting every opcode in `forbidden`
+    pub fn new(forbidden: &[Opcode]) -> Self {
+        Self {
+            forbidden: forbidden.to_vec(),
+            hit: None,
+        }
+    }
+
+    /// The first forbidden opcode reached, if any
+    pub fn hit(&self) -> Option<Opcode> {
+        self.hit
+    }
+}
+
+impl<DB: Database> Inspector<DB> for OpcodeFilter {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if self.hit.is_none() {
+            self.hit = Opcode::new(interp.current_opcode())
+                .filter(|opcode| self.forbidden.contains(opcode));
+        }
+        if self.hit.is_some() {
+            interp.instruction_result = InstructionResult::OpcodeNotFound;
+        }
+    }
+}
@@ -0,0 +1,536 @@
+//! Sparse Merkle tree over accounts for incremental state roots
+//!
+//! The other state roots are recomputed from the whole state. When proving
+//! a sequence of blocks that each touch a few accounts, re-hashing every
+//! account (with all its storage and code) dominates the cost. This module
+//! defines a root that can instead be updated from a small witness:
+//!
+//! ```text
+//! key(address)  = keccak256(address)                 -- 256-bit path
+//! leaf(account) = hash_struct(account)               -- ZERO if absent
+//! node(l, r)    = ZERO if l == r == ZERO, else keccak256(l || r)
+//! ```
+//!
+//! Every empty subtree hashes to ZERO, so a proof only carries the
+//! non-empty siblings along an account's path. A [`StateDelta`] holds the
+//! base root plus one proof per changed account; [`StateDelta::apply`]
+//! checks each proof against the running root and swaps in the new leaf.
+//! Unchanged accounts are never hashed.
+//!
+//! A large state can also be split into [`StateChunk`]s, each carrying the
+//! proofs of its accounts against the root, to be checked and streamed
+//! independently.
+//!
+//! This root is NOT Ethereum-compatible.
+
+use crate::errors::{Result, ShadowEvmError};
+use crate::hashing::{hash_concat, hash_struct, keccak256};
+use crate::state::{AccountState, InMemoryDB};
+use crate::types::{Address, Hash};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+extern crate alloc;
+
+/// Depth of the tree (one level per bit of the key)
+const DEPTH: usize = 256;
+
+/// Path of an account in the tree
+fn key(address: &Address) -> Hash {
+    keccak256(address.as_slice())
+}
+
+/// Bit of `key` selecting the child at `depth` (`true` = right)
+fn bit(key: &Hash, depth: usize) -> bool {
+    (key[depth / 8] >> (7 - depth % 8)) & 1 == 1
+}
+
+/// Hash of an inner node
+fn node(left: &Hash, right: &Hash) -> Hash {
+    if left.is_zero() && right.is_zero() {
+        Hash::ZERO
+    } else {
+        hash_concat(&[left.as_slice(), right.as_slice()])
+    }
+}
+
+/// Leaf hash of an account (ZERO for an absent account)
+pub fn account_leaf(account: Option<&AccountState>) -> Hash {
+    account.map_or(Hash::ZERO, hash_struct)
+}
+
+/// `(key, leaf)` pairs of all accounts, sorted by key
+fn leaves(db: &InMemoryDB) -> Vec<(Hash, Hash)> {
+    let mut leaves: Vec<_> = db
+        .accounts
+        .iter()
+        .map(|(address, account)| (key(address), account_leaf(Some(account))))
+        .collect();
+    leaves.sort_unstable();
+    leaves
+}
+
+/// Root of the subtree at `depth` holding `leaves` (sorted by key)
+fn subtree_root(leaves: &[(Hash, Hash)], depth: usize) -> Hash {
+    match leaves {
+        [] => Hash::ZERO,
+        [(_, leaf), ..] if depth == DEPTH => *leaf,
+        _ => {
+            let split = leaves.partition_point(|(key, _)| !bit(key, depth));
+            node(
+                &subtree_root(&leaves[..split], depth + 1),
+                &subtree_root(&leaves[split..], depth + 1),
+            )
+        }
+    }
+}
+
+/// Compute the sparse Merkle root of a state
+pub fn sparse_state_root(db: &InMemoryDB) -> Hash {
+    subtree_root(&leaves(db), 0)
+}
+
+/// Sparse Merkle tree of a state, hashed once to read many proofs from
+struct SparseTree {
+    /// `(key, leaf)` pairs of all accounts, sorted by key
+    leaves: Vec<(Hash, Hash)>,
+    /// Root of the tree
+    root: Hash,
+    /// Root of every non-empty subtree with a non-empty sibling, by depth
+    /// and the index of its first leaf
+    ///
+    /// Only these subtrees appear as siblings in the proof of an account
+    /// in the state, so other subtrees are not kept.
+    subtrees: BTreeMap<(usize, usize), Hash>,
+}
+
+impl SparseTree {
+    /// Hash the tree of `db`
+    fn new(db: &InMemoryDB) -> Self {
+        let leaves = leaves(db);
+        let mut subtrees = BTreeMap::new();
+        let root = Self::hash_subtree(&leaves, 0, 0, &mut subtrees);
+        Self {
+            leaves,
+            root,
+            subtrees,
+        }
+    }
+
+    /// `subtree_root` of `leaves`, which start at index `start`, recording
+    /// the subtrees that can be siblings
+    fn hash_subtree(
+        leaves: &[(Hash, Hash)],
+        start: usize,
+        depth: usize,
+        subtrees: &mut BTreeMap<(usize, usize), Hash>,
+    ) -> Hash {
+        match leaves {
+            [] => Hash::ZERO,
+            [(_, leaf), ..] if depth == DEPTH => *leaf,
+            _ => {
+                let split = leaves.partition_point(|(key, _)| !bit(key, depth));
+                let left = Self::hash_subtree(&leaves[..split], start, depth + 1, subtrees);
+                let right =
+                    Self::hash_subtree(&leaves[split..], start + split, depth + 1, subtrees);
+                if split > 0 && split < leaves.len() {
+                    subtrees.insert((depth + 1, start), left);
+                    subtrees.insert((depth + 1, start + split), right);
+                }
+                node(&left, &right)
+            }
+        }
+    }
+
+    /// Proof for `address`, which need not exist
+    fn proof(&self, address: Address) -> AccountProof {
+        let path = key(&address);
+        let mut subtree = self.leaves.as_slice();
+        let mut start = 0;
+        let mut siblings = Vec::with_capacity(DEPTH);
+
+        for depth in 0..DEPTH {
+            let split = subtree.partition_point(|(key, _)| !bit(key, depth));
+            let (left, right) = subtree.split_at(split);
+            let (own, own_start, other, other_start) = if bit(&path, depth) {
+                (right, start + split, left, start)
+            } else {
+                (left, start, right, start + split)
+            };
+
+            // The path of an absent account can leave the tree beside a
+            // subtree that was not kept
+            let sibling = if other.is_empty() {
+                None
+            } else {
+                let root = self.subtrees.get(&(depth + 1, other_start)).copied();
+                Some(root.unwrap_or_else(|| subtree_root(other, depth + 1)))
+            };
+            siblings.push(sibling);
+            subtree = own;
+            start = own_start;
+        }
+
+        AccountProof { address, siblings }
+    }
+}
+
+/// Merkle path of one account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountProof {
+    /// Account the path leads to
+    pub address: Address,
+    /// Sibling hashes from the root down (`None` for an empty subtree)
+    pub siblings: Vec<Option<Hash>>,
+}
+
+impl AccountProof {
+    /// Build the proof for `address` in `db`
+    ///
+    /// Works whether or not the account exists.
+    pub fn generate(db: &InMemoryDB, address: Address) -> Self {
+        SparseTree::new(db).proof(address)
+    }
+
+    /// Root of the tree with `leaf` at this proof's path
+    pub fn root_with_leaf(&self, leaf: Hash) -> Hash {
+        let path = key(&self.address);
+        self.siblings
+            .iter()
+            .enumerate()
+            .rev()
+            .fold(leaf, |hash, (depth, sibling)| {
+                let sibling = sibling.unwrap_or(Hash::ZERO);
+                if bit(&path, depth) {
+                    node(&sibling, &hash)
+                } else {
+                    node(&hash, &sibling)
+                }
+            })
+    }
+}
+
+/// Accounts of one slice of a state, provable against its root
+///
+/// Produced by [`InMemoryDB::chunk`]. The first chunk also carries the
+/// block hashes, which are not part of the root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StateChunk {
+    /// Sparse Merkle root of the whole state
+    pub root: Hash,
+    /// Accounts (and, in the first chunk, block hashes) of this chunk
+    pub state: InMemoryDB,
+    /// Path of each account, in address order
+    pub proofs: Vec<AccountProof>,
+}
+
+impl StateChunk {
+    /// Split `db` into chunks of at most `max_accounts` accounts each
+    ///
+    /// Always returns at least one chunk, so an empty state still carries
+    /// its block hashes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_accounts` is zero.
+    pub fn split(db: &InMemoryDB, max_accounts: usize) -> Vec<Self> {
+        assert!(max_accounts > 0, "chunks must hold at least one account");
+
+        let tree = SparseTree::new(db);
+        let root = tree.root;
+        let accounts: Vec<_> = db.accounts.iter().collect();
+
+        let mut chunks: Vec<Self> = accounts
+            .chunks(max_accounts)
+            .map(|slice| {
+                let mut state = InMemoryDB::new();
+                let mut proofs = Vec::with_capacity(slice.len());
+                for (address, account) in slice {
+                    state.insert_account(**address, (*account).clone());
+                    proofs.push(tree.proof(**address));
+                }
+                Self {
+                    root,
+                    state,
+                    proofs,
+                }
+            })
+            .collect();
+
+        if chunks.is_empty() {
+            chunks.push(Self {
+                root,
+                state: InMemoryDB::new(),
+                proofs: Vec::new(),
+            });
+        }
+        chunks[0].state.block_hashes = db.block_hashes.clone();
+        chunks
+    }
+
+    /// Check every account of the chunk against `root`
+    ///
+    /// Needs only this chunk, so chunks can be verified as they arrive.
+    pub fn verify(&self) -> Result<()> {
+        if let Some(extra) = self.proofs.get(self.state.account_count()) {
+            return Err(ShadowEvmError::InvalidStateProof {
+                address: extra.address,
+            });
+        }
+
+        for (i, (address, account)) in self.state.accounts.iter().enumerate() {
+            let valid = self.proofs.get(i).is_some_and(|proof| {
+                proof.address == *address
+                    && proof.siblings.len() == DEPTH
+                    && proof.root_with_leaf(account_leaf(Some(account))) == self.root
+            });
+            if !valid {
+                return Err(ShadowEvmError::InvalidStateProof { address: *address });
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify `chunks` and merge them back into one state
+    ///
+    /// Fails if any chunk does not verify, the chunks disagree on the
+    /// root, or the merged state does not hash to it (a chunk is missing).
+    pub fn reassemble(chunks: &[Self]) -> Result<InMemoryDB> {
+        let mut db = InMemoryDB::new();
+        let Some(first) = chunks.first() else {
+            return Ok(db);
+        };
+
+        for chunk in chunks {
+            if chunk.root != first.root {
+                return Err(ShadowEvmError::CommitmentMismatch {
+                    expected: first.root,
+                    actual: chunk.root,
+                });
+            }
+            chunk.verify()?;
+            db.merge(&chunk.state);
+            db.block_hashes.extend(&chunk.state.block_hashes);
+        }
+
+        let root = sparse_state_root(&db);
+        if root != first.root {
+            return Err(ShadowEvmError::CommitmentMismatch {
+                expected: first.root,
+                actual: root,
+            });
+        }
+        Ok(db)
+    }
+}
+
+/// Update of one account within a [`StateDelta`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountUpdate {
+    /// Path of the account, against the root before this update
+    pub proof: AccountProof,
+    /// Leaf hash before the update (ZERO if the account did not exist)
+    pub old_leaf: Hash,
+    /// Account after the update (`None` if it was removed)
+    pub new_account: Option<AccountState>,
+}
+
+/// Changed accounts between two states, provable against the base root
+///
+/// Built on the host from the full base and post states; applied in the
+/// guest knowing only the base root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDelta {
+    /// Sparse Merkle root of the base state
+    pub base_root: Hash,
+    /// Updates in application order; each proof is against the root
+    /// left by the previous update
+    pub updates: Vec<AccountUpdate>,
+}
+
+impl StateDelta {
+    /// Build the delta taking `base` to `post`
+    pub fn build(base: &InMemoryDB, post: &InMemoryDB) -> Self {
+        let changed = post.changed_accounts(base);
+        let mut current = base.clone();
+        let mut updates = Vec::with_capacity(changed.len());
+
+        for address in changed {
+            let proof = AccountProof::generate(&current, address);
+            let old_leaf = account_leaf(current.get_account(&address));
+            let new_account = post.get_account(&address).cloned();
+
+            match &new_account {
+                Some(account) => current.insert_account(address, account.clone()),
+                None => {
+                    current.remove_account(&address);
+                }
+            }
+
+            updates.push(AccountUpdate {
+                proof,
+                old_leaf,
+                new_account,
+            });
+        }
+
+        Self {
+            base_root: sparse_state_root(base),
+            updates,
+        }
+    }
+
+    /// Verify every update against the running root and return the new root
+    ///
+    /// Only the changed accounts are hashed. Fails if any proof does not
+    /// match the root it is applied to, i.e. if the delta does not extend
+    /// `base_root`.
+    pub fn apply(&self) -> Result<Hash> {
+        self.updates
+            .iter()
+            .try_fold(self.base_root, |root, update| {
+                // A short path would let one leaf stand in for a whole subtree
+                if update.proof.siblings.len() != DEPTH
+                    || update.proof.root_with_leaf(update.old_leaf) != root
+                {
+                    return Err(ShadowEvmError::InvalidStateProof {
+                        address: update.proof.address,
+                    });
+                }
+                let new_leaf = account_leaf(update.new_account.as_ref());
+                Ok(update.proof.root_with_leaf(new_leaf))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::ShadowExecutor;
+    use crate::input::{BlockEnv, ExecutionInput, TxInput};
+    use crate::types::U256;
+
+    fn state() -> InMemoryDB {
+        let mut db = InMemoryDB::new();
+        for i in 1..=8u8 {
+            db.insert_account(
+                Address::repeat_byte(i),
+                AccountState::new_with_balance(U256::from(10u128.pow(18))),
+            );
+        }
+        db
+    }
+
+    #[test]
+    fn test_proof_reproduces_root() {
+        let db = state();
+        let root = sparse_state_root(&db);
+
+        let tree = SparseTree::new(&db);
+        assert_eq!(tree.root, root);
+        for (present, account) in &db.accounts {
+            let proof = tree.proof(*present);
+            assert_eq!(proof.root_with_leaf(account_leaf(Some(account))), root);
+        }
+
+        for i in 0x40..0x48u8 {
+            let proof = AccountProof::generate(&db, Address::repeat_byte(i));
+            assert_eq!(proof.root_with_leaf(Hash::ZERO), root);
+        }
+    }
+
+    #[test]
+    fn test_delta_applies_next_block() {
+        // Block N
+        let block_n = state();
+        let root_n = sparse_state_root(&block_n);
+
+        // Block N+1: one transfer, changing sender and receiver
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::transfer(
+                Address::repeat_byte(0x01),
+                Address::repeat_byte(0x99),
+                U256::from(1000u64),
+            ),
+            block_n.clone(),
+        );
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+        let block_n1 = output.post_state;
+
+        let delta = StateDelta::build(&block_n, &block_n1);
+        assert_eq!(delta.base_root, root_n);
+        assert!(delta.updates.len() < block_n1.account_count());
+
+        assert_eq!(delta.apply().unwrap(), sparse_state_root(&block_n1));
+    }
+
+    #[test]
+    fn test_delta_handles_removed_account() {
+        let base = state();
+        let mut post = base.clone();
+        post.remove_account(&Address::repeat_byte(0x05));
+
+        let delta = StateDelta::build(&base, &post);
+        assert_eq!(delta.updates.len(), 1);
+        assert_eq!(delta.apply().unwrap(), sparse_state_root(&post));
+    }
+
+    #[test]
+    fn test_delta_rejects_wrong_base() {
+        let base = state();
+        let mut post = base.clone();
+        post.get_account_mut(&Address::repeat_byte(0x02))
+            .unwrap()
+            .balance = U256::ZERO;
+
+        let delta = StateDelta::build(&base, &post);
+
+        let mut wrong_base = delta.clone();
+        wrong_base.base_root = Hash::repeat_byte(0xff);
+        assert!(matches!(
+            wrong_base.apply(),
+            Err(ShadowEvmError::InvalidStateProof { .. })
+        ));
+
+        let mut truncated = delta;
+        truncated.updates[0].proof.siblings.truncate(1);
+        assert!(truncated.apply().is_err());
+    }
+
+    #[test]
+    fn test_chunks_reassemble() {
+        let mut db = state();
+        db.insert_block_hash(7, Hash::repeat_byte(0x07));
+
+        let chunks = db.chunk(3);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|chunk| chunk.state.account_count() <= 3));
+        for chunk in &chunks {
+            chunk.verify().unwrap();
+        }
+
+        let reassembled = InMemoryDB::from_chunks(&chunks).unwrap();
+        assert_eq!(reassembled.accounts, db.accounts);
+        assert_eq!(reassembled.block_hashes, db.block_hashes);
+        assert_eq!(reassembled.compute_state_root(), db.compute_state_root());
+        assert_eq!(sparse_state_root(&reassembled), chunks[0].root);
+
+        // A missing chunk no longer hashes to the root
+        assert!(matches!(
+            InMemoryDB::from_chunks(&chunks[..2]),
+            Err(ShadowEvmError::CommitmentMismatch { .. })
+        ));
+
+        // A tampered account fails its proof
+        let mut tampered = chunks[1].clone();
+        let address = *tampered.state.accounts.keys().next().unwrap();
+        tampered.state.get_account_mut(&address).unwrap().balance = U256::ZERO;
+        assert!(matches!(
+            tampered.verify(),
+            Err(ShadowEvmError::InvalidStateProof { address: a }) if a == address
+        ));
+    }
+}
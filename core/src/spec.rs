@@ -0,0 +1,81 @@
+//! Hardfork selection for Shadow-EVM
+//!
+//! Which set of EVM rules (gas schedule, available opcodes, precompiles)
+//! an execution runs under. Part of the block environment, and therefore
+//! part of the committed input, so a proof always states its rules.
+
+use revm::primitives::SpecId;
+use serde::{Deserialize, Serialize};
+
+/// Ethereum hardfork an execution runs under
+///
+/// Ordered chronologically, so `a < b` means `a` activated before `b`.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Hardfork {
+    /// Istanbul (December 2019)
+    Istanbul,
+    /// Berlin (April 2021): access lists, cold/warm access costs
+    Berlin,
+    /// London (August 2021): EIP-1559 base fee
+    London,
+    /// The Merge (September 2022): PREVRANDAO replaces DIFFICULTY
+    Merge,
+    /// Shanghai (April 2023): PUSH0
+    Shanghai,
+    /// Cancun (March 2024): transient storage, MCOPY, blob transactions
+    #[default]
+    Cancun,
+    /// Prague (May 2025): EIP-7702 set-code transactions
+    Prague,
+}
+
+impl Hardfork {
+    /// The revm spec implementing this hardfork
+    pub fn spec_id(self) -> SpecId {
+        match self {
+            Hardfork::Istanbul => SpecId::ISTANBUL,
+            Hardfork::Berlin => SpecId::BERLIN,
+            Hardfork::London => SpecId::LONDON,
+            Hardfork::Merge => SpecId::MERGE,
+            Hardfork::Shanghai => SpecId::SHANGHAI,
+            Hardfork::Cancun => SpecId::CANCUN,
+            Hardfork::Prague => SpecId::PRAGUE,
+        }
+    }
+}
+
+impl From<Hardfork> for SpecId {
+    fn from(hardfork: Hardfork) -> Self {
+        hardfork.spec_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_cancun() {
+        assert_eq!(Hardfork::default(), Hardfork::Cancun);
+        assert_eq!(SpecId::from(Hardfork::default()), SpecId::CANCUN);
+    }
+
+    #[test]
+    fn test_order_matches_spec_order() {
+        let forks = [
+            Hardfork::Istanbul,
+            Hardfork::Berlin,
+            Hardfork::London,
+            Hardfork::Merge,
+            Hardfork::Shanghai,
+            Hardfork::Cancun,
+            Hardfork::Prague,
+        ];
+        for pair in forks.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].spec_id() < pair[1].spec_id());
+        }
+    }
+}
@@ -1,16 +1,30 @@
 //! In-memory state database for Shadow-EVM
 //!
 //! Implements `revm::Database` for EVM state access during execution.
-//! Uses a simplified HashMap-based model (not full Merkle Patricia Trie).
+//! Accounts and storage live in `BTreeMap`s, so iteration is deterministic;
+//! the state root is computed from them on demand in the chosen
+//! [`StateRootMode`], including an Ethereum-compatible MPT root.
 
 use crate::errors::{Result, ShadowEvmError};
-use crate::hashing::keccak256;
+use crate::hashing::{keccak256, Hasher, StateHashAlgo};
+use crate::smt::StateChunk;
+use crate::trie::{
+    encode_account, rlp, secure_trie_proof, secure_trie_root, SlotProof, StorageProof,
+};
 use crate::types::{Address, Hash, U256};
-use alloc::collections::BTreeMap;
+use alloc::collections::btree_map::Entry;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
 use alloc::vec::Vec;
+use alloy_primitives::U64;
+use core::fmt;
+use core::str::FromStr;
 use revm::primitives::{AccountInfo, Bytecode, KECCAK_EMPTY};
 use revm::Database;
-use serde::{Deserialize, Serialize};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use sha3::{Digest, Keccak256};
 
 extern crate alloc;
 
@@ -21,7 +35,8 @@ extern crate alloc;
 /// - Transaction nonce
 /// - Contract code and its hash
 /// - Storage slots
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct AccountState {
     /// Account balance in wei
     pub balance: U256,
@@ -98,20 +113,179 @@ impl AccountState {
     pub fn get_storage(&self, slot: &U256) -> U256 {
         self.storage.get(slot).copied().unwrap_or(U256::ZERO)
     }
+
+    /// Non-zero storage slots in the given order
+    ///
+    /// See [`StateRootMode::slot_order`] for the order each root uses.
+    pub fn storage_slots(&self, order: SlotOrder) -> Vec<(U256, U256)> {
+        let mut slots: Vec<(U256, U256)> = self
+            .storage
+            .iter()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(slot, value)| (*slot, *value))
+            .collect();
+        if order == SlotOrder::Hashed {
+            slots.sort_by_cached_key(|(slot, _)| keccak256(&slot.to_be_bytes::<32>()));
+        }
+        slots
+    }
+
+    /// Compute the Ethereum storage root of this account
+    ///
+    /// Slots are keyed by `keccak256(slot)` in the trie, so the trie order
+    /// is [`SlotOrder::Hashed`], not the numeric order of the `storage` map.
+    pub fn compute_storage_root(&self) -> Hash {
+        secure_trie_root(&self.storage_trie_entries())
+    }
+
+    /// Storage trie entries: `keccak256(slot)` to the RLP-encoded value
+    fn storage_trie_entries(&self) -> BTreeMap<Hash, Vec<u8>> {
+        self.storage
+            .iter()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(slot, value)| {
+                (
+                    keccak256(&slot.to_be_bytes::<32>()),
+                    rlp::encode_u256(*value),
+                )
+            })
+            .collect()
+    }
+
+    /// RLP encoding of the account as stored in the state trie
+    ///
+    /// `[nonce, balance, storage_root, code_hash]`
+    pub fn rlp_encode(&self) -> Vec<u8> {
+        encode_account(
+            self.nonce,
+            self.balance,
+            self.compute_storage_root(),
+            self.trie_code_hash(),
+        )
+    }
+
+    /// Code hash as stored in the state trie
+    fn trie_code_hash(&self) -> Hash {
+        // Accounts built via `Default` carry a zero code hash; treat them as EOAs
+        if self.code_hash.is_zero() && self.code.is_empty() {
+            KECCAK_EMPTY
+        } else {
+            self.code_hash
+        }
+    }
+}
+
+/// Order in which storage slots are visited
+///
+/// `AccountState::storage` is keyed by slot number, so iterating it yields
+/// `Numeric` order. The Ethereum storage trie is keyed by the slot's hash
+/// and so walks slots in `Hashed` order. Two roots agree only if they are
+/// computed in the same order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SlotOrder {
+    /// Ascending slot number
+    Numeric,
+    /// Ascending `keccak256(slot)`
+    Hashed,
+}
+
+/// How `InMemoryDB` state is reduced to a single root hash
+///
+/// Accounts and slots are visited in a fixed order in every mode:
+///
+/// | Mode           | Accounts                   | Slots                    |
+/// |----------------|----------------------------|--------------------------|
+/// | `Nested`       | by address                 | by slot number           |
+/// | `Mpt`          | by `keccak256(address)`    | by `keccak256(slot)`     |
+/// | `FlatList`     | by address                 | by slot number           |
+/// | `SparseMerkle` | by `keccak256(address)`    | by slot number, per leaf |
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StateRootMode {
+    /// Hash of the serialized account map, storage nested per account
+    #[default]
+    Nested,
+    /// Ethereum-compatible Merkle Patricia Trie root
+    Mpt,
+    /// One hash over a flat, sorted list of accounts and storage slots
+    ///
+    /// Cheaper than the nested and MPT modes. NOT Ethereum-compatible.
+    FlatList,
+    /// Sparse Merkle tree over accounts, updatable from a small witness
+    ///
+    /// See [`crate::smt`]. NOT Ethereum-compatible.
+    SparseMerkle,
+}
+
+impl StateRootMode {
+    /// Every mode, in declaration order
+    pub const ALL: [StateRootMode; 4] = [
+        StateRootMode::Nested,
+        StateRootMode::Mpt,
+        StateRootMode::FlatList,
+        StateRootMode::SparseMerkle,
+    ];
+
+    /// Order in which this mode visits each account's storage slots
+    pub fn slot_order(&self) -> SlotOrder {
+        match self {
+            StateRootMode::Mpt => SlotOrder::Hashed,
+            StateRootMode::Nested | StateRootMode::FlatList | StateRootMode::SparseMerkle => {
+                SlotOrder::Numeric
+            }
+        }
+    }
+
+    /// Name of the mode, as used in error messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            StateRootMode::Nested => "nested",
+            StateRootMode::Mpt => "mpt",
+            StateRootMode::FlatList => "flat-list",
+            StateRootMode::SparseMerkle => "sparse-merkle",
+        }
+    }
 }
 
 /// In-memory state database
 ///
 /// Implements `revm::Database` for EVM execution.
 /// Uses BTreeMap for deterministic iteration order (important for ZK proofs).
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct InMemoryDB {
     /// Account states indexed by address
+    ///
+    /// When deserialized, addresses are parsed to bytes regardless of case
+    /// or surrounding whitespace; see [`deserialize_accounts`].
+    #[serde(deserialize_with = "deserialize_accounts")]
     pub accounts: BTreeMap<Address, AccountState>,
     /// Block hashes for BLOCKHASH opcode (block number -> hash)
     pub block_hashes: BTreeMap<u64, Hash>,
+    /// Account maps saved by `snapshot`, oldest first
+    ///
+    /// Not serialized, so not part of any hash or commitment, and not
+    /// cloned either: every copy of the state starts without snapshots.
+    #[serde(skip)]
+    snapshots: Vec<BTreeMap<Address, AccountState>>,
 }
 
+impl Clone for InMemoryDB {
+    /// Copy the accounts and block hashes, but not the snapshots
+    ///
+    /// Executions clone their state freely, and carrying every saved account
+    /// map along would multiply the cost of each copy.
+    fn clone(&self) -> Self {
+        Self {
+            accounts: self.accounts.clone(),
+            block_hashes: self.block_hashes.clone(),
+            snapshots: Vec::new(),
+        }
+    }
+}
+
+/// Handle of a state snapshot, returned by [`InMemoryDB::snapshot`]
+pub type SnapshotId = u64;
+
 impl InMemoryDB {
     /// Create a new empty database
     pub fn new() -> Self {
@@ -150,15 +324,256 @@ impl InMemoryDB {
 
     /// Compute state root commitment (simplified - not MPT)
     ///
-    /// For v0.1, we use a deterministic hash of all accounts.
+    /// A deterministic hash of all accounts, used as the
+    /// [`StateRootMode::Nested`] root and in every `ExecutionCommitment`.
     /// This is NOT Ethereum-compatible but is sufficient for ZK proofs.
     ///
-    /// Future versions will implement full Merkle Patricia Trie.
+    /// For a root matching an Ethereum block header, use
+    /// [`compute_state_root_mpt`](Self::compute_state_root_mpt).
     pub fn compute_state_root(&self) -> Hash {
         use crate::hashing::hash_struct;
         hash_struct(&self.accounts)
     }
 
+    /// Compute the state root with the given hash function
+    ///
+    /// Same construction as `compute_state_root`, which it equals for
+    /// `StateHashAlgo::Keccak`.
+    pub fn compute_state_root_with_algo(&self, algo: StateHashAlgo) -> Hash {
+        algo.hash_struct(&self.accounts)
+    }
+
+    /// Compute the Ethereum-compatible Merkle Patricia Trie state root
+    ///
+    /// Builds a secure trie keyed by `keccak256(address)` over the RLP-encoded
+    /// accounts, matching the `stateRoot` of an Ethereum block header for the
+    /// same account set.
+    pub fn compute_state_root_mpt(&self) -> Hash {
+        secure_trie_root(&self.account_trie_entries())
+    }
+
+    /// Build the proof of one storage slot against the MPT state root
+    ///
+    /// The proof has the shape of an `eth_getProof` response for `address`
+    /// and `[slot]`, and is checked with
+    /// [`verify_storage_proof`](crate::trie::verify_storage_proof). An unset
+    /// slot yields a proof of absence with a zero value, and a missing
+    /// account a proof of absence with the fields of an empty account.
+    pub fn storage_proof(&self, address: Address, slot: U256) -> StorageProof {
+        let empty = AccountState::new_with_balance(U256::ZERO);
+        let account = self.accounts.get(&address).unwrap_or(&empty);
+        let storage = account.storage_trie_entries();
+
+        let account_proof =
+            secure_trie_proof(&self.account_trie_entries(), &keccak256(address.as_slice()));
+        let slot_proof = secure_trie_proof(&storage, &keccak256(&slot.to_be_bytes::<32>()));
+
+        StorageProof {
+            address,
+            balance: account.balance,
+            nonce: U64::from(account.nonce),
+            code_hash: account.trie_code_hash(),
+            storage_hash: secure_trie_root(&storage),
+            account_proof: account_proof.into_iter().map(Into::into).collect(),
+            storage_proof: alloc::vec![SlotProof {
+                key: slot,
+                value: account.get_storage(&slot),
+                proof: slot_proof.into_iter().map(Into::into).collect(),
+            }],
+        }
+    }
+
+    /// Account trie entries: `keccak256(address)` to the RLP-encoded account
+    fn account_trie_entries(&self) -> BTreeMap<Hash, Vec<u8>> {
+        self.accounts
+            .iter()
+            .map(|(address, account)| (keccak256(address.as_slice()), account.rlp_encode()))
+            .collect()
+    }
+
+    /// Compute the flat-list state root
+    ///
+    /// Hashes once over all accounts followed by all non-zero storage slots,
+    /// both sorted by address (and slot):
+    ///
+    /// ```text
+    /// keccak256(
+    ///     (len || address || nonce || balance || code_hash)*  ||
+    ///     (len || address || slot || value)*
+    /// )
+    /// ```
+    ///
+    /// `len` is the byte length of the entry that follows, as a 4-byte
+    /// big-endian integer, so the hashed bytes split into entries only one
+    /// way. Deterministic and cheaper than the other modes, but NOT
+    /// Ethereum-compatible.
+    pub fn compute_state_root_flat(&self) -> Hash {
+        let mut hasher = Keccak256::new();
+
+        for (address, account) in &self.accounts {
+            flat_entry(
+                &mut hasher,
+                &[
+                    address.as_slice(),
+                    &account.nonce.to_be_bytes(),
+                    &account.balance.to_be_bytes::<32>(),
+                    account.code_hash.as_slice(),
+                ],
+            );
+        }
+
+        for (address, account) in &self.accounts {
+            for (slot, value) in account.storage_slots(SlotOrder::Numeric) {
+                flat_entry(
+                    &mut hasher,
+                    &[
+                        address.as_slice(),
+                        &slot.to_be_bytes::<32>(),
+                        &value.to_be_bytes::<32>(),
+                    ],
+                );
+            }
+        }
+
+        Hash::from_slice(&hasher.finalize())
+    }
+
+    /// Compute the state root in the given mode
+    pub fn compute_state_root_with(&self, mode: StateRootMode) -> Hash {
+        match mode {
+            StateRootMode::Nested => self.compute_state_root(),
+            StateRootMode::Mpt => self.compute_state_root_mpt(),
+            StateRootMode::FlatList => self.compute_state_root_flat(),
+            StateRootMode::SparseMerkle => crate::smt::sparse_state_root(self),
+        }
+    }
+
+    /// Check that every referenced code hash resolves to actual code
+    ///
+    /// An account may carry a `code_hash` with empty `code` if another account
+    /// holds the matching bytecode. If no account does, `code_by_hash` would
+    /// silently hand revm empty code, so this is reported as `MissingCode`.
+    pub fn validate_code_hashes(&self) -> Result<()> {
+        for account in self.accounts.values() {
+            let hash = account.code_hash;
+            if !account.code.is_empty() || hash == KECCAK_EMPTY || hash.is_zero() {
+                continue;
+            }
+            let resolved = self
+                .accounts
+                .values()
+                .any(|other| other.code_hash == hash && !other.code.is_empty());
+            if !resolved {
+                return Err(ShadowEvmError::MissingCode { code_hash: hash });
+            }
+        }
+        Ok(())
+    }
+
+    /// Addresses whose account differs between `before` and this database
+    ///
+    /// Covers accounts that were created, removed, or changed in any field
+    /// (balance, nonce, code or storage). Returned in address order.
+    pub fn changed_accounts(&self, before: &InMemoryDB) -> Vec<Address> {
+        let mut changed: Vec<Address> = self
+            .accounts
+            .iter()
+            .filter(|(address, account)| before.accounts.get(*address) != Some(*account))
+            .map(|(address, _)| *address)
+            .collect();
+        changed.extend(
+            before
+                .accounts
+                .keys()
+                .filter(|address| !self.accounts.contains_key(*address)),
+        );
+        changed.sort();
+        changed
+    }
+
+    /// Changes taking this database to `after`
+    ///
+    /// Only accounts listed by `after.changed_accounts(self)` appear in the
+    /// diff; see [`StateDiff`].
+    pub fn diff(&self, after: &InMemoryDB) -> StateDiff {
+        let mut diff = StateDiff::default();
+        for address in after.changed_accounts(self) {
+            match (self.get_account(&address), after.get_account(&address)) {
+                (None, Some(account)) => {
+                    diff.created.insert(address, account.clone());
+                }
+                (Some(_), None) => {
+                    diff.deleted.insert(address);
+                }
+                (Some(before), Some(account)) => {
+                    diff.modified
+                        .insert(address, AccountDiff::between(before, account));
+                }
+                (None, None) => {}
+            }
+        }
+        diff
+    }
+
+    /// Save the current accounts to return to them later
+    ///
+    /// Snapshots nest: reverting to or committing a snapshot also discards
+    /// every snapshot taken after it. Each snapshot is a full copy of the
+    /// accounts; block hashes are not included. Snapshots stay with this
+    /// database and are not carried over by `clone`.
+    pub fn snapshot(&mut self) -> SnapshotId {
+        self.snapshots.push(self.accounts.clone());
+        (self.snapshots.len() - 1) as SnapshotId
+    }
+
+    /// Restore the accounts saved by snapshot `id`
+    ///
+    /// The snapshot is consumed, as are all later ones.
+    ///
+    /// # Errors
+    /// Returns `ShadowEvmError::UnknownSnapshot` if `id` was never taken or
+    /// has already been reverted to or committed.
+    pub fn revert_to_snapshot(&mut self, id: SnapshotId) -> Result<()> {
+        let index = usize::try_from(id)
+            .ok()
+            .filter(|index| *index < self.snapshots.len())
+            .ok_or(ShadowEvmError::UnknownSnapshot(id))?;
+        self.accounts = self.snapshots.swap_remove(index);
+        self.snapshots.truncate(index);
+        Ok(())
+    }
+
+    /// Keep the current accounts and discard snapshot `id` and later ones
+    ///
+    /// Does nothing for an unknown `id`.
+    pub fn commit_snapshot(&mut self, id: SnapshotId) {
+        if let Ok(index) = usize::try_from(id) {
+            self.snapshots.truncate(index);
+        }
+    }
+
+    /// Number of snapshots that can still be reverted to
+    pub fn snapshot_count(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Split into chunks of at most `max_accounts_per_chunk` accounts
+    ///
+    /// Each chunk carries sparse Merkle proofs linking its accounts to the
+    /// root of the whole state; see [`StateChunk`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_accounts_per_chunk` is zero.
+    pub fn chunk(&self, max_accounts_per_chunk: usize) -> Vec<StateChunk> {
+        StateChunk::split(self, max_accounts_per_chunk)
+    }
+
+    /// Verify chunks made by `chunk` and merge them back into one state
+    pub fn from_chunks(chunks: &[StateChunk]) -> Result<Self> {
+        StateChunk::reassemble(chunks)
+    }
+
     /// Get the number of accounts in the database
     pub fn account_count(&self) -> usize {
         self.accounts.len()
@@ -180,6 +595,170 @@ impl InMemoryDB {
     }
 }
 
+/// Account-level changes between two states
+///
+/// Built by [`InMemoryDB::diff`]. Much smaller than a full post-state when
+/// an execution touches a few accounts of a large state.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    /// Accounts that did not exist before, as they are after
+    pub created: BTreeMap<Address, AccountState>,
+    /// Accounts that exist on both sides but differ
+    pub modified: BTreeMap<Address, AccountDiff>,
+    /// Accounts that existed before and no longer do
+    pub deleted: BTreeSet<Address>,
+}
+
+impl StateDiff {
+    /// Whether the two states were identical
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.modified.is_empty() && self.deleted.is_empty()
+    }
+
+    /// Replay the changes onto `db`
+    ///
+    /// Applied to the state the diff was taken from, this yields the state
+    /// it was taken to. The `*_before` values are not checked against `db`.
+    pub fn apply_to(&self, db: &mut InMemoryDB) {
+        for address in &self.deleted {
+            db.remove_account(address);
+        }
+        for (address, account) in &self.created {
+            db.insert_account(*address, account.clone());
+        }
+        for (address, diff) in &self.modified {
+            let account = db.accounts.entry(*address).or_default();
+            account.balance = diff.balance_after;
+            account.nonce = diff.nonce_after;
+            if let Some(code) = &diff.code_after {
+                account.code_hash = if code.is_empty() {
+                    KECCAK_EMPTY
+                } else {
+                    keccak256(code)
+                };
+                account.code = code.clone();
+            }
+            for (slot, (_, value)) in &diff.storage_changes {
+                account.set_storage(*slot, *value);
+            }
+        }
+    }
+}
+
+/// Changes to one account that exists before and after
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountDiff {
+    /// Balance before
+    pub balance_before: U256,
+    /// Balance after
+    pub balance_after: U256,
+    /// Nonce before
+    pub nonce_before: u64,
+    /// Nonce after
+    pub nonce_after: u64,
+    /// New code, if the code changed
+    pub code_after: Option<Vec<u8>>,
+    /// Changed storage slots: slot to `(before, after)`, zero if unset
+    pub storage_changes: BTreeMap<U256, (U256, U256)>,
+}
+
+impl AccountDiff {
+    /// Changes taking `before` to `after`
+    pub fn between(before: &AccountState, after: &AccountState) -> Self {
+        let slots: BTreeSet<&U256> = before.storage.keys().chain(after.storage.keys()).collect();
+        let storage_changes = slots
+            .into_iter()
+            .map(|slot| (*slot, (before.get_storage(slot), after.get_storage(slot))))
+            .filter(|(_, (old, new))| old != new)
+            .collect();
+
+        Self {
+            balance_before: before.balance,
+            balance_after: after.balance,
+            nonce_before: before.nonce,
+            nonce_after: after.nonce,
+            code_after: (before.code != after.code).then(|| after.code.clone()),
+            storage_changes,
+        }
+    }
+
+    /// Whether the code changed
+    pub fn code_changed(&self) -> bool {
+        self.code_after.is_some()
+    }
+}
+
+/// Deserialize an account map, merging spellings of the same address
+///
+/// JSON sources may write one address checksummed in one place and
+/// lowercase in another. Both parse to the same bytes; identical entries
+/// merge into one account, while entries with conflicting data fail instead
+/// of the last one silently winning.
+fn deserialize_accounts<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> core::result::Result<BTreeMap<Address, AccountState>, D::Error> {
+    struct AccountsVisitor {
+        human_readable: bool,
+    }
+
+    impl<'de> Visitor<'de> for AccountsVisitor {
+        type Value = BTreeMap<Address, AccountState>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a map from addresses to accounts")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> core::result::Result<Self::Value, A::Error> {
+            let mut accounts = BTreeMap::new();
+            loop {
+                let address = if self.human_readable {
+                    let Some(key) = map.next_key::<String>()? else {
+                        break;
+                    };
+                    Address::from_str(key.trim()).map_err(|e| {
+                        de::Error::custom(format!("invalid address {:?}: {}", key, e))
+                    })?
+                } else {
+                    let Some(address) = map.next_key::<Address>()? else {
+                        break;
+                    };
+                    address
+                };
+                let account: AccountState = map.next_value()?;
+
+                match accounts.entry(address) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(account);
+                    }
+                    Entry::Occupied(entry) if *entry.get() == account => {}
+                    Entry::Occupied(_) => {
+                        return Err(de::Error::custom(format!(
+                            "duplicate account {} with conflicting data",
+                            address
+                        )));
+                    }
+                }
+            }
+            Ok(accounts)
+        }
+    }
+
+    let human_readable = deserializer.is_human_readable();
+    deserializer.deserialize_map(AccountsVisitor { human_readable })
+}
+
+/// Feed one length-prefixed entry of the flat-list root to `hasher`
+fn flat_entry(hasher: &mut Keccak256, parts: &[&[u8]]) {
+    let len: usize = parts.iter().map(|part| part.len()).sum();
+    hasher.update((len as u32).to_be_bytes());
+    for part in parts {
+        hasher.update(part);
+    }
+}
+
 impl Database for InMemoryDB {
     type Error = ShadowEvmError;
 
@@ -315,6 +894,87 @@ mod tests {
         assert_eq!(db1.compute_state_root(), db2.compute_state_root());
     }
 
+    #[test]
+    fn test_state_root_hash_algos() {
+        let mut db = InMemoryDB::new();
+        db.insert_account(
+            Address::repeat_byte(0x01),
+            AccountState::new_with_balance(U256::from(1000u64)),
+        );
+
+        let root = |algo| {
+            let first = db.compute_state_root_with_algo(algo);
+            assert_eq!(first, db.compute_state_root_with_algo(algo));
+            first
+        };
+        let keccak = root(StateHashAlgo::Keccak);
+        let sha256 = root(StateHashAlgo::Sha256);
+        let blake3 = root(StateHashAlgo::Blake3);
+
+        assert_eq!(keccak, db.compute_state_root());
+        assert_ne!(keccak, sha256);
+        assert_ne!(keccak, blake3);
+        assert_ne!(sha256, blake3);
+    }
+
+    #[test]
+    fn test_flat_list_root() {
+        let build = || {
+            let mut db = InMemoryDB::new();
+            db.insert_account(
+                Address::repeat_byte(0x01),
+                AccountState::new_with_balance(U256::from(1000u64)),
+            );
+            let mut contract = AccountState::new_contract(vec![0x00], U256::ZERO);
+            contract.set_storage(U256::from(1u64), U256::from(42u64));
+            contract.set_storage(U256::from(2u64), U256::from(7u64));
+            db.insert_account(Address::repeat_byte(0x02), contract);
+            db
+        };
+
+        let db1 = build();
+        let db2 = build();
+        let flat = db1.compute_state_root_with(StateRootMode::FlatList);
+
+        assert_eq!(flat, db2.compute_state_root_with(StateRootMode::FlatList));
+        assert_ne!(flat, db1.compute_state_root_with(StateRootMode::Nested));
+
+        // Zero-valued slots are not committed
+        let mut db3 = build();
+        db3.get_account_mut(&Address::repeat_byte(0x02))
+            .unwrap()
+            .storage
+            .insert(U256::from(3u64), U256::ZERO);
+        assert_eq!(flat, db3.compute_state_root_flat());
+    }
+
+    #[test]
+    fn test_validate_code_hashes() {
+        let code = vec![0x60, 0x00, 0x60, 0x00, 0xf3];
+        let contract = AccountState::new_contract(code, U256::ZERO);
+
+        // Code-less account pointing at code held by another account is fine
+        let mut proxy = AccountState::new_with_balance(U256::ZERO);
+        proxy.code_hash = contract.code_hash;
+
+        let mut db = InMemoryDB::new();
+        db.insert_account(Address::repeat_byte(0x01), contract);
+        db.insert_account(Address::repeat_byte(0x02), proxy);
+        db.insert_account(Address::repeat_byte(0x03), AccountState::default());
+        assert!(db.validate_code_hashes().is_ok());
+
+        // Dangling code hash is rejected
+        let mut broken = AccountState::new_with_balance(U256::ZERO);
+        broken.code_hash = Hash::repeat_byte(0xab);
+        db.insert_account(Address::repeat_byte(0x04), broken);
+
+        let err = db.validate_code_hashes().unwrap_err();
+        assert!(matches!(
+            err,
+            ShadowEvmError::MissingCode { code_hash } if code_hash == Hash::repeat_byte(0xab)
+        ));
+    }
+
     #[test]
     fn test_block_hash() {
         let mut db = InMemoryDB::new();
@@ -325,4 +985,363 @@ mod tests {
         assert_eq!(db.block_hash(100).unwrap(), hash);
         assert_eq!(db.block_hash(101).unwrap(), Hash::ZERO);
     }
+
+    #[test]
+    fn test_mpt_root_uses_hashed_address_order() {
+        let low = Address::repeat_byte(0x01);
+        let high = Address::repeat_byte(0x02);
+
+        // Raw byte order and secure-trie (hashed) order disagree for this pair
+        assert!(low < high);
+        assert!(keccak256(low.as_slice()) > keccak256(high.as_slice()));
+
+        let mut db = InMemoryDB::new();
+        db.insert_account(
+            low,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let mut other = AccountState::new_with_balance(U256::from(5u64));
+        other.nonce = 3;
+        db.insert_account(high, other);
+
+        // Canonical Ethereum root for this account set
+        assert_eq!(
+            hex::encode(db.compute_state_root_mpt()),
+            "b2f9db5384a5d38b307abb96e9cdd56d6334706268f744af4bcdfd67e02d066a"
+        );
+    }
+
+    #[test]
+    fn test_storage_root_uses_hashed_slot_order() {
+        let mut account = AccountState::new_contract(vec![0x60, 0x00], U256::ZERO);
+        account.set_storage(U256::from(1u64), U256::from(42u64));
+        account.set_storage(U256::from(2u64), U256::from(7u64));
+        account.set_storage(U256::from(256u64), U256::from(1u64));
+
+        let mut db = InMemoryDB::new();
+        db.insert_account(Address::repeat_byte(0xee), account.clone());
+        assert_eq!(
+            hex::encode(db.compute_state_root_mpt()),
+            "4caeb677ce8df84ddf34c070c7268affaccdda59178080a38bed9379ecc8040d"
+        );
+
+        // Zero-valued slots are not part of the trie
+        account.storage.insert(U256::from(9u64), U256::ZERO);
+        assert_eq!(
+            account.compute_storage_root(),
+            db.get_account(&Address::repeat_byte(0xee))
+                .unwrap()
+                .compute_storage_root()
+        );
+        assert_eq!(
+            AccountState::default().compute_storage_root(),
+            crate::trie::EMPTY_ROOT
+        );
+    }
+
+    #[test]
+    fn test_storage_proof() {
+        use crate::trie::verify_storage_proof;
+
+        let token = Address::repeat_byte(0xee);
+        let mut account = AccountState::new_contract(vec![0x60, 0x00], U256::ZERO);
+        for slot in 0..5u64 {
+            account.set_storage(U256::from(slot), U256::from(100 + slot));
+        }
+        let mut db = InMemoryDB::new();
+        db.insert_account(token, account);
+        db.insert_account(
+            Address::repeat_byte(0x01),
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let root = db.compute_state_root_mpt();
+
+        for slot in 0..5u64 {
+            let slot = U256::from(slot);
+            let proof = db.storage_proof(token, slot);
+            assert_eq!(proof.storage_proof[0].value, slot + U256::from(100u64));
+            verify_storage_proof(root, token, slot, &proof).unwrap();
+        }
+
+        // An unset slot is proven absent, with a zero value
+        let absent = U256::from(42u64);
+        let proof = db.storage_proof(token, absent);
+        assert_eq!(proof.storage_proof[0].value, U256::ZERO);
+        verify_storage_proof(root, token, absent, &proof).unwrap();
+
+        // Forged values and account fields, and proofs for other slots, are
+        // rejected
+        let mut forged = db.storage_proof(token, U256::from(1u64));
+        forged.storage_proof[0].value = U256::from(7u64);
+        assert!(verify_storage_proof(root, token, U256::from(1u64), &forged).is_err());
+        assert!(verify_storage_proof(root, token, absent, &forged).is_err());
+        let mut forged = db.storage_proof(token, U256::from(1u64));
+        forged.balance = U256::from(1u64);
+        assert!(verify_storage_proof(root, token, U256::from(1u64), &forged).is_err());
+
+        // A missing account is proven absent, as an empty account
+        let missing = Address::repeat_byte(0x99);
+        let proof = db.storage_proof(missing, absent);
+        assert_eq!(proof.code_hash, KECCAK_EMPTY);
+        assert_eq!(proof.storage_hash, crate::trie::EMPTY_ROOT);
+        verify_storage_proof(root, missing, absent, &proof).unwrap();
+        let mut forged = proof.clone();
+        forged.balance = U256::from(1u64);
+        assert!(verify_storage_proof(root, missing, absent, &forged).is_err());
+
+        let json = serde_json::to_string(&proof).unwrap();
+        assert!(json.contains("\"storageHash\""));
+        assert_eq!(serde_json::from_str::<StorageProof>(&json).unwrap(), proof);
+    }
+
+    /// `eth_getProof` responses for the state below, generated with the
+    /// independent `alloy-trie` implementation
+    const ETH_GET_PROOF_FIXTURE: &str = r#"[
+        {
+            "address": "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee",
+            "balance": "0x5",
+            "codeHash": "0x07ad118d6cc8642c86c03827f276d8b791a65e5c99a3845faf186be720a1455d",
+            "nonce": "0x1",
+            "storageHash": "0x974ae40af23bf0377dc0864de1f2d286f6694aa07cda0318127873fe5be20139",
+            "accountProof": [
+                "0xf851808080808080808080a0a5c923995d289ddbb3f14c4daf5c6034744d1ae6eafe372667c0e10cb776169780808080a09cde0a906d9c79b70c4599ede2cc5e49bee80d5eb97f0a5c638e72b0ae09b1ec8080",
+                "0xf869a03f74bd52020a869dbd6c5918e246e54fe47bed2b9e96439c406e5c0732d089bfb846f8440105a0974ae40af23bf0377dc0864de1f2d286f6694aa07cda0318127873fe5be20139a007ad118d6cc8642c86c03827f276d8b791a65e5c99a3845faf186be720a1455d"
+            ],
+            "storageProof": [
+                {
+                    "key": "0x2",
+                    "value": "0x66",
+                    "proof": [
+                        "0xf8918080a0cdc63838757ca3aabb8e86ace6e954c12b51c090ac3ec8b383fc1894bb2765fa80a09eb19b09f0e1b171b79401731ae17eaa5975763c5e6c570165bed3c4886513b1808080808080a0573ddaf95a0465ad5da7c733470cef9823ac0b1f2190ab06d71ccff78948c50ea0a677d87d9c86e16cb36e786cc1ebba8bbf84bc6c1bdfc8b9a7ecc92961810c7180808080",
+                        "0xe2a0305787fa12a823e0f2b7631cc41b3ba8828b3321ca811111fa75cd3aa3bb5ace66"
+                    ]
+                }
+            ]
+        },
+        {
+            "address": "0x9999999999999999999999999999999999999999",
+            "balance": "0x0",
+            "codeHash": "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            "nonce": "0x0",
+            "storageHash": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "accountProof": [
+                "0xf851808080808080808080a0a5c923995d289ddbb3f14c4daf5c6034744d1ae6eafe372667c0e10cb776169780808080a09cde0a906d9c79b70c4599ede2cc5e49bee80d5eb97f0a5c638e72b0ae09b1ec8080"
+            ],
+            "storageProof": [{ "key": "0x0", "value": "0x0", "proof": [] }]
+        }
+    ]"#;
+
+    #[test]
+    fn test_storage_proof_matches_eth_get_proof() {
+        use crate::trie::verify_storage_proof;
+
+        let token = Address::repeat_byte(0xee);
+        let mut account = AccountState::new_contract(vec![0x60, 0x00], U256::from(5u64));
+        for slot in 0..4u64 {
+            account.set_storage(U256::from(slot), U256::from(100 + slot));
+        }
+        let mut sender = AccountState::new_with_balance(U256::from(10u128.pow(18)));
+        sender.nonce = 3;
+        let mut db = InMemoryDB::new();
+        db.insert_account(token, account);
+        db.insert_account(Address::repeat_byte(0x01), sender);
+
+        let root = db.compute_state_root_mpt();
+        let expected: Vec<StorageProof> = serde_json::from_str(ETH_GET_PROOF_FIXTURE).unwrap();
+        let queries = [
+            (token, U256::from(2u64)),
+            (Address::repeat_byte(0x99), U256::ZERO),
+        ];
+        for ((address, slot), expected) in queries.into_iter().zip(expected) {
+            assert_eq!(db.storage_proof(address, slot), expected);
+            verify_storage_proof(root, address, slot, &expected).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_state_diff() {
+        let kept = Address::repeat_byte(0x01);
+        let removed = Address::repeat_byte(0x02);
+        let added = Address::repeat_byte(0x03);
+        let untouched = Address::repeat_byte(0x04);
+
+        let mut before = InMemoryDB::new();
+        let mut account = AccountState::new_with_balance(U256::from(100u64));
+        account.set_storage(U256::from(1u64), U256::from(10u64));
+        account.set_storage(U256::from(2u64), U256::from(20u64));
+        before.insert_account(kept, account.clone());
+        before.insert_account(removed, AccountState::new_with_balance(U256::from(1u64)));
+        before.insert_account(untouched, AccountState::new_with_balance(U256::from(5u64)));
+
+        let mut after = before.clone();
+        after.remove_account(&removed);
+        after.insert_account(added, AccountState::new_contract(vec![0x00], U256::ZERO));
+        let changed = after.get_account_mut(&kept).unwrap();
+        changed.balance = U256::from(90u64);
+        changed.nonce = 1;
+        changed.set_storage(U256::from(1u64), U256::ZERO);
+        changed.set_storage(U256::from(3u64), U256::from(30u64));
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.created.keys().collect::<Vec<_>>(), vec![&added]);
+        assert_eq!(diff.deleted.iter().collect::<Vec<_>>(), vec![&removed]);
+        assert_eq!(diff.modified.len(), 1);
+
+        let account_diff = &diff.modified[&kept];
+        assert_eq!(account_diff.balance_before, U256::from(100u64));
+        assert_eq!(account_diff.balance_after, U256::from(90u64));
+        assert_eq!(
+            (account_diff.nonce_before, account_diff.nonce_after),
+            (0, 1)
+        );
+        assert!(!account_diff.code_changed());
+        assert_eq!(
+            account_diff.storage_changes.iter().collect::<Vec<_>>(),
+            vec![
+                (&U256::from(1u64), &(U256::from(10u64), U256::ZERO)),
+                (&U256::from(3u64), &(U256::ZERO, U256::from(30u64))),
+            ]
+        );
+
+        let mut replayed = before.clone();
+        diff.apply_to(&mut replayed);
+        assert_eq!(replayed.accounts, after.accounts);
+        assert!(after.diff(&replayed).is_empty());
+
+        // Deploying onto a funded address changes its code
+        let mut deployed = after.clone();
+        let target = deployed.get_account_mut(&kept).unwrap();
+        target.code = vec![0x60, 0x00];
+        target.code_hash = keccak256(&target.code);
+        let diff = after.diff(&deployed);
+        assert!(diff.modified[&kept].code_changed());
+        diff.apply_to(&mut after);
+        assert_eq!(after.accounts, deployed.accounts);
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let address = Address::repeat_byte(0x01);
+        let mut db = InMemoryDB::new();
+        db.insert_account(address, AccountState::new_with_balance(U256::from(1u64)));
+        let root = db.compute_state_root();
+        let json = serde_json::to_string(&db).unwrap();
+
+        let first = db.snapshot();
+        db.get_account_mut(&address).unwrap().balance = U256::from(2u64);
+        let second = db.snapshot();
+        db.remove_account(&address);
+        assert_eq!(db.snapshot_count(), 2);
+
+        db.revert_to_snapshot(second).unwrap();
+        assert_eq!(db.get_account(&address).unwrap().balance, U256::from(2u64));
+        assert!(db.revert_to_snapshot(second).is_err());
+
+        // Reverting to the first snapshot drops any taken after it
+        let third = db.snapshot();
+        db.revert_to_snapshot(first).unwrap();
+        assert_eq!(db.compute_state_root(), root);
+        assert_eq!(serde_json::to_string(&db).unwrap(), json);
+        assert!(db.revert_to_snapshot(third).is_err());
+
+        // Clones leave the snapshots behind
+        db.snapshot();
+        let copy = db.clone();
+        assert_eq!(copy.snapshot_count(), 0);
+        assert_eq!(copy.accounts, db.accounts);
+        db.commit_snapshot(0);
+
+        // Committing keeps the current state
+        let id = db.snapshot();
+        db.clear();
+        db.commit_snapshot(id);
+        assert_eq!(db.snapshot_count(), 0);
+        assert_eq!(db.account_count(), 0);
+    }
+
+    #[test]
+    fn test_slot_order_per_mode() {
+        let low = U256::from(1u64);
+        let high = U256::from(2u64);
+        let hashed = |slot: U256| keccak256(&slot.to_be_bytes::<32>());
+
+        // Numeric and hashed order disagree for this pair
+        assert!(low < high);
+        assert!(hashed(low) > hashed(high));
+
+        let address = Address::repeat_byte(0xee);
+        let mut account = AccountState::new_contract(vec![0x00], U256::ZERO);
+        account.set_storage(low, U256::from(42u64));
+        account.set_storage(high, U256::from(7u64));
+        assert_eq!(
+            account.storage_slots(SlotOrder::Numeric),
+            vec![(low, U256::from(42u64)), (high, U256::from(7u64))]
+        );
+        assert_eq!(
+            account.storage_slots(SlotOrder::Hashed),
+            vec![(high, U256::from(7u64)), (low, U256::from(42u64))]
+        );
+
+        let mut db = InMemoryDB::new();
+        db.insert_account(address, account.clone());
+
+        // The flat-list root hashes slots in numeric order...
+        let flat_root = |order| {
+            let mut hasher = Keccak256::new();
+            hasher.update(92u32.to_be_bytes());
+            hasher.update(address.as_slice());
+            hasher.update(account.nonce.to_be_bytes());
+            hasher.update(account.balance.to_be_bytes::<32>());
+            hasher.update(account.code_hash.as_slice());
+            for (slot, value) in account.storage_slots(order) {
+                hasher.update(84u32.to_be_bytes());
+                hasher.update(address.as_slice());
+                hasher.update(slot.to_be_bytes::<32>());
+                hasher.update(value.to_be_bytes::<32>());
+            }
+            Hash::from_slice(&hasher.finalize())
+        };
+        assert_eq!(StateRootMode::FlatList.slot_order(), SlotOrder::Numeric);
+        assert_eq!(db.compute_state_root_flat(), flat_root(SlotOrder::Numeric));
+        assert_ne!(db.compute_state_root_flat(), flat_root(SlotOrder::Hashed));
+
+        // ...while the storage trie is keyed, and so ordered, by slot hash.
+        // Root computed independently with `alloy-trie`
+        assert_eq!(StateRootMode::Mpt.slot_order(), SlotOrder::Hashed);
+        let expected: Hash = "0x5c70ca43ce56e43cb28cc0f1c8fd8db8b2626ab17b6f5aac3ee7309e07c7c4c1"
+            .parse()
+            .unwrap();
+        assert_eq!(account.compute_storage_root(), expected);
+    }
+
+    #[test]
+    fn test_json_merges_address_spellings() {
+        let account =
+            serde_json::to_string(&AccountState::new_with_balance(U256::from(7u64))).unwrap();
+        let other =
+            serde_json::to_string(&AccountState::new_with_balance(U256::from(8u64))).unwrap();
+        let json = |first: &str, second: &str| {
+            format!(
+                r#"{{"accounts": {{
+                    "0x52908400098527886e0f7030069857d2e4169ee7": {first},
+                    " 0x52908400098527886E0F7030069857D2E4169EE7 ": {second}
+                }}, "block_hashes": {{}}}}"#
+            )
+        };
+
+        let db: InMemoryDB = serde_json::from_str(&json(&account, &account)).unwrap();
+        assert_eq!(db.account_count(), 1);
+        let address: Address = "0x52908400098527886E0F7030069857D2E4169EE7"
+            .parse()
+            .unwrap();
+        assert_eq!(db.get_account(&address).unwrap().balance, U256::from(7u64));
+
+        let err = serde_json::from_str::<InMemoryDB>(&json(&account, &other)).unwrap_err();
+        assert!(err.to_string().contains("conflicting"));
+
+        // Binary formats go through the same path
+        let bytes = bincode::serialize(&db).unwrap();
+        let decoded: InMemoryDB = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.accounts, db.accounts);
+    }
 }
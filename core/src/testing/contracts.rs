@@ -0,0 +1,416 @@
+//! Hand-assembled contracts for common patterns
+//!
+//! Each contract is small enough to read opcode by opcode, but dispatches
+//! on real ABI selectors and uses the storage layout `solc` would, so tests
+//! and examples can exercise actual state changes:
+//!
+//! - [`COUNTER_CODE`] - `increment()` and `count()` over slot 0
+//! - [`ERC20_CODE`] - `balanceOf(address)` and `transfer(address,uint256)`
+//!   over a `mapping(address => uint256)` at slot 0, logging `Transfer`
+//! - [`EMITTER_CODE`] - `emit(uint256)`, logging `Emitted(address,uint256)`
+//!
+//! The constants are runtime code, to place in the pre-state with
+//! `AccountState::new_contract`. Wrap them with [`deploy_code`] to deploy
+//! them with a create transaction instead. Unknown selectors revert.
+
+use crate::hashing::keccak256;
+use crate::state::AccountState;
+use crate::types::{Address, U256};
+use alloc::vec::Vec;
+
+extern crate alloc;
+
+/// Selector of `increment()`
+pub const INCREMENT_SELECTOR: [u8; 4] = [0xd0, 0x9d, 0xe0, 0x8a];
+/// Selector of `count()`
+pub const COUNT_SELECTOR: [u8; 4] = [0x06, 0x66, 0x1a, 0xbd];
+/// Selector of `balanceOf(address)`
+pub const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+/// Selector of `transfer(address,uint256)`
+pub const TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+/// Selector of `emit(uint256)`
+pub const EMIT_SELECTOR: [u8; 4] = [0x19, 0x59, 0x77, 0xa6];
+
+/// Slot of the counter's value
+pub const COUNTER_SLOT: U256 = U256::ZERO;
+/// Slot of the ERC20 `balances` mapping
+pub const BALANCES_SLOT: U256 = U256::ZERO;
+
+/// Counter: `increment()` adds one to slot 0, `count()` returns it
+pub const COUNTER_CODE: &[u8] = &[
+    0x60, 0x00, // PUSH1 0
+    0x35, // CALLDATALOAD
+    0x60, 0xe0, // PUSH1 0xe0
+    0x1c, // SHR (selector)
+    0x80, // DUP1
+    0x63, 0xd0, 0x9d, 0xe0, 0x8a, // PUSH4 0xd09de08a
+    0x14, // EQ
+    0x60, 0x1d, // PUSH1 0x1d (increment())
+    0x57, // JUMPI
+    0x63, 0x06, 0x66, 0x1a, 0xbd, // PUSH4 0x06661abd
+    0x14, // EQ
+    0x60, 0x28, // PUSH1 0x28 (count())
+    0x57, // JUMPI
+    0x60, 0x00, // PUSH1 0
+    0x80, // DUP1
+    0xfd, // REVERT (unknown selector)
+    // increment():
+    0x5b, // JUMPDEST
+    0x60, 0x00, // PUSH1 0
+    0x54, // SLOAD
+    0x60, 0x01, // PUSH1 1
+    0x01, // ADD
+    0x60, 0x00, // PUSH1 0
+    0x55, // SSTORE
+    0x00, // STOP
+    // count():
+    0x5b, // JUMPDEST
+    0x60, 0x00, // PUSH1 0
+    0x54, // SLOAD
+    0x60, 0x00, // PUSH1 0
+    0x52, // MSTORE
+    0x60, 0x20, // PUSH1 0x20
+    0x60, 0x00, // PUSH1 0
+    0xf3, // RETURN
+];
+
+/// Minimal ERC20: `balanceOf(address)` and `transfer(address,uint256)`
+///
+/// `transfer` moves `amount` from the caller to `to`, logs
+/// `Transfer(address indexed from, address indexed to, uint256 amount)`
+/// and returns `true`. It reverts if the caller's balance is too low.
+/// There is no supply, allowance or overflow check.
+pub const ERC20_CODE: &[u8] = &[
+    0x60, 0x00, // PUSH1 0
+    0x35, // CALLDATALOAD
+    0x60, 0xe0, // PUSH1 0xe0
+    0x1c, // SHR (selector)
+    0x80, // DUP1
+    0x63, 0x70, 0xa0, 0x82, 0x31, // PUSH4 0x70a08231
+    0x14, // EQ
+    0x60, 0x1d, // PUSH1 0x1d (balanceOf(address))
+    0x57, // JUMPI
+    0x63, 0xa9, 0x05, 0x9c, 0xbb, // PUSH4 0xa9059cbb
+    0x14, // EQ
+    0x60, 0x37, // PUSH1 0x37 (transfer(address,uint256))
+    0x57, // JUMPI
+    0x60, 0x00, // PUSH1 0
+    0x80, // DUP1
+    0xfd, // REVERT (unknown selector)
+    // balanceOf(address):
+    0x5b, // JUMPDEST
+    0x60, 0x04, // PUSH1 4
+    0x35, // CALLDATALOAD (owner)
+    0x60, 0x00, // PUSH1 0
+    0x52, // MSTORE
+    0x60, 0x00, // PUSH1 0
+    0x60, 0x20, // PUSH1 0x20
+    0x52, // MSTORE
+    0x60, 0x40, // PUSH1 0x40
+    0x60, 0x00, // PUSH1 0
+    0x20, // SHA3 (balances[owner] slot)
+    0x54, // SLOAD
+    0x60, 0x00, // PUSH1 0
+    0x52, // MSTORE
+    0x60, 0x20, // PUSH1 0x20
+    0x60, 0x00, // PUSH1 0
+    0xf3, // RETURN
+    // transfer(address,uint256):
+    0x5b, // JUMPDEST
+    0x60, 0x24, // PUSH1 0x24
+    0x35, // CALLDATALOAD (amount)
+    0x33, // CALLER
+    0x60, 0x00, // PUSH1 0
+    0x52, // MSTORE
+    0x60, 0x00, // PUSH1 0
+    0x60, 0x20, // PUSH1 0x20
+    0x52, // MSTORE
+    0x60, 0x40, // PUSH1 0x40
+    0x60, 0x00, // PUSH1 0
+    0x20, // SHA3 (balances[caller] slot)
+    0x80, // DUP1
+    0x54, // SLOAD (balance)
+    0x82, // DUP3
+    0x81, // DUP2
+    0x10, // LT (balance < amount)
+    0x60, 0x9e, // PUSH1 0x9e (insufficient)
+    0x57, // JUMPI
+    0x82, // DUP3
+    0x90, // SWAP1
+    0x03, // SUB
+    0x90, // SWAP1
+    0x55, // SSTORE (balance - amount)
+    0x60, 0x04, // PUSH1 4
+    0x35, // CALLDATALOAD (to)
+    0x60, 0x00, // PUSH1 0
+    0x52, // MSTORE
+    0x60, 0x40, // PUSH1 0x40
+    0x60, 0x00, // PUSH1 0
+    0x20, // SHA3 (balances[to] slot)
+    0x80, // DUP1
+    0x54, // SLOAD
+    0x82, // DUP3
+    0x01, // ADD
+    0x90, // SWAP1
+    0x55, // SSTORE (balance + amount)
+    0x60, 0x00, // PUSH1 0
+    0x52, // MSTORE
+    0x60, 0x04, // PUSH1 4
+    0x35, // CALLDATALOAD
+    0x33, // CALLER
+    0x7f, // PUSH32 Transfer topic
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+    0x60, 0x20, // PUSH1 0x20
+    0x60, 0x00, // PUSH1 0
+    0xa3, // LOG3 (Transfer(caller, to, amount))
+    0x60, 0x01, // PUSH1 1
+    0x60, 0x00, // PUSH1 0
+    0x52, // MSTORE
+    0x60, 0x20, // PUSH1 0x20
+    0x60, 0x00, // PUSH1 0
+    0xf3, // RETURN (true)
+    // insufficient:
+    0x5b, // JUMPDEST
+    0x60, 0x00, // PUSH1 0
+    0x80, // DUP1
+    0xfd, // REVERT
+];
+
+/// Event emitter: `emit(uint256 value)` logs
+/// `Emitted(address indexed sender, uint256 value)`
+pub const EMITTER_CODE: &[u8] = &[
+    0x60, 0x00, // PUSH1 0
+    0x35, // CALLDATALOAD
+    0x60, 0xe0, // PUSH1 0xe0
+    0x1c, // SHR (selector)
+    0x63, 0x19, 0x59, 0x77, 0xa6, // PUSH4 0x195977a6
+    0x14, // EQ
+    0x60, 0x13, // PUSH1 0x13 (emit(uint256))
+    0x57, // JUMPI
+    0x60, 0x00, // PUSH1 0
+    0x80, // DUP1
+    0xfd, // REVERT (unknown selector)
+    // emit(uint256):
+    0x5b, // JUMPDEST
+    0x60, 0x04, // PUSH1 4
+    0x35, // CALLDATALOAD (value)
+    0x60, 0x00, // PUSH1 0
+    0x52, // MSTORE
+    0x33, // CALLER
+    0x7f, // PUSH32 Emitted topic
+    0x6d, 0x77, 0x47, 0xff, 0x9a, 0xab, 0xa2, 0x38, 0xde, 0x65, 0x89, 0x57, 0xa1, 0x2a, 0x32, 0xc8,
+    0xa9, 0x4f, 0x6e, 0xc3, 0xaa, 0x05, 0x08, 0x44, 0x1f, 0xe4, 0x00, 0xca, 0x79, 0xed, 0x45, 0x7c,
+    0x60, 0x20, // PUSH1 0x20
+    0x60, 0x00, // PUSH1 0
+    0xa2, // LOG2 (Emitted(caller, value))
+    0x00, // STOP
+];
+
+/// Init code deploying `runtime`
+///
+/// Copies the code following a 12-byte prefix to memory and returns it:
+/// `PUSH2 len, DUP1, PUSH1 12, PUSH1 0, CODECOPY, PUSH1 0, RETURN`.
+///
+/// # Panics
+///
+/// If `runtime` is longer than `0xffff` bytes.
+pub fn deploy_code(runtime: &[u8]) -> Vec<u8> {
+    let len = u16::try_from(runtime.len()).expect("runtime code too long");
+
+    let mut code = Vec::with_capacity(12 + runtime.len());
+    code.push(0x61); // PUSH2 len
+    code.extend_from_slice(&len.to_be_bytes());
+    code.extend_from_slice(&[
+        0x80, // DUP1
+        0x60, 0x0c, // PUSH1 12
+        0x60, 0x00, // PUSH1 0
+        0x39, // CODECOPY
+        0x60, 0x00, // PUSH1 0
+        0xf3, // RETURN
+    ]);
+    code.extend_from_slice(runtime);
+    code
+}
+
+/// Calldata for `balanceOf(owner)`
+pub fn balance_of_calldata(owner: Address) -> Vec<u8> {
+    let mut data = BALANCE_OF_SELECTOR.to_vec();
+    data.extend_from_slice(owner.into_word().as_slice());
+    data
+}
+
+/// Calldata for `transfer(to, amount)`
+pub fn transfer_calldata(to: Address, amount: U256) -> Vec<u8> {
+    let mut data = TRANSFER_SELECTOR.to_vec();
+    data.extend_from_slice(to.into_word().as_slice());
+    data.extend_from_slice(&amount.to_be_bytes::<32>());
+    data
+}
+
+/// Calldata for `emit(value)`
+pub fn emit_calldata(value: U256) -> Vec<u8> {
+    let mut data = EMIT_SELECTOR.to_vec();
+    data.extend_from_slice(&value.to_be_bytes::<32>());
+    data
+}
+
+/// Storage slot of `owner`'s ERC20 balance: `keccak256(owner || 0)`
+pub fn erc20_balance_slot(owner: Address) -> U256 {
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(owner.into_word().as_slice());
+    preimage[32..].copy_from_slice(&BALANCES_SLOT.to_be_bytes::<32>());
+    U256::from_be_bytes(keccak256(&preimage).0)
+}
+
+/// ERC20 account holding the given balances
+pub fn erc20_with_balances(balances: &[(Address, U256)]) -> AccountState {
+    let mut token = AccountState::new_contract(ERC20_CODE.to_vec(), U256::ZERO);
+    for (owner, balance) in balances {
+        token.set_storage(erc20_balance_slot(*owner), *balance);
+    }
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::ShadowExecutor;
+    use crate::input::{BlockEnv, ExecutionInput, TxInput};
+    use crate::state::InMemoryDB;
+    use crate::types::Hash;
+
+    fn funded(account: Address) -> InMemoryDB {
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            account,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state
+    }
+
+    #[test]
+    fn test_selectors_and_topics() {
+        for (selector, signature) in [
+            (INCREMENT_SELECTOR, "increment()"),
+            (COUNT_SELECTOR, "count()"),
+            (BALANCE_OF_SELECTOR, "balanceOf(address)"),
+            (TRANSFER_SELECTOR, "transfer(address,uint256)"),
+            (EMIT_SELECTOR, "emit(uint256)"),
+        ] {
+            assert_eq!(
+                selector,
+                keccak256(signature.as_bytes())[..4],
+                "{signature}"
+            );
+        }
+
+        let contains = |code: &[u8], topic: Hash| code.windows(32).any(|w| w == topic.as_slice());
+        assert!(contains(
+            ERC20_CODE,
+            keccak256(b"Transfer(address,address,uint256)")
+        ));
+        assert!(contains(
+            EMITTER_CODE,
+            keccak256(b"Emitted(address,uint256)")
+        ));
+    }
+
+    #[test]
+    fn test_counter_increments_twice() {
+        let caller = Address::repeat_byte(0x01);
+
+        let deploy = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::create(caller, deploy_code(COUNTER_CODE), U256::ZERO)
+                .with_gas_limit(1_000_000),
+            funded(caller),
+        );
+        let (deployed, _) = ShadowExecutor::execute(deploy).unwrap();
+        let counter = deployed.created_address.unwrap();
+
+        let increment = TxInput::call(caller, counter, INCREMENT_SELECTOR.to_vec());
+        let count = TxInput::call(caller, counter, COUNT_SELECTOR.to_vec());
+        let outputs = ShadowExecutor::execute_sequence(
+            BlockEnv::default(),
+            vec![increment.clone(), increment, count],
+            deployed.post_state,
+        )
+        .unwrap();
+
+        assert!(outputs.iter().all(|output| output.is_success()));
+        let last = outputs.last().unwrap();
+        assert_eq!(last.storage_of(counter, COUNTER_SLOT), U256::from(2u64));
+        assert_eq!(last.return_data, U256::from(2u64).to_be_bytes::<32>());
+    }
+
+    #[test]
+    fn test_erc20_transfer() {
+        let alice = Address::repeat_byte(0x01);
+        let bob = Address::repeat_byte(0x02);
+        let token = Address::repeat_byte(0xee);
+
+        let mut state = funded(alice);
+        state.insert_account(token, erc20_with_balances(&[(alice, U256::from(100u64))]));
+
+        let transfer = TxInput::call(alice, token, transfer_calldata(bob, U256::from(30u64)));
+        let balance = TxInput::call(alice, token, balance_of_calldata(bob));
+        let overdraw = TxInput::call(alice, token, transfer_calldata(bob, U256::from(71u64)));
+        let outputs = ShadowExecutor::execute_sequence(
+            BlockEnv::default(),
+            vec![transfer, balance, overdraw],
+            state,
+        )
+        .unwrap();
+
+        let sent = &outputs[0];
+        assert!(sent.is_success());
+        assert_eq!(
+            sent.storage_of(token, erc20_balance_slot(alice)),
+            U256::from(70u64)
+        );
+        assert_eq!(
+            sent.storage_of(token, erc20_balance_slot(bob)),
+            U256::from(30u64)
+        );
+        assert_eq!(
+            sent.logs[0].topics,
+            vec![
+                keccak256(b"Transfer(address,address,uint256)"),
+                alice.into_word(),
+                bob.into_word(),
+            ]
+        );
+
+        assert_eq!(
+            outputs[1].return_data,
+            U256::from(30u64).to_be_bytes::<32>()
+        );
+        assert!(outputs[2].is_revert());
+    }
+
+    #[test]
+    fn test_emitter_logs_value() {
+        let caller = Address::repeat_byte(0x01);
+        let emitter = Address::repeat_byte(0xcc);
+
+        let mut state = funded(caller);
+        state.insert_account(
+            emitter,
+            AccountState::new_contract(EMITTER_CODE.to_vec(), U256::ZERO),
+        );
+
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::call(caller, emitter, emit_calldata(U256::from(7u64))),
+            state,
+        );
+        let (output, _) = ShadowExecutor::execute(input).unwrap();
+
+        assert!(output.is_success());
+        assert_eq!(output.logs.len(), 1);
+        assert_eq!(output.logs[0].address, emitter);
+        assert_eq!(output.logs[0].topics[1], caller.into_word());
+        assert_eq!(output.logs[0].data, U256::from(7u64).to_be_bytes::<32>());
+    }
+}
@@ -0,0 +1,6 @@
+//! Fixtures for tests and examples
+//!
+//! Nothing in this module is used during execution.
+
+pub mod contracts;
+pub mod samples;
@@ -0,0 +1,144 @@
+//! Ready-to-run sample inputs
+//!
+//! Each [`SampleKind`] builds a complete [`ExecutionInput`] around the
+//! mock contracts in [`super::contracts`], so a new user can execute and
+//! prove a realistic transaction without writing one first.
+
+use super::contracts::{deploy_code, erc20_with_balances, transfer_calldata, COUNTER_CODE};
+use crate::input::{BlockEnv, ExecutionInput, TxInput};
+use crate::state::{AccountState, InMemoryDB};
+use crate::types::{Address, U256};
+
+/// Scenario of a sample input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleKind {
+    /// 0.001 ETH from the sender to a fresh account
+    Transfer,
+    /// Create transaction deploying the counter contract
+    Deploy,
+    /// ERC20 `transfer` of 30 of the sender's 100 tokens
+    Erc20,
+    /// ERC20 `transfer` of more tokens than the sender holds, which reverts
+    Revert,
+}
+
+impl SampleKind {
+    /// Every kind, in declaration order
+    pub const ALL: [SampleKind; 4] = [
+        SampleKind::Transfer,
+        SampleKind::Deploy,
+        SampleKind::Erc20,
+        SampleKind::Revert,
+    ];
+
+    /// Name of the kind, as accepted by [`SampleKind::parse`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            SampleKind::Transfer => "transfer",
+            SampleKind::Deploy => "deploy",
+            SampleKind::Erc20 => "erc20",
+            SampleKind::Revert => "revert",
+        }
+    }
+
+    /// Look up a kind by name
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// Account sending the sample transaction
+    pub fn sender() -> Address {
+        Address::repeat_byte(0x01)
+    }
+
+    /// Account receiving ETH or tokens in the transfer samples
+    pub fn receiver() -> Address {
+        Address::repeat_byte(0x02)
+    }
+
+    /// ERC20 contract of the `erc20` and `revert` samples
+    pub fn token() -> Address {
+        Address::repeat_byte(0xee)
+    }
+
+    /// Build the sample input
+    ///
+    /// The sender holds 1 ETH in every sample.
+    pub fn input(&self) -> ExecutionInput {
+        let sender = Self::sender();
+        let receiver = Self::receiver();
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let tx = match self {
+            SampleKind::Transfer => {
+                TxInput::transfer(sender, receiver, U256::from(1_000_000_000_000_000u64))
+            }
+            SampleKind::Deploy => TxInput::create(sender, deploy_code(COUNTER_CODE), U256::ZERO)
+                .with_gas_limit(1_000_000),
+            SampleKind::Erc20 | SampleKind::Revert => {
+                state.insert_account(
+                    Self::token(),
+                    erc20_with_balances(&[(sender, U256::from(100u64))]),
+                );
+                let amount = if *self == SampleKind::Erc20 {
+                    30u64
+                } else {
+                    101
+                };
+                TxInput::call(
+                    sender,
+                    Self::token(),
+                    transfer_calldata(receiver, U256::from(amount)),
+                )
+            }
+        };
+
+        ExecutionInput::new(BlockEnv::default(), tx, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::ShadowExecutor;
+    use crate::testing::contracts::{erc20_balance_slot, COUNTER_SLOT};
+
+    #[test]
+    fn test_samples_execute() {
+        for kind in SampleKind::ALL {
+            assert_eq!(SampleKind::parse(kind.name()), Some(kind));
+
+            let json = serde_json::to_string(&kind.input()).unwrap();
+            let input: ExecutionInput = serde_json::from_str(&json).unwrap();
+            let (output, _) = ShadowExecutor::execute(input).unwrap();
+
+            match kind {
+                SampleKind::Transfer => assert!(output.is_success()),
+                SampleKind::Deploy => {
+                    assert!(output.is_success());
+                    let counter = output.created_address.unwrap();
+                    let code = &output.post_state.get_account(&counter).unwrap().code;
+                    assert_eq!(code.as_slice(), COUNTER_CODE);
+                    assert_eq!(output.storage_of(counter, COUNTER_SLOT), U256::ZERO);
+                }
+                SampleKind::Erc20 => {
+                    assert!(output.is_success());
+                    assert_eq!(
+                        output.storage_of(
+                            SampleKind::token(),
+                            erc20_balance_slot(SampleKind::receiver())
+                        ),
+                        U256::from(30u64)
+                    );
+                }
+                SampleKind::Revert => assert!(output.is_revert()),
+            }
+        }
+        assert_eq!(SampleKind::parse("swap"), None);
+    }
+}
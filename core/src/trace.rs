@@ -0,0 +1,981 @@
+//! Execution tracing for Shadow-EVM
+//!
+//! Opcode-level tracing for debugging and profiling. Tracing runs through a
+//! revm inspector and is only enabled by `ShadowExecutor::execute_traced`;
+//! plain execution (and therefore proving) does not pay for it.
+//!
+//! Besides opcodes, tracers see every call and create frame; [`CallTrace`]
+//! assembles them into a call tree exportable in Geth's `callTracer` format.
+
+use crate::output::ExecutionOutput;
+use crate::types::{Address, Gas, U256};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use revm::interpreter::opcode::{OpCode, SSTORE};
+use revm::interpreter::{
+    CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, Interpreter,
+    InterpreterResult,
+};
+use revm::primitives::CreateScheme;
+use revm::{Database, EvmContext, Inspector};
+use serde::{Deserialize, Serialize};
+
+extern crate alloc;
+
+/// A single executed opcode
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceStep {
+    /// Program counter within the current frame's code
+    pub pc: usize,
+    /// Opcode byte
+    pub opcode: u8,
+    /// Call depth (1 for the transaction's top-level frame)
+    pub depth: usize,
+    /// Gas remaining in the frame before the opcode executed
+    pub gas_remaining: Gas,
+    /// Gas charged by the opcode (includes gas forwarded by calls)
+    pub gas_cost: Gas,
+    /// Part of `gas_cost` spent expanding memory
+    #[serde(default)]
+    pub memory_gas: Gas,
+    /// Top of the stack before the opcode executed
+    pub stack_top: Option<U256>,
+}
+
+impl TraceStep {
+    /// Mnemonic of the opcode (e.g. `SSTORE`), or `UNKNOWN`
+    pub fn opcode_name(&self) -> &'static str {
+        OpCode::new(self.opcode).map_or("UNKNOWN", OpCode::as_str)
+    }
+
+    /// Check if two steps executed the same instruction on the same data
+    ///
+    /// Gas is ignored: it legitimately differs between otherwise identical
+    /// runs (e.g. cold vs. warm storage access).
+    pub fn same_execution(&self, other: &TraceStep) -> bool {
+        self.pc == other.pc
+            && self.opcode == other.opcode
+            && self.depth == other.depth
+            && self.stack_top == other.stack_top
+    }
+}
+
+/// A storage write made by an `SSTORE`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageWrite {
+    /// Account whose storage was written
+    pub address: Address,
+    /// Storage slot
+    pub slot: U256,
+    /// Value written
+    pub value: U256,
+    /// Call depth of the writing frame, as in `TraceStep::depth`
+    pub depth: usize,
+}
+
+/// Receives every executed opcode during a traced execution
+pub trait Tracer {
+    /// Called once per executed opcode, after it has executed
+    fn on_step(&mut self, step: &TraceStep);
+
+    /// Called after each successful `SSTORE`, before any revert unwinds it
+    fn on_storage_write(&mut self, _write: &StorageWrite) {}
+
+    /// Called when a call or create frame starts, before its first opcode
+    ///
+    /// `frame` has no result yet: `gas_used`, `output` and `error` are
+    /// unset, and `to` is `None` for a create.
+    fn on_call_enter(&mut self, _frame: &CallFrame) {}
+
+    /// Called when the innermost open frame returns
+    fn on_call_exit(&mut self, _exit: &CallExit) {}
+}
+
+/// Kind of a call frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CallKind {
+    /// `CALL` (and the transaction's top-level call)
+    Call,
+    /// `CALLCODE`
+    CallCode,
+    /// `DELEGATECALL`
+    DelegateCall,
+    /// `STATICCALL`
+    StaticCall,
+    /// `CREATE` (and a contract creation transaction)
+    Create,
+    /// `CREATE2`
+    Create2,
+}
+
+impl CallKind {
+    /// Opcode name, as in Geth's `callTracer` `type` field
+    pub fn name(&self) -> &'static str {
+        match self {
+            CallKind::Call => "CALL",
+            CallKind::CallCode => "CALLCODE",
+            CallKind::DelegateCall => "DELEGATECALL",
+            CallKind::StaticCall => "STATICCALL",
+            CallKind::Create => "CREATE",
+            CallKind::Create2 => "CREATE2",
+        }
+    }
+}
+
+/// Result of a call frame, reported to `Tracer::on_call_exit`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallExit {
+    /// Created contract address, for a successful create
+    pub created: Option<Address>,
+    /// Gas used by the frame, including gas forwarded to its subcalls
+    pub gas_used: Gas,
+    /// Return data (deployed code for a create)
+    pub output: Vec<u8>,
+    /// Why the frame failed (`None` on success)
+    pub error: Option<String>,
+}
+
+/// One call or create frame with its subcalls
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallFrame {
+    /// Kind of call
+    pub kind: CallKind,
+    /// Calling account
+    pub from: Address,
+    /// Called account, or the created contract (`None` for a failed create)
+    pub to: Option<Address>,
+    /// Value sent (apparent value for `DELEGATECALL`)
+    pub value: U256,
+    /// Gas available to the frame
+    pub gas: Gas,
+    /// Gas used by the frame, including gas forwarded to its subcalls
+    pub gas_used: Gas,
+    /// Calldata, or init code for a create
+    pub input: Vec<u8>,
+    /// Return data, or deployed code for a create
+    pub output: Vec<u8>,
+    /// Why the frame failed (`None` on success)
+    pub error: Option<String>,
+    /// Frames started by this one, in execution order
+    pub calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+    /// This frame as a Geth `callTracer` object
+    ///
+    /// Addresses and bytes are lowercase `0x`-prefixed hex, quantities are
+    /// `0x`-prefixed hex without leading zeros. As in Geth, `to`, `output`,
+    /// `error` and `calls` are omitted when empty, and `value` is omitted
+    /// for `STATICCALL`. The top-level frame's `gas` excludes the
+    /// transaction's intrinsic gas.
+    pub fn to_geth_json(&self) -> serde_json::Value {
+        let hex = |bytes: &[u8]| alloy_primitives::hex::encode_prefixed(bytes);
+        let mut json = serde_json::Map::new();
+        json.insert("type".into(), self.kind.name().into());
+        json.insert("from".into(), hex(self.from.as_slice()).into());
+        if let Some(to) = self.to {
+            json.insert("to".into(), hex(to.as_slice()).into());
+        }
+        if self.kind != CallKind::StaticCall {
+            json.insert("value".into(), format!("{:#x}", self.value).into());
+        }
+        json.insert("gas".into(), format!("{:#x}", self.gas).into());
+        json.insert("gasUsed".into(), format!("{:#x}", self.gas_used).into());
+        json.insert("input".into(), hex(&self.input).into());
+        if !self.output.is_empty() {
+            json.insert("output".into(), hex(&self.output).into());
+        }
+        if let Some(error) = &self.error {
+            json.insert("error".into(), error.as_str().into());
+        }
+        if !self.calls.is_empty() {
+            let calls = self.calls.iter().map(CallFrame::to_geth_json).collect();
+            json.insert("calls".into(), serde_json::Value::Array(calls));
+        }
+        serde_json::Value::Object(json)
+    }
+}
+
+/// Call tree of an execution
+///
+/// Run with `ShadowExecutor::execute_traced(input, CallTrace::new())`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallTrace {
+    /// Top-level frame (`None` if no frame ran, e.g. a failed validation)
+    pub root: Option<CallFrame>,
+    /// Frames entered but not yet exited, outermost first
+    #[serde(skip)]
+    open: Vec<CallFrame>,
+}
+
+impl CallTrace {
+    /// Create an empty call trace
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The call tree in Geth's `callTracer` format (`null` if empty)
+    ///
+    /// See [`CallFrame::to_geth_json`].
+    pub fn to_geth_json(&self) -> serde_json::Value {
+        self.root
+            .as_ref()
+            .map_or(serde_json::Value::Null, CallFrame::to_geth_json)
+    }
+}
+
+impl Tracer for CallTrace {
+    fn on_step(&mut self, _step: &TraceStep) {}
+
+    fn on_call_enter(&mut self, frame: &CallFrame) {
+        self.open.push(frame.clone());
+    }
+
+    fn on_call_exit(&mut self, exit: &CallExit) {
+        let Some(mut frame) = self.open.pop() else {
+            return;
+        };
+        if frame.to.is_none() {
+            frame.to = exit.created;
+        }
+        frame.gas_used = exit.gas_used;
+        frame.output = exit.output.clone();
+        frame.error = exit.error.clone();
+
+        match self.open.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+}
+
+/// Full opcode-level trace of an execution
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    /// Executed opcodes, in execution order
+    pub steps: Vec<TraceStep>,
+}
+
+impl ExecutionTrace {
+    /// Create an empty trace
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of executed opcodes
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Check if no opcode was executed (e.g. a plain transfer)
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+impl Tracer for ExecutionTrace {
+    fn on_step(&mut self, step: &TraceStep) {
+        self.steps.push(step.clone());
+    }
+}
+
+/// Trace of an execution together with every storage write it attempted
+///
+/// Unlike the post-state, `writes` keeps the writes of frames that later
+/// reverted, showing what a failed transaction meant to do.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DebugTrace {
+    /// Executed opcodes, in execution order
+    pub trace: ExecutionTrace,
+    /// Storage writes, in execution order, including unwound ones
+    pub writes: Vec<StorageWrite>,
+}
+
+impl DebugTrace {
+    /// Create an empty debug trace
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Final value of each written slot, had no frame reverted
+    pub fn would_be_storage(&self) -> BTreeMap<Address, BTreeMap<U256, U256>> {
+        let mut storage: BTreeMap<Address, BTreeMap<U256, U256>> = BTreeMap::new();
+        for write in &self.writes {
+            storage
+                .entry(write.address)
+                .or_default()
+                .insert(write.slot, write.value);
+        }
+        storage
+    }
+}
+
+impl Tracer for DebugTrace {
+    fn on_step(&mut self, step: &TraceStep) {
+        self.trace.on_step(step);
+    }
+
+    fn on_storage_write(&mut self, write: &StorageWrite) {
+        self.writes.push(write.clone());
+    }
+}
+
+/// Step-by-step comparison of two traces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceDiff {
+    /// Index of the first step that differs (`None` if the traces are identical)
+    pub first_divergence: Option<usize>,
+    /// Steps of `a` after the identical prefix
+    pub steps_only_in_a: usize,
+    /// Steps of `b` after the identical prefix
+    pub steps_only_in_b: usize,
+    /// Number of leading steps both traces share
+    pub identical_prefix_len: usize,
+}
+
+impl TraceDiff {
+    /// Check if the traces were identical
+    pub fn is_identical(&self) -> bool {
+        self.first_divergence.is_none()
+    }
+}
+
+/// Compare two traces step by step
+///
+/// Steps are compared with [`TraceStep::same_execution`]. Once the traces
+/// diverge, all remaining steps of each trace count as unique to it.
+pub fn diff_traces(a: &ExecutionTrace, b: &ExecutionTrace) -> TraceDiff {
+    let identical_prefix_len = a
+        .steps
+        .iter()
+        .zip(&b.steps)
+        .take_while(|(x, y)| x.same_execution(y))
+        .count();
+
+    let identical = identical_prefix_len == a.len() && identical_prefix_len == b.len();
+
+    TraceDiff {
+        first_divergence: (!identical).then_some(identical_prefix_len),
+        steps_only_in_a: a.len() - identical_prefix_len,
+        steps_only_in_b: b.len() - identical_prefix_len,
+        identical_prefix_len,
+    }
+}
+
+/// Gas spent on one opcode across an execution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpcodeGas {
+    /// Opcode mnemonic
+    pub name: String,
+    /// Number of times the opcode executed
+    pub count: u64,
+    /// Total gas charged to the opcode
+    pub gas: Gas,
+}
+
+/// Per-opcode gas usage of an execution, most expensive first
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasProfile {
+    /// Opcodes sorted by total gas, descending
+    pub opcodes: Vec<OpcodeGas>,
+    /// Gas used by category
+    ///
+    /// Set by `ShadowExecutor::execute_profiled`; all zero in a profile
+    /// from `GasProfiler::into_profile`, which does not see the result.
+    #[serde(default)]
+    pub breakdown: GasBreakdown,
+}
+
+impl GasProfile {
+    /// Total gas charged to opcodes (excludes intrinsic gas)
+    pub fn total_gas(&self) -> Gas {
+        self.opcodes.iter().map(|op| op.gas).sum()
+    }
+}
+
+impl fmt::Display for GasProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:>10} {:>12} {:>7}",
+            "OPCODE", "COUNT", "GAS", "%"
+        )?;
+
+        let total = self.total_gas().max(1);
+        for op in &self.opcodes {
+            writeln!(
+                f,
+                "{:<16} {:>10} {:>12} {:>6.2}%",
+                op.name,
+                op.count,
+                op.gas,
+                op.gas as f64 * 100.0 / total as f64
+            )?;
+        }
+
+        write!(f, "{:<16} {:>10} {:>12}", "TOTAL", "", self.total_gas())
+    }
+}
+
+/// Gas used by an execution, by category
+///
+/// Computed by [`GasProfiler::breakdown`]; the categories sum to the
+/// transaction's `gas_used`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasBreakdown {
+    /// Transaction base cost: 21000, or 53000 for a create
+    pub base: Gas,
+    /// Rest of the intrinsic gas: calldata, and init code for a create
+    pub calldata: Gas,
+    /// Every other opcode, and gas burned by a halt
+    pub compute: Gas,
+    /// `SLOAD` and `SSTORE` net of the refund, and a created contract's code deposit
+    pub storage: Gas,
+    /// Memory expansion, whichever opcode caused it
+    pub memory: Gas,
+    /// Own cost of call and create opcodes, e.g. cold account access and
+    /// value transfer (the forwarded gas is attributed to the callee)
+    pub call: Gas,
+}
+
+impl GasBreakdown {
+    /// Sum of all categories
+    pub fn total(&self) -> Gas {
+        self.base + self.calldata + self.compute + self.storage + self.memory + self.call
+    }
+}
+
+impl fmt::Display for GasBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<16} {:>12} {:>7}", "CATEGORY", "GAS", "%")?;
+
+        let total = self.total().max(1);
+        for (name, gas) in [
+            ("base", self.base),
+            ("calldata", self.calldata),
+            ("compute", self.compute),
+            ("storage", self.storage),
+            ("memory", self.memory),
+            ("call", self.call),
+        ] {
+            writeln!(
+                f,
+                "{:<16} {:>12} {:>6.2}%",
+                name,
+                gas,
+                gas as f64 * 100.0 / total as f64
+            )?;
+        }
+
+        write!(f, "{:<16} {:>12}", "TOTAL", self.total())
+    }
+}
+
+/// Mnemonics of the opcodes that open a call or create frame
+const CALL_OPCODES: [&str; 6] = [
+    "CALL",
+    "CALLCODE",
+    "DELEGATECALL",
+    "STATICCALL",
+    "CREATE",
+    "CREATE2",
+];
+
+/// Call or create awaiting its return
+#[derive(Debug, Clone)]
+struct OpenCall {
+    name: &'static str,
+    depth: usize,
+    gas_before: Gas,
+    raw_cost: Gas,
+    child_gas: Gas,
+}
+
+/// Tracer aggregating gas usage per opcode
+///
+/// Call-family opcodes (`CALL`, `CREATE`, ...) are charged only their own
+/// cost: gas forwarded to the callee is attributed to the callee's opcodes,
+/// and unused gas returned to the caller is not charged at all. Precompile
+/// gas is charged to the calling opcode.
+#[derive(Debug, Clone, Default)]
+pub struct GasProfiler {
+    opcodes: BTreeMap<&'static str, (u64, Gas)>,
+    open_calls: Vec<OpenCall>,
+    /// Gas available to the top-level frame, after intrinsic gas
+    frame_gas: Option<Gas>,
+    memory_gas: Gas,
+    call_memory_gas: Gas,
+}
+
+impl GasProfiler {
+    /// Create an empty profiler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opcode name to `(call_count, total_gas)`
+    pub fn opcodes(&self) -> &BTreeMap<&'static str, (u64, Gas)> {
+        &self.opcodes
+    }
+
+    /// The `k` opcodes with the highest total gas, most expensive first
+    ///
+    /// Returns `(name, call_count, total_gas)`; ties are ordered by name.
+    pub fn top_k_by_gas(&self, k: usize) -> Vec<(&'static str, u64, Gas)> {
+        let mut sorted: Vec<_> = self
+            .opcodes
+            .iter()
+            .map(|(name, (count, gas))| (*name, *count, *gas))
+            .collect();
+        sorted.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+        sorted.truncate(k);
+        sorted
+    }
+
+    /// Convert into a serializable profile
+    pub fn into_profile(self) -> GasProfile {
+        let opcodes = self
+            .top_k_by_gas(usize::MAX)
+            .into_iter()
+            .map(|(name, count, gas)| OpcodeGas {
+                name: name.to_string(),
+                count,
+                gas,
+            })
+            .collect();
+        GasProfile {
+            opcodes,
+            breakdown: GasBreakdown::default(),
+        }
+    }
+
+    /// Split the gas used by the profiled execution into categories
+    ///
+    /// `gas_limit` and `is_create` describe the profiled transaction and
+    /// `output` is its result.
+    pub fn breakdown(
+        &self,
+        gas_limit: Gas,
+        is_create: bool,
+        output: &ExecutionOutput,
+    ) -> GasBreakdown {
+        let opcode_gas = |names: &[&str]| -> Gas {
+            names
+                .iter()
+                .filter_map(|name| self.opcodes.get(name))
+                .map(|(_, gas)| gas)
+                .sum()
+        };
+
+        let intrinsic = self
+            .frame_gas
+            .map_or(0, |gas| gas_limit.saturating_sub(gas));
+        let base = if is_create { 53_000 } else { 21_000 }.min(intrinsic);
+        let storage = (opcode_gas(&["SLOAD", "SSTORE"]) + output.code_deposit_gas().unwrap_or(0))
+            .saturating_sub(output.gas_refunded);
+        let call = opcode_gas(&CALL_OPCODES).saturating_sub(self.call_memory_gas);
+
+        let mut breakdown = GasBreakdown {
+            base,
+            calldata: intrinsic - base,
+            compute: 0,
+            storage,
+            memory: self.memory_gas,
+            call,
+        };
+        breakdown.compute = output.gas_used.saturating_sub(breakdown.total());
+        breakdown
+    }
+
+    /// Add gas spent inside the innermost pending call
+    fn charge_enclosing(&mut self, gas: Gas) {
+        if let Some(call) = self.open_calls.last_mut() {
+            call.child_gas += gas;
+        }
+    }
+
+    /// Settle calls that returned before `step`
+    fn settle_calls(&mut self, step: &TraceStep) {
+        while let Some(call) = self.open_calls.pop() {
+            if step.depth > call.depth {
+                self.open_calls.push(call);
+                break;
+            }
+
+            // Only a step back in the calling frame shows what the call cost
+            let inclusive = if step.depth == call.depth {
+                call.gas_before.saturating_sub(step.gas_remaining)
+            } else {
+                call.raw_cost
+            };
+            let own_cost = inclusive.saturating_sub(call.child_gas);
+
+            let entry = self.opcodes.entry(call.name).or_default();
+            entry.1 = entry.1 - call.raw_cost + own_cost;
+            self.charge_enclosing(inclusive);
+        }
+    }
+}
+
+impl Tracer for GasProfiler {
+    fn on_step(&mut self, step: &TraceStep) {
+        self.settle_calls(step);
+
+        let name = step.opcode_name();
+        let entry = self.opcodes.entry(name).or_default();
+        entry.0 += 1;
+        entry.1 += step.gas_cost;
+        self.memory_gas += step.memory_gas;
+
+        if matches!(step.opcode, 0xf0 | 0xf1 | 0xf2 | 0xf4 | 0xf5 | 0xfa) {
+            self.call_memory_gas += step.memory_gas;
+            self.open_calls.push(OpenCall {
+                name,
+                depth: step.depth,
+                gas_before: step.gas_remaining,
+                raw_cost: step.gas_cost,
+                child_gas: 0,
+            });
+        } else {
+            self.charge_enclosing(step.gas_cost);
+        }
+    }
+
+    fn on_call_enter(&mut self, frame: &CallFrame) {
+        self.frame_gas.get_or_insert(frame.gas);
+    }
+}
+
+/// Gas cost of `len` bytes of memory: `3 * words + words^2 / 512`
+fn memory_cost(len: usize) -> Gas {
+    let words = len.div_ceil(32) as Gas;
+    3 * words + words * words / 512
+}
+
+/// Adapts a [`Tracer`] to revm's inspector interface
+pub(crate) struct TracerInspector<T> {
+    tracer: T,
+    pending: Option<TraceStep>,
+    pending_write: Option<StorageWrite>,
+    /// Memory size of the frame before the pending step
+    memory_len: usize,
+}
+
+impl<T: Tracer> TracerInspector<T> {
+    /// Wrap a tracer
+    pub(crate) fn new(tracer: T) -> Self {
+        Self {
+            tracer,
+            pending: None,
+            pending_write: None,
+            memory_len: 0,
+        }
+    }
+
+    /// Unwrap the tracer
+    pub(crate) fn into_tracer(self) -> T {
+        self.tracer
+    }
+
+    /// Report a returning frame
+    fn exit(&mut self, result: &InterpreterResult, created: Option<Address>) {
+        let error = if result.is_ok() {
+            None
+        } else if result.is_revert() {
+            Some(String::from("execution reverted"))
+        } else {
+            Some(format!("{:?}", result.result))
+        };
+        self.tracer.on_call_exit(&CallExit {
+            created,
+            gas_used: result.gas.spent(),
+            output: result.output.to_vec(),
+            error,
+        });
+    }
+}
+
+impl<T: Tracer, DB: Database> Inspector<DB> for TracerInspector<T> {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.pending = Some(TraceStep {
+            pc: interp.program_counter(),
+            opcode: interp.current_opcode(),
+            depth: context.journaled_state.depth,
+            gas_remaining: interp.gas.remaining(),
+            gas_cost: 0,
+            memory_gas: 0,
+            stack_top: interp.stack.peek(0).ok(),
+        });
+        self.memory_len = interp.shared_memory.len();
+
+        if interp.current_opcode() == SSTORE {
+            if let (Ok(slot), Ok(value)) = (interp.stack.peek(0), interp.stack.peek(1)) {
+                self.pending_write = Some(StorageWrite {
+                    address: interp.contract.target_address,
+                    slot,
+                    value,
+                    depth: context.journaled_state.depth,
+                });
+            }
+        }
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if let Some(mut step) = self.pending.take() {
+            step.gas_cost = step.gas_remaining.saturating_sub(interp.gas.remaining());
+            step.memory_gas = memory_cost(interp.shared_memory.len())
+                .saturating_sub(memory_cost(self.memory_len));
+            self.tracer.on_step(&step);
+        }
+        if let Some(write) = self.pending_write.take() {
+            // An SSTORE that ran out of gas or hit a static call wrote nothing
+            if !interp.instruction_result.is_error() {
+                self.tracer.on_storage_write(&write);
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let kind = match inputs.scheme {
+            CallScheme::CallCode => CallKind::CallCode,
+            CallScheme::DelegateCall => CallKind::DelegateCall,
+            CallScheme::StaticCall => CallKind::StaticCall,
+            // CALL, and the EOF call variants
+            _ => CallKind::Call,
+        };
+        self.tracer.on_call_enter(&CallFrame {
+            kind,
+            from: inputs.caller,
+            to: Some(inputs.target_address),
+            value: inputs.value.get(),
+            gas: inputs.gas_limit,
+            gas_used: 0,
+            input: inputs.input.to_vec(),
+            output: Vec::new(),
+            error: None,
+            calls: Vec::new(),
+        });
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.exit(&outcome.result, None);
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        let kind = match inputs.scheme {
+            CreateScheme::Create2 { .. } => CallKind::Create2,
+            _ => CallKind::Create,
+        };
+        self.tracer.on_call_enter(&CallFrame {
+            kind,
+            from: inputs.caller,
+            to: None,
+            value: inputs.value,
+            gas: inputs.gas_limit,
+            gas_used: 0,
+            input: inputs.init_code.to_vec(),
+            output: Vec::new(),
+            error: None,
+            calls: Vec::new(),
+        });
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        let created = outcome.address.filter(|_| outcome.result.is_ok());
+        self.exit(&outcome.result, created);
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(pc: usize, opcode: u8) -> TraceStep {
+        TraceStep {
+            pc,
+            opcode,
+            depth: 0,
+            gas_remaining: 100_000 - pc as Gas,
+            gas_cost: 3,
+            memory_gas: 0,
+            stack_top: None,
+        }
+    }
+
+    fn trace(steps: &[(usize, u8)]) -> ExecutionTrace {
+        ExecutionTrace {
+            steps: steps.iter().map(|(pc, op)| step(*pc, *op)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_identical_traces() {
+        let a = trace(&[(0, 0x60), (2, 0x60), (4, 0x55)]);
+        let diff = diff_traces(&a, &a.clone());
+
+        assert!(diff.is_identical());
+        assert_eq!(diff.identical_prefix_len, 3);
+        assert_eq!(diff.steps_only_in_a, 0);
+        assert_eq!(diff.steps_only_in_b, 0);
+    }
+
+    #[test]
+    fn test_divergent_traces() {
+        let a = trace(&[(0, 0x60), (2, 0x57), (3, 0x00)]);
+        let b = trace(&[(0, 0x60), (2, 0x57), (7, 0x5b), (8, 0x60), (10, 0x00)]);
+        let diff = diff_traces(&a, &b);
+
+        assert_eq!(diff.first_divergence, Some(2));
+        assert_eq!(diff.identical_prefix_len, 2);
+        assert_eq!(diff.steps_only_in_a, 1);
+        assert_eq!(diff.steps_only_in_b, 3);
+    }
+
+    #[test]
+    fn test_prefix_trace_diverges_at_end() {
+        let a = trace(&[(0, 0x60), (2, 0x60)]);
+        let b = trace(&[(0, 0x60)]);
+        let diff = diff_traces(&a, &b);
+
+        assert_eq!(diff.first_divergence, Some(1));
+        assert_eq!(diff.steps_only_in_a, 1);
+        assert_eq!(diff.steps_only_in_b, 0);
+    }
+
+    #[test]
+    fn test_gas_ignored_in_comparison() {
+        let a = trace(&[(0, 0x54)]);
+        let mut b = a.clone();
+        b.steps[0].gas_cost = 2100;
+
+        assert!(diff_traces(&a, &b).is_identical());
+    }
+
+    #[test]
+    fn test_gas_profiler_call_charges_own_cost() {
+        let mut profiler = GasProfiler::new();
+        let steps = [
+            // CALL forwarding 50_000 gas, raw cost includes the forwarded gas
+            (0, 0xf1, 0, 100_000, 52_600),
+            (0, 0x60, 1, 50_000, 3),
+            (2, 0x55, 1, 49_997, 20_000),
+            (3, 0x00, 1, 29_997, 0),
+            // Back in the caller: 29_997 unused gas was returned
+            (10, 0x50, 0, 77_397, 2),
+        ];
+        for (pc, opcode, depth, gas_remaining, gas_cost) in steps {
+            profiler.on_step(&TraceStep {
+                pc,
+                opcode,
+                depth,
+                gas_remaining,
+                gas_cost,
+                memory_gas: 0,
+                stack_top: None,
+            });
+        }
+
+        assert_eq!(profiler.opcodes()["CALL"], (1, 2600));
+        assert_eq!(
+            profiler.top_k_by_gas(2),
+            vec![("SSTORE", 1, 20_000), ("CALL", 1, 2600)]
+        );
+
+        let profile = profiler.into_profile();
+        assert_eq!(profile.total_gas(), 100_000 - 77_397 + 2);
+        assert_eq!(profile.opcodes.len(), 5);
+    }
+
+    #[test]
+    fn test_opcode_name() {
+        assert_eq!(step(0, 0x55).opcode_name(), "SSTORE");
+        assert_eq!(step(0, 0x0c).opcode_name(), "UNKNOWN");
+    }
+
+    #[test]
+    fn test_call_trace_geth_json() {
+        use crate::evm::ShadowExecutor;
+        use crate::input::{BlockEnv, ExecutionInput, TxInput};
+        use crate::state::{AccountState, InMemoryDB};
+
+        let caller = Address::repeat_byte(0x01);
+        let outer = Address::repeat_byte(0xaa);
+        let inner = Address::repeat_byte(0xbb);
+
+        // Returns 42 as one word:
+        // PUSH1 42, PUSH1 0, MSTORE, PUSH1 32, PUSH1 0, RETURN
+        let inner_code = vec![0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+        // CALLs `inner` with all gas and returns its output:
+        // PUSH1 32, PUSH1 0, PUSH1 0, PUSH1 0, PUSH1 0, PUSH20 inner, GAS,
+        // CALL, POP, PUSH1 32, PUSH1 0, RETURN
+        let mut outer_code = vec![0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00];
+        outer_code.push(0x73);
+        outer_code.extend_from_slice(inner.as_slice());
+        outer_code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(outer, AccountState::new_contract(outer_code, U256::ZERO));
+        state.insert_account(inner, AccountState::new_contract(inner_code, U256::ZERO));
+
+        let tx = TxInput::call(caller, outer, vec![0xab]).with_gas_limit(100_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx, state);
+        let (_, _, trace) = ShadowExecutor::execute_traced(input, CallTrace::new()).unwrap();
+
+        let word = format!("0x{:064x}", 42);
+        let json = trace.to_geth_json();
+        assert_eq!(json["type"], "CALL");
+        assert_eq!(json["from"], "0x0101010101010101010101010101010101010101");
+        assert_eq!(json["to"], "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+        assert_eq!(json["value"], "0x0");
+        assert_eq!(json["input"], "0xab");
+        assert_eq!(json["output"], word.as_str());
+
+        let calls = json["calls"].as_array().unwrap();
+        assert_eq!(calls.len(), 1);
+        let call = &calls[0];
+        assert_eq!(call["type"], "CALL");
+        assert_eq!(call["from"], "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+        assert_eq!(call["to"], "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+        assert_eq!(call["input"], "0x");
+        assert_eq!(call["output"], word.as_str());
+        assert!(call.get("calls").is_none());
+        assert!(call.get("error").is_none());
+
+        // Nested gas is part of the outer frame's
+        let root = trace.root.unwrap();
+        assert!(root.gas_used > root.calls[0].gas_used);
+        assert_eq!(
+            call["gasUsed"],
+            format!("{:#x}", root.calls[0].gas_used).as_str()
+        );
+    }
+}
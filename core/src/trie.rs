@@ -0,0 +1,632 @@
+//! Merkle Patricia Trie for Shadow-EVM
+//!
+//! Minimal, `no_std` implementation of Ethereum's hexary Merkle Patricia Trie
+//! and the RLP encoding it relies on. Roots and inclusion proofs are
+//! supported; the trie is rebuilt from a sorted key set rather than mutated
+//! in place.
+//!
+//! Keys are expected to be pre-hashed (secure trie): accounts are keyed by
+//! `keccak256(address)` and storage slots by `keccak256(slot)`, so the
+//! iteration order of the trie is the order of the hashed keys, not of the
+//! raw addresses or slot numbers.
+
+use crate::errors::{Result, ShadowEvmError};
+use crate::hashing::keccak256;
+use crate::types::{Address, Bytes, Hash, U256};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use alloy_primitives::U64;
+use revm::primitives::KECCAK_EMPTY;
+use serde::{Deserialize, Serialize};
+
+extern crate alloc;
+
+/// Root of an empty trie: `keccak256(rlp(""))`
+pub const EMPTY_ROOT: Hash = Hash::new([
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
+
+/// Minimal RLP encoding helpers
+pub mod rlp {
+    use super::*;
+
+    /// Encode a byte string
+    pub fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+        if bytes.len() == 1 && bytes[0] < 0x80 {
+            return bytes.to_vec();
+        }
+        let mut out = encode_header(0x80, bytes.len());
+        out.extend_from_slice(bytes);
+        out
+    }
+
+    /// Encode a list from already-encoded items
+    pub fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let payload_len = items.iter().map(Vec::len).sum();
+        let mut out = encode_header(0xc0, payload_len);
+        for item in items {
+            out.extend_from_slice(item);
+        }
+        out
+    }
+
+    /// Encode an unsigned integer (big-endian, no leading zeros)
+    pub fn encode_u64(value: u64) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+        encode_bytes(&bytes[start..])
+    }
+
+    /// Encode a 256-bit unsigned integer (big-endian, no leading zeros)
+    pub fn encode_u256(value: U256) -> Vec<u8> {
+        let bytes = value.to_be_bytes::<32>();
+        let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+        encode_bytes(&bytes[start..])
+    }
+
+    /// Split the first item off `input`
+    ///
+    /// Returns whether the item is a list, its payload and the bytes after
+    /// it, or `None` if `input` does not start with a complete item.
+    pub fn decode_item(input: &[u8]) -> Option<(bool, &[u8], &[u8])> {
+        let first = *input.first()?;
+        let (is_list, header_len, payload_len) = match first {
+            0x00..=0x7f => return Some((false, &input[..1], &input[1..])),
+            0x80..=0xb7 => (false, 1, usize::from(first - 0x80)),
+            0xb8..=0xbf => decode_long_header(input, false, first - 0xb7)?,
+            0xc0..=0xf7 => (true, 1, usize::from(first - 0xc0)),
+            0xf8..=0xff => decode_long_header(input, true, first - 0xf7)?,
+        };
+        let end = header_len.checked_add(payload_len)?;
+        let payload = input.get(header_len..end)?;
+        Some((is_list, payload, &input[end..]))
+    }
+
+    /// Split an encoded list into the encodings of its items
+    ///
+    /// Returns `None` unless `input` is exactly one well-formed list.
+    pub fn decode_list(input: &[u8]) -> Option<Vec<&[u8]>> {
+        let (is_list, mut payload, rest) = decode_item(input)?;
+        if !is_list || !rest.is_empty() {
+            return None;
+        }
+        let mut items = Vec::new();
+        while !payload.is_empty() {
+            let (_, _, tail) = decode_item(payload)?;
+            items.push(&payload[..payload.len() - tail.len()]);
+            payload = tail;
+        }
+        Some(items)
+    }
+
+    /// Payload of an encoded byte string, or `None` for a list
+    pub fn decode_bytes(item: &[u8]) -> Option<&[u8]> {
+        match decode_item(item)? {
+            (false, payload, []) => Some(payload),
+            _ => None,
+        }
+    }
+
+    /// Header length and payload length of a long string or list
+    fn decode_long_header(
+        input: &[u8],
+        is_list: bool,
+        len_len: u8,
+    ) -> Option<(bool, usize, usize)> {
+        let len_len = usize::from(len_len);
+        let len_bytes = input.get(1..1 + len_len)?;
+        if len_len > 8 {
+            return None;
+        }
+        let mut buf = [0u8; 8];
+        buf[8 - len_len..].copy_from_slice(len_bytes);
+        let len = usize::try_from(u64::from_be_bytes(buf)).ok()?;
+        Some((is_list, 1 + len_len, len))
+    }
+
+    /// Encode a string or list header for a payload of `len` bytes
+    fn encode_header(offset: u8, len: usize) -> Vec<u8> {
+        if len <= 55 {
+            return alloc::vec![offset + len as u8];
+        }
+        let len_bytes = (len as u64).to_be_bytes();
+        let start = len_bytes.iter().position(|b| *b != 0).unwrap_or(7);
+        let mut out = alloc::vec![offset + 55 + (8 - start) as u8];
+        out.extend_from_slice(&len_bytes[start..]);
+        out
+    }
+}
+
+/// Compute the root of a trie whose keys are already hashed
+///
+/// `entries` maps each 32-byte trie key to its RLP-encoded value.
+pub fn secure_trie_root(entries: &BTreeMap<Hash, Vec<u8>>) -> Hash {
+    if entries.is_empty() {
+        return EMPTY_ROOT;
+    }
+    let leaves: Vec<(Vec<u8>, &[u8])> = entries
+        .iter()
+        .map(|(key, value)| (to_nibbles(key.as_slice()), value.as_slice()))
+        .collect();
+    keccak256(&encode_node(&leaves, 0))
+}
+
+/// Compute the root of a trie keyed by raw byte strings
+///
+/// Unlike in a secure trie, keys may have any length and one may be a
+/// prefix of another, as in the transaction and receipt tries.
+pub fn trie_root(entries: &BTreeMap<Vec<u8>, Vec<u8>>) -> Hash {
+    if entries.is_empty() {
+        return EMPTY_ROOT;
+    }
+    let leaves: Vec<(Vec<u8>, &[u8])> = entries
+        .iter()
+        .map(|(key, value)| (to_nibbles(key), value.as_slice()))
+        .collect();
+    keccak256(&encode_node(&leaves, 0))
+}
+
+/// Build an inclusion (or exclusion) proof for `key`
+///
+/// Returns the RLP-encoded nodes on the path to `key`, root first, in the
+/// format of `eth_getProof`: nodes embedded in their parent (shorter than
+/// 32 bytes) are not listed separately. The proof of an empty trie is empty.
+pub fn secure_trie_proof(entries: &BTreeMap<Hash, Vec<u8>>, key: &Hash) -> Vec<Vec<u8>> {
+    let leaves: Vec<(Vec<u8>, &[u8])> = entries
+        .iter()
+        .map(|(key, value)| (to_nibbles(key.as_slice()), value.as_slice()))
+        .collect();
+    let path = to_nibbles(key.as_slice());
+
+    let mut proof = Vec::new();
+    let mut group = leaves.as_slice();
+    let mut depth = 0;
+    while !group.is_empty() {
+        let node = encode_node(group, depth);
+        if proof.is_empty() || node.len() >= 32 {
+            proof.push(node);
+        }
+        if group.len() == 1 {
+            break;
+        }
+
+        let shared = shared_prefix(group, depth);
+        if shared > 0 {
+            if path[depth..depth + shared] != group[0].0[depth..depth + shared] {
+                break;
+            }
+            depth += shared;
+        } else {
+            let start = group.partition_point(|(key, _)| key[depth] < path[depth]);
+            let end = group.partition_point(|(key, _)| key[depth] <= path[depth]);
+            group = &group[start..end];
+            depth += 1;
+        }
+    }
+    proof
+}
+
+/// Check a proof built by [`secure_trie_proof`] against `root`
+///
+/// Returns the value stored under `key`, or `None` if the proof shows
+/// that `key` is absent.
+///
+/// # Errors
+/// Returns `ShadowEvmError::InvalidTrieProof` if a node is malformed or
+/// missing, or does not hash to the reference in its parent.
+pub fn verify_secure_trie_proof<N: AsRef<[u8]>>(
+    root: Hash,
+    key: &Hash,
+    proof: &[N],
+) -> Result<Option<Vec<u8>>> {
+    if root == EMPTY_ROOT {
+        return Ok(None);
+    }
+    let malformed = || ShadowEvmError::InvalidTrieProof("malformed node");
+    let path = to_nibbles(key.as_slice());
+    let mut nodes = proof.iter().map(AsRef::as_ref);
+    let mut node = hashed_node(&mut nodes, root)?;
+    let mut depth = 0;
+
+    loop {
+        let items = rlp::decode_list(node).ok_or_else(malformed)?;
+        let child = match items.len() {
+            17 => {
+                let nibble = *path.get(depth).ok_or_else(malformed)?;
+                depth += 1;
+                items[usize::from(nibble)]
+            }
+            2 => {
+                let encoded_path = rlp::decode_bytes(items[0]).ok_or_else(malformed)?;
+                let (nibbles, is_leaf) = decode_hex_prefix(encoded_path).ok_or_else(malformed)?;
+                let rest = &path[depth..];
+                if is_leaf {
+                    if rest != nibbles.as_slice() {
+                        return Ok(None);
+                    }
+                    let value = rlp::decode_bytes(items[1]).ok_or_else(malformed)?;
+                    return Ok(Some(value.to_vec()));
+                }
+                if !rest.starts_with(&nibbles) {
+                    return Ok(None);
+                }
+                depth += nibbles.len();
+                items[1]
+            }
+            _ => return Err(malformed()),
+        };
+
+        // A child is either embedded, absent, or referenced by hash
+        let (is_list, payload, _) = rlp::decode_item(child).ok_or_else(malformed)?;
+        if is_list {
+            node = child;
+        } else if payload.is_empty() {
+            return Ok(None);
+        } else if payload.len() == 32 {
+            node = hashed_node(&mut nodes, Hash::from_slice(payload))?;
+        } else {
+            return Err(malformed());
+        }
+    }
+}
+
+/// Take the next proof node, which must hash to `expected`
+fn hashed_node<'a>(nodes: &mut impl Iterator<Item = &'a [u8]>, expected: Hash) -> Result<&'a [u8]> {
+    let node = nodes
+        .next()
+        .ok_or(ShadowEvmError::InvalidTrieProof("missing node"))?;
+    if keccak256(node) != expected {
+        return Err(ShadowEvmError::InvalidTrieProof("node hash mismatch"));
+    }
+    Ok(node)
+}
+
+/// Proof of one account and its storage, as returned by `eth_getProof`
+///
+/// `account_proof` lists the RLP-encoded trie nodes from the MPT state root
+/// to the account, root first, and each `storage_proof` entry those from
+/// `storage_hash` to its slot. An account missing from the state is proven
+/// absent, with the fields of an empty account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageProof {
+    /// Account proven
+    pub address: Address,
+    /// Account balance in wei
+    pub balance: U256,
+    /// Account nonce, a hex quantity in JSON
+    pub nonce: U64,
+    /// Hash of the account's code
+    pub code_hash: Hash,
+    /// Root of the account's storage trie
+    pub storage_hash: Hash,
+    /// Account trie nodes on the path to `address`
+    pub account_proof: Vec<Bytes>,
+    /// Proofs of the requested storage slots
+    pub storage_proof: Vec<SlotProof>,
+}
+
+/// Proof of one storage slot, an entry of `StorageProof::storage_proof`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotProof {
+    /// Slot number
+    pub key: U256,
+    /// Value of the slot, zero if unset
+    pub value: U256,
+    /// Storage trie nodes on the path to `key`
+    pub proof: Vec<Bytes>,
+}
+
+/// RLP encoding of an account as stored in the state trie
+///
+/// `[nonce, balance, storage_root, code_hash]`
+pub fn encode_account(nonce: u64, balance: U256, storage_root: Hash, code_hash: Hash) -> Vec<u8> {
+    rlp::encode_list(&[
+        rlp::encode_u64(nonce),
+        rlp::encode_u256(balance),
+        rlp::encode_bytes(storage_root.as_slice()),
+        rlp::encode_bytes(code_hash.as_slice()),
+    ])
+}
+
+/// Check a storage proof against an MPT state root
+///
+/// `root` is the state root as computed by
+/// [`InMemoryDB::compute_state_root_mpt`](crate::state::InMemoryDB::compute_state_root_mpt)
+/// or found in a block header. The account fields and every entry of
+/// `proof.storage_proof` are checked, one of which must be for `slot`. A
+/// slot proven absent verifies with a zero `value`.
+///
+/// # Errors
+/// Returns `ShadowEvmError::InvalidTrieProof` if a proof is malformed, and
+/// `ShadowEvmError::InvalidStateProof` if the proof is for another address,
+/// has no entry for `slot`, or does not prove the fields and values it
+/// claims.
+pub fn verify_storage_proof(
+    root: Hash,
+    address: Address,
+    slot: U256,
+    proof: &StorageProof,
+) -> Result<()> {
+    let invalid = || ShadowEvmError::InvalidStateProof { address };
+    if proof.address != address || !proof.storage_proof.iter().any(|entry| entry.key == slot) {
+        return Err(invalid());
+    }
+
+    // An absent account must claim the fields of an empty one
+    let account =
+        verify_secure_trie_proof(root, &keccak256(address.as_slice()), &proof.account_proof)?
+            .unwrap_or_else(|| encode_account(0, U256::ZERO, EMPTY_ROOT, KECCAK_EMPTY));
+    let claimed = encode_account(
+        proof.nonce.to(),
+        proof.balance,
+        proof.storage_hash,
+        proof.code_hash,
+    );
+    if account != claimed {
+        return Err(invalid());
+    }
+
+    for entry in &proof.storage_proof {
+        let value = match verify_secure_trie_proof(
+            proof.storage_hash,
+            &keccak256(&entry.key.to_be_bytes::<32>()),
+            &entry.proof,
+        )? {
+            Some(encoded) => rlp::decode_bytes(&encoded)
+                .and_then(U256::try_from_be_slice)
+                .ok_or_else(invalid)?,
+            None => U256::ZERO,
+        };
+        if value != entry.value {
+            return Err(invalid());
+        }
+    }
+    Ok(())
+}
+
+/// Split bytes into nibbles (high nibble first)
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+/// Hex-prefix encoding of a nibble path (Yellow Paper, appendix C)
+fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 };
+    let mut out = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        out.push(((flag + 1) << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        out.push(flag << 4);
+        nibbles
+    };
+    for pair in rest.chunks(2) {
+        out.push((pair[0] << 4) | pair[1]);
+    }
+    out
+}
+
+/// Inverse of [`hex_prefix`]: the nibble path and whether it ends in a leaf
+fn decode_hex_prefix(encoded: &[u8]) -> Option<(Vec<u8>, bool)> {
+    let (first, rest) = encoded.split_first()?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return None;
+    }
+    let mut nibbles = if flag & 1 == 1 {
+        alloc::vec![first & 0x0f]
+    } else if first & 0x0f == 0 {
+        Vec::new()
+    } else {
+        return None;
+    };
+    nibbles.extend(to_nibbles(rest));
+    Some((nibbles, flag & 2 == 2))
+}
+
+/// Number of nibbles after `depth` shared by all of `leaves` (sorted)
+fn shared_prefix(leaves: &[(Vec<u8>, &[u8])], depth: usize) -> usize {
+    // Leaves are sorted, so the common prefix of all is that of first and last
+    let first = &leaves[0].0;
+    let last = &leaves[leaves.len() - 1].0;
+    first[depth..]
+        .iter()
+        .zip(&last[depth..])
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Reference a child node: inline if shorter than 32 bytes, hashed otherwise
+fn node_ref(encoded: Vec<u8>) -> Vec<u8> {
+    if encoded.len() < 32 {
+        encoded
+    } else {
+        rlp::encode_bytes(keccak256(&encoded).as_slice())
+    }
+}
+
+/// RLP-encode the node covering `leaves` (sorted, sharing the first `depth` nibbles)
+///
+/// Keys are distinct but may be prefixes of each other.
+fn encode_node(leaves: &[(Vec<u8>, &[u8])], depth: usize) -> Vec<u8> {
+    if let [(key, value)] = leaves {
+        return rlp::encode_list(&[
+            rlp::encode_bytes(&hex_prefix(&key[depth..], true)),
+            rlp::encode_bytes(value),
+        ]);
+    }
+
+    let shared = shared_prefix(leaves, depth);
+    if shared > 0 {
+        let first = &leaves[0].0;
+        let child = encode_node(leaves, depth + shared);
+        return rlp::encode_list(&[
+            rlp::encode_bytes(&hex_prefix(&first[depth..depth + shared], false)),
+            node_ref(child),
+        ]);
+    }
+
+    // A key ending here sorts first and goes in the branch's value slot
+    let (value, mut rest) = match leaves {
+        [(key, value), tail @ ..] if key.len() == depth => (*value, tail),
+        _ => (&[][..], leaves),
+    };
+    let mut items = Vec::with_capacity(17);
+    for nibble in 0..16u8 {
+        let count = rest
+            .iter()
+            .take_while(|(key, _)| key[depth] == nibble)
+            .count();
+        let (group, tail) = rest.split_at(count);
+        rest = tail;
+        items.push(if group.is_empty() {
+            rlp::encode_bytes(&[])
+        } else {
+            node_ref(encode_node(group, depth + 1))
+        });
+    }
+    items.push(rlp::encode_bytes(value));
+    rlp::encode_list(&items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_root() {
+        let expected: Hash = "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+            .parse()
+            .unwrap();
+        assert_eq!(EMPTY_ROOT, expected);
+        assert_eq!(EMPTY_ROOT, keccak256(&rlp::encode_bytes(&[])));
+        assert_eq!(secure_trie_root(&BTreeMap::new()), EMPTY_ROOT);
+        assert_eq!(trie_root(&BTreeMap::new()), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn test_known_root() {
+        // Keys that prefix each other, from the Ethereum trie test vectors
+        let entries: BTreeMap<Vec<u8>, Vec<u8>> = [
+            ("do", "verb"),
+            ("dog", "puppy"),
+            ("doge", "coin"),
+            ("horse", "stallion"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.as_bytes().to_vec(), value.as_bytes().to_vec()))
+        .collect();
+        let expected: Hash = "0x5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"
+            .parse()
+            .unwrap();
+        assert_eq!(trie_root(&entries), expected);
+    }
+
+    #[test]
+    fn test_rlp_encoding() {
+        assert_eq!(rlp::encode_bytes(&[0x7f]), vec![0x7f]);
+        assert_eq!(rlp::encode_bytes(&[0x80]), vec![0x81, 0x80]);
+        assert_eq!(rlp::encode_u64(0), vec![0x80]);
+        assert_eq!(rlp::encode_u64(1024), vec![0x82, 0x04, 0x00]);
+        assert_eq!(rlp::encode_u256(U256::from(15u64)), vec![0x0f]);
+
+        let long = [0xaa; 56];
+        let encoded = rlp::encode_bytes(&long);
+        assert_eq!(&encoded[..2], &[0xb8, 56]);
+        assert_eq!(encoded.len(), 58);
+
+        // ["cat", "dog"]
+        let list = rlp::encode_list(&[rlp::encode_bytes(b"cat"), rlp::encode_bytes(b"dog")]);
+        assert_eq!(
+            list,
+            vec![0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']
+        );
+    }
+
+    #[test]
+    fn test_hex_prefix() {
+        assert_eq!(hex_prefix(&[1, 2, 3, 4, 5], false), vec![0x11, 0x23, 0x45]);
+        assert_eq!(
+            hex_prefix(&[0, 1, 2, 3, 4, 5], false),
+            vec![0x00, 0x01, 0x23, 0x45]
+        );
+        assert_eq!(
+            hex_prefix(&[0x0f, 1, 0x0c, 0x0b, 8], true),
+            vec![0x3f, 0x1c, 0xb8]
+        );
+        assert_eq!(
+            hex_prefix(&[0, 0x0f, 1, 0x0c, 0x0b, 8], true),
+            vec![0x20, 0x0f, 0x1c, 0xb8]
+        );
+    }
+
+    #[test]
+    fn test_rlp_decoding() {
+        let long = [0xaa; 56];
+        let list = rlp::encode_list(&[
+            rlp::encode_bytes(&[0x7f]),
+            rlp::encode_bytes(b"dog"),
+            rlp::encode_bytes(&long),
+        ]);
+        let items = rlp::decode_list(&list).unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(rlp::decode_bytes(items[0]), Some(&[0x7f][..]));
+        assert_eq!(rlp::decode_bytes(items[1]), Some(&b"dog"[..]));
+        assert_eq!(rlp::decode_bytes(items[2]), Some(&long[..]));
+
+        assert_eq!(rlp::decode_list(&list[..list.len() - 1]), None);
+        assert_eq!(rlp::decode_bytes(&list), None);
+    }
+
+    #[test]
+    fn test_proof_roundtrip() {
+        let entries: BTreeMap<Hash, Vec<u8>> = (0u8..40)
+            .map(|i| (keccak256(&[i]), rlp::encode_u64(u64::from(i) + 1)))
+            .collect();
+        let root = secure_trie_root(&entries);
+
+        for (key, value) in &entries {
+            let proof = secure_trie_proof(&entries, key);
+            assert_eq!(
+                verify_secure_trie_proof(root, key, &proof)
+                    .unwrap()
+                    .as_ref(),
+                Some(value)
+            );
+        }
+        for i in 40u8..60 {
+            let key = keccak256(&[i]);
+            let proof = secure_trie_proof(&entries, &key);
+            assert_eq!(verify_secure_trie_proof(root, &key, &proof).unwrap(), None);
+        }
+
+        // Proofs against another root, or with a node missing, are rejected
+        let key = keccak256(&[0]);
+        let mut proof = secure_trie_proof(&entries, &key);
+        assert!(verify_secure_trie_proof(Hash::repeat_byte(0xab), &key, &proof).is_err());
+        proof.pop();
+        assert!(verify_secure_trie_proof(root, &key, &proof).is_err());
+
+        assert!(secure_trie_proof(&BTreeMap::new(), &key).is_empty());
+    }
+
+    #[test]
+    fn test_root_independent_of_insertion_order() {
+        let keys: Vec<Hash> = (0u8..20).map(|i| keccak256(&[i])).collect();
+
+        let forward: BTreeMap<Hash, Vec<u8>> = keys
+            .iter()
+            .map(|k| (*k, rlp::encode_bytes(k.as_slice())))
+            .collect();
+        let backward: BTreeMap<Hash, Vec<u8>> = keys
+            .iter()
+            .rev()
+            .map(|k| (*k, rlp::encode_bytes(k.as_slice())))
+            .collect();
+
+        assert_eq!(secure_trie_root(&forward), secure_trie_root(&backward));
+        assert_ne!(secure_trie_root(&forward), EMPTY_ROOT);
+    }
+}
@@ -5,6 +5,9 @@
 
 pub use alloy_primitives::{Address, B256, Bytes, U256};
 
+/// EVM opcode, e.g. `Opcode::SELFDESTRUCT`
+pub use revm::interpreter::OpCode as Opcode;
+
 /// 32-byte hash (Keccak256 output)
 pub type Hash = B256;
 
@@ -0,0 +1,148 @@
+//! Execution witnesses for Shadow-EVM
+//!
+//! The pre-state sent to the prover is the witness of an execution, and its
+//! size drives proving cost. This module records which parts of the
+//! pre-state an execution actually read, reports where the witness bytes
+//! go, and can drop everything the execution never touched.
+
+use crate::codec;
+use crate::errors::Result;
+use crate::evm::ShadowExecutor;
+use crate::input::ExecutionInput;
+use crate::state::InMemoryDB;
+use crate::types::{Address, U256};
+use alloc::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
+
+extern crate alloc;
+
+/// Size breakdown of a witness
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WitnessStats {
+    /// Accounts in the pre-state
+    pub account_count: usize,
+    /// Storage slots across all accounts
+    pub total_storage_slots: usize,
+    /// Contract code across all accounts
+    pub total_code_bytes: usize,
+    /// Estimated serialized size of the pre-state
+    pub estimated_witness_bytes: usize,
+}
+
+/// Pre-state of an execution together with the parts it accessed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionWitness {
+    /// State the execution runs against
+    pub pre_state: InMemoryDB,
+    /// Accounts loaded during execution
+    pub accessed_accounts: BTreeSet<Address>,
+    /// Storage slots loaded during execution
+    pub accessed_slots: BTreeSet<(Address, U256)>,
+}
+
+impl ExecutionWitness {
+    /// Execute `input` natively and record what it accessed
+    ///
+    /// Includes accounts that were only read, as well as the caller,
+    /// target and coinbase.
+    pub fn from_input(input: &ExecutionInput) -> Result<Self> {
+        let (raw, _) = ShadowExecutor::execute_raw(input.clone())?;
+
+        let mut accessed_accounts = BTreeSet::new();
+        let mut accessed_slots = BTreeSet::new();
+        for (address, account) in &raw.state {
+            accessed_accounts.insert(*address);
+            accessed_slots.extend(account.storage.keys().map(|slot| (*address, *slot)));
+        }
+
+        Ok(Self {
+            pre_state: input.pre_state.clone(),
+            accessed_accounts,
+            accessed_slots,
+        })
+    }
+
+    /// Size breakdown of the pre-state
+    pub fn stats(&self) -> WitnessStats {
+        let accounts = self.pre_state.accounts.values();
+
+        WitnessStats {
+            account_count: self.pre_state.account_count(),
+            total_storage_slots: accounts.clone().map(|account| account.storage.len()).sum(),
+            total_code_bytes: accounts.map(|account| account.code.len()).sum(),
+            estimated_witness_bytes: codec::estimated_serialized_bytes(&self.pre_state),
+        }
+    }
+
+    /// Remove accounts and slots that were not accessed from the pre-state
+    ///
+    /// The execution result is unchanged, but the pre-state root (and so
+    /// the commitment) is that of the shrunk state. Shrink before proving,
+    /// not after.
+    pub fn shrink_to_accessed(&mut self) {
+        let accessed_accounts = &self.accessed_accounts;
+        let accessed_slots = &self.accessed_slots;
+
+        self.pre_state
+            .accounts
+            .retain(|address, _| accessed_accounts.contains(address));
+        for (address, account) in self.pre_state.accounts.iter_mut() {
+            account
+                .storage
+                .retain(|slot, _| accessed_slots.contains(&(*address, *slot)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{BlockEnv, TxInput};
+    use crate::state::AccountState;
+
+    #[test]
+    fn test_shrink_to_accessed() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+        let bystander = Address::repeat_byte(0xbb);
+
+        // PUSH1 1, SLOAD, STOP: reads slot 1 only
+        let mut code_account = AccountState::new_contract(vec![0x60, 0x01, 0x54, 0x00], U256::ZERO);
+        code_account.set_storage(U256::from(1u64), U256::from(11u64));
+        code_account.set_storage(U256::from(2u64), U256::from(22u64));
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        state.insert_account(contract, code_account);
+        state.insert_account(
+            bystander,
+            AccountState::new_contract(vec![0x00; 100], U256::ZERO),
+        );
+
+        let tx = TxInput::call(caller, contract, vec![]).with_gas_limit(100_000);
+        let input = ExecutionInput::new(BlockEnv::default(), tx.clone(), state);
+
+        let mut witness = ExecutionWitness::from_input(&input).unwrap();
+        let before = witness.stats();
+        assert_eq!(before.account_count, 3);
+        assert_eq!(before.total_storage_slots, 2);
+        assert_eq!(before.total_code_bytes, 104);
+
+        witness.shrink_to_accessed();
+        let after = witness.stats();
+        assert_eq!(after.account_count, 2);
+        assert_eq!(after.total_storage_slots, 1);
+        assert_eq!(after.total_code_bytes, 4);
+        assert!(after.estimated_witness_bytes < before.estimated_witness_bytes);
+
+        // The shrunk witness executes identically
+        let (full, _) = ShadowExecutor::execute(input).unwrap();
+        let shrunk_input = ExecutionInput::new(BlockEnv::default(), tx, witness.pre_state);
+        let (shrunk, _) = ShadowExecutor::execute(shrunk_input).unwrap();
+        assert_eq!(shrunk.gas_used, full.gas_used);
+        assert_eq!(shrunk.status, full.status);
+    }
+}
@@ -0,0 +1,140 @@
+//! Determinism checks for execution and commitment
+//!
+//! A proof is only reproducible if the same input always serializes,
+//! executes and hashes to the same bytes. These tests run a mix of
+//! transfers, deployments and contract calls and compare every serialized
+//! input, output and commitment:
+//!
+//! - across repeated runs in one process, and
+//! - across fresh processes, by re-running this test binary as a child.
+//!
+//! A fresh process gets a fresh heap layout, so a dependency on pointer
+//! addresses or on the iteration order of a randomly seeded collection
+//! shows up as a mismatch. For uninitialized memory, run the in-process
+//! checks under Miri:
+//!
+//! ```text
+//! cargo +nightly miri test -p shadow-evm-core --test determinism
+//! ```
+
+use shadow_evm_core::keccak256;
+use shadow_evm_core::prelude::*;
+use std::process::Command;
+
+/// Set in the child process to make `child_prints_digests` report
+const CHILD_ENV: &str = "SHADOW_EVM_DETERMINISM_CHILD";
+
+/// Prefix of the lines the child reports digests on
+const DIGEST_PREFIX: &str = "digest: ";
+
+/// Inputs covering transfers, deployments and contract calls
+fn scenarios() -> Vec<ExecutionInput> {
+    let alice = Address::repeat_byte(0x01);
+    let bob = Address::repeat_byte(0x02);
+    let contract = Address::repeat_byte(0xcc);
+
+    let mut state = InMemoryDB::new();
+    state.insert_account(
+        alice,
+        AccountState::new_with_balance(U256::from(10u128.pow(18))),
+    );
+    state.insert_account(bob, AccountState::new_with_balance(U256::from(5u64)));
+    // Stores CALLVALUE at slot 1, logs it and returns it:
+    // CALLVALUE, PUSH1 1, SSTORE, CALLVALUE, PUSH1 0, MSTORE,
+    // PUSH1 32, PUSH1 0, LOG0, PUSH1 32, PUSH1 0, RETURN
+    state.insert_account(
+        contract,
+        AccountState::new_contract(
+            vec![
+                0x34, 0x60, 0x01, 0x55, 0x34, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xa0, 0x60,
+                0x20, 0x60, 0x00, 0xf3,
+            ],
+            U256::ZERO,
+        ),
+    );
+
+    // Deploys a one-byte STOP contract:
+    // PUSH1 0, PUSH1 0, MSTORE8, PUSH1 1, PUSH1 0, RETURN
+    let init_code = vec![0x60, 0x00, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3];
+
+    let txs = [
+        TxInput::transfer(alice, bob, U256::from(1000u64)),
+        TxInput::create(alice, init_code, U256::ZERO).with_gas_limit(100_000),
+        TxInput::call(alice, contract, vec![0xde, 0xad]).with_gas_limit(100_000),
+        TxInput {
+            value: U256::from(7u64),
+            ..TxInput::call(alice, contract, vec![]).with_gas_limit(100_000)
+        },
+    ];
+
+    txs.into_iter()
+        .map(|tx| ExecutionInput::new(BlockEnv::default(), tx, state.clone()))
+        .collect()
+}
+
+/// Serialized input, output and commitment of every scenario
+fn run_scenarios() -> Vec<Vec<u8>> {
+    scenarios()
+        .into_iter()
+        .flat_map(|input| {
+            let input_bytes = input.serialize_auto().unwrap();
+            let (output, commitment) = ShadowExecutor::execute(input).unwrap();
+            [
+                input_bytes,
+                output.serialize_auto().unwrap(),
+                bincode::serialize(&commitment).unwrap(),
+            ]
+        })
+        .collect()
+}
+
+/// One hex digest per serialized artifact
+fn digests(artifacts: &[Vec<u8>]) -> Vec<String> {
+    artifacts
+        .iter()
+        .map(|bytes| hex::encode(keccak256(bytes)))
+        .collect()
+}
+
+#[test]
+fn test_identical_across_runs() {
+    let first = run_scenarios();
+    for _ in 0..3 {
+        assert_eq!(run_scenarios(), first);
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri cannot spawn processes
+fn test_identical_across_processes() {
+    let expected = digests(&run_scenarios());
+
+    for _ in 0..2 {
+        let child = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "child_prints_digests", "--nocapture"])
+            .env(CHILD_ENV, "1")
+            .output()
+            .unwrap();
+        assert!(child.status.success(), "child process failed");
+
+        let stdout = String::from_utf8(child.stdout).unwrap();
+        let reported: Vec<String> = stdout
+            .lines()
+            // The test harness may print the test name on the same line
+            .filter_map(|line| line.split_once(DIGEST_PREFIX))
+            .map(|(_, digest)| String::from(digest))
+            .collect();
+        assert_eq!(reported, expected);
+    }
+}
+
+/// Reports digests when run as the child of `test_identical_across_processes`
+#[test]
+fn child_prints_digests() {
+    if std::env::var_os(CHILD_ENV).is_none() {
+        return;
+    }
+    for digest in digests(&run_scenarios()) {
+        println!("{DIGEST_PREFIX}{digest}");
+    }
+}
@@ -1,9 +1,12 @@
 //! ERC20 Token Transfer Example
 //!
-//! Demonstrates calling an ERC20 contract's transfer function.
-//! Note: Uses minimal bytecode for demonstration purposes.
+//! Demonstrates calling an ERC20 contract's transfer function, using the
+//! minimal token from `shadow_evm_core::testing::contracts`.
 
 use shadow_evm_core::prelude::*;
+use shadow_evm_core::testing::contracts::{
+    erc20_balance_slot, erc20_with_balances, transfer_calldata,
+};
 
 fn main() {
     println!("Shadow-EVM: ERC20 Transfer\n");
@@ -21,17 +24,14 @@ fn main() {
         AccountState::new_with_balance(U256::from(10u128.pow(18))),
     );
 
-    // Token contract with storage
-    let mut token_acc = AccountState::new_with_balance(U256::ZERO);
-    token_acc.code = vec![0x60, 0x01, 0xf3]; // minimal bytecode
-    token_acc.set_storage(U256::from(1), U256::from(1_000_000)); // balance slot
-    state.insert_account(token, token_acc);
+    // Token contract holding the sender's token balance
+    state.insert_account(
+        token,
+        erc20_with_balances(&[(sender, U256::from(1_000_000u64))]),
+    );
 
-    // Build transfer calldata: transfer(address,uint256)
-    let mut calldata = vec![0xa9, 0x05, 0x9c, 0xbb]; // function selector
-    calldata.extend_from_slice(&[0u8; 12]);
-    calldata.extend_from_slice(recipient.as_slice());
-    calldata.extend_from_slice(&U256::from(1000u64).to_be_bytes::<32>());
+    // transfer(recipient, 1000)
+    let calldata = transfer_calldata(recipient, U256::from(1000u64));
 
     let input = ExecutionInput::new(
         BlockEnv::default(),
@@ -56,11 +56,17 @@ fn main() {
         }
     );
     println!("Gas used:   {}", output.gas_used);
+    println!("Logs:       {}", output.logs.len());
     println!(
         "Commitment: 0x{}",
         hex::encode(commitment.commitment.as_slice())
     );
 
-    println!("\nNote: Minimal bytecode returns immediately.");
-    println!("Deploy real ERC20 bytecode for full functionality.");
+    // Token balances live in the token's storage
+    let sender_bal = output.storage_of(token, erc20_balance_slot(sender));
+    let recipient_bal = output.storage_of(token, erc20_balance_slot(recipient));
+
+    println!("\n--- Final Token Balances ---");
+    println!("Sender:    {}", sender_bal);
+    println!("Recipient: {}", recipient_bal);
 }
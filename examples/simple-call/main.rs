@@ -54,18 +54,8 @@ fn main() {
     );
 
     // Show balances
-    let sender_bal = output
-        .post_state
-        .accounts
-        .get(&sender)
-        .map(|a| a.balance)
-        .unwrap_or(U256::ZERO);
-    let receiver_bal = output
-        .post_state
-        .accounts
-        .get(&receiver)
-        .map(|a| a.balance)
-        .unwrap_or(U256::ZERO);
+    let sender_bal = output.balance_of(sender);
+    let receiver_bal = output.balance_of(receiver);
 
     println!("\n--- Final Balances ---");
     println!("Sender:   {} wei", sender_bal);
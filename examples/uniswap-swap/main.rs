@@ -1,9 +1,11 @@
 //! Uniswap-style Swap Example
 //!
 //! Demonstrates the concept of proving DEX swaps off-chain.
-//! Uses placeholder contracts to show the structure.
+//! The tokens are the minimal ERC20 from `shadow_evm_core::testing`; the
+//! router is a placeholder to show the structure.
 
 use shadow_evm_core::prelude::*;
+use shadow_evm_core::testing::contracts::{erc20_balance_slot, erc20_with_balances};
 
 fn main() {
     println!("Shadow-EVM: DEX Swap\n");
@@ -22,16 +24,17 @@ fn main() {
         AccountState::new_with_balance(U256::from(10u128.pow(18))),
     );
 
-    // WETH contract
-    let mut weth_acc = AccountState::new_with_balance(U256::ZERO);
-    weth_acc.code = vec![0x60, 0x01, 0xf3];
-    weth_acc.set_storage(U256::from(1), U256::from(10u128.pow(18)));
-    state.insert_account(weth, weth_acc);
+    // WETH contract: 1 WETH held by the trader
+    state.insert_account(
+        weth,
+        erc20_with_balances(&[(trader, U256::from(10u128.pow(18)))]),
+    );
 
-    // USDC contract
-    let mut usdc_acc = AccountState::new_with_balance(U256::ZERO);
-    usdc_acc.code = vec![0x60, 0x01, 0xf3];
-    state.insert_account(usdc, usdc_acc);
+    // USDC contract: liquidity held by the router
+    state.insert_account(
+        usdc,
+        erc20_with_balances(&[(router, U256::from(1_000_000 * 10u128.pow(6)))]),
+    );
 
     // Router contract
     let mut router_acc = AccountState::new_with_balance(U256::ZERO);
@@ -57,6 +60,10 @@ fn main() {
 
     println!("\n--- Result ---");
     println!("Gas used:        {}", output.gas_used);
+    println!(
+        "Trader WETH:     {}",
+        output.storage_of(weth, erc20_balance_slot(trader))
+    );
     println!(
         "Pre-state root:  0x{}",
         hex::encode(commitment.pre_state_root.as_slice())
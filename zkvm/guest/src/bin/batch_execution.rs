@@ -0,0 +1,27 @@
+//! Shadow-EVM Batch-Execution Guest
+//!
+//! Executes a sequence of transactions on one shared pre-state, each on the
+//! post-state of the previous one, as for a block or rollup batch. Reverts
+//! and halts are committed like in the main guest; a transaction that
+//! cannot be executed aborts the proof.
+//!
+//! # Public Output (Journal)
+//! The journal contains the ExecutionCommitment over the whole batch, as
+//! computed by `ShadowExecutor::execute_batch`. When the input carries
+//! per-transaction block contexts, the commitment also binds the block and
+//! timestamp of the last transaction, so the proven range is public.
+
+#![no_main]
+#![no_std]
+
+use risc0_zkvm::guest::env;
+use shadow_evm_core::{BatchExecutionInput, GUEST_VERSION};
+
+risc0_zkvm::guest::entry!(main);
+
+/// Guest main entry point
+fn main() {
+    let batch: BatchExecutionInput = env::read();
+    let (_, commitment) = batch.execute().expect("EVM execution failed");
+    env::commit(&commitment.with_guest_version(GUEST_VERSION));
+}
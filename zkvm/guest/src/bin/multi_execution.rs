@@ -0,0 +1,24 @@
+//! Shadow-EVM Multi-Execution Guest
+//!
+//! Executes several independent inputs in one run, amortizing the proof
+//! overhead over small executions. Reverts and halts are committed like in
+//! the main guest; an input that cannot be executed aborts the proof.
+//!
+//! # Public Output (Journal)
+//! The journal contains the Merkle root over the execution commitments,
+//! as computed by `shadow_evm_core::commitments_root`.
+
+#![no_main]
+#![no_std]
+
+use risc0_zkvm::guest::env;
+use shadow_evm_core::MultiExecutionInput;
+
+risc0_zkvm::guest::entry!(main);
+
+/// Guest main entry point
+fn main() {
+    let batch: MultiExecutionInput = env::read();
+    let (_, root) = batch.execute().expect("EVM execution failed");
+    env::commit(&root);
+}
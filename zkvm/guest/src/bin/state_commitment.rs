@@ -0,0 +1,21 @@
+//! Shadow-EVM State Commitment Guest
+//!
+//! Attests that a state has a given root, without executing any transaction.
+//! Useful for checkpointing state between execution proofs.
+//!
+//! # Public Output (Journal)
+//! The journal contains only the 32-byte state root of the input state.
+
+#![no_main]
+#![no_std]
+
+use risc0_zkvm::guest::env;
+use shadow_evm_core::InMemoryDB;
+
+risc0_zkvm::guest::entry!(main);
+
+/// Guest main entry point
+fn main() {
+    let state: InMemoryDB = env::read();
+    env::commit(&state.compute_state_root());
+}
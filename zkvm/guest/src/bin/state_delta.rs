@@ -0,0 +1,31 @@
+//! Shadow-EVM State Delta Guest
+//!
+//! Attests that applying a set of account changes to a state with a known
+//! sparse Merkle root yields a new root, without reading the unchanged
+//! accounts. Chained with earlier proofs through `base_root`.
+//!
+//! # Trust
+//! Only the Merkle math is proven: the new account values are taken as
+//! given, not derived by executing any transaction. A proof shows that
+//! *some* set of changes takes `base_root` to `new_root`, not that the EVM
+//! would make them. Pair it with an execution proof for that.
+//!
+//! # Public Output (Journal)
+//! The journal contains `(base_root, new_root)`.
+
+#![no_main]
+#![no_std]
+
+use risc0_zkvm::guest::env;
+use shadow_evm_core::StateDelta;
+
+risc0_zkvm::guest::entry!(main);
+
+/// Guest main entry point
+fn main() {
+    let delta: StateDelta = env::read();
+    let new_root = delta
+        .apply()
+        .expect("state delta does not extend the base root");
+    env::commit(&(delta.base_root, new_root));
+}
@@ -15,8 +15,8 @@ impl ZkExecutor {
     ///
     /// This is the main entry point for ZK execution.
     /// It takes an ExecutionInput and returns either:
-    /// - Ok((output, commitment)) on success
-    /// - Err(error) on failure
+    /// - Ok((output, commitment)) once executed, including reverts and halts
+    /// - Err(error) if the transaction could not be executed
     ///
     /// # ZK Properties
     /// - Execution is deterministic
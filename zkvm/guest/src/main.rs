@@ -17,7 +17,10 @@
 //! - output_hash: Hash of the execution output
 //! - pre_state_root: Merkle root of pre-execution state
 //! - post_state_root: Merkle root of post-execution state
+//! - state_root_mode: How both roots were computed, as the input selects
 //! - commitment: Combined cryptographic commitment
+//! - chain_id: Chain ID the execution ran under
+//! - guest_version: `GUEST_VERSION` of this guest
 
 #![no_main]
 #![no_std]
@@ -28,13 +31,16 @@ mod evm;
 mod types;
 
 use evm::{commit_output, read_input, ZkExecutor};
+use shadow_evm_core::GUEST_VERSION;
 
 risc0_zkvm::guest::entry!(main);
 
 /// Guest main entry point
 ///
 /// This function is called when the ZK-VM starts executing.
-/// Any panic will cause the proof generation to fail.
+/// Reverted and halted transactions are committed like successful ones,
+/// with their status in the committed output. Only inputs that cannot be
+/// executed at all panic, which causes the proof generation to fail.
 fn main() {
     // Step 1: Read the execution input from the host
     let input = read_input();
@@ -46,12 +52,14 @@ fn main() {
     // Step 3: Handle the result and commit to journal
     match result {
         Ok((_output, commitment)) => {
-            // Commit the execution commitment to the journal
+            // Commit the execution commitment to the journal, whatever the
+            // status: a proven revert is as useful as a proven success
             // This becomes the public output of the ZK proof
-            commit_output(&commitment);
+            commit_output(&commitment.with_guest_version(GUEST_VERSION));
         }
         Err(_err) => {
-            // Execution failed - panic to abort proof generation
+            // The transaction could not be executed (invalid input, nonce,
+            // balance) - panic to abort proof generation
             // The host should validate inputs before proving
             panic!("EVM execution failed");
         }
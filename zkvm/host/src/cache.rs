@@ -0,0 +1,95 @@
+//! On-disk cache of execution proofs
+//!
+//! Proofs are stored per guest image and keyed by the input hash:
+//!
+//! ```text
+//! <dir>/<image id>/<input hash>.<dev|succinct>.proof
+//! ```
+//!
+//! A rebuilt guest has a new image ID, so its proofs never mix with those
+//! of an older build. Entries are also verified against the current image
+//! ID when loaded; one that does not verify is treated as a miss and
+//! overwritten by the next proof. Entries are written to a temporary file
+//! first and renamed into place, so a reader never sees a partial one.
+
+use anyhow::{Context, Result};
+use risc0_zkvm::sha::Digest;
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize};
+use shadow_evm_core::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::prover::get_image_id;
+
+/// A proof as stored in the cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedProof {
+    /// The receipt
+    pub receipt: Receipt,
+    /// Guest execution cycles of the original proving run
+    pub cycles: u64,
+}
+
+/// Directory of cached proofs
+#[derive(Debug, Clone)]
+pub struct ProofCache {
+    dir: PathBuf,
+}
+
+impl ProofCache {
+    /// Use `dir` as the cache (created on the first store)
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// File holding the proof of `input_hash`
+    ///
+    /// Dev mode and production receipts are kept apart, so a fast receipt
+    /// is never returned where a succinct one was asked for.
+    fn path(&self, input_hash: &Hash, dev_mode: bool) -> PathBuf {
+        let image_id = Digest::from(get_image_id());
+        let mode = if dev_mode { "dev" } else { "succinct" };
+        self.dir.join(image_id.to_string()).join(format!(
+            "{}.{}.proof",
+            hex::encode(input_hash.as_slice()),
+            mode
+        ))
+    }
+
+    /// Look up the proof of `input_hash`
+    ///
+    /// Returns `None` if there is no entry or it does not verify against
+    /// the current guest.
+    pub fn load(&self, input_hash: &Hash, dev_mode: bool) -> Result<Option<CachedProof>> {
+        let path = self.path(input_hash, dev_mode);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let Ok(cached) = bincode::deserialize::<CachedProof>(&bytes) else {
+            return Ok(None);
+        };
+        if cached.receipt.verify(get_image_id()).is_err() {
+            return Ok(None);
+        }
+
+        Ok(Some(cached))
+    }
+
+    /// Store the proof of `input_hash`, replacing any existing entry
+    pub fn store(&self, input_hash: &Hash, dev_mode: bool, proof: &CachedProof) -> Result<()> {
+        let path = self.path(input_hash, dev_mode);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create cache directory {:?}", parent))?;
+        }
+
+        let bytes = bincode::serialize(proof).context("Failed to serialize cached proof")?;
+        let tmp = path.with_extension(format!("proof.tmp.{}", std::process::id()));
+        fs::write(&tmp, bytes).with_context(|| format!("Failed to write {:?}", tmp))?;
+        fs::rename(&tmp, &path).with_context(|| format!("Failed to move {:?} into place", tmp))?;
+        Ok(())
+    }
+}
@@ -4,18 +4,28 @@
 //! for communication with the guest and external systems.
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use shadow_evm_core::prelude::*;
+use shadow_evm_core::types::{BlockNumber, Timestamp};
+use shadow_evm_core::{BatchExecutionInput, ExecutionTrace, StorageClaim};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::prover::{get_image_id, ProofResult};
 
 /// Serialize ExecutionInput to bytes
+///
+/// Uses bincode for small inputs and CBOR for large ones; see
+/// `ExecutionInput::serialize_auto`.
 pub fn serialize_input(input: &ExecutionInput) -> Result<Vec<u8>> {
-    bincode::serialize(input).context("Failed to serialize ExecutionInput")
+    input
+        .serialize_auto()
+        .context("Failed to serialize ExecutionInput")
 }
 
-/// Deserialize ExecutionInput from bytes
+/// Deserialize ExecutionInput from bytes produced by `serialize_input`
 pub fn deserialize_input(data: &[u8]) -> Result<ExecutionInput> {
-    bincode::deserialize(data).context("Failed to deserialize ExecutionInput")
+    ExecutionInput::deserialize_auto(data).context("Failed to deserialize ExecutionInput")
 }
 
 /// Serialize ExecutionCommitment to bytes
@@ -36,29 +46,158 @@ pub fn save_input_json<P: AsRef<Path>>(input: &ExecutionInput, path: P) -> Resul
 }
 
 /// Load ExecutionInput from a JSON file
+///
+/// Unknown fields are rejected, so a misspelled key fails with its name and
+/// position rather than silently falling back to a default.
 pub fn load_input_json<P: AsRef<Path>>(path: P) -> Result<ExecutionInput> {
+    let path = path.as_ref();
     let json = fs::read_to_string(path)?;
-    let input: ExecutionInput = serde_json::from_str(&json)?;
+    let input: ExecutionInput = serde_json::from_str(&json)
+        .with_context(|| format!("Invalid ExecutionInput JSON in {:?}", path))?;
     Ok(input)
 }
 
+/// Load a BatchExecutionInput from a JSON file
+pub fn load_batch_json<P: AsRef<Path>>(path: P) -> Result<BatchExecutionInput> {
+    let path = path.as_ref();
+    let json = fs::read_to_string(path)?;
+    let batch: BatchExecutionInput = serde_json::from_str(&json)
+        .with_context(|| format!("Invalid BatchExecutionInput JSON in {:?}", path))?;
+    Ok(batch)
+}
+
+/// Record a fully assembled input for later replay
+///
+/// Writes the bytes `serialize_input` produces, so the replayed input is
+/// exactly the one executed, whatever produced it (e.g. RPC fetching).
+pub fn record_input<P: AsRef<Path>>(input: &ExecutionInput, path: P) -> Result<()> {
+    let path = path.as_ref();
+    let bytes = serialize_input(input)?;
+    fs::write(path, bytes).with_context(|| format!("Failed to record input to {:?}", path))
+}
+
+/// Load an input recorded by `record_input`
+pub fn load_recorded_input<P: AsRef<Path>>(path: P) -> Result<ExecutionInput> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).with_context(|| format!("Failed to read recording {:?}", path))?;
+    deserialize_input(&bytes).with_context(|| format!("Invalid input recording {:?}", path))
+}
+
+/// Load InMemoryDB from a JSON file
+pub fn load_state_json<P: AsRef<Path>>(path: P) -> Result<InMemoryDB> {
+    let path = path.as_ref();
+    let json = fs::read_to_string(path)?;
+    let state: InMemoryDB =
+        serde_json::from_str(&json).with_context(|| format!("Invalid state JSON in {:?}", path))?;
+    Ok(state)
+}
+
 /// Save binary data to a file
 pub fn save_bytes<P: AsRef<Path>>(data: &[u8], path: P) -> Result<()> {
     fs::write(path, data)?;
     Ok(())
 }
 
+/// Save an execution trace as JSON
+pub fn save_trace_json<P: AsRef<Path>>(trace: &ExecutionTrace, path: P) -> Result<()> {
+    let json = serde_json::to_string_pretty(trace)?;
+    fs::write(path, json)?;
+    Ok(())
+}
+
+/// Load an execution trace from JSON
+pub fn load_trace_json<P: AsRef<Path>>(path: P) -> Result<ExecutionTrace> {
+    let json = fs::read_to_string(path)?;
+    let trace: ExecutionTrace = serde_json::from_str(&json)?;
+    Ok(trace)
+}
+
 /// Load binary data from a file
 pub fn load_bytes<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
     let data = fs::read(path)?;
     Ok(data)
 }
 
+/// Backend-independent description of a proof
+///
+/// Written next to each receipt by `prove`, so proofs from different
+/// zkVMs can be compared without decoding their receipts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofMetadata {
+    /// Guest image ID, `0x`-prefixed hex
+    pub image_id: String,
+    /// Execution commitment
+    pub commitment: Hash,
+    /// Pre-state root
+    pub pre_root: Hash,
+    /// Post-state root
+    pub post_root: Hash,
+    /// Guest execution cycles
+    pub cycles: u64,
+    /// Time taken to generate (or load) the proof
+    pub proving_time_ms: u128,
+    /// `GUEST_VERSION` committed by the guest
+    pub guest_version: u32,
+    /// Version of `shadow-evm-core` the host was built with
+    pub crate_version: String,
+}
+
+impl ProofMetadata {
+    /// Describe a proof of the execution guest
+    pub fn from_result(result: &ProofResult) -> Self {
+        Self {
+            image_id: format!("0x{}", hex::encode(get_image_id())),
+            commitment: result.commitment.commitment,
+            pre_root: result.commitment.pre_state_root,
+            post_root: result.commitment.post_state_root,
+            cycles: result.cycles,
+            proving_time_ms: result.proving_time_ms,
+            guest_version: result.commitment.guest_version,
+            crate_version: shadow_evm_core::VERSION.to_string(),
+        }
+    }
+}
+
+/// Path of the metadata sidecar of a receipt: `<receipt>.meta.json`
+pub fn metadata_path<P: AsRef<Path>>(receipt_path: P) -> PathBuf {
+    let mut path = receipt_path.as_ref().as_os_str().to_owned();
+    path.push(".meta.json");
+    PathBuf::from(path)
+}
+
+/// Save proof metadata as JSON
+pub fn save_metadata_json<P: AsRef<Path>>(metadata: &ProofMetadata, path: P) -> Result<()> {
+    let json = serde_json::to_string_pretty(metadata)?;
+    fs::write(path, json)?;
+    Ok(())
+}
+
 /// Format a hash as a hex string
 pub fn format_hash(hash: &Hash) -> String {
     format!("0x{}", hex::encode(hash.as_slice()))
 }
 
+/// Format a hash that may be absent
+fn format_optional_hash(hash: Option<Hash>) -> String {
+    hash.map_or_else(|| String::from("none"), |hash| format_hash(&hash))
+}
+
+/// Format a storage claim that may be absent
+fn format_storage_claim(claim: Option<StorageClaim>) -> String {
+    claim.map_or_else(
+        || String::from("none"),
+        |claim| format!("{} slot {} = {}", claim.address, claim.slot, claim.value),
+    )
+}
+
+/// Format the closing block context of a block range that may be absent
+fn format_last_block_context(context: Option<(BlockNumber, Timestamp)>) -> String {
+    context.map_or_else(
+        || String::from("none"),
+        |(number, timestamp)| format!("block {} at {}", number, timestamp),
+    )
+}
+
 /// Format commitment for display
 pub fn format_commitment(commitment: &ExecutionCommitment) -> String {
     format!(
@@ -67,16 +206,45 @@ pub fn format_commitment(commitment: &ExecutionCommitment) -> String {
          output_hash: {},\n  \
          pre_state_root: {},\n  \
          post_state_root: {},\n  \
-         commitment: {}\n\
+         commitment: {},\n  \
+         hash_algo: {:?},\n  \
+         state_root_mode: {:?},\n  \
+         public_inputs_hash: {},\n  \
+         block_number: {},\n  \
+         timestamp: {},\n  \
+         chain_id: {},\n  \
+         guest_version: {},\n  \
+         tx_hash: {},\n  \
+         calldata_hash: {},\n  \
+         return_data_hash: {},\n  \
+         storage_claim: {},\n  \
+         last_block_context: {}\n\
          }}",
         format_hash(&commitment.input_hash),
         format_hash(&commitment.output_hash),
         format_hash(&commitment.pre_state_root),
         format_hash(&commitment.post_state_root),
         format_hash(&commitment.commitment),
+        commitment.hash_algo,
+        commitment.state_root_mode,
+        format_hash(&commitment.public_inputs_hash),
+        commitment.block_number,
+        commitment.timestamp,
+        commitment.chain_id,
+        commitment.guest_version,
+        format_optional_hash(commitment.tx_hash),
+        format_optional_hash(commitment.calldata_hash),
+        format_optional_hash(commitment.return_data_hash),
+        format_storage_claim(commitment.storage_claim),
+        format_last_block_context(commitment.last_block_context),
     )
 }
 
+/// Format commitment as canonical JSON (for `--json` output)
+pub fn format_commitment_json(commitment: &ExecutionCommitment) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&commitment.to_json())?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,4 +258,68 @@ mod tests {
 
         assert_eq!(input.hash(), decoded.hash());
     }
+
+    #[test]
+    fn test_record_replay() {
+        let input = shadow_evm_core::testing::samples::SampleKind::Erc20
+            .input()
+            .with_public_inputs(b"request-7".to_vec());
+
+        let path = std::env::temp_dir().join(format!("shadow-evm-{}.input", std::process::id()));
+        record_input(&input, &path).unwrap();
+        let recorded = fs::read(&path).unwrap();
+        let replayed = load_recorded_input(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(recorded, serialize_input(&replayed).unwrap());
+        let (_, original) = ShadowExecutor::execute(input).unwrap();
+        let (_, replayed) = ShadowExecutor::execute(replayed).unwrap();
+        assert_eq!(original, replayed);
+    }
+
+    #[test]
+    fn test_metadata_path() {
+        assert_eq!(
+            metadata_path("out/proof.bin"),
+            PathBuf::from("out/proof.bin.meta.json")
+        );
+    }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_proof_metadata() {
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64)),
+            state,
+        );
+        let result = crate::prover::prove(input, &crate::prover::ProveOptions::dev()).unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("shadow-evm-{}.meta.json", std::process::id()));
+        save_metadata_json(&ProofMetadata::from_result(&result), &path).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "image_id": format!("0x{}", hex::encode(get_image_id())),
+                "commitment": format_hash(&result.commitment.commitment),
+                "pre_root": format_hash(&result.commitment.pre_state_root),
+                "post_root": format_hash(&result.commitment.post_state_root),
+                "cycles": result.cycles,
+                "proving_time_ms": result.proving_time_ms as u64,
+                "guest_version": shadow_evm_core::GUEST_VERSION,
+                "crate_version": shadow_evm_core::VERSION,
+            })
+        );
+    }
 }
@@ -3,13 +3,17 @@
 //! Command-line interface for generating and verifying ZK proofs
 //! of EVM execution.
 
+mod cache;
 mod io;
 mod prover;
+mod replay;
 mod verifier;
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use shadow_evm_core::prelude::*;
+use shadow_evm_core::testing::samples::SampleKind;
+use shadow_evm_core::{diff_traces, ExecutionTrace, ExecutionWitness, MultiExecutionInput};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -21,13 +25,50 @@ struct Cli {
     command: Commands,
 }
 
+/// Where `prove` and `execute` take their input from
+#[derive(Args)]
+struct InputArgs {
+    /// Path to the execution input JSON file
+    #[arg(short, long, required_unless_present = "replay")]
+    input: Option<PathBuf>,
+
+    /// Save the exact input run to this file, for reproduction with `--replay`
+    #[arg(long)]
+    record: Option<PathBuf>,
+
+    /// Run an input saved with `--record` instead of `--input`
+    #[arg(long, conflicts_with = "input")]
+    replay: Option<PathBuf>,
+}
+
+impl InputArgs {
+    /// Load the input, recording it if asked
+    ///
+    /// Returns the input together with the path it was loaded from.
+    fn load(self) -> Result<(ExecutionInput, PathBuf)> {
+        let (input, path) = match (self.input, self.replay) {
+            (_, Some(path)) => (io::load_recorded_input(&path)?, path),
+            (Some(path), None) => (
+                io::load_input_json(&path)
+                    .context(format!("Failed to load input from {:?}", path))?,
+                path,
+            ),
+            (None, None) => anyhow::bail!("Either --input or --replay is required"),
+        };
+
+        if let Some(record) = self.record {
+            io::record_input(&input, &record)?;
+        }
+        Ok((input, path))
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Generate a ZK proof for an EVM execution
     Prove {
-        /// Path to the execution input JSON file
-        #[arg(short, long)]
-        input: PathBuf,
+        #[command(flatten)]
+        input: InputArgs,
 
         /// Path to save the proof receipt
         #[arg(short, long)]
@@ -37,9 +78,86 @@ enum Commands {
         #[arg(long)]
         dev: bool,
 
+        /// Do not print the dev mode warning (for automated use)
+        #[arg(long)]
+        quiet_dev_warning: bool,
+
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Print only the commitment as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Reuse proofs of identical inputs stored in this directory
+        #[arg(long)]
+        cache_dir: Option<PathBuf>,
+
+        /// Refuse inputs already proven by a run using this directory
+        #[arg(long)]
+        replay_dir: Option<PathBuf>,
+    },
+
+    /// Generate a ZK proof that a state has a given root (no execution)
+    ProveState {
+        /// Path to the state JSON file
+        #[arg(short, long)]
+        state: PathBuf,
+
+        /// Path to save the proof receipt
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Enable dev mode (faster but not cryptographically secure)
+        #[arg(long)]
+        dev: bool,
+
+        /// Do not print the dev mode warning (for automated use)
+        #[arg(long)]
+        quiet_dev_warning: bool,
+    },
+
+    /// Generate one ZK proof of a batch of transactions on a running state
+    ProveBatch {
+        /// Path to the batch execution input JSON file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Path to save the proof receipt
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Enable dev mode (faster but not cryptographically secure)
+        #[arg(long)]
+        dev: bool,
+
+        /// Do not print the dev mode warning (for automated use)
+        #[arg(long)]
+        quiet_dev_warning: bool,
+
+        /// Print only the commitment as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Generate one ZK proof over several independent executions
+    ProveMulti {
+        /// Path to an execution input JSON file (repeat for each execution)
+        #[arg(short, long = "input", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Path to save the proof receipt
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Enable dev mode (faster but not cryptographically secure)
+        #[arg(long)]
+        dev: bool,
+
+        /// Do not print the dev mode warning (for automated use)
+        #[arg(long)]
+        quiet_dev_warning: bool,
     },
 
     /// Verify a ZK proof
@@ -48,13 +166,82 @@ enum Commands {
         #[arg(short, long)]
         proof: PathBuf,
 
-        /// Expected commitment (optional)
+        /// Expected commitment; fail unless the proof commits to it
         #[arg(long)]
         commitment: Option<String>,
 
+        /// Expected output hash; fail unless the proof commits to it
+        #[arg(long)]
+        expect_output_hash: Option<String>,
+
+        /// Accept dev mode receipts (not cryptographically secure)
+        #[arg(long)]
+        dev: bool,
+
+        /// Do not print the dev mode warning (for automated use)
+        #[arg(long)]
+        quiet_dev_warning: bool,
+
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Print only the commitment as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Verify a batch-execution proof
+    VerifyBatch {
+        /// Path to the proof receipt file
+        #[arg(short, long)]
+        proof: PathBuf,
+
+        /// Accept dev mode receipts (not cryptographically secure)
+        #[arg(long)]
+        dev: bool,
+
+        /// Do not print the dev mode warning (for automated use)
+        #[arg(long)]
+        quiet_dev_warning: bool,
+
+        /// Print only the commitment as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Verify a multi-execution proof
+    VerifyMulti {
+        /// Path to the proof receipt file
+        #[arg(short, long)]
+        proof: PathBuf,
+
+        /// Expected commitments root; fail unless the proof commits to it
+        #[arg(long)]
+        expect_root: Option<String>,
+
+        /// Accept dev mode receipts (not cryptographically secure)
+        #[arg(long)]
+        dev: bool,
+
+        /// Do not print the dev mode warning (for automated use)
+        #[arg(long)]
+        quiet_dev_warning: bool,
+    },
+
+    /// Verify that execution proofs chain into one state transition
+    VerifyChain {
+        /// Path to a proof receipt file (repeat for each proof, in order)
+        #[arg(short, long = "proof", required = true)]
+        proofs: Vec<PathBuf>,
+
+        /// Accept dev mode receipts (not cryptographically secure)
+        #[arg(long)]
+        dev: bool,
+
+        /// Do not print the dev mode warning (for automated use)
+        #[arg(long)]
+        quiet_dev_warning: bool,
     },
 
     /// Export proof for on-chain verification
@@ -68,18 +255,53 @@ enum Commands {
         output: PathBuf,
     },
 
+    /// Write the raw journal of a proof, e.g. for use as calldata
+    Journal {
+        /// Path to the proof receipt file
+        #[arg(short, long)]
+        proof: PathBuf,
+
+        /// Path to save the journal bytes
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
     /// Get the guest image ID
     ImageId,
 
     /// Execute without proving (for testing)
     Execute {
-        /// Path to the execution input JSON file
-        #[arg(short, long)]
-        input: PathBuf,
+        #[command(flatten)]
+        input: InputArgs,
 
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Print only the commitment as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Save an opcode-level trace of the execution to this file
+        #[arg(long)]
+        trace: Option<PathBuf>,
+
+        /// Print per-opcode gas usage, most expensive first
+        ///
+        /// The transaction then runs natively only, not in the guest.
+        #[arg(long)]
+        gas_profile: bool,
+    },
+
+    /// Compare two execution traces and report where they diverge
+    DiffTrace {
+        /// Path to the first trace JSON file
+        #[arg(long)]
+        a: PathBuf,
+
+        /// Path to the second trace JSON file
+        #[arg(long)]
+        b: PathBuf,
     },
 
     /// Generate a sample input file
@@ -87,6 +309,10 @@ enum Commands {
         /// Path to save the sample input
         #[arg(short, long)]
         output: PathBuf,
+
+        /// Scenario: transfer, deploy, erc20 or revert
+        #[arg(short, long, default_value = "transfer")]
+        kind: String,
     },
 }
 
@@ -99,69 +325,228 @@ async fn main() -> Result<()> {
             input,
             output,
             dev,
+            quiet_dev_warning,
             verbose,
+            json,
+            cache_dir,
+            replay_dir,
+        } => {
+            if dev && !quiet_dev_warning {
+                print_dev_warning();
+            }
+            cmd_prove(input, output, dev, verbose, json, cache_dir, replay_dir).await?;
+        }
+        Commands::ProveState {
+            state,
+            output,
+            dev,
+            quiet_dev_warning,
+        } => {
+            if dev && !quiet_dev_warning {
+                print_dev_warning();
+            }
+            cmd_prove_state(state, output, dev).await?;
+        }
+        Commands::ProveBatch {
+            input,
+            output,
+            dev,
+            quiet_dev_warning,
+            json,
+        } => {
+            if dev && !quiet_dev_warning {
+                print_dev_warning();
+            }
+            cmd_prove_batch(input, output, dev, json)?;
+        }
+        Commands::ProveMulti {
+            inputs,
+            output,
+            dev,
+            quiet_dev_warning,
         } => {
-            cmd_prove(input, output, dev, verbose).await?;
+            if dev && !quiet_dev_warning {
+                print_dev_warning();
+            }
+            cmd_prove_multi(inputs, output, dev)?;
         }
         Commands::Verify {
             proof,
             commitment,
+            expect_output_hash,
+            dev,
+            quiet_dev_warning,
             verbose,
+            json,
         } => {
-            cmd_verify(proof, commitment, verbose).await?;
+            if dev && !quiet_dev_warning {
+                print_dev_warning();
+            }
+            cmd_verify(proof, commitment, expect_output_hash, dev, verbose, json).await?;
+        }
+        Commands::VerifyBatch {
+            proof,
+            dev,
+            quiet_dev_warning,
+            json,
+        } => {
+            if dev && !quiet_dev_warning {
+                print_dev_warning();
+            }
+            cmd_verify_batch(proof, dev, json)?;
+        }
+        Commands::VerifyMulti {
+            proof,
+            expect_root,
+            dev,
+            quiet_dev_warning,
+        } => {
+            if dev && !quiet_dev_warning {
+                print_dev_warning();
+            }
+            cmd_verify_multi(proof, expect_root, dev)?;
+        }
+        Commands::VerifyChain {
+            proofs,
+            dev,
+            quiet_dev_warning,
+        } => {
+            if dev && !quiet_dev_warning {
+                print_dev_warning();
+            }
+            cmd_verify_chain(proofs, dev)?;
         }
         Commands::Export { proof, output } => {
             cmd_export(proof, output).await?;
         }
+        Commands::Journal { proof, output } => {
+            cmd_journal(proof, output)?;
+        }
         Commands::ImageId => {
             cmd_image_id()?;
         }
-        Commands::Execute { input, verbose } => {
-            cmd_execute(input, verbose).await?;
+        Commands::Execute {
+            input,
+            verbose,
+            json,
+            trace,
+            gas_profile,
+        } => {
+            cmd_execute(input, verbose, json, trace, gas_profile).await?;
+        }
+        Commands::DiffTrace { a, b } => {
+            cmd_diff_trace(a, b)?;
         }
-        Commands::Sample { output } => {
-            cmd_sample(output)?;
+        Commands::Sample { output, kind } => {
+            cmd_sample(output, &kind)?;
         }
     }
 
     Ok(())
 }
 
+/// Warn that dev mode proofs carry no security
+///
+/// Printed to stderr so `--json` output stays machine-readable.
+fn print_dev_warning() {
+    eprintln!();
+    eprintln!("⚠️  DEV MODE: This proof is NOT cryptographically secure and must not be used in production");
+    eprintln!();
+}
+
+/// Print prover progress for `prove`
+///
+/// The final time and cycle count are already part of the command's own
+/// output, so only the start and each segment are printed here.
+fn print_prover_event(event: prover::ProverEvent) {
+    match event {
+        prover::ProverEvent::Started => println!("  Proving..."),
+        prover::ProverEvent::SegmentProven { index, total } => {
+            println!("  Segment {}/{} proven", index + 1, total)
+        }
+        prover::ProverEvent::Finished { .. } => {}
+    }
+}
+
 async fn cmd_prove(
-    input_path: PathBuf,
+    input_args: InputArgs,
     output_path: PathBuf,
     dev: bool,
     verbose: bool,
+    json: bool,
+    cache_dir: Option<PathBuf>,
+    replay_dir: Option<PathBuf>,
 ) -> Result<()> {
-    println!("Shadow-EVM ZK Prover");
-    println!("====================");
+    if !json {
+        println!("Shadow-EVM ZK Prover");
+        println!("====================");
+    }
 
     // Load input
-    let input = io::load_input_json(&input_path)
-        .context(format!("Failed to load input from {:?}", input_path))?;
+    let (input, input_path) = input_args.load()?;
 
-    if verbose {
+    if verbose && !json {
         println!("Loaded input from {:?}", input_path);
         println!("  Caller: {:?}", input.caller());
         println!("  Target: {:?}", input.target());
         println!("  Pre-state root: 0x{}", hex::encode(input.pre_state_root().as_slice()));
+
+        let witness = ExecutionWitness::from_input(&input)?;
+        let stats = witness.stats();
+        println!("Witness:");
+        println!(
+            "  Accounts: {} ({} accessed)",
+            stats.account_count,
+            witness.accessed_accounts.len()
+        );
+        println!(
+            "  Storage slots: {} ({} accessed)",
+            stats.total_storage_slots,
+            witness.accessed_slots.len()
+        );
+        println!("  Code: {} bytes", stats.total_code_bytes);
+        println!("  Estimated size: {} bytes", stats.estimated_witness_bytes);
     }
 
     // Generate proof
-    let options = prover::ProveOptions {
+    let mut options = prover::ProveOptions {
         dev_mode: dev,
-        verbose,
+        verbose: verbose && !json,
+        on_event: None,
+        replay_guard: None,
     };
+    if !json {
+        options = options.with_event_handler(print_prover_event);
+    }
+    if let Some(dir) = replay_dir {
+        let store = replay::FileReplayStore::new(dir);
+        options = options.with_replay_guard(replay::ReplayGuard::with_store(store));
+    }
 
-    let result = prover::prove(input, &options)?;
+    let result = match cache_dir {
+        Some(dir) => prover::prove_cached(input, &options, &cache::ProofCache::new(dir))?,
+        None => prover::prove(input, &options)?,
+    };
 
     // Save receipt
     let receipt_bytes = bincode::serialize(&result.receipt)?;
     io::save_bytes(&receipt_bytes, &output_path)?;
+    let metadata_path = io::metadata_path(&output_path);
+    io::save_metadata_json(&io::ProofMetadata::from_result(&result), &metadata_path)?;
+
+    if json {
+        println!("{}", io::format_commitment_json(&result.commitment)?);
+        return Ok(());
+    }
 
     println!("\nProof generated successfully!");
     println!("  Output: {:?}", output_path);
-    println!("  Proving time: {}ms", result.proving_time_ms);
+    println!("  Metadata: {:?}", metadata_path);
+    if result.from_cache {
+        println!("  Loaded from cache in {}ms", result.proving_time_ms);
+    } else {
+        println!("  Proving time: {}ms", result.proving_time_ms);
+    }
     println!("  Cycles: {}", result.cycles);
     println!("\nCommitment:");
     println!("{}", io::format_commitment(&result.commitment));
@@ -169,49 +554,288 @@ async fn cmd_prove(
     Ok(())
 }
 
+async fn cmd_prove_state(state_path: PathBuf, output_path: PathBuf, dev: bool) -> Result<()> {
+    println!("Shadow-EVM State Commitment Prover");
+    println!("==================================");
+
+    let state = io::load_state_json(&state_path)
+        .context(format!("Failed to load state from {:?}", state_path))?;
+
+    let options = prover::ProveOptions {
+        dev_mode: dev,
+        verbose: false,
+        on_event: None,
+        replay_guard: None,
+    };
+    let receipt = prover::prove_state_commitment(&state, &options)?;
+    let root = verifier::verify_state_commitment(&receipt)?;
+
+    let receipt_bytes = bincode::serialize(&receipt)?;
+    io::save_bytes(&receipt_bytes, &output_path)?;
+
+    println!("\nState commitment proof generated!");
+    println!("  Output: {:?}", output_path);
+    println!("  Accounts: {}", state.account_count());
+    println!("  State root: {}", io::format_hash(&root));
+
+    Ok(())
+}
+
+fn cmd_prove_batch(input_path: PathBuf, output_path: PathBuf, dev: bool, json: bool) -> Result<()> {
+    if !json {
+        println!("Shadow-EVM Batch Prover");
+        println!("=======================");
+    }
+
+    let batch = io::load_batch_json(&input_path)
+        .context(format!("Failed to load batch from {:?}", input_path))?;
+
+    let options = prover::ProveOptions {
+        dev_mode: dev,
+        verbose: false,
+        on_event: None,
+        replay_guard: None,
+    };
+    let receipt = prover::prove_batch(&batch, &options)?;
+    let commitment = verifier::verify_batch(&receipt)?;
+
+    let receipt_bytes = bincode::serialize(&receipt)?;
+    io::save_bytes(&receipt_bytes, &output_path)?;
+
+    if json {
+        println!("{}", io::format_commitment_json(&commitment)?);
+        return Ok(());
+    }
+
+    println!("\nBatch proof generated!");
+    println!("  Output: {:?}", output_path);
+    println!("  Transactions: {}", batch.txs.len());
+    println!("\nCommitment:");
+    println!("{}", io::format_commitment(&commitment));
+
+    Ok(())
+}
+
+fn cmd_prove_multi(input_paths: Vec<PathBuf>, output_path: PathBuf, dev: bool) -> Result<()> {
+    println!("Shadow-EVM Multi-Execution Prover");
+    println!("=================================");
+
+    let inputs = input_paths
+        .iter()
+        .map(|path| {
+            io::load_input_json(path).context(format!("Failed to load input from {:?}", path))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let batch = MultiExecutionInput::new(inputs);
+
+    let options = prover::ProveOptions {
+        dev_mode: dev,
+        verbose: false,
+        on_event: None,
+        replay_guard: None,
+    };
+    let receipt = prover::prove_multi(&batch, &options)?;
+    let root = verifier::verify_multi(&receipt)?;
+
+    let receipt_bytes = bincode::serialize(&receipt)?;
+    io::save_bytes(&receipt_bytes, &output_path)?;
+
+    println!("\nMulti-execution proof generated!");
+    println!("  Output: {:?}", output_path);
+    println!("  Executions: {}", batch.inputs.len());
+    println!("  Commitments root: {}", io::format_hash(&root));
+
+    Ok(())
+}
+
 async fn cmd_verify(
     proof_path: PathBuf,
     expected_commitment: Option<String>,
+    expected_output_hash: Option<String>,
+    dev: bool,
     verbose: bool,
+    json: bool,
 ) -> Result<()> {
-    println!("Shadow-EVM ZK Verifier");
-    println!("======================");
+    let expected_commitment = expected_commitment
+        .map(|hash| {
+            hash.parse::<Hash>()
+                .with_context(|| format!("Invalid commitment {:?}", hash))
+        })
+        .transpose()?;
+    let expected_output_hash = expected_output_hash
+        .map(|hash| {
+            hash.parse::<Hash>()
+                .with_context(|| format!("Invalid output hash {:?}", hash))
+        })
+        .transpose()?;
+
+    if !json {
+        println!("Shadow-EVM ZK Verifier");
+        println!("======================");
+    }
 
     // Load receipt
     let receipt_bytes = io::load_bytes(&proof_path)?;
     let receipt: risc0_zkvm::Receipt = bincode::deserialize(&receipt_bytes)?;
 
-    if verbose {
+    if verbose && !json {
         println!("Loaded proof from {:?}", proof_path);
     }
 
+    // Fake receipts from dev mode proving only verify with dev mode enabled
+    if dev {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+    }
+
     // Verify
     let result = verifier::verify(&receipt)?;
 
-    if result.valid {
+    // Every failure exits non-zero, with or without --json
+    if !result.valid {
+        let error = result.error.unwrap_or_else(|| "unknown error".to_string());
+        if !json {
+            println!("✗ Proof is INVALID");
+            println!("  Error: {}", error);
+        }
+        anyhow::bail!("proof is invalid: {}", error);
+    }
+
+    if !json {
         println!("✓ Proof is VALID");
         println!("\nCommitment:");
         println!("{}", io::format_commitment(&result.commitment));
+    }
 
-        // Check expected commitment if provided
-        if let Some(expected) = expected_commitment {
-            let expected_bytes = hex::decode(expected.trim_start_matches("0x"))?;
-            let expected_hash = Hash::from_slice(&expected_bytes);
-            
-            if result.commitment.commitment == expected_hash {
-                println!("\n✓ Commitment matches expected value");
-            } else {
+    if let Some(expected) = expected_commitment {
+        if result.commitment.commitment != expected {
+            if !json {
                 println!("\n✗ Commitment does NOT match expected value");
-                return Ok(());
             }
+            anyhow::bail!("commitment does not match expected value");
         }
-    } else {
-        println!("✗ Proof is INVALID");
-        if let Some(error) = result.error {
-            println!("  Error: {}", error);
+        if !json {
+            println!("\n✓ Commitment matches expected value");
+        }
+    }
+
+    if let Some(expected) = expected_output_hash {
+        if !result.commitment.verify_output(&expected) {
+            if !json {
+                println!("\n✗ Output hash does NOT match expected value");
+            }
+            anyhow::bail!("output hash does not match expected value");
+        }
+        if !json {
+            println!("\n✓ Output hash matches expected value");
         }
     }
 
+    if json {
+        println!("{}", io::format_commitment_json(&result.commitment)?);
+    }
+
+    Ok(())
+}
+
+fn cmd_verify_batch(proof_path: PathBuf, dev: bool, json: bool) -> Result<()> {
+    let receipt_bytes = io::load_bytes(&proof_path)?;
+    let receipt: risc0_zkvm::Receipt = bincode::deserialize(&receipt_bytes)?;
+
+    // Fake receipts from dev mode proving only verify with dev mode enabled
+    if dev {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+    }
+
+    let commitment = verifier::verify_batch(&receipt)?;
+
+    if json {
+        println!("{}", io::format_commitment_json(&commitment)?);
+        return Ok(());
+    }
+
+    println!("Shadow-EVM Batch Verifier");
+    println!("=========================");
+    println!("✓ Batch proof is VALID");
+    println!("\nCommitment:");
+    println!("{}", io::format_commitment(&commitment));
+
+    Ok(())
+}
+
+fn cmd_verify_multi(proof_path: PathBuf, expected_root: Option<String>, dev: bool) -> Result<()> {
+    let expected_root = expected_root
+        .map(|root| {
+            root.parse::<Hash>()
+                .with_context(|| format!("Invalid commitments root {:?}", root))
+        })
+        .transpose()?;
+
+    println!("Shadow-EVM Multi-Execution Verifier");
+    println!("===================================");
+
+    let receipt_bytes = io::load_bytes(&proof_path)?;
+    let receipt: risc0_zkvm::Receipt = bincode::deserialize(&receipt_bytes)?;
+
+    // Fake receipts from dev mode proving only verify with dev mode enabled
+    if dev {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+    }
+
+    let root = verifier::verify_multi(&receipt)?;
+    println!("✓ Multi-execution proof is VALID");
+    println!("  Commitments root: {}", io::format_hash(&root));
+
+    if let Some(expected) = expected_root {
+        if root != expected {
+            println!("\n✗ Commitments root does NOT match expected value");
+            anyhow::bail!("commitments root does not match expected value");
+        }
+        println!("\n✓ Commitments root matches expected value");
+    }
+
+    Ok(())
+}
+
+fn cmd_verify_chain(proof_paths: Vec<PathBuf>, dev: bool) -> Result<()> {
+    println!("Shadow-EVM Chain Verifier");
+    println!("=========================");
+
+    let receipts = proof_paths
+        .iter()
+        .map(|path| {
+            let receipt_bytes = io::load_bytes(path)?;
+            bincode::deserialize(&receipt_bytes)
+                .with_context(|| format!("Invalid proof receipt {:?}", path))
+        })
+        .collect::<Result<Vec<risc0_zkvm::Receipt>>>()?;
+
+    // Fake receipts from dev mode proving only verify with dev mode enabled
+    if dev {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+    }
+
+    if !verifier::verify_chain(&receipts, prover::get_image_id())? {
+        println!("✗ Proofs do NOT form a valid chain");
+        anyhow::bail!("proof chain is invalid");
+    }
+
+    let commitments = receipts
+        .iter()
+        .map(|receipt| receipt.journal.decode())
+        .collect::<Result<Vec<ExecutionCommitment>, _>>()
+        .context("Failed to decode commitment from journal")?;
+    let (first, last) = (&commitments[0], &commitments[commitments.len() - 1]);
+
+    println!("✓ Chain of {} proofs is VALID", receipts.len());
+    println!(
+        "  Pre-state root: {}",
+        io::format_hash(&first.pre_state_root)
+    );
+    println!(
+        "  Post-state root: {}",
+        io::format_hash(&last.post_state_root)
+    );
+
     Ok(())
 }
 
@@ -244,57 +868,174 @@ async fn cmd_export(proof_path: PathBuf, output_path: PathBuf) -> Result<()> {
     Ok(())
 }
 
+fn cmd_journal(proof_path: PathBuf, output_path: PathBuf) -> Result<()> {
+    let receipt_bytes = io::load_bytes(&proof_path)?;
+    let receipt: risc0_zkvm::Receipt = bincode::deserialize(&receipt_bytes)?;
+
+    let journal = verifier::journal_bytes(&receipt);
+    io::save_bytes(&journal, &output_path)?;
+
+    println!("Saved {} journal bytes to {:?}", journal.len(), output_path);
+    Ok(())
+}
+
 fn cmd_image_id() -> Result<()> {
     let image_id = prover::get_image_id();
     println!("Guest Image ID: 0x{}", hex::encode(&image_id));
+    println!(
+        "Batch Guest Image ID: 0x{}",
+        hex::encode(prover::get_batch_execution_image_id())
+    );
+    println!(
+        "Multi Guest Image ID: 0x{}",
+        hex::encode(prover::get_multi_execution_image_id())
+    );
     Ok(())
 }
 
-async fn cmd_execute(input_path: PathBuf, verbose: bool) -> Result<()> {
-    println!("Shadow-EVM Execute (no proof)");
-    println!("=============================");
+async fn cmd_execute(
+    input_args: InputArgs,
+    verbose: bool,
+    json: bool,
+    trace_path: Option<PathBuf>,
+    gas_profile: bool,
+) -> Result<()> {
+    if !json {
+        println!("Shadow-EVM Execute (no proof)");
+        println!("=============================");
+    }
 
     // Load input
-    let input = io::load_input_json(&input_path)?;
+    let (input, input_path) = input_args.load()?;
 
-    if verbose {
+    if verbose && !json {
         println!("Loaded input from {:?}", input_path);
     }
 
-    // Execute without proving
-    let commitment = prover::execute_only(input)?;
+    // Tracing, gas profiling and the revert reason, which the commitment
+    // does not carry, come from a native run; the guest never pays for
+    // them. The revert reason reuses the output of any earlier native run.
+    let mut native_output = None;
+
+    if let Some(trace_path) = trace_path {
+        let (output, _, trace) =
+            ShadowExecutor::execute_traced(input.clone(), ExecutionTrace::new())?;
+        io::save_trace_json(&trace, &trace_path)?;
+        if !json {
+            println!("Saved trace ({} steps) to {:?}", trace.len(), trace_path);
+        }
+        native_output = Some(output);
+    }
+
+    // A profiled run already computes the commitment the guest would, so
+    // the guest is skipped rather than executing the transaction again
+    let commitment = if gas_profile && !json {
+        let (output, commitment) = ShadowExecutor::execute_profiled(input)?;
+        if let Some(profile) = &output.gas_profile {
+            println!("\nGas profile:");
+            println!("{}", profile);
+            println!("\nGas by category:");
+            println!("{}", profile.breakdown);
+        }
+        native_output = Some(output);
+        commitment
+    } else {
+        if native_output.is_none() && !json {
+            native_output = ShadowExecutor::execute(input.clone())
+                .ok()
+                .map(|(output, _)| output);
+        }
+
+        // Execute without proving
+        prover::execute_only(input)?
+    };
+
+    if json {
+        println!("{}", io::format_commitment_json(&commitment)?);
+        return Ok(());
+    }
 
     println!("Execution complete!");
+    if let Some(reason) = native_output.and_then(|output| output.revert_reason()) {
+        println!("Revert reason: {}", reason);
+    }
     println!("\nCommitment:");
     println!("{}", io::format_commitment(&commitment));
 
     Ok(())
 }
 
-fn cmd_sample(output_path: PathBuf) -> Result<()> {
-    println!("Generating sample input...");
+fn cmd_diff_trace(a_path: PathBuf, b_path: PathBuf) -> Result<()> {
+    let a =
+        io::load_trace_json(&a_path).context(format!("Failed to load trace from {:?}", a_path))?;
+    let b =
+        io::load_trace_json(&b_path).context(format!("Failed to load trace from {:?}", b_path))?;
 
-    let sender = Address::repeat_byte(0x01);
-    let receiver = Address::repeat_byte(0x02);
+    let diff = diff_traces(&a, &b);
 
-    let mut state = InMemoryDB::new();
-    state.insert_account(
-        sender,
-        AccountState::new_with_balance(U256::from(10u128.pow(18))),
-    );
+    println!("Shadow-EVM Trace Diff");
+    println!("=====================");
+    println!("  Steps in a: {}", a.len());
+    println!("  Steps in b: {}", b.len());
+    println!("  Identical prefix: {}", diff.identical_prefix_len);
 
-    let input = ExecutionInput::new(
-        BlockEnv::default(),
-        TxInput::transfer(sender, receiver, U256::from(1_000_000_000_000_000u64)),
-        state,
-    );
+    match diff.first_divergence {
+        None => println!("\n✓ Traces are identical"),
+        Some(index) => {
+            println!("\n✗ Traces diverge at step {}", index);
+            for (label, trace) in [("a", &a), ("b", &b)] {
+                match trace.steps.get(index) {
+                    Some(step) => println!(
+                        "  {}: pc={} {} depth={} stack_top={:?}",
+                        label,
+                        step.pc,
+                        step.opcode_name(),
+                        step.depth,
+                        step.stack_top
+                    ),
+                    None => println!("  {}: <end of trace>", label),
+                }
+            }
+            println!("  Steps only in a: {}", diff.steps_only_in_a);
+            println!("  Steps only in b: {}", diff.steps_only_in_b);
+        }
+    }
 
+    Ok(())
+}
+
+fn cmd_sample(output_path: PathBuf, kind: &str) -> Result<()> {
+    let Some(kind) = SampleKind::parse(kind) else {
+        let names: Vec<_> = SampleKind::ALL.iter().map(|kind| kind.name()).collect();
+        anyhow::bail!(
+            "Unknown sample kind {:?} (expected one of: {})",
+            kind,
+            names.join(", ")
+        );
+    };
+
+    println!("Generating {} sample input...", kind.name());
+
+    let input = kind.input();
     io::save_input_json(&input, &output_path)?;
 
     println!("Sample input saved to {:?}", output_path);
-    println!("  Sender: {:?}", sender);
-    println!("  Receiver: {:?}", receiver);
-    println!("  Value: 0.001 ETH");
+    println!("  Sender: {:?}", SampleKind::sender());
+    match kind {
+        SampleKind::Transfer => {
+            println!("  Receiver: {:?}", SampleKind::receiver());
+            println!("  Value: 0.001 ETH");
+        }
+        SampleKind::Deploy => println!("  Deploys: counter contract"),
+        SampleKind::Erc20 => {
+            println!("  Token: {:?}", SampleKind::token());
+            println!("  Transfers: 30 of 100 tokens");
+        }
+        SampleKind::Revert => {
+            println!("  Token: {:?}", SampleKind::token());
+            println!("  Transfers: 101 of 100 tokens (reverts)");
+        }
+    }
 
     Ok(())
 }
@@ -2,22 +2,61 @@
 //!
 //! Handles the creation of STARK proofs using RISC Zero.
 
+use crate::cache::{CachedProof, ProofCache};
+use crate::replay::ReplayGuard;
 use anyhow::{Context, Result};
-use risc0_zkvm::{default_prover, ExecutorEnv, ExitCode, ProverOpts, Receipt};
+use risc0_zkvm::{
+    default_prover, get_prover_server, ExecutorEnv, ExecutorImpl, ExitCode, NullSegmentRef,
+    ProveInfo, ProverOpts, Receipt, Segment, SessionEvents, VerifierContext,
+};
 use shadow_evm_core::prelude::*;
+use shadow_evm_core::{BatchExecutionInput, MultiExecutionInput, StateDelta};
+use std::fmt;
+use std::sync::Arc;
 use std::time::Instant;
 
 // Include the guest ELF binary at compile time
 // This will be generated by the build script
 include!(concat!(env!("OUT_DIR"), "/methods.rs"));
 
+/// Progress of a proof generation, reported to `ProveOptions::on_event`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProverEvent {
+    /// The guest is about to be executed and proven
+    Started,
+    /// A segment of the execution has been proven
+    ///
+    /// Reported as each segment's proof completes, before the segments are
+    /// lifted and joined into the final receipt.
+    SegmentProven {
+        /// Zero-based index of the segment
+        index: usize,
+        /// Number of segments in the execution
+        total: usize,
+    },
+    /// The proof is complete
+    Finished {
+        /// Guest execution cycles
+        cycles: u64,
+        /// Time taken to generate the proof
+        time_ms: u128,
+    },
+}
+
+/// Callback receiving prover events
+pub type ProverEventHandler = Arc<dyn Fn(ProverEvent) + Send + Sync>;
+
 /// Proof generation options
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ProveOptions {
     /// Enable dev mode (faster but not cryptographically secure)
     pub dev_mode: bool,
     /// Verbose output
     pub verbose: bool,
+    /// Called as proving progresses
+    pub on_event: Option<ProverEventHandler>,
+    /// Refuse inputs this guard has already admitted
+    pub replay_guard: Option<ReplayGuard>,
 }
 
 impl Default for ProveOptions {
@@ -25,16 +64,31 @@ impl Default for ProveOptions {
         Self {
             dev_mode: false,
             verbose: false,
+            on_event: None,
+            replay_guard: None,
         }
     }
 }
 
+impl fmt::Debug for ProveOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProveOptions")
+            .field("dev_mode", &self.dev_mode)
+            .field("verbose", &self.verbose)
+            .field("on_event", &self.on_event.is_some())
+            .field("replay_guard", &self.replay_guard.is_some())
+            .finish()
+    }
+}
+
 impl ProveOptions {
     /// Create options for development (fast proofs)
     pub fn dev() -> Self {
         Self {
             dev_mode: true,
             verbose: true,
+            on_event: None,
+            replay_guard: None,
         }
     }
 
@@ -43,6 +97,54 @@ impl ProveOptions {
         Self {
             dev_mode: false,
             verbose: false,
+            on_event: None,
+            replay_guard: None,
+        }
+    }
+
+    /// Report progress to `handler`
+    pub fn with_event_handler(
+        mut self,
+        handler: impl Fn(ProverEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_event = Some(Arc::new(handler));
+        self
+    }
+
+    /// Reject inputs that `guard` has already admitted
+    ///
+    /// See [`ReplayGuard`]; a rejected `prove` fails with
+    /// `ReplayRejected` before doing any work.
+    pub fn with_replay_guard(mut self, guard: ReplayGuard) -> Self {
+        self.replay_guard = Some(guard);
+        self
+    }
+
+    /// Admit `input_hash` through the replay guard, if any
+    fn admit(&self, input_hash: &Hash) -> Result<()> {
+        match &self.replay_guard {
+            Some(guard) => guard.admit(input_hash),
+            None => Ok(()),
+        }
+    }
+
+    /// Run `prove` for an admitted `input_hash`, releasing it on failure
+    ///
+    /// An input whose proof failed, e.g. because the prover ran out of
+    /// memory, may then be submitted again.
+    fn admitted<T>(&self, input_hash: &Hash, prove: impl FnOnce() -> Result<T>) -> Result<T> {
+        prove().or_else(|err| {
+            if let Some(guard) = &self.replay_guard {
+                guard.release(input_hash)?;
+            }
+            Err(err)
+        })
+    }
+
+    /// Pass `event` to the handler, if any
+    fn emit(&self, event: ProverEvent) {
+        if let Some(on_event) = &self.on_event {
+            on_event(event);
         }
     }
 }
@@ -58,6 +160,8 @@ pub struct ProofResult {
     pub proving_time_ms: u128,
     /// Guest execution cycles
     pub cycles: u64,
+    /// Whether the receipt was taken from a `ProofCache` instead of proven
+    pub from_cache: bool,
 }
 
 /// Generate a ZK proof for an EVM execution
@@ -69,7 +173,26 @@ pub struct ProofResult {
 /// # Returns
 /// * `Ok(ProofResult)` - The proof and commitment on success
 /// * `Err(...)` - Error if proof generation fails
+///
+/// # Events
+/// `options.on_event` receives `Started` before proving, one
+/// `SegmentProven` as each segment is proven and `Finished` once the proof
+/// is complete. With a handler set, the guest is proven locally so that
+/// segments can be reported. Under `RISC0_DEV_MODE` no segment is proven,
+/// so none is reported.
+///
+/// # Replay guard
+/// A replay guard in `options` is consulted first and rejects an input it
+/// has already admitted. If proving then fails, the input is released so
+/// it can be retried.
 pub fn prove(input: ExecutionInput, options: &ProveOptions) -> Result<ProofResult> {
+    let input_hash = input.hash();
+    options.admit(&input_hash)?;
+    options.admitted(&input_hash, || prove_admitted(input, options))
+}
+
+/// `prove` without consulting the replay guard
+fn prove_admitted(input: ExecutionInput, options: &ProveOptions) -> Result<ProofResult> {
     if options.verbose {
         println!("Starting proof generation...");
         println!("  Input hash: 0x{}", hex::encode(input.hash().as_slice()));
@@ -86,13 +209,7 @@ pub fn prove(input: ExecutionInput, options: &ProveOptions) -> Result<ProofResul
 
     // Get the prover
     let prover = default_prover();
-
-    // Set prover options
-    let prover_opts = if options.dev_mode {
-        ProverOpts::fast()
-    } else {
-        ProverOpts::succinct()
-    };
+    let prover_opts = prover_opts(options);
 
     if options.verbose {
         println!(
@@ -103,13 +220,17 @@ pub fn prove(input: ExecutionInput, options: &ProveOptions) -> Result<ProofResul
                 "production"
             }
         );
-        println!("  Proving...");
     }
 
     // Generate the proof
-    let prove_info = prover
-        .prove_with_opts(env, SHADOW_EVM_GUEST_ELF, &prover_opts)
-        .context("Failed to generate proof")?;
+    options.emit(ProverEvent::Started);
+    let prove_info = match &options.on_event {
+        Some(on_event) => {
+            prove_reporting_segments(env, SHADOW_EVM_GUEST_ELF, &prover_opts, on_event)
+        }
+        None => prover.prove_with_opts(env, SHADOW_EVM_GUEST_ELF, &prover_opts),
+    }
+    .context("Failed to generate proof")?;
 
     let receipt = prove_info.receipt;
     let proving_time_ms = start.elapsed().as_millis();
@@ -123,6 +244,11 @@ pub fn prove(input: ExecutionInput, options: &ProveOptions) -> Result<ProofResul
     // Get cycle count
     let cycles = prove_info.stats.total_cycles;
 
+    options.emit(ProverEvent::Finished {
+        cycles,
+        time_ms: proving_time_ms,
+    });
+
     if options.verbose {
         println!("Proof generation complete!");
         println!("  Time: {}ms", proving_time_ms);
@@ -138,9 +264,184 @@ pub fn prove(input: ExecutionInput, options: &ProveOptions) -> Result<ProofResul
         commitment,
         proving_time_ms,
         cycles,
+        from_cache: false,
     })
 }
 
+/// Generate a ZK proof, reusing a cached proof of the same input
+///
+/// Looks up `input.hash()` in `cache` and returns the stored receipt if it
+/// verifies against the current guest and its journal commits to that
+/// same input hash; `proving_time_ms` is then the lookup time and no
+/// events are reported. Otherwise proves like `prove` and stores the
+/// result before returning it.
+///
+/// A replay guard in `options` is consulted first: a repeated input is
+/// rejected even if its proof is cached, and released again if proving
+/// it fails.
+pub fn prove_cached(
+    input: ExecutionInput,
+    options: &ProveOptions,
+    cache: &ProofCache,
+) -> Result<ProofResult> {
+    let start = Instant::now();
+    let input_hash = input.hash();
+    options.admit(&input_hash)?;
+
+    // An entry proving another input, e.g. a file copied over, is a miss
+    let hit = cache
+        .load(&input_hash, options.dev_mode)?
+        .and_then(|cached| {
+            let commitment: ExecutionCommitment = cached.receipt.journal.decode().ok()?;
+            (commitment.input_hash == input_hash).then_some((cached, commitment))
+        });
+
+    if let Some((cached, commitment)) = hit {
+        if options.verbose {
+            println!("Loaded proof from cache");
+        }
+
+        return Ok(ProofResult {
+            receipt: cached.receipt,
+            commitment,
+            proving_time_ms: start.elapsed().as_millis(),
+            cycles: cached.cycles,
+            from_cache: true,
+        });
+    }
+
+    let result = options.admitted(&input_hash, || prove_admitted(input, options))?;
+    cache.store(
+        &input_hash,
+        options.dev_mode,
+        &CachedProof {
+            receipt: result.receipt.clone(),
+            cycles: result.cycles,
+        },
+    )?;
+
+    Ok(result)
+}
+
+/// Generate a ZK proof that a state has a given root
+///
+/// Runs the state commitment guest, whose journal contains only
+/// `state.compute_state_root()`. No transaction is executed.
+pub fn prove_state_commitment(state: &InMemoryDB, options: &ProveOptions) -> Result<Receipt> {
+    let env = ExecutorEnv::builder()
+        .write(state)
+        .context("Failed to write state to executor environment")?
+        .build()
+        .context("Failed to build executor environment")?;
+
+    let prove_info = default_prover()
+        .prove_with_opts(env, STATE_COMMITMENT_ELF, &prover_opts(options))
+        .context("Failed to generate state commitment proof")?;
+
+    Ok(prove_info.receipt)
+}
+
+/// Generate a ZK proof that a state delta takes one root to another
+///
+/// Runs the state delta guest, whose journal contains
+/// `(delta.base_root, new_root)` for the sparse Merkle state root. Only the
+/// changed accounts are sent to the guest, and their new values are taken
+/// on trust: the proof covers the root update, not EVM execution.
+pub fn prove_state_delta(delta: &StateDelta, options: &ProveOptions) -> Result<Receipt> {
+    let env = ExecutorEnv::builder()
+        .write(delta)
+        .context("Failed to write state delta to executor environment")?
+        .build()
+        .context("Failed to build executor environment")?;
+
+    let prove_info = default_prover()
+        .prove_with_opts(env, STATE_DELTA_ELF, &prover_opts(options))
+        .context("Failed to generate state delta proof")?;
+
+    Ok(prove_info.receipt)
+}
+
+/// Generate one ZK proof over several independent executions
+///
+/// Runs the multi-execution guest, whose journal contains the Merkle root
+/// over the executions' commitments (see `shadow_evm_core::multi`).
+pub fn prove_multi(batch: &MultiExecutionInput, options: &ProveOptions) -> Result<Receipt> {
+    let env = ExecutorEnv::builder()
+        .write(batch)
+        .context("Failed to write batch to executor environment")?
+        .build()
+        .context("Failed to build executor environment")?;
+
+    let prove_info = default_prover()
+        .prove_with_opts(env, MULTI_EXECUTION_ELF, &prover_opts(options))
+        .context("Failed to generate multi-execution proof")?;
+
+    Ok(prove_info.receipt)
+}
+
+/// Generate a ZK proof of a batch of transactions on one running state
+///
+/// Runs the batch-execution guest, whose journal contains the
+/// `ExecutionCommitment` over the whole batch.
+pub fn prove_batch(batch: &BatchExecutionInput, options: &ProveOptions) -> Result<Receipt> {
+    let env = ExecutorEnv::builder()
+        .write(batch)
+        .context("Failed to write batch to executor environment")?
+        .build()
+        .context("Failed to build executor environment")?;
+
+    let prove_info = default_prover()
+        .prove_with_opts(env, BATCH_EXECUTION_ELF, &prover_opts(options))
+        .context("Failed to generate batch-execution proof")?;
+
+    Ok(prove_info.receipt)
+}
+
+/// Reports each proven segment of a session to a `ProverEventHandler`
+struct SegmentProgress {
+    on_event: ProverEventHandler,
+    /// Number of segments in the session
+    total: usize,
+}
+
+impl SessionEvents for SegmentProgress {
+    fn on_post_prove_segment(&self, segment: &Segment) {
+        (self.on_event)(ProverEvent::SegmentProven {
+            index: segment.index as usize,
+            total: self.total,
+        });
+    }
+}
+
+/// Execute `elf` and prove it locally, reporting each proven segment
+///
+/// What the local prover's `prove_with_opts` does, except that the session
+/// is executed first, so the number of segments is known before the first
+/// one is proven.
+fn prove_reporting_segments(
+    env: ExecutorEnv<'_>,
+    elf: &[u8],
+    opts: &ProverOpts,
+    on_event: &ProverEventHandler,
+) -> Result<ProveInfo> {
+    let mut session = ExecutorImpl::from_elf(env, elf)?.run()?;
+    let total = session.segments.len();
+    session.add_hook(SegmentProgress {
+        on_event: Arc::clone(on_event),
+        total,
+    });
+    get_prover_server(opts)?.prove_session(&VerifierContext::default(), &session)
+}
+
+/// Select prover options for the requested mode
+fn prover_opts(options: &ProveOptions) -> ProverOpts {
+    if options.dev_mode {
+        ProverOpts::fast()
+    } else {
+        ProverOpts::succinct()
+    }
+}
+
 /// Execute without proving (for testing)
 ///
 /// Runs the guest program in the executor without generating a proof.
@@ -163,6 +464,75 @@ pub fn execute_only(input: ExecutionInput) -> Result<ExecutionCommitment> {
     Ok(commitment)
 }
 
+/// Approximate cycles to page one 1 KiB page in or out of the guest
+///
+/// Average cost from the RISC Zero optimization guide; the exact cost
+/// depends on how deep in the page table the page sits.
+const CYCLES_PER_PAGE_OPERATION: u64 = 1_130;
+
+/// Paging operations per resident page and segment: one in, one out
+const PAGE_OPERATIONS_PER_PAGE: u64 = 2;
+
+/// Approximate cycles a segment spends on each page it holds
+const CYCLES_PER_RESIDENT_PAGE: u64 = PAGE_OPERATIONS_PER_PAGE * CYCLES_PER_PAGE_OPERATION;
+
+/// Cost and memory estimate of proving an input, without proving it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DryRunStats {
+    /// Total cycles, including paging and padding
+    pub cycles: u64,
+    /// Number of segments the execution splits into
+    pub segments: usize,
+    /// Cycles spent paging memory in and out
+    pub paging_cycles: u64,
+    /// Most pages (1 KiB) the busiest segment can hold
+    ///
+    /// RISC Zero reports paging for the whole execution only, so this is an
+    /// upper bound rather than an average: the busiest segment pages no more
+    /// than the whole execution, and no more than the cycles of the largest
+    /// segment allow. Compare against the guest's memory to spot inputs
+    /// that may run out of memory.
+    pub peak_pages: u64,
+}
+
+/// Run the guest in the executor and report its cost, without proving
+///
+/// Much cheaper than `prove`: use it to reject inputs that are too large
+/// before committing to a long proof.
+pub fn dry_run_stats(input: &ExecutionInput) -> Result<DryRunStats> {
+    let env = ExecutorEnv::builder()
+        .write(input)
+        .context("Failed to write input to executor environment")?
+        .build()
+        .context("Failed to build executor environment")?;
+
+    // Segments are not kept; only the size of the largest is needed
+    let mut max_po2 = 0;
+    let session = ExecutorImpl::from_elf(env, SHADOW_EVM_GUEST_ELF)
+        .context("Failed to load guest")?
+        .run_with_callback(|segment| {
+            max_po2 = max_po2.max(segment.po2());
+            Ok(Box::new(NullSegmentRef {}))
+        })
+        .context("Failed to execute guest")?;
+
+    match session.exit_code {
+        ExitCode::Halted(0) => {}
+        exit_code => anyhow::bail!("Guest execution failed: {:?}", exit_code),
+    }
+
+    let stats = session.stats();
+    let paged = stats.paging_cycles / CYCLES_PER_RESIDENT_PAGE;
+    let largest_segment = (1u64 << max_po2) / CYCLES_PER_RESIDENT_PAGE;
+
+    Ok(DryRunStats {
+        cycles: stats.total_cycles,
+        segments: stats.segments,
+        paging_cycles: stats.paging_cycles,
+        peak_pages: paged.min(largest_segment),
+    })
+}
+
 /// Get the image ID for the guest program
 ///
 /// This is needed for on-chain verification.
@@ -170,6 +540,26 @@ pub fn get_image_id() -> [u8; 32] {
     SHADOW_EVM_GUEST_ID
 }
 
+/// Get the image ID for the state commitment guest program
+pub fn get_state_commitment_image_id() -> [u8; 32] {
+    STATE_COMMITMENT_ID
+}
+
+/// Get the image ID for the state delta guest program
+pub fn get_state_delta_image_id() -> [u8; 32] {
+    STATE_DELTA_ID
+}
+
+/// Get the image ID for the multi-execution guest program
+pub fn get_multi_execution_image_id() -> [u8; 32] {
+    MULTI_EXECUTION_ID
+}
+
+/// Get the image ID for the batch-execution guest program
+pub fn get_batch_execution_image_id() -> [u8; 32] {
+    BATCH_EXECUTION_ID
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,4 +585,255 @@ mod tests {
         let commitment = execute_only(input).unwrap();
         assert!(!commitment.commitment.is_zero());
     }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_dry_run_stats() {
+        let caller = Address::repeat_byte(0x01);
+        let contract = Address::repeat_byte(0xcc);
+
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            caller,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        // Writes 32 storage slots: PUSH1 32, JUMPDEST, PUSH1 1, SWAP1, SUB,
+        // DUP1, DUP1, SSTORE, DUP1, PUSH1 2, JUMPI, STOP
+        state.insert_account(
+            contract,
+            AccountState::new_contract(
+                vec![
+                    0x60, 0x20, 0x5b, 0x60, 0x01, 0x90, 0x03, 0x80, 0x80, 0x55, 0x80, 0x60, 0x02,
+                    0x57, 0x00,
+                ],
+                U256::ZERO,
+            ),
+        );
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::call(caller, contract, vec![]).with_gas_limit(1_000_000),
+            state,
+        );
+
+        let stats = dry_run_stats(&input).unwrap();
+
+        assert!(stats.cycles > 0);
+        assert!(stats.segments > 0);
+        assert!(stats.paging_cycles > 0);
+        assert!(stats.peak_pages > 0);
+        assert!(stats.peak_pages <= stats.paging_cycles / CYCLES_PER_RESIDENT_PAGE);
+    }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_prove_fires_events() {
+        use std::sync::Mutex;
+
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64)),
+            state,
+        );
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        let options = ProveOptions::dev()
+            .with_event_handler(move |event| recorded.lock().unwrap().push(event));
+
+        let result = prove(input, &options).unwrap();
+        let events = events.lock().unwrap();
+
+        // Started, each segment in order, then Finished
+        let total = events.len() - 2;
+        assert!(total > 0);
+        let mut expected = vec![ProverEvent::Started];
+        expected.extend((0..total).map(|index| ProverEvent::SegmentProven { index, total }));
+        expected.push(ProverEvent::Finished {
+            cycles: result.cycles,
+            time_ms: result.proving_time_ms,
+        });
+        assert_eq!(*events, expected);
+    }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_prove_cached_hits_cache() {
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64)),
+            state,
+        );
+
+        let dir = std::env::temp_dir().join(format!("shadow-evm-cache-{}", std::process::id()));
+        let cache = ProofCache::new(&dir);
+        let options = ProveOptions::dev();
+
+        let first = prove_cached(input.clone(), &options, &cache).unwrap();
+        let second = prove_cached(input.clone(), &options, &cache).unwrap();
+
+        assert!(!first.from_cache);
+        assert!(second.from_cache);
+        assert_eq!(second.commitment, first.commitment);
+        assert_eq!(second.cycles, first.cycles);
+
+        // A proof filed under another input's hash is not returned for it
+        let other = input.with_public_inputs(vec![0x01]);
+        let cached = CachedProof {
+            receipt: first.receipt,
+            cycles: first.cycles,
+        };
+        cache.store(&other.hash(), true, &cached).unwrap();
+        let third = prove_cached(other.clone(), &options, &cache).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!third.from_cache);
+        assert_eq!(third.commitment.input_hash, other.hash());
+    }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_prove_rejects_replay() {
+        use crate::replay::{ReplayGuard, ReplayRejected};
+
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64)),
+            state,
+        );
+
+        let options = ProveOptions::dev().with_replay_guard(ReplayGuard::new());
+        prove(input.clone(), &options).unwrap();
+
+        let err = prove(input.clone(), &options).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ReplayRejected>(),
+            Some(&ReplayRejected {
+                input_hash: input.hash()
+            })
+        );
+
+        // Without a guard the same input is proven again
+        prove(input.clone(), &ProveOptions::dev()).unwrap();
+
+        // A failed proof releases its input, so it fails the same way again
+        // instead of being rejected
+        let unfunded = ExecutionInput::new(input.block, input.tx, InMemoryDB::new());
+        for _ in 0..2 {
+            let err = prove(unfunded.clone(), &options).unwrap_err();
+            assert!(err.downcast_ref::<ReplayRejected>().is_none());
+        }
+    }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_prove_state_commitment() {
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            Address::repeat_byte(0x01),
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+
+        let receipt = prove_state_commitment(&state, &ProveOptions::dev()).unwrap();
+        let root = crate::verifier::verify_state_commitment(&receipt).unwrap();
+
+        assert_eq!(root, state.compute_state_root());
+    }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_prove_state_delta() {
+        use shadow_evm_core::StateRootMode;
+
+        let mut base = InMemoryDB::new();
+        for i in 1..=4u8 {
+            base.insert_account(
+                Address::repeat_byte(i),
+                AccountState::new_with_balance(U256::from(10u128.pow(18))),
+            );
+        }
+        let mut post = base.clone();
+        post.get_account_mut(&Address::repeat_byte(0x02))
+            .unwrap()
+            .balance = U256::ZERO;
+
+        let delta = StateDelta::build(&base, &post);
+        let receipt = prove_state_delta(&delta, &ProveOptions::dev()).unwrap();
+        let (base_root, new_root) = crate::verifier::verify_state_delta(&receipt).unwrap();
+
+        assert_eq!(
+            base_root,
+            base.compute_state_root_with(StateRootMode::SparseMerkle)
+        );
+        assert_eq!(
+            new_root,
+            post.compute_state_root_with(StateRootMode::SparseMerkle)
+        );
+    }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_prove_multi() {
+        let transfer = |from: u8, to: u8| {
+            let sender = Address::repeat_byte(from);
+            let mut state = InMemoryDB::new();
+            state.insert_account(
+                sender,
+                AccountState::new_with_balance(U256::from(10u128.pow(18))),
+            );
+            ExecutionInput::new(
+                BlockEnv::default(),
+                TxInput::transfer(sender, Address::repeat_byte(to), U256::from(1000u64)),
+                state,
+            )
+        };
+        let first = transfer(0x01, 0x02);
+        let second = transfer(0x03, 0x04);
+        let (_, c1) = ShadowExecutor::execute(first.clone()).unwrap();
+        let (_, c2) = ShadowExecutor::execute(second.clone()).unwrap();
+
+        let batch = MultiExecutionInput::new(vec![first, second]);
+        let receipt = prove_multi(&batch, &ProveOptions::dev()).unwrap();
+        let root = crate::verifier::verify_multi(&receipt).unwrap();
+
+        assert_eq!(root, shadow_evm_core::commitments_root(&[c1, c2]));
+    }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_prove_batch() {
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let txs = (0..2u8)
+            .map(|i| TxInput::transfer(sender, Address::repeat_byte(0x10 + i), U256::from(1000u64)))
+            .collect();
+        let batch = BatchExecutionInput::new(BlockEnv::default(), txs, state);
+        let (_, expected) = ShadowExecutor::execute_batch(batch.clone()).unwrap();
+
+        let receipt = prove_batch(&batch, &ProveOptions::dev()).unwrap();
+        let commitment = crate::verifier::verify_batch(&receipt).unwrap();
+
+        assert_eq!(commitment.commitment, expected.commitment);
+    }
 }
@@ -0,0 +1,221 @@
+//! Replay guard for proving services
+//!
+//! A service proving inputs on behalf of others can refuse to prove the
+//! same input twice, so a requester cannot make it burn prover time on
+//! repeats. Inputs are keyed by `ExecutionInput::hash`; to admit the same
+//! execution once per requester, bind the requester into the input with
+//! `ExecutionInput::with_public_inputs`.
+//!
+//! Unlike a `ProofCache`, which serves a repeated input from disk, the
+//! guard denies it with a [`ReplayRejected`] error.
+
+use anyhow::{Context, Result};
+use shadow_evm_core::prelude::*;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Storage of the input hashes a guard has admitted
+pub trait ReplayStore: Send + Sync {
+    /// Record `input_hash`, returning `false` if it was already recorded
+    fn insert(&self, input_hash: &Hash) -> Result<bool>;
+
+    /// Forget `input_hash`, so it can be recorded again
+    fn remove(&self, input_hash: &Hash) -> Result<()>;
+}
+
+/// Process-local replay store, forgotten on restart
+#[derive(Debug, Default)]
+pub struct InMemoryReplayStore {
+    seen: Mutex<HashSet<Hash>>,
+}
+
+impl ReplayStore for InMemoryReplayStore {
+    fn insert(&self, input_hash: &Hash) -> Result<bool> {
+        let mut seen = self
+            .seen
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Replay store lock poisoned"))?;
+        Ok(seen.insert(*input_hash))
+    }
+
+    fn remove(&self, input_hash: &Hash) -> Result<()> {
+        let mut seen = self
+            .seen
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Replay store lock poisoned"))?;
+        seen.remove(input_hash);
+        Ok(())
+    }
+}
+
+/// Replay store kept in a directory, one empty file per input hash
+///
+/// Survives restarts and can be shared by processes on the same file
+/// system: a hash is recorded by creating its file, which fails if another
+/// process got there first.
+#[derive(Debug, Clone)]
+pub struct FileReplayStore {
+    dir: PathBuf,
+}
+
+impl FileReplayStore {
+    /// Use `dir` as the store (created on the first insert)
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// File recording `input_hash`
+    fn path(&self, input_hash: &Hash) -> PathBuf {
+        self.dir.join(hex::encode(input_hash.as_slice()))
+    }
+}
+
+impl ReplayStore for FileReplayStore {
+    fn insert(&self, input_hash: &Hash) -> Result<bool> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create replay directory {:?}", self.dir))?;
+
+        let path = self.path(input_hash);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(false),
+            Err(err) => Err(err).with_context(|| format!("Failed to create {:?}", path)),
+        }
+    }
+
+    fn remove(&self, input_hash: &Hash) -> Result<()> {
+        let path = self.path(input_hash);
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("Failed to remove {:?}", path))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Error returned when an input has already been admitted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayRejected {
+    /// Hash of the rejected input
+    pub input_hash: Hash,
+}
+
+impl fmt::Display for ReplayRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Input 0x{} was already proven",
+            hex::encode(self.input_hash.as_slice())
+        )
+    }
+}
+
+impl std::error::Error for ReplayRejected {}
+
+/// Admits each input hash once
+///
+/// Clones share their store, so one guard can be handed to several
+/// `ProveOptions`.
+#[derive(Clone)]
+pub struct ReplayGuard {
+    store: Arc<dyn ReplayStore>,
+}
+
+impl Default for ReplayGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ReplayGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplayGuard").finish_non_exhaustive()
+    }
+}
+
+impl ReplayGuard {
+    /// Guard backed by an `InMemoryReplayStore`
+    pub fn new() -> Self {
+        Self::with_store(InMemoryReplayStore::default())
+    }
+
+    /// Guard backed by `store`, e.g. one shared between service instances
+    pub fn with_store(store: impl ReplayStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+
+    /// Admit `input_hash` if it has not been seen before
+    ///
+    /// Fails with `ReplayRejected` for a repeat. The hash is recorded on
+    /// admission, so concurrent requests for the same input admit only one;
+    /// if its proof then fails, `release` lets the input be retried.
+    pub fn admit(&self, input_hash: &Hash) -> Result<()> {
+        if self.store.insert(input_hash)? {
+            Ok(())
+        } else {
+            Err(ReplayRejected {
+                input_hash: *input_hash,
+            }
+            .into())
+        }
+    }
+
+    /// Forget an admitted `input_hash`, e.g. because proving it failed
+    pub fn release(&self, input_hash: &Hash) -> Result<()> {
+        self.store.remove(input_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_admits_once() {
+        let guard = ReplayGuard::new();
+        let first = Hash::repeat_byte(0x01);
+        let second = Hash::repeat_byte(0x02);
+
+        guard.admit(&first).unwrap();
+        guard.admit(&second).unwrap();
+
+        let err = guard.clone().admit(&first).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ReplayRejected>(),
+            Some(&ReplayRejected { input_hash: first })
+        );
+
+        guard.release(&first).unwrap();
+        guard.admit(&first).unwrap();
+    }
+
+    #[test]
+    fn test_file_store_shared_between_guards() {
+        let dir = std::env::temp_dir().join(format!("shadow-evm-replay-{}", std::process::id()));
+        let input_hash = Hash::repeat_byte(0x01);
+
+        let guard = ReplayGuard::with_store(FileReplayStore::new(&dir));
+        guard.admit(&input_hash).unwrap();
+
+        // A guard opened later, e.g. by the next CLI run, sees the hash
+        let reopened = ReplayGuard::with_store(FileReplayStore::new(&dir));
+        let rejected = reopened.admit(&input_hash).is_err();
+        reopened.release(&input_hash).unwrap();
+        let readmitted = guard.admit(&input_hash).is_ok();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(rejected);
+        assert!(readmitted);
+    }
+}
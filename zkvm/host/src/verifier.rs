@@ -6,8 +6,11 @@ use anyhow::{Context, Result};
 use risc0_zkvm::Receipt;
 use shadow_evm_core::prelude::*;
 
-// Import the guest image ID
-use crate::prover::get_image_id;
+// Import the guest image IDs
+use crate::prover::{
+    get_batch_execution_image_id, get_image_id, get_multi_execution_image_id,
+    get_state_commitment_image_id, get_state_delta_image_id,
+};
 
 /// Verification result
 #[derive(Debug)]
@@ -100,6 +103,111 @@ pub fn verify_state_transition(
         && commitment.post_state_root == *expected_post_root)
 }
 
+/// Verify a chain of execution proofs over one evolving state
+///
+/// Checks every receipt against `image_id`, then that each proof's
+/// `pre_state_root` is the previous proof's `post_state_root`. Together
+/// they prove one continuous state transition, from the first proof's
+/// pre-state to the last proof's post-state.
+///
+/// # Returns
+/// * `true` if every proof is valid and consecutive roots link
+/// * `false` otherwise, including for an empty chain, which proves nothing
+pub fn verify_chain(receipts: &[Receipt], image_id: [u8; 32]) -> Result<bool> {
+    if receipts.is_empty() {
+        return Ok(false);
+    }
+
+    let mut previous: Option<ExecutionCommitment> = None;
+    for (i, receipt) in receipts.iter().enumerate() {
+        if receipt.verify(image_id).is_err() {
+            return Ok(false);
+        }
+
+        let commitment: ExecutionCommitment = receipt
+            .journal
+            .decode()
+            .with_context(|| format!("Failed to decode commitment of proof {}", i))?;
+        if let Some(previous) = &previous {
+            if commitment.pre_state_root != previous.post_state_root {
+                return Ok(false);
+            }
+        }
+        previous = Some(commitment);
+    }
+
+    Ok(true)
+}
+
+/// Verify a state commitment proof
+///
+/// Checks the receipt against the state commitment guest image ID and
+/// returns the state root committed in its journal.
+pub fn verify_state_commitment(receipt: &Receipt) -> Result<Hash> {
+    receipt
+        .verify(get_state_commitment_image_id())
+        .context("State commitment proof verification failed")?;
+
+    receipt
+        .journal
+        .decode()
+        .context("Failed to decode state root from journal")
+}
+
+/// Verify a state delta proof
+///
+/// Checks the receipt against the state delta guest image ID and returns
+/// the `(base_root, new_root)` pair committed in its journal. A valid proof
+/// says nothing about which transactions, if any, produced the change.
+pub fn verify_state_delta(receipt: &Receipt) -> Result<(Hash, Hash)> {
+    receipt
+        .verify(get_state_delta_image_id())
+        .context("State delta proof verification failed")?;
+
+    receipt
+        .journal
+        .decode()
+        .context("Failed to decode state roots from journal")
+}
+
+/// Verify a multi-execution proof
+///
+/// Checks the receipt against the multi-execution guest image ID and
+/// returns the Merkle root of commitments committed in its journal.
+pub fn verify_multi(receipt: &Receipt) -> Result<Hash> {
+    receipt
+        .verify(get_multi_execution_image_id())
+        .context("Multi-execution proof verification failed")?;
+
+    receipt
+        .journal
+        .decode()
+        .context("Failed to decode commitments root from journal")
+}
+
+/// Verify a batch-execution proof
+///
+/// Checks the receipt against the batch-execution guest image ID and
+/// returns the commitment over the whole batch committed in its journal.
+pub fn verify_batch(receipt: &Receipt) -> Result<ExecutionCommitment> {
+    receipt
+        .verify(get_batch_execution_image_id())
+        .context("Batch-execution proof verification failed")?;
+
+    receipt
+        .journal
+        .decode()
+        .context("Failed to decode commitment from journal")
+}
+
+/// Raw journal bytes committed by the guest
+///
+/// This is what an on-chain verifier takes as calldata alongside the seal.
+/// The receipt is not verified; use `verify` for that.
+pub fn journal_bytes(receipt: &Receipt) -> Vec<u8> {
+    receipt.journal.bytes.clone()
+}
+
 /// Export proof for on-chain verification
 ///
 /// Returns the data needed to verify the proof in a Solidity contract.
@@ -125,7 +233,7 @@ pub fn export_for_onchain(receipt: &Receipt) -> Result<OnChainProof> {
     Ok(OnChainProof {
         seal,
         image_id: get_image_id(),
-        journal: receipt.journal.bytes.clone(),
+        journal: journal_bytes(receipt),
     })
 }
 
@@ -151,4 +259,89 @@ mod tests {
         assert!(result.valid);
         assert_eq!(result.commitment.commitment, commitment.commitment);
     }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_journal_bytes() {
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64)),
+            state,
+        );
+
+        let result = crate::prover::prove(input, &crate::prover::ProveOptions::dev()).unwrap();
+        let journal = journal_bytes(&result.receipt);
+        let decoded: ExecutionCommitment = risc0_zkvm::serde::from_slice(&journal).unwrap();
+
+        assert_eq!(decoded, result.commitment);
+    }
+
+    /// Transfers 1000 wei from 0x01 to 0x02 on `state`
+    fn transfer_input(state: InMemoryDB, nonce: u64) -> ExecutionInput {
+        let tx = TxInput::transfer(
+            Address::repeat_byte(0x01),
+            Address::repeat_byte(0x02),
+            U256::from(1000u64),
+        )
+        .with_nonce(nonce);
+        ExecutionInput::new(BlockEnv::default(), tx, state)
+    }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_verify_chain() {
+        let mut genesis = InMemoryDB::new();
+        genesis.insert_account(
+            Address::repeat_byte(0x01),
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let options = crate::prover::ProveOptions::dev();
+
+        let first = transfer_input(genesis.clone(), 0);
+        let (output, _) = ShadowExecutor::execute(first.clone()).unwrap();
+        let first = crate::prover::prove(first, &options).unwrap();
+        let second = crate::prover::prove(transfer_input(output.post_state, 1), &options).unwrap();
+
+        let linked = [first.receipt.clone(), second.receipt.clone()];
+        assert!(verify_chain(&linked, get_image_id()).unwrap());
+
+        // The second proof again starts from genesis, skipping the first
+        let restarted = crate::prover::prove(transfer_input(genesis, 0), &options).unwrap();
+        let unlinked = [first.receipt, restarted.receipt];
+        assert!(!verify_chain(&unlinked, get_image_id()).unwrap());
+
+        assert!(!verify_chain(&[], get_image_id()).unwrap());
+    }
+
+    #[test]
+    #[ignore] // Requires RISC Zero toolchain installed
+    fn test_journal_carries_guest_version() {
+        let sender = Address::repeat_byte(0x01);
+        let mut state = InMemoryDB::new();
+        state.insert_account(
+            sender,
+            AccountState::new_with_balance(U256::from(10u128.pow(18))),
+        );
+        let input = ExecutionInput::new(
+            BlockEnv::default(),
+            TxInput::transfer(sender, Address::repeat_byte(0x02), U256::from(1000u64)),
+            state,
+        );
+        let (_, native) = ShadowExecutor::execute(input.clone()).unwrap();
+
+        let result = crate::prover::prove(input, &crate::prover::ProveOptions::dev()).unwrap();
+        let decoded: ExecutionCommitment =
+            risc0_zkvm::serde::from_slice(&journal_bytes(&result.receipt)).unwrap();
+
+        assert_eq!(decoded.guest_version, shadow_evm_core::GUEST_VERSION);
+        // The version is not part of the commitment itself
+        assert_eq!(native.guest_version, 0);
+        assert_eq!(decoded.commitment, native.commitment);
+    }
 }